@@ -3,22 +3,266 @@ use ansi_term::Style as AnsiTermStyle;
 use rpassword::{
     prompt_password, prompt_password_from_bufread, read_password, read_password_from_bufread,
 };
-use rprompt::{prompt_reply, prompt_reply_from_bufread, read_reply, read_reply_from_bufread};
+use rprompt::{
+    prompt_reply, prompt_reply_from_bufread, prompt_reply_with_history, read_reply,
+    read_reply_from_bufread,
+};
+pub use rprompt::{Completer, History};
 use rtoolbox::atty;
 use rtoolbox::safe_string::SafeString;
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::io::Result as IoResult;
-use std::io::{Cursor, StderrLock, StdinLock, StdoutLock, Write};
+use std::io::{Cursor, Error as IoError, ErrorKind as IoErrorKind};
+use std::io::{StderrLock, StdinLock, StdoutLock, Write};
+use std::rc::Rc;
+use std::time::Duration;
 
 pub enum OutputType {
     Standard,
     Error,
 }
 
+/// Answers the questions asked to the user, ie: from the TTY, or scripted answers in tests
+///
+/// Frontends other than the terminal can provide their own, and reuse the rest of a program.
+pub trait PromptProvider {
+    fn read_line(&mut self) -> IoResult<String>;
+    fn prompt_line(&mut self, prompt: &str) -> IoResult<String>;
+    fn read_password(&mut self) -> IoResult<SafeString>;
+    fn prompt_password(&mut self, prompt: &str) -> IoResult<SafeString>;
+
+    /// Reads a command in an interactive shell and adds it to `history`, failing with `TimedOut`
+    /// if nothing is typed within `idle`
+    ///
+    /// Only the TTY completes commands with `completer` and recalls `history` with Up and Down,
+    /// other providers answer it like `prompt_line`.
+    fn prompt_command(
+        &mut self,
+        prompt: &str,
+        _completer: &dyn Completer,
+        history: &mut History,
+        _idle: Option<Duration>,
+    ) -> IoResult<String> {
+        let command = self.prompt_line(prompt)?;
+        history.push(&command);
+        Ok(command)
+    }
+}
+
+/// Asks the user in the TTY, with rprompt and rpassword
+pub struct TtyPrompts;
+
+impl PromptProvider for TtyPrompts {
+    fn read_line(&mut self) -> IoResult<String> {
+        if !atty::is(atty::Stream::Stdin) {
+            panic!("Need a TTY to read password");
+        }
+
+        Ok(read_reply()?)
+    }
+
+    fn prompt_line(&mut self, prompt: &str) -> IoResult<String> {
+        if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+            panic!("Need a TTY to read password");
+        }
+
+        Ok(prompt_reply(prompt)?)
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        if !atty::is(atty::Stream::Stdin) {
+            panic!("Need a TTY to read password");
+        }
+
+        Ok(SafeString::from_string(read_password()?))
+    }
+
+    fn prompt_password(&mut self, prompt: &str) -> IoResult<SafeString> {
+        if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+            panic!("Need a TTY to read password");
+        }
+
+        Ok(SafeString::from_string(prompt_password(prompt)?))
+    }
+
+    fn prompt_command(
+        &mut self,
+        prompt: &str,
+        completer: &dyn Completer,
+        history: &mut History,
+        idle: Option<Duration>,
+    ) -> IoResult<String> {
+        if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+            panic!("Need a TTY to read commands");
+        }
+
+        Ok(prompt_reply_with_history(prompt, completer, history, idle)?)
+    }
+}
+
+/// Reads answers from a cursor as if they were typed in the TTY, and writes prompts to another
+#[derive(Default)]
+pub struct CursorPrompts {
+    pub ttyin_cursor: Cursor<Vec<u8>>,
+    pub ttyout_cursor: Cursor<Vec<u8>>,
+}
+
+impl CursorPrompts {
+    pub fn new(ttyin: &str) -> CursorPrompts {
+        CursorPrompts {
+            ttyin_cursor: Cursor::new(ttyin.as_bytes().to_owned()),
+            ttyout_cursor: Cursor::new(Vec::new()),
+        }
+    }
+}
+
+impl PromptProvider for CursorPrompts {
+    fn read_line(&mut self) -> IoResult<String> {
+        Ok(read_reply_from_bufread(&mut self.ttyin_cursor)?)
+    }
+
+    fn prompt_line(&mut self, prompt: &str) -> IoResult<String> {
+        Ok(prompt_reply_from_bufread(
+            &mut self.ttyin_cursor,
+            &mut self.ttyout_cursor,
+            prompt,
+        )?)
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        Ok(SafeString::from_string(read_password_from_bufread(
+            &mut self.ttyin_cursor,
+        )?))
+    }
+
+    fn prompt_password(&mut self, prompt: &str) -> IoResult<SafeString> {
+        Ok(SafeString::from_string(prompt_password_from_bufread(
+            &mut self.ttyin_cursor,
+            &mut self.ttyout_cursor,
+            prompt,
+        )?))
+    }
+}
+
+/// Gives answers from a script, in order, and keeps the prompts they answered, useful for tests
+///
+/// Clones share the same script, so a test can keep one to check the prompts once done. Asking
+/// for more answers than the script has is an `UnexpectedEof` error.
+#[derive(Clone, Default)]
+pub struct ScriptedPrompts {
+    script: Rc<RefCell<Script>>,
+}
+
+#[derive(Default)]
+struct Script {
+    answers: VecDeque<String>,
+    prompts: Vec<String>,
+}
+
+impl ScriptedPrompts {
+    pub fn new(answers: &[&str]) -> ScriptedPrompts {
+        let script = Script {
+            answers: answers.iter().map(|answer| answer.to_string()).collect(),
+            prompts: Vec::new(),
+        };
+        ScriptedPrompts {
+            script: Rc::new(RefCell::new(script)),
+        }
+    }
+
+    /// Returns the prompts answered so far, with an empty one for reads without a prompt
+    pub fn prompts(&self) -> Vec<String> {
+        self.script.borrow().prompts.clone()
+    }
+
+    /// Returns how many answers are left
+    pub fn remaining(&self) -> usize {
+        self.script.borrow().answers.len()
+    }
+
+    fn answer(&mut self, prompt: &str) -> IoResult<String> {
+        let mut script = self.script.borrow_mut();
+        script.prompts.push(prompt.to_string());
+        script.answers.pop_front().ok_or_else(|| {
+            IoError::new(
+                IoErrorKind::UnexpectedEof,
+                format!("no scripted answer for {:?}", prompt),
+            )
+        })
+    }
+}
+
+impl PromptProvider for ScriptedPrompts {
+    fn read_line(&mut self) -> IoResult<String> {
+        self.answer("")
+    }
+
+    fn prompt_line(&mut self, prompt: &str) -> IoResult<String> {
+        self.answer(prompt)
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        self.answer("").map(SafeString::from_string)
+    }
+
+    fn prompt_password(&mut self, prompt: &str) -> IoResult<SafeString> {
+        self.answer(prompt).map(SafeString::from_string)
+    }
+}
+
+/// Refuses to answer any question, for programs that must not wait for the user, ie: in scripts
+pub struct NoPrompts {
+    reason: String,
+}
+
+impl NoPrompts {
+    /// Fails every question with `reason`
+    pub fn new(reason: impl ToString) -> NoPrompts {
+        NoPrompts {
+            reason: reason.to_string(),
+        }
+    }
+
+    fn refuse<T>(&self) -> IoResult<T> {
+        Err(IoError::other(self.reason.clone()))
+    }
+}
+
+impl PromptProvider for NoPrompts {
+    fn read_line(&mut self) -> IoResult<String> {
+        self.refuse()
+    }
+
+    fn prompt_line(&mut self, _prompt: &str) -> IoResult<String> {
+        self.refuse()
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        self.refuse()
+    }
+
+    fn prompt_password(&mut self, _prompt: &str) -> IoResult<SafeString> {
+        self.refuse()
+    }
+
+    fn prompt_command(
+        &mut self,
+        _prompt: &str,
+        _completer: &dyn Completer,
+        _history: &mut History,
+        _idle: Option<Duration>,
+    ) -> IoResult<String> {
+        self.refuse()
+    }
+}
+
 /// Struct that reads and writes data from the TTY, stdin and stdout
 pub struct RegularInputOutput<'a> {
     stdin_lock: StdinLock<'a>,
     stdout_lock: StdoutLock<'a>,
     stderr_lock: StderrLock<'a>,
+    prompts: Box<dyn PromptProvider + 'a>,
 }
 
 impl<'a> RegularInputOutput<'a> {
@@ -26,26 +270,45 @@ impl<'a> RegularInputOutput<'a> {
         stdin_lock: StdinLock<'b>,
         stdout_lock: StdoutLock<'b>,
         stderr_lock: StderrLock<'b>,
+    ) -> RegularInputOutput<'b> {
+        RegularInputOutput::with_prompts(stdin_lock, stdout_lock, stderr_lock, Box::new(TtyPrompts))
+    }
+
+    /// Like `new`, but asks questions with `prompts` instead of the TTY
+    pub fn with_prompts<'b>(
+        stdin_lock: StdinLock<'b>,
+        stdout_lock: StdoutLock<'b>,
+        stderr_lock: StderrLock<'b>,
+        prompts: Box<dyn PromptProvider + 'b>,
     ) -> RegularInputOutput<'b> {
         RegularInputOutput {
             stdin_lock,
             stdout_lock,
             stderr_lock,
+            prompts,
         }
     }
 }
 
 /// Struct similar to `RegularInputOutput` but that reads and writes from a cursor, useful for tests
-#[derive(Default)]
 pub struct CursorInputOutput {
     pub stdin_cursor: Cursor<Vec<u8>>,
     pub ttyin_cursor: Cursor<Vec<u8>>,
     pub stdout_cursor: Cursor<Vec<u8>>,
     pub stderr_cursor: Cursor<Vec<u8>>,
     pub ttyout_cursor: Cursor<Vec<u8>>,
+    /// Answers questions instead of `ttyin_cursor`, see `with_prompts`
+    pub prompts: Option<Box<dyn PromptProvider>>,
+}
+
+impl Default for CursorInputOutput {
+    fn default() -> CursorInputOutput {
+        CursorInputOutput::new("", "")
+    }
 }
 
 impl CursorInputOutput {
+    /// Reads answers from `ttyin` as if they were typed in the TTY
     pub fn new(stdin: &str, ttyin: &str) -> CursorInputOutput {
         CursorInputOutput {
             stdin_cursor: Cursor::new(stdin.as_bytes().to_owned()),
@@ -53,15 +316,89 @@ impl CursorInputOutput {
             stdout_cursor: Cursor::new(Vec::new()),
             stderr_cursor: Cursor::new(Vec::new()),
             ttyout_cursor: Cursor::new(Vec::new()),
+            prompts: None,
+        }
+    }
+
+    /// Like `new`, but answers questions with `prompts`, ie: `ScriptedPrompts`
+    pub fn with_prompts(stdin: &str, prompts: Box<dyn PromptProvider>) -> CursorInputOutput {
+        CursorInputOutput {
+            prompts: Some(prompts),
+            ..CursorInputOutput::new(stdin, "")
         }
     }
 }
 
+impl PromptProvider for CursorInputOutput {
+    fn read_line(&mut self) -> IoResult<String> {
+        Ok(read_reply_from_bufread(&mut self.ttyin_cursor)?)
+    }
+
+    fn prompt_line(&mut self, prompt: &str) -> IoResult<String> {
+        Ok(prompt_reply_from_bufread(
+            &mut self.ttyin_cursor,
+            &mut self.ttyout_cursor,
+            prompt,
+        )?)
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        Ok(SafeString::from_string(read_password_from_bufread(
+            &mut self.ttyin_cursor,
+        )?))
+    }
+
+    fn prompt_password(&mut self, prompt: &str) -> IoResult<SafeString> {
+        Ok(SafeString::from_string(prompt_password_from_bufread(
+            &mut self.ttyin_cursor,
+            &mut self.ttyout_cursor,
+            prompt,
+        )?))
+    }
+}
+
+/// Writes like another `CliInputOutput`, but asks questions with other prompts, ie: `NoPrompts`
+pub struct WithPrompts<'a, T: CliInputOutput> {
+    io: &'a mut T,
+    prompts: Box<dyn PromptProvider + 'a>,
+}
+
+impl<'a, T: CliInputOutput> WithPrompts<'a, T> {
+    pub fn new(io: &'a mut T, prompts: Box<dyn PromptProvider + 'a>) -> WithPrompts<'a, T> {
+        WithPrompts { io, prompts }
+    }
+}
+
 pub trait CliInputOutput {
-    fn read_line(&mut self) -> IoResult<String>;
-    fn prompt_line(&mut self, prompt: impl ToString) -> IoResult<String>;
-    fn read_password(&mut self) -> IoResult<SafeString>;
-    fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString>;
+    /// Returns what answers the questions asked to the user
+    fn prompts(&mut self) -> &mut dyn PromptProvider;
+
+    fn read_line(&mut self) -> IoResult<String> {
+        self.prompts().read_line()
+    }
+
+    fn prompt_line(&mut self, prompt: impl ToString) -> IoResult<String> {
+        self.prompts().prompt_line(&prompt.to_string())
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        self.prompts().read_password()
+    }
+
+    fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString> {
+        self.prompts().prompt_password(&prompt.to_string())
+    }
+
+    fn prompt_command(
+        &mut self,
+        prompt: impl ToString,
+        completer: &dyn Completer,
+        history: &mut History,
+        idle: Option<Duration>,
+    ) -> IoResult<String> {
+        self.prompts()
+            .prompt_command(&prompt.to_string(), completer, history, idle)
+    }
 
     fn nl(&mut self, output_type: OutputType);
     fn write(&mut self, s: impl ToString, output_type: OutputType);
@@ -102,36 +439,8 @@ pub trait CliInputOutput {
 }
 
 impl<'a> CliInputOutput for RegularInputOutput<'a> {
-    fn read_line(&mut self) -> IoResult<String> {
-        if !atty::is(atty::Stream::Stdin) {
-            panic!("Need a TTY to read password");
-        }
-
-        read_reply()
-    }
-
-    fn prompt_line(&mut self, prompt: impl ToString) -> IoResult<String> {
-        if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
-            panic!("Need a TTY to read password");
-        }
-
-        prompt_reply(prompt)
-    }
-
-    fn read_password(&mut self) -> IoResult<SafeString> {
-        if !atty::is(atty::Stream::Stdin) {
-            panic!("Need a TTY to read password");
-        }
-
-        Ok(SafeString::from_string(read_password()?))
-    }
-
-    fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString> {
-        if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
-            panic!("Need a TTY to read password");
-        }
-
-        Ok(SafeString::from_string(prompt_password(prompt)?))
+    fn prompts(&mut self) -> &mut dyn PromptProvider {
+        self.prompts.as_mut()
     }
 
     fn nl(&mut self, output_type: OutputType) {
@@ -185,26 +494,11 @@ impl<'a> CliInputOutput for RegularInputOutput<'a> {
 }
 
 impl CliInputOutput for CursorInputOutput {
-    fn read_line(&mut self) -> IoResult<String> {
-        read_reply_from_bufread(&mut self.ttyin_cursor)
-    }
-
-    fn prompt_line(&mut self, prompt: impl ToString) -> IoResult<String> {
-        prompt_reply_from_bufread(&mut self.ttyin_cursor, &mut self.ttyout_cursor, prompt)
-    }
-
-    fn read_password(&mut self) -> IoResult<SafeString> {
-        Ok(SafeString::from_string(read_password_from_bufread(
-            &mut self.ttyin_cursor,
-        )?))
-    }
-
-    fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString> {
-        Ok(SafeString::from_string(prompt_password_from_bufread(
-            &mut self.ttyin_cursor,
-            &mut self.ttyout_cursor,
-            prompt,
-        )?))
+    fn prompts(&mut self) -> &mut dyn PromptProvider {
+        if self.prompts.is_none() {
+            return self;
+        }
+        self.prompts.as_mut().unwrap().as_mut()
     }
 
     fn nl(&mut self, output_type: OutputType) {
@@ -260,3 +554,21 @@ impl CliInputOutput for CursorInputOutput {
         }
     }
 }
+
+impl<'a, T: CliInputOutput> CliInputOutput for WithPrompts<'a, T> {
+    fn prompts(&mut self) -> &mut dyn PromptProvider {
+        self.prompts.as_mut()
+    }
+
+    fn nl(&mut self, output_type: OutputType) {
+        self.io.nl(output_type)
+    }
+
+    fn write(&mut self, s: impl ToString, output_type: OutputType) {
+        self.io.write(s, output_type)
+    }
+
+    fn writeln(&mut self, s: impl ToString, output_type: OutputType) {
+        self.io.writeln(s, output_type)
+    }
+}
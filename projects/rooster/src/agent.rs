@@ -0,0 +1,435 @@
+//! Keeps the key derived from the master password in a background process, the agent, so that
+//! other Rooster commands can unlock the password file without asking for the master password
+//!
+//! The agent listens on a UNIX socket next to the password file, ie: `passwords.rooster.agent`.
+//! Only the user can use it: the socket is only readable and writable by them, and the agent
+//! checks that the processes that connect to it run as them too. The key is kept in memory that
+//! isn't swapped out to disk, and forgotten once the agent's time to live runs out.
+
+use crate::password::v2::KeyFile;
+use rtoolbox::safe_string::SafeString;
+use rtoolbox::safe_vec::SafeVec;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::path::{Path, PathBuf};
+
+/// Asks the agent for its secret
+const REQUEST_SECRET: u8 = 1;
+
+/// Asks the agent to forget its secret and stop
+const REQUEST_STOP: u8 = 2;
+
+/// What the agent holds: the key derived from the master password, and the key file it was
+/// derived with if any
+pub struct Secret {
+    pub key: SafeVec,
+    pub key_file: Option<KeyFile>,
+}
+
+/// The socket of the agent, next to the password file
+pub fn socket_path(rooster_file_path: &Path) -> PathBuf {
+    let mut path = rooster_file_path.as_os_str().to_owned();
+    path.push(".agent");
+    PathBuf::from(path)
+}
+
+impl Secret {
+    /// Returns the length of the key, the key, then the key file digest if any
+    pub fn to_bytes(&self) -> SafeVec {
+        let mut bytes = SafeVec::new(vec![self.key.len() as u8]);
+        bytes.inner_mut().extend_from_slice(&self.key);
+        if let Some(key_file) = &self.key_file {
+            bytes.inner_mut().extend_from_slice(key_file.digest());
+        }
+        bytes
+    }
+
+    /// Reads a secret returned by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> IoResult<Secret> {
+        let invalid = || IoError::new(IoErrorKind::InvalidData, "the agent sent an invalid key");
+        let (key_len, rest) = bytes.split_first().ok_or_else(invalid)?;
+        if rest.len() < *key_len as usize {
+            return Err(invalid());
+        }
+        let (key, digest) = rest.split_at(*key_len as usize);
+        let key_file = match digest.is_empty() {
+            true => None,
+            false => Some(KeyFile::from_digest(digest).ok_or_else(invalid)?),
+        };
+        Ok(Secret {
+            key: SafeVec::new(key.to_vec()),
+            key_file,
+        })
+    }
+
+    /// Returns the bytes of the secret in hexadecimal, to pass them to another process as text
+    pub fn to_hex(&self) -> SafeString {
+        let mut hex = String::new();
+        for byte in self.to_bytes().iter() {
+            hex.push_str(&format!("{:02x}", byte));
+        }
+        SafeString::from_string(hex)
+    }
+
+    /// Reads a secret returned by `to_hex`
+    pub fn from_hex(hex: &str) -> IoResult<Secret> {
+        let invalid = || IoError::new(IoErrorKind::InvalidData, "invalid key");
+        let mut bytes = SafeVec::new(Vec::with_capacity(hex.len() / 2));
+        for pair in hex.as_bytes().chunks(2) {
+            let byte = std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(invalid)?;
+            bytes.inner_mut().push(byte);
+        }
+        Secret::from_bytes(&bytes)
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::{socket_path, Secret, REQUEST_SECRET, REQUEST_STOP};
+    use rtoolbox::safe_vec::SafeVec;
+    use rtoolbox::secure_memory::{self, LockedMemory};
+    use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Write};
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    /// How long the agent and the commands wait for each other once connected
+    const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// A listening agent, whose socket is removed when it's dropped
+    pub struct Agent {
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl Drop for Agent {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// A directory only the user can enter, removed with what's in it when it's dropped
+    struct PrivateDir(PathBuf);
+
+    impl PrivateDir {
+        /// Creates the directory next to `path`, since files can only be linked on the same disk
+        fn next_to(path: &Path) -> IoResult<PrivateDir> {
+            let dir = path.with_file_name(format!(".rooster-agent-{}", std::process::id()));
+            // Left behind by a process that was killed and had the same id
+            match std::fs::remove_dir_all(&dir) {
+                Err(err) if err.kind() != IoErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+            std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+            Ok(PrivateDir(dir))
+        }
+    }
+
+    impl Drop for PrivateDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Returns the user the process runs as
+    fn current_uid() -> libc::uid_t {
+        unsafe { libc::geteuid() }
+    }
+
+    /// Returns true if the file belongs to the user, and other users can't read or write it
+    fn is_private(metadata: &std::fs::Metadata) -> bool {
+        metadata.uid() == current_uid() && metadata.mode() & 0o077 == 0
+    }
+
+    /// Returns the user the process at the other end of the socket runs as
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn peer_uid(stream: &UnixStream) -> IoResult<libc::uid_t> {
+        let mut credentials: libc::ucred = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut credentials as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if result != 0 {
+            return Err(IoError::last_os_error());
+        }
+        Ok(credentials.uid)
+    }
+
+    /// Returns the user the process at the other end of the socket runs as
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn peer_uid(stream: &UnixStream) -> IoResult<libc::uid_t> {
+        let mut uid: libc::uid_t = 0;
+        let mut gid: libc::gid_t = 0;
+        if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+            return Err(IoError::last_os_error());
+        }
+        Ok(uid)
+    }
+
+    /// Waits up to `timeout` for a command to connect, returning false if none did
+    fn wait_for_connection(listener: &UnixListener, timeout: Duration) -> IoResult<bool> {
+        let mut pollfd = libc::pollfd {
+            fd: listener.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            -1 => {
+                let err = IoError::last_os_error();
+                match err.kind() {
+                    IoErrorKind::Interrupted => Ok(true),
+                    _ => Err(err),
+                }
+            }
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    /// Listens on the socket, unless another agent already does
+    pub fn bind(rooster_file_path: &Path) -> IoResult<Agent> {
+        let path = socket_path(rooster_file_path);
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(IoError::new(
+                    IoErrorKind::AlreadyExists,
+                    "an agent is already running",
+                ));
+            }
+            // Left behind by an agent that was killed
+            std::fs::remove_file(&path)?;
+        }
+
+        // Nobody else can connect to the socket while it's in the directory, so it's only linked
+        // in place once it's private
+        let dir = PrivateDir::next_to(&path)?;
+        let private_path = dir.0.join("socket");
+        let listener = UnixListener::bind(&private_path)?;
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))?;
+        if !is_private(&std::fs::metadata(&private_path)?) {
+            return Err(IoError::new(
+                IoErrorKind::PermissionDenied,
+                format!("{} can be used by other users", private_path.display()),
+            ));
+        }
+        std::fs::hard_link(&private_path, &path)?;
+
+        Ok(Agent { listener, path })
+    }
+
+    impl Agent {
+        /// Gives the secret to the commands that ask for it, until `ttl` runs out or a command
+        /// asks the agent to stop
+        pub fn serve(self, secret: Secret, ttl: Duration) -> IoResult<()> {
+            // As far as the OS allows, the agent keeps going otherwise
+            let _ = secure_memory::disable_core_dumps();
+            let _key = LockedMemory::new(&secret.key).ok();
+            let _key_file = secret
+                .key_file
+                .as_ref()
+                .and_then(|key_file| LockedMemory::new(key_file.digest()).ok());
+            let deadline = Instant::now() + ttl;
+
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() || !wait_for_connection(&self.listener, remaining)? {
+                    return Ok(());
+                }
+                let mut stream = match self.listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(_) => continue,
+                };
+                if !matches!(peer_uid(&stream), Ok(uid) if uid == current_uid()) {
+                    continue;
+                }
+
+                let mut request = [0u8; 1];
+                let read = stream
+                    .set_read_timeout(Some(IO_TIMEOUT))
+                    .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+                    .and_then(|_| stream.read_exact(&mut request));
+                match (read, request[0]) {
+                    (Ok(()), REQUEST_SECRET) => {
+                        let _ = stream.write_all(&secret.to_bytes());
+                    }
+                    (Ok(()), REQUEST_STOP) => {
+                        let _ = stream.write_all(&[REQUEST_STOP]);
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Connects to the agent, if one is running and only the user can talk to it
+    fn connect(rooster_file_path: &Path) -> IoResult<Option<UnixStream>> {
+        let path = socket_path(rooster_file_path);
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == IoErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        if !is_private(&metadata) {
+            return Err(IoError::new(
+                IoErrorKind::PermissionDenied,
+                format!("{} can be used by other users", path.display()),
+            ));
+        }
+
+        let stream = match UnixStream::connect(&path) {
+            Ok(stream) => stream,
+            // Left behind by an agent that was killed
+            Err(err) if err.kind() == IoErrorKind::ConnectionRefused => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        Ok(Some(stream))
+    }
+
+    /// Reads the secret written in hexadecimal on the standard input, ie: by `rooster agent start`
+    ///
+    /// The standard input is read directly, because `main` holds the lock of `std::io::stdin`.
+    pub fn read_secret_from_stdin() -> IoResult<Secret> {
+        use std::os::unix::io::FromRawFd;
+        let mut stdin =
+            std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(libc::STDIN_FILENO) });
+        let mut hex = SafeVec::new(Vec::new());
+        stdin.read_to_end(hex.inner_mut())?;
+        let hex =
+            std::str::from_utf8(&hex).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?;
+        Secret::from_hex(hex.trim_end())
+    }
+
+    /// Returns the secret of the agent, if one is running
+    pub fn request_secret(rooster_file_path: &Path) -> IoResult<Option<Secret>> {
+        let mut stream = match connect(rooster_file_path)? {
+            Some(stream) => stream,
+            None => return Ok(None),
+        };
+        stream.write_all(&[REQUEST_SECRET])?;
+        let mut bytes = SafeVec::new(Vec::new());
+        stream.read_to_end(bytes.inner_mut())?;
+        Secret::from_bytes(&bytes).map(Some)
+    }
+
+    /// Stops the agent, and returns whether one was running
+    pub fn stop(rooster_file_path: &Path) -> IoResult<bool> {
+        let mut stream = match connect(rooster_file_path)? {
+            Some(stream) => stream,
+            None => return Ok(false),
+        };
+        stream.write_all(&[REQUEST_STOP])?;
+        stream.read_exact(&mut [0u8; 1])?;
+        Ok(true)
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use super::Secret;
+    use std::io::{Error as IoError, Result as IoResult};
+    use std::path::Path;
+    use std::time::Duration;
+
+    pub struct Agent;
+
+    fn unsupported() -> IoError {
+        IoError::other("the agent is only supported on Linux, BSD and OSX")
+    }
+
+    pub fn bind(_rooster_file_path: &Path) -> IoResult<Agent> {
+        Err(unsupported())
+    }
+
+    impl Agent {
+        pub fn serve(self, _secret: Secret, _ttl: Duration) -> IoResult<()> {
+            Err(unsupported())
+        }
+    }
+
+    pub fn read_secret_from_stdin() -> IoResult<Secret> {
+        Err(unsupported())
+    }
+
+    pub fn request_secret(_rooster_file_path: &Path) -> IoResult<Option<Secret>> {
+        Ok(None)
+    }
+
+    pub fn stop(_rooster_file_path: &Path) -> IoResult<bool> {
+        Err(unsupported())
+    }
+}
+
+pub use platform::{bind, read_secret_from_stdin, request_secret, stop};
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::{bind, request_secret, stop, Secret};
+    use crate::password::v2::KeyFile;
+    use rtoolbox::safe_vec::SafeVec;
+    use std::ops::Deref;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+
+    #[test]
+    fn test_agent() {
+        let dir = tempfile::tempdir().unwrap();
+        let rooster_file = dir.path().join("passwords.rooster");
+        assert!(request_secret(&rooster_file).unwrap().is_none());
+        assert!(!stop(&rooster_file).unwrap());
+
+        let agent = bind(&rooster_file).unwrap();
+        assert!(bind(&rooster_file).is_err());
+        // Only the socket is left, without permissions for others
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        let mode = std::fs::metadata(super::socket_path(&rooster_file))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let key_file = KeyFile::new(b"key file");
+        let secret = Secret {
+            key: SafeVec::new(vec![7u8; 32]),
+            key_file: Some(key_file.clone()),
+        };
+        // The secret is passed to the agent process as text
+        let secret = Secret::from_hex(&secret.to_hex()).unwrap();
+        assert!(Secret::from_hex("0").is_err());
+        let server = std::thread::spawn(move || agent.serve(secret, Duration::from_secs(60)));
+
+        let secret = request_secret(&rooster_file).unwrap().unwrap();
+        assert_eq!(secret.key.deref(), [7u8; 32]);
+        assert_eq!(secret.key_file, Some(key_file));
+
+        assert!(stop(&rooster_file).unwrap());
+        server.join().unwrap().unwrap();
+        assert!(request_secret(&rooster_file).unwrap().is_none());
+
+        // The time to live runs out
+        let agent = bind(&rooster_file).unwrap();
+        let secret = Secret {
+            key: SafeVec::new(vec![7u8; 32]),
+            key_file: None,
+        };
+        agent.serve(secret, Duration::from_millis(10)).unwrap();
+        assert!(!socket_exists(&rooster_file));
+    }
+
+    fn socket_exists(rooster_file: &std::path::Path) -> bool {
+        super::socket_path(rooster_file).exists()
+    }
+}
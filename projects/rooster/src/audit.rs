@@ -0,0 +1,161 @@
+// Password health checks, as run by `rooster audit`.
+
+use crate::ffi;
+use crate::password::v2::Password;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+/// Passwords shorter than this are weak, however random they look
+const MIN_LENGTH: usize = 12;
+
+/// Passwords with less entropy than this are weak, ie: 12 lowercase letters have about 56 bits
+const MIN_ENTROPY_BITS: f64 = 60.0;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct Report {
+    /// Groups of apps that share the same password
+    pub reused: Vec<Vec<String>>,
+    pub weak: Vec<WeakPassword>,
+    pub stale: Vec<StalePassword>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct WeakPassword {
+    pub app: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StalePassword {
+    pub app: String,
+    pub age_days: u64,
+}
+
+impl Report {
+    pub fn has_problems(&self) -> bool {
+        !self.reused.is_empty() || !self.weak.is_empty() || !self.stale.is_empty()
+    }
+}
+
+/// Checks passwords for reuse, weakness and age, where passwords older than `max_age_days` are
+/// stale, unless it is 0
+pub fn audit(passwords: &[&Password], max_age_days: u64, now: ffi::time_t) -> Report {
+    let mut report = Report::default();
+
+    let mut apps_by_password: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for p in passwords {
+        apps_by_password
+            .entry(p.password.deref())
+            .or_default()
+            .push(p.name.clone());
+    }
+    report.reused = apps_by_password
+        .into_values()
+        .filter(|apps| apps.len() > 1)
+        .collect();
+
+    for p in passwords {
+        if let Some(reason) = weakness(p.password.deref()) {
+            report.weak.push(WeakPassword {
+                app: p.name.clone(),
+                reason,
+            });
+        }
+
+        let age_days = now.saturating_sub(p.updated_at) as u64 / SECONDS_PER_DAY;
+        if max_age_days > 0 && age_days >= max_age_days {
+            report.stale.push(StalePassword {
+                app: p.name.clone(),
+                age_days,
+            });
+        }
+    }
+
+    report
+}
+
+/// Returns why a password is weak, if it is
+///
+/// The entropy is estimated as if each character was picked at random among the kinds of
+/// characters the password uses, which is generous for passwords made of words.
+fn weakness(password: &str) -> Option<String> {
+    let length = password.chars().count();
+    if length < MIN_LENGTH {
+        return Some(format!("shorter than {} characters", MIN_LENGTH));
+    }
+
+    if password.chars().all(|c| password.starts_with(c)) {
+        return Some("the same character repeated".to_string());
+    }
+
+    let mut pool_size = 0;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        pool_size += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        pool_size += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        pool_size += 10;
+    }
+    if password
+        .chars()
+        .any(|c| c.is_ascii_punctuation() || c == ' ')
+    {
+        pool_size += 33;
+    }
+    if !password.is_ascii() {
+        pool_size += 100;
+    }
+
+    let entropy_bits = length as f64 * (pool_size as f64).log2();
+    if entropy_bits < MIN_ENTROPY_BITS {
+        return Some(format!(
+            "easy to guess (about {} bits of entropy)",
+            entropy_bits.round()
+        ));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::{audit, weakness, SECONDS_PER_DAY};
+    use crate::password::v2::Password;
+
+    #[test]
+    fn test_weakness() {
+        assert!(weakness("hunter2").unwrap().contains("shorter"));
+        assert!(weakness("aaaaaaaaaaaaaaaa").unwrap().contains("repeated"));
+        assert!(weakness("abcdefghijkl").unwrap().contains("56 bits"));
+        assert_eq!(weakness("correct horse battery staple"), None);
+        assert_eq!(weakness("Tr0ub4dor&3xyz"), None);
+    }
+
+    #[test]
+    fn test_audit() {
+        let now = 1000 * SECONDS_PER_DAY as u32;
+        let mut old = Password::new("Old", "me", "ZpL2#vQ9!xT4@mW8");
+        old.updated_at = now - 400 * SECONDS_PER_DAY as u32;
+        let mut first = Password::new("First", "me", "sh0rt");
+        first.updated_at = now;
+        let mut second = first.clone();
+        second.name = "Second".to_string();
+
+        let report = audit(&[&old, &first, &second], 365, now);
+        assert!(report.has_problems());
+        assert_eq!(report.reused, vec![vec!["First", "Second"]]);
+        assert_eq!(report.weak.len(), 2);
+        assert_eq!(report.stale.len(), 1);
+        assert_eq!(
+            (report.stale[0].app.as_str(), report.stale[0].age_days),
+            ("Old", 400)
+        );
+
+        assert!(audit(&[&old], 0, now) == Default::default());
+    }
+}
@@ -0,0 +1,257 @@
+//! Encrypted snapshots of the password file in a secondary location, ie: an external drive or a
+//! directory synced elsewhere, so that losing the main disk doesn't lose the passwords
+//!
+//! Snapshots are plain copies of the password file, which is already encrypted, named after it
+//! and the time they were made: `passwords.rooster.20240102T030405Z.snapshot`.
+//!
+//! Backups are copies too, made automatically before each command that changes the password file,
+//! in a directory next to it. Only the latest ones are kept, and their names have milliseconds,
+//! ie: `passwords.rooster.20240102T030405.678Z.backup`, since commands can follow each other quickly.
+
+use crate::ffi;
+use std::fs::{self, File};
+use std::io::Result as IoResult;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+const BACKUP_EXTENSION: &str = "backup";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Copies the password file to a new snapshot in `dir`, and returns the path of the snapshot
+pub fn write_snapshot(rooster_file_path: &Path, dir: &Path, now: ffi::time_t) -> IoResult<PathBuf> {
+    let name = format!(
+        "{}.{}.{}",
+        file_name(rooster_file_path),
+        format_utc_time(now),
+        SNAPSHOT_EXTENSION
+    );
+    write_copy(rooster_file_path, &dir.join(name))
+}
+
+/// Returns the snapshots of the password file in `dir`, oldest first
+pub fn list_snapshots(rooster_file_path: &Path, dir: &Path) -> IoResult<Vec<PathBuf>> {
+    list_copies(rooster_file_path, dir, SNAPSHOT_EXTENSION)
+}
+
+/// The directory where backups of the password file are made, next to it
+pub fn backups_dir(rooster_file_path: &Path) -> PathBuf {
+    let mut path = rooster_file_path.as_os_str().to_owned();
+    path.push(".backups");
+    PathBuf::from(path)
+}
+
+/// Copies the password file to a new backup, and removes the oldest backups so that only `keep`
+/// are left
+pub fn write_backup(rooster_file_path: &Path, keep: usize) -> IoResult<PathBuf> {
+    let dir = backups_dir(rooster_file_path);
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    let time = format_utc_time(now.as_secs() as ffi::time_t);
+    let name = format!(
+        "{}.{}.{:03}Z.{}",
+        file_name(rooster_file_path),
+        time.trim_end_matches('Z'),
+        now.subsec_millis(),
+        BACKUP_EXTENSION
+    );
+    let backup = write_copy(rooster_file_path, &dir.join(name))?;
+
+    let backups = list_backups(rooster_file_path)?;
+    for old in backups.iter().take(backups.len().saturating_sub(keep)) {
+        fs::remove_file(old)?;
+    }
+    Ok(backup)
+}
+
+/// Returns the backups of the password file, oldest first
+pub fn list_backups(rooster_file_path: &Path) -> IoResult<Vec<PathBuf>> {
+    match list_copies(
+        rooster_file_path,
+        &backups_dir(rooster_file_path),
+        BACKUP_EXTENSION,
+    ) {
+        // The directory is made with the first backup
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        result => result,
+    }
+}
+
+/// Copies a file to `copy`, making its directory if needed
+pub fn write_copy(source: &Path, copy: &Path) -> IoResult<PathBuf> {
+    if let Some(dir) = copy.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Copying to a temporary file first means an interrupted copy never looks like a finished one
+    let temporary = copy.with_extension("tmp");
+    fs::copy(source, &temporary)?;
+    File::open(&temporary)?.sync_all()?;
+    fs::rename(&temporary, copy)?;
+    Ok(copy.to_path_buf())
+}
+
+/// Returns the copies of the password file in `dir` with this extension, oldest first
+fn list_copies(rooster_file_path: &Path, dir: &Path, extension: &str) -> IoResult<Vec<PathBuf>> {
+    let prefix = format!("{}.", file_name(rooster_file_path));
+    let mut copies = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_copy = path.extension() == Some(extension.as_ref())
+            && path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .starts_with(&prefix);
+        if is_copy {
+            copies.push(path);
+        }
+    }
+    // Timestamps in names sort in chronological order
+    copies.sort();
+    Ok(copies)
+}
+
+/// Writes a snapshot if the latest one is older than `interval_days`, which is 0 when snapshots are
+/// only made with `rooster backup now`
+pub fn write_snapshot_if_due(
+    rooster_file_path: &Path,
+    dir: &Path,
+    interval_days: u64,
+) -> IoResult<Option<PathBuf>> {
+    if interval_days == 0 {
+        return Ok(None);
+    }
+
+    let latest = match list_snapshots(rooster_file_path, dir) {
+        Ok(snapshots) => snapshots.last().cloned(),
+        // The directory is made with the first snapshot
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    if let Some(latest) = latest {
+        let age = fs::metadata(latest)?
+            .modified()?
+            .elapsed()
+            .unwrap_or(Duration::ZERO);
+        if age < Duration::from_secs(interval_days * SECONDS_PER_DAY) {
+            return Ok(None);
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|now| now.as_secs() as ffi::time_t)
+        .unwrap_or_else(|_| ffi::time());
+    write_snapshot(rooster_file_path, dir, now).map(Some)
+}
+
+fn file_name(rooster_file_path: &Path) -> String {
+    rooster_file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "rooster".to_string())
+}
+
+/// Formats a Unix time like `20240102T030405Z`, the compact form of ISO 8601 in UTC
+pub fn format_utc_time(time: ffi::time_t) -> String {
+    let time = time as u64;
+    let (days, seconds) = (time / SECONDS_PER_DAY, time % SECONDS_PER_DAY);
+
+    // Converts days since 1970-01-01 to a date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        format_utc_time, list_backups, list_snapshots, write_backup, write_snapshot,
+        write_snapshot_if_due,
+    };
+
+    #[test]
+    fn test_format_utc_time() {
+        assert_eq!(format_utc_time(0), "19700101T000000Z");
+        assert_eq!(format_utc_time(951782400), "20000229T000000Z");
+        assert_eq!(format_utc_time(1614834367), "20210304T050607Z");
+    }
+
+    #[test]
+    fn test_write_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let rooster_file = dir.path().join("passwords.rooster");
+        std::fs::write(&rooster_file, b"encrypted").unwrap();
+        let snapshots_dir = dir.path().join("snapshots");
+
+        let first = write_snapshot_if_due(&rooster_file, &snapshots_dir, 7)
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read(&first).unwrap(), b"encrypted");
+        assert!(write_snapshot_if_due(&rooster_file, &snapshots_dir, 7)
+            .unwrap()
+            .is_none());
+        assert!(write_snapshot_if_due(&rooster_file, &snapshots_dir, 0)
+            .unwrap()
+            .is_none());
+
+        let older = write_snapshot(&rooster_file, &snapshots_dir, 0).unwrap();
+        assert!(older.ends_with("passwords.rooster.19700101T000000Z.snapshot"));
+        std::fs::write(
+            snapshots_dir.join("other.rooster.19700101T000000Z.snapshot"),
+            b"",
+        )
+        .unwrap();
+        assert_eq!(
+            list_snapshots(&rooster_file, &snapshots_dir).unwrap(),
+            vec![older, first]
+        );
+    }
+
+    #[test]
+    fn test_write_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let rooster_file = dir.path().join("passwords.rooster");
+        assert!(list_backups(&rooster_file).unwrap().is_empty());
+
+        let mut backups = vec![];
+        for i in 0..4u8 {
+            std::fs::write(&rooster_file, [i]).unwrap();
+            backups.push(write_backup(&rooster_file, 3).unwrap());
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert!(backups[0]
+            .parent()
+            .unwrap()
+            .ends_with("passwords.rooster.backups"));
+
+        // The oldest backup was removed
+        assert_eq!(list_backups(&rooster_file).unwrap(), backups[1..]);
+        assert_eq!(std::fs::read(&backups[3]).unwrap(), [3]);
+    }
+}
@@ -2,7 +2,10 @@ use crate::password;
 use rclio::{CliInputOutput, OutputType};
 use rtoolbox::safe_string::SafeString;
 
+use std::io::Result as IoResult;
 use std::ops::Deref;
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
 // On Windows and Mac, we'll use the native solutions provided by the OS libraries
 #[cfg(any(windows, target_os = "macos"))]
@@ -15,6 +18,17 @@ pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
     Ok(())
 }
 
+#[cfg(any(windows, target_os = "macos"))]
+pub fn paste_from_clipboard() -> Result<SafeString, ()> {
+    use clipboard::ClipboardContext;
+    use clipboard::ClipboardProvider;
+
+    let mut context: ClipboardContext = ClipboardProvider::new().map_err(|_| ())?;
+    Ok(SafeString::from_string(
+        context.get_contents().map_err(|_| ())?,
+    ))
+}
+
 // On UNIX, the most stable way to copy to the clipboard is using one of the existing
 // and battle tested tools: xsel and xclip.
 #[cfg(all(unix, not(target_os = "macos")))]
@@ -103,6 +117,116 @@ pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
     }
 }
 
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn paste_from_clipboard() -> Result<SafeString, ()> {
+    use crate::quale::which;
+    use std::env;
+
+    let wayland = match env::var_os("XDG_SESSION_TYPE") {
+        Some(s) => s == "wayland",
+        None => false,
+    };
+    let commands = [
+        ("wl-paste", vec!["--no-newline"]),
+        ("xsel", vec!["-ob"]),
+        ("xclip", vec!["-o", "-selection", "clipboard"]),
+    ];
+    for (program, args) in commands.iter().skip(if wayland { 0 } else { 1 }) {
+        if let Some(path) = which(program) {
+            let output = Command::new(path)
+                .args(args)
+                .stderr(Stdio::null())
+                .output()
+                .map_err(|_| ())?;
+            if output.status.success() {
+                return String::from_utf8(output.stdout)
+                    .map(SafeString::from_string)
+                    .map_err(|_| ());
+            }
+        }
+    }
+    Err(())
+}
+
+/// Clears the clipboard after `delay`, from a background process so that it happens even once
+/// Rooster has exited
+///
+/// The process runs `rooster clear-clipboard`, which only clears the clipboard if it still holds
+/// what was there when it started, so that something copied in the meantime is kept.
+pub fn schedule_clipboard_clear(delay: Duration) -> IoResult<()> {
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .args(["clear-clipboard", delay.as_secs().to_string().as_str()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Detaches the process from the terminal, which would otherwise stop it when closed
+    #[cfg(unix)]
+    unsafe {
+        use std::os::unix::process::CommandExt;
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x00000008;
+        command.creation_flags(DETACHED_PROCESS);
+    }
+
+    command.spawn().map(|_| ())
+}
+
+/// Clears the clipboard once `delay` has passed, if it still holds the same thing
+pub fn clear_clipboard_after(delay: Duration) -> Result<(), ()> {
+    let copied = paste_from_clipboard()?;
+    std::thread::sleep(delay);
+    if paste_from_clipboard()? == copied {
+        copy_to_clipboard(&SafeString::new())?;
+    }
+    Ok(())
+}
+
+/// Returns how long copied passwords stay on the clipboard, or `None` if they stay there
+pub fn clear_after(matches: &clap::ArgMatches) -> Option<Duration> {
+    match matches.get_one::<u64>("clear-clipboard-after") {
+        Some(0) | None => None,
+        Some(seconds) => Some(Duration::from_secs(*seconds)),
+    }
+}
+
+/// Copies a password to the clipboard, and has it cleared after `clear_after`
+///
+/// Returns a note for the user about when the clipboard is cleared, which is empty if it isn't.
+pub fn copy_to_clipboard_and_clear(
+    s: &SafeString,
+    clear_after: Option<Duration>,
+    io: &mut impl CliInputOutput,
+) -> Result<String, ()> {
+    copy_to_clipboard(s)?;
+
+    let delay = match clear_after {
+        Some(delay) => delay,
+        None => return Ok(String::new()),
+    };
+    match schedule_clipboard_clear(delay) {
+        Ok(()) => Ok(format!(", cleared in {} seconds", delay.as_secs())),
+        Err(err) => {
+            io.warning(
+                format!(
+                    "Hmm, I couldn't schedule clearing the clipboard (reason: {}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Ok(String::new())
+        }
+    }
+}
+
 #[cfg(target_os = "macos")]
 pub fn paste_keys() -> &'static str {
     "Cmd+V"
@@ -116,6 +240,7 @@ pub fn paste_keys() -> &'static str {
 pub fn confirm_password_retrieved(
     show: bool,
     password: &password::v2::Password,
+    clear_after: Option<Duration>,
     io: &mut impl CliInputOutput,
 ) {
     if show {
@@ -132,7 +257,8 @@ pub fn confirm_password_retrieved(
             OutputType::Standard,
         );
     } else {
-        if copy_to_clipboard(&password.password).is_err() {
+        let clear_note = copy_to_clipboard_and_clear(&password.password, clear_after, io);
+        if let Err(()) = clear_note {
             io.success(
                 format!(
                     "Hmm, I tried to copy your new password to your clipboard, but \
@@ -152,8 +278,9 @@ pub fn confirm_password_retrieved(
             );
             io.success(
                 format!(
-                    "Password: ******** (copied to clipboard, paste with {})",
-                    paste_keys()
+                    "Password: ******** (copied to clipboard, paste with {}{})",
+                    paste_keys(),
+                    clear_note.unwrap_or_default()
                 ),
                 OutputType::Standard,
             );
@@ -1,4 +1,4 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
+use crate::clip::{self, copy_to_clipboard_and_clear, paste_keys};
 use crate::password;
 use rclio::CliInputOutput;
 use rclio::OutputType;
@@ -38,7 +38,12 @@ pub fn callback_exec(
                         return Ok(());
                     }
 
-                    if copy_to_clipboard(&password_as_string_clipboard).is_err() {
+                    let copied = copy_to_clipboard_and_clear(
+                        &password_as_string_clipboard,
+                        clip::clear_after(matches),
+                        io,
+                    );
+                    if let Err(()) = copied {
                         io.success(
                             format!(
                                 "Hmm, I tried to copy your new password to your clipboard, \
@@ -52,8 +57,9 @@ pub fn callback_exec(
                         io.success(
                             format!(
                                 "Alright! I've saved your new password. You can paste it \
-                                 anywhere with {}.",
-                                paste_keys()
+                                 anywhere with {}{}.",
+                                paste_keys(),
+                                copied.unwrap_or_default()
                             ),
                             OutputType::Standard,
                         );
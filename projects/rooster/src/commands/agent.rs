@@ -0,0 +1,151 @@
+use crate::agent::{self, Secret};
+use crate::password;
+use rclio::{CliInputOutput, OutputType};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// What the agent process prints once it listens, or else why it couldn't
+const READY: &str = "ok";
+
+fn ttl(matches: &clap::ArgMatches) -> Duration {
+    Duration::from_secs(*matches.get_one::<u64>("ttl").unwrap())
+}
+
+/// Starts the agent in another process, which reads the secret on its standard input, and returns
+/// once it listens
+fn spawn(secret: &Secret, ttl: Duration, rooster_file_path: &Path) -> Result<(), String> {
+    let mut command = Command::new(std::env::current_exe().map_err(|err| err.to_string())?);
+    command
+        .args([
+            "agent",
+            "serve",
+            "--ttl",
+            ttl.as_secs().to_string().as_str(),
+        ])
+        .env("ROOSTER_FILE", rooster_file_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+
+    // Detaches the process from the terminal, which would otherwise stop it when closed
+    #[cfg(unix)]
+    unsafe {
+        use std::os::unix::process::CommandExt;
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn().map_err(|err| err.to_string())?;
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "{}", secret.to_hex().as_str()).map_err(|err| err.to_string())?;
+    drop(stdin);
+
+    let mut reply = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut reply)
+        .map_err(|err| err.to_string())?;
+    match reply.trim_end() {
+        READY => Ok(()),
+        "" => Err(String::from("the agent stopped before it was ready")),
+        reason => Err(reason.to_string()),
+    }
+}
+
+/// Keeps the key of the unlocked store in an agent, in this process with `--foreground`, or else
+/// in the background
+pub fn callback_exec_start(
+    matches: &clap::ArgMatches,
+    store: &password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let secret = Secret {
+        key: store.derived_key().clone(),
+        key_file: store.key_file().cloned(),
+    };
+    let ttl = ttl(matches);
+
+    let result = match matches.get_flag("foreground") {
+        true => agent::bind(rooster_file_path)
+            .map_err(|err| err.to_string())
+            .inspect(|_| {
+                io.success(
+                    format!(
+                        "The agent is running, until it is stopped or for {} seconds.",
+                        ttl.as_secs()
+                    ),
+                    OutputType::Standard,
+                );
+            })
+            .and_then(|agent| agent.serve(secret, ttl).map_err(|err| err.to_string())),
+        false => spawn(&secret, ttl, rooster_file_path).map(|_| {
+            io.success(
+                format!(
+                    "The agent is running, until `rooster agent stop` or for {} seconds.",
+                    ttl.as_secs()
+                ),
+                OutputType::Standard,
+            );
+            io.info(
+                "Commands that only read your passwords, ie: `rooster get`, won't ask for your master password until then.",
+                OutputType::Standard,
+            );
+        }),
+    };
+
+    result.map_err(|err| {
+        io.error(
+            format!("Woops, I could not start the agent (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+/// Runs the agent started by `callback_exec_start` in the background, until its time to live
+/// runs out
+pub fn callback_exec_serve(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let secret = agent::read_secret_from_stdin();
+    let agent = secret.and_then(|secret| Ok((agent::bind(rooster_file_path)?, secret)));
+    let (agent, secret) = match agent {
+        Ok(agent) => agent,
+        Err(err) => {
+            io.writeln(err, OutputType::Standard);
+            return Err(1);
+        }
+    };
+
+    io.writeln(READY, OutputType::Standard);
+    agent.serve(secret, ttl(matches)).map_err(|_| 1)
+}
+
+pub fn callback_exec_stop(
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    match agent::stop(rooster_file_path) {
+        Ok(true) => {
+            io.success("The agent has been stopped.", OutputType::Standard);
+            Ok(())
+        }
+        Ok(false) => {
+            io.info("The agent is not running.", OutputType::Standard);
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I could not stop the agent (reason: {}).", err),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::audit::{self, Report};
+use crate::ffi;
+use crate::output;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let max_age_days = *matches.get_one::<u64>("max-age").unwrap();
+    let report = audit::audit(&store.get_all_passwords(), max_age_days, ffi::time());
+
+    if matches.get_flag("json") || output::is_json(matches) {
+        output::print_json(&report, io)?;
+    } else {
+        print_report(&report, io);
+    }
+
+    // Scripts can tell whether there is anything to fix from the exit code
+    match report.has_problems() {
+        true => Err(1),
+        false => Ok(()),
+    }
+}
+
+fn print_report(report: &Report, io: &mut impl CliInputOutput) {
+    if !report.has_problems() {
+        io.success(
+            "All good, I found no problems with your passwords.",
+            OutputType::Standard,
+        );
+        return;
+    }
+
+    if !report.reused.is_empty() {
+        io.title("Reused passwords", OutputType::Standard);
+        for apps in report.reused.iter() {
+            io.warning(
+                format!("The same password is used for: {}", apps.join(", ")),
+                OutputType::Standard,
+            );
+        }
+        io.nl(OutputType::Standard);
+    }
+
+    if !report.weak.is_empty() {
+        io.title("Weak passwords", OutputType::Standard);
+        for weak in report.weak.iter() {
+            io.warning(
+                format!("{}: {}", weak.app, weak.reason),
+                OutputType::Standard,
+            );
+        }
+        io.nl(OutputType::Standard);
+    }
+
+    if !report.stale.is_empty() {
+        io.title("Stale passwords", OutputType::Standard);
+        for stale in report.stale.iter() {
+            io.warning(
+                format!("{}: last changed {} days ago", stale.app, stale.age_days),
+                OutputType::Standard,
+            );
+        }
+        io.nl(OutputType::Standard);
+    }
+
+    io.info(
+        "Change them with `rooster change` or `rooster regenerate`.",
+        OutputType::Standard,
+    );
+}
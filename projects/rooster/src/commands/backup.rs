@@ -0,0 +1,129 @@
+use crate::backup;
+use crate::config::Config;
+use crate::ffi;
+use crate::password::v2::PasswordStore;
+use rclio::{CliInputOutput, OutputType};
+use rtoolbox::safe_vec::SafeVec;
+use std::path::{Path, PathBuf};
+
+/// Returns the backup directory, given with --dir or in the config
+fn backup_dir(
+    matches: &clap::ArgMatches,
+    config: &Config,
+    io: &mut impl CliInputOutput,
+) -> Result<PathBuf, i32> {
+    match matches.get_one::<String>("dir") {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => config.backup_dir.clone().ok_or_else(|| {
+            io.error(
+                "Woops, there is nowhere to back up to. Set `backup_dir` in your config, or use --dir.",
+                OutputType::Error,
+            );
+            1
+        }),
+    }
+}
+
+/// Makes a snapshot without unlocking the password file, since it is copied as is
+pub fn callback_exec_now(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+    config: &Config,
+) -> Result<(), i32> {
+    let dir = backup_dir(matches, config, io)?;
+    match backup::write_snapshot(rooster_file_path, &dir, ffi::time()) {
+        Ok(snapshot) => {
+            io.success(
+                format!(
+                    "Your password file was backed up to {}.",
+                    snapshot.display()
+                ),
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I could not back up your password file to {} (reason: {}).",
+                    dir.display(),
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+/// Checks that a snapshot, the latest by default, can be decrypted with the master password and
+/// that its signature matches
+pub fn callback_exec_verify(
+    matches: &clap::ArgMatches,
+    store: &PasswordStore,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+    config: &Config,
+) -> Result<(), i32> {
+    let snapshot = match matches.get_one::<String>("snapshot") {
+        Some(snapshot) => PathBuf::from(snapshot),
+        None => {
+            let dir = backup_dir(matches, config, io)?;
+            let snapshots = backup::list_snapshots(rooster_file_path, &dir).unwrap_or_default();
+            match snapshots.last() {
+                Some(snapshot) => snapshot.clone(),
+                None => {
+                    io.error(
+                        format!(
+                            "Woops, there are no backups in {}. Make one with `rooster backup now`.",
+                            dir.display()
+                        ),
+                        OutputType::Error,
+                    );
+                    return Err(1);
+                }
+            }
+        }
+    };
+
+    let input = std::fs::read(&snapshot).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not read {} (reason: {}).",
+                snapshot.display(),
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+    match PasswordStore::from_input(
+        store.master_password(),
+        store.key_file(),
+        SafeVec::new(input),
+    ) {
+        Ok(backup) => {
+            io.success(
+                format!(
+                    "{} is intact, and has {} password(s).",
+                    snapshot.display(),
+                    backup.get_all_passwords().len()
+                ),
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, {} is corrupted, or was made before your master password changed (reason: {:?}).",
+                    snapshot.display(),
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::backup;
+use crate::config::Config;
+use crate::password::v2::{KeyFile, PasswordStore};
+use rclio::{CliInputOutput, OutputType};
+use rtoolbox::safe_vec::SafeVec;
+use std::path::{Path, PathBuf};
+
+/// Returns the backups of the password file, latest first, which is how they are numbered
+fn list_backups(
+    rooster_file_path: &Path,
+    io: &mut impl CliInputOutput,
+) -> Result<Vec<PathBuf>, i32> {
+    match backup::list_backups(rooster_file_path) {
+        Ok(mut backups) => {
+            backups.reverse();
+            Ok(backups)
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I could not list the backups in {} (reason: {}).",
+                    backup::backups_dir(rooster_file_path).display(),
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+/// Lists the backups without unlocking the password file, which may be the one that's broken
+pub fn callback_exec_list(
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+    config: &Config,
+) -> Result<(), i32> {
+    let backups = list_backups(rooster_file_path, io)?;
+    if backups.is_empty() {
+        io.info(
+            format!(
+                "There are no backups yet. The last {} versions of your password file are kept in {}.",
+                config.backups_keep,
+                backup::backups_dir(rooster_file_path).display()
+            ),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    for (i, path) in backups.iter().enumerate() {
+        io.info(
+            format!("{:2} {}", i + 1, path.display()),
+            OutputType::Standard,
+        );
+    }
+    Ok(())
+}
+
+/// Replaces the password file by a backup, once the master password unlocks it, after backing up
+/// the password file too so that the restore can be undone
+pub fn callback_exec_restore(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+    key_file: Option<KeyFile>,
+) -> Result<(), i32> {
+    let backups = list_backups(rooster_file_path, io)?;
+    let number = *matches.get_one::<usize>("backup").unwrap();
+    let backup = match number.checked_sub(1).and_then(|i| backups.get(i)) {
+        Some(backup) => backup.clone(),
+        None => {
+            io.error(
+                format!(
+                    "Woops, there is no backup number {}. See them with `rooster backups list`.",
+                    number
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    let master_password = io
+        .prompt_password("Type your master password: ")
+        .map_err(|err| {
+            io.error(
+                format!(
+                    "Woops, I could not read your master password (reason: {}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            1
+        })?;
+    let input = std::fs::read(&backup).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not read {} (reason: {}).",
+                backup.display(),
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+    if let Err(err) =
+        PasswordStore::from_input(master_password, key_file.as_ref(), SafeVec::new(input))
+    {
+        io.error(
+            format!(
+                "Woops, I could not unlock {} (reason: {:?}).",
+                backup.display(),
+                err
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    // All backups are kept this time, so that the one being restored isn't removed
+    let result = match rooster_file_path.exists() {
+        true => backup::write_backup(rooster_file_path, backups.len() + 1).map(Some),
+        false => Ok(None),
+    }
+    .and_then(|current| {
+        backup::write_copy(&backup, rooster_file_path)?;
+        Ok(current)
+    });
+    match result {
+        Ok(current) => {
+            io.success(
+                format!("Your password file was restored from {}.", backup.display()),
+                OutputType::Standard,
+            );
+            if let Some(current) = current {
+                io.info(
+                    format!("The password file it replaced is in {}.", current.display()),
+                    OutputType::Standard,
+                );
+            }
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I could not restore your password file (reason: {}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
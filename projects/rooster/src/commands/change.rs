@@ -1,9 +1,12 @@
 use crate::clip;
+use crate::commands::policy;
 use crate::ffi;
+use crate::generate::PasswordSpec;
 use crate::list;
 use crate::password;
 use rclio::CliInputOutput;
 use rclio::OutputType;
+use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
@@ -35,14 +38,37 @@ pub fn callback_exec(
             1
         })?;
 
+    if let Some(policy) = &password.policy {
+        let violation = PasswordSpec::from_policy(policy).violation(password_as_string.deref());
+        if let Some(violation) = violation {
+            io.warning(
+                format!(
+                    "This password doesn't follow the policy of {} ({}): {}. I'm saving it anyway.",
+                    password.name,
+                    policy::describe(policy),
+                    violation
+                ),
+                OutputType::Error,
+            );
+        }
+    }
+
     let password = store
         .change_password(&password.name, &|old_password: password::v2::Password| {
             password::v2::Password {
-                name: old_password.name,
-                username: old_password.username,
+                name: old_password.name.clone(),
+                username: old_password.username.clone(),
                 password: password_as_string.clone(),
                 created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                totp: old_password.totp.clone(),
+                url: old_password.url.clone(),
+                notes: old_password.notes.clone(),
+                fields: old_password.fields.clone(),
+                history: old_password.history_with_current(ffi::time()),
+                tags: old_password.tags.clone(),
+                policy: old_password.policy.clone(),
+                expiry: old_password.renewed_expiry(ffi::time()),
             }
         })
         .map_err(|err| {
@@ -57,6 +83,6 @@ pub fn callback_exec(
         })?;
 
     let show = matches.get_flag("show");
-    clip::confirm_password_retrieved(show, &password, io);
+    clip::confirm_password_retrieved(show, &password, clip::clear_after(matches), io);
     Ok(())
 }
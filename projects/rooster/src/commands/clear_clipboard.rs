@@ -0,0 +1,8 @@
+use crate::clip;
+use std::time::Duration;
+
+/// Runs in the background, started by `clip::schedule_clipboard_clear`, so it has no output
+pub fn callback_exec(matches: &clap::ArgMatches) -> Result<(), i32> {
+    let seconds = *matches.get_one::<u64>("seconds").unwrap();
+    clip::clear_clipboard_after(Duration::from_secs(seconds)).map_err(|()| 1)
+}
@@ -0,0 +1,12 @@
+use crate::completions;
+use rclio::{CliInputOutput, OutputType};
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    rooster: &clap::Command,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let shell = matches.get_one::<String>("shell").unwrap();
+    io.write(completions::script(shell, rooster), OutputType::Standard);
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use crate::clip;
+use crate::list;
+use crate::password;
+use rclio::{CliInputOutput, OutputType};
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let prompt = "Which password would you like to copy to your clipboard? ";
+    let password =
+        list::search_and_choose_password(store, query, list::WITH_NUMBERS, prompt, io).ok_or(1)?;
+
+    match clip::copy_to_clipboard_and_clear(&password.password, clip::clear_after(matches), io) {
+        Ok(clear_note) => {
+            io.success(
+                format!(
+                    "Alright! Your password for {} is on your clipboard, paste it with {}{}.",
+                    password.name,
+                    clip::paste_keys(),
+                    clear_note
+                ),
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Err(()) => {
+            io.error(
+                "Woops, I couldn't copy the password to your clipboard.",
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::clip;
+use crate::generate::{check_password_len, PasswordSpec};
+use crate::password::v2::{DerivedSite, PasswordStore};
+use rclio::{CliInputOutput, OutputType};
+use std::ops::Deref;
+
+/// Derives the password with the counter saved in the password file, which `--next` increments
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let site = matches
+        .get_one::<String>("site")
+        .unwrap()
+        .trim()
+        .to_lowercase();
+    let saved_counter = store.get_derived_site(&site).map(|s| s.counter);
+
+    let counter = match matches.get_one::<u32>("counter") {
+        Some(counter) => *counter,
+        None if matches.get_flag("next") => saved_counter.unwrap_or(1) + 1,
+        None => saved_counter.unwrap_or(1),
+    };
+    if saved_counter != Some(counter) {
+        store.set_derived_site(DerivedSite {
+            site: site.clone(),
+            counter,
+        });
+    }
+
+    derive_and_output(matches, &site, counter, io)
+}
+
+/// Derives the password without opening the password file, so the counter must be given if it
+/// isn't the first one
+pub fn callback_exec_stateless(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    if matches.get_flag("next") {
+        io.error(
+            "Woops, I can't know the next counter without your password file. Use --counter instead.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    let site = matches.get_one::<String>("site").unwrap();
+    let counter = matches.get_one::<u32>("counter").copied().unwrap_or(1);
+    derive_and_output(matches, site, counter, io)
+}
+
+fn derive_and_output(
+    matches: &clap::ArgMatches,
+    site: &str,
+    counter: u32,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let len = check_password_len(*matches.get_one::<usize>("length").unwrap(), io).ok_or(1)?;
+    let pwspec = PasswordSpec::new(matches.get_flag("alnum"), Some(len));
+
+    let seed = io
+        .prompt_password("Type your master seed (this is not your master password): ")
+        .map_err(|err| {
+            io.error(
+                format!(
+                    "Woops, I couldn't read the master seed (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            1
+        })?;
+    if seed.is_empty() {
+        io.error("Woops, the master seed can't be empty.", OutputType::Error);
+        return Err(1);
+    }
+
+    let password = pwspec
+        .derive_hard_password(&seed, site, counter)
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't derive the password (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    if matches.get_flag("show") {
+        io.info(format!("Counter: {}", counter), OutputType::Error);
+        io.writeln(password.deref(), OutputType::Standard);
+        return Ok(());
+    }
+
+    match clip::copy_to_clipboard_and_clear(&password, clip::clear_after(matches), io) {
+        Ok(clear_note) => {
+            io.success(
+                format!(
+                    "Alright! Your password for {} (counter {}) is on your clipboard, paste it with {}{}.",
+                    site,
+                    counter,
+                    clip::paste_keys(),
+                    clear_note
+                ),
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Err(()) => {
+            io.error(
+                "Woops, I couldn't copy the password to your clipboard. Use --show to see it.",
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
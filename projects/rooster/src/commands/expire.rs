@@ -0,0 +1,83 @@
+use crate::expiry;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use crate::password::v2::Expiry;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like the expiry of?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    let expiry = match matches.get_one::<u32>("after") {
+        Some(days) => Some(Expiry::new(*days, ffi::time())),
+        None if matches.get_flag("clear") => None,
+        None => {
+            match &password.expiry {
+                Some(expiry) => io.writeln(
+                    format!(
+                        "The password for {} lasts {} days, and {}.",
+                        password.name,
+                        expiry.days,
+                        expiry::status(&password, u32::MAX, ffi::time())
+                            .map(|status| status.describe())
+                            .unwrap_or_default()
+                    ),
+                    OutputType::Standard,
+                ),
+                None => io.info(
+                    format!(
+                        "The password for {} doesn't expire. Set an expiry with `rooster expire '{}' 90d`.",
+                        password.name, password.name
+                    ),
+                    OutputType::Standard,
+                ),
+            }
+            return Ok(());
+        }
+    };
+
+    store
+        .change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                expiry: expiry.clone(),
+                ..old_password
+            }
+        })
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't save the expiry (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    match &expiry {
+        Some(expiry) => io.success(
+            format!(
+                "Done! The password for {} expires in {} days, and again {} days after each change.",
+                password.name, expiry.days, expiry.days
+            ),
+            OutputType::Standard,
+        ),
+        None => io.success(
+            format!("Done! The password for {} doesn't expire anymore.", password.name),
+            OutputType::Standard,
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use crate::expiry::{self, Status};
+use crate::ffi;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let within_days = *matches.get_one::<u32>("within").unwrap();
+    let now = ffi::time();
+
+    let mut passwords = store
+        .get_all_passwords()
+        .into_iter()
+        .filter_map(|p| expiry::status(p, within_days, now).map(|status| (p, status)))
+        .collect::<Vec<_>>();
+    passwords.sort_by_key(|(p, _)| p.expiry.as_ref().map(|expiry| expiry.expires_at));
+
+    if passwords.is_empty() {
+        io.success(
+            format!("No passwords expire within {} days.", within_days),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    let mut expired = false;
+    for (p, status) in passwords.iter() {
+        let s = format!("{}: {}", p.name, status.describe());
+        match status {
+            Status::Expired { .. } => {
+                expired = true;
+                io.error(s, OutputType::Standard);
+            }
+            Status::Expiring { .. } => io.warning(s, OutputType::Standard),
+        }
+    }
+    io.info(
+        "Change them with `rooster change` or `rooster regenerate`.",
+        OutputType::Standard,
+    );
+
+    // Reminders run from cron can tell whether there is anything to change from the exit code
+    match expired && matches.get_flag("fail-if-expired") {
+        true => Err(1),
+        false => Ok(()),
+    }
+}
@@ -1,13 +1,23 @@
+use crate::ffi;
 use crate::password;
-use crate::password::v2::Password;
+use crate::password::v2::{ExportColumn, ExportProfile, Password};
 use csv::Writer;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 use rtoolbox::safe_string::SafeString;
+use rtoolbox::safe_vec::SafeVec;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::io::Cursor;
+use serde_json::{Map, Value};
+use std::io::{Result as IoResult, Write};
 use std::ops::Deref;
+use std::path::Path;
+
+/// The fields of a password that can be exported, in the order they are exported
+const EXPORT_FIELDS: [&str; 5] = ["name", "username", "password", "created_at", "updated_at"];
+
+/// What masked fields look like in exports
+const MASK: &str = "********";
 
 #[derive(Serialize, Deserialize)]
 pub struct JsonExport {
@@ -22,70 +32,410 @@ pub fn callback_exec(
     let subcommand_name = matches.subcommand_name().unwrap();
     let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
 
-    if subcommand_name == "json" {
-        export_to_json(subcommand_matches, store, io)
+    let export = if subcommand_name == "json" {
+        let columns = get_export_columns(subcommand_matches, store, io)?;
+        export_to_json(columns, store, io)?
     } else if subcommand_name == "csv" {
-        export_to_csv(subcommand_matches, store, io)
+        let columns = get_export_columns(subcommand_matches, store, io)?;
+        export_to_csv(columns, store)?
     } else if subcommand_name == "1password" {
-        export_to_csv(subcommand_matches, store, io)
+        export_to_csv(None, store)?
+    } else if subcommand_name == "keepass-xml" {
+        export_to_keepass_xml(store)
     } else {
         unimplemented!("Invalid export destination")
+    };
+
+    match subcommand_matches.get_one::<String>("file") {
+        Some(output) => {
+            if let Err(err) = write_export_file(Path::new(output), &export) {
+                io.error(
+                    format!(
+                        "Woops, I could not write the export to {} (reason: {:?}).",
+                        output, err
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+            io.success(
+                format!(
+                    "Your passwords were exported to {}. Keep this file safe, it is not encrypted.",
+                    output
+                ),
+                OutputType::Standard,
+            );
+        }
+        None => io.write(export.deref(), OutputType::Standard),
     }
+
+    Ok(())
+}
+
+/// Writes the export to a new file, or overwrites an existing one, readable by the owner only
+fn write_export_file(path: &Path, export: &SafeString) -> IoResult<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)?;
+    // The mode only applies to new files, so an existing one, ie: a previous export, may be
+    // readable by others
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(export.as_bytes())?;
+    file.sync_all()
 }
 
 fn export_to_csv(
-    _matches: &clap::ArgMatches,
+    columns: Option<Vec<ExportColumn>>,
     store: &mut password::v2::PasswordStore,
-    io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<SafeString, i32> {
     let passwords_ref = store.get_all_passwords();
-    let output_cursor: Cursor<Vec<u8>> = Cursor::new(Vec::new());
-    let mut csv_writer = Writer::from_writer(output_cursor);
-    for password in passwords_ref {
-        match csv_writer.write_record(&[
-            &password.name,
-            &password.username,
-            password.password.deref().as_str(),
-        ]) {
-            Ok(_) => {}
-            Err(_) => return Err(1),
+    let mut csv_writer = Writer::from_writer(SafeVec::new(Vec::new()));
+    match columns {
+        Some(columns) => {
+            if csv_writer
+                .write_record(columns.iter().map(|c| c.header.as_str()))
+                .is_err()
+            {
+                return Err(1);
+            }
+            for password in passwords_ref {
+                let record = columns.iter().map(|c| match export_value(password, c) {
+                    Value::String(s) => s,
+                    value => value.to_string(),
+                });
+                match csv_writer.write_record(record) {
+                    Ok(_) => {}
+                    Err(_) => return Err(1),
+                }
+            }
+        }
+        None => {
+            for password in passwords_ref {
+                match csv_writer.write_record(&[
+                    &password.name,
+                    &password.username,
+                    password.password.deref().as_str(),
+                ]) {
+                    Ok(_) => {}
+                    Err(_) => return Err(1),
+                }
+            }
         }
     }
-    io.write(
-        String::from_utf8(csv_writer.into_inner().unwrap().into_inner()).unwrap(),
-        OutputType::Standard,
-    );
-
-    return Ok(());
+    Ok(into_safe_string(csv_writer.into_inner().unwrap()))
 }
 
 fn export_to_json(
-    _matches: &clap::ArgMatches,
+    columns: Option<Vec<ExportColumn>>,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
-    let export = JsonExport {
-        passwords: store
-            .get_all_passwords()
-            .into_iter()
-            .map(|password| password.clone())
-            .collect(),
-    };
-    let passwords_json = match serde_json::to_string(&export) {
-        Ok(passwords_json) => passwords_json,
-        Err(json_err) => {
-            io.error(
-                format!(
-                    "Woops, I could not encode the passwords into JSON (reason: {:?}).",
-                    json_err
-                ),
-                OutputType::Error,
-            );
-            return Err(1);
+) -> Result<SafeString, i32> {
+    let mut passwords_json = SafeVec::new(Vec::new());
+    let json_result = match columns {
+        Some(columns) => {
+            let passwords = store
+                .get_all_passwords()
+                .into_iter()
+                .map(|password| {
+                    let mut object = Map::new();
+                    for column in columns.iter() {
+                        object.insert(column.header.clone(), export_value(password, column));
+                    }
+                    Value::Object(object)
+                })
+                .collect::<Vec<Value>>();
+            let mut export = Map::new();
+            export.insert("passwords".to_string(), Value::Array(passwords));
+            serde_json::to_writer(&mut passwords_json, &export)
         }
+        None => serde_json::to_writer(
+            &mut passwords_json,
+            &JsonExport {
+                passwords: store
+                    .get_all_passwords()
+                    .into_iter()
+                    .map(|password| password.clone())
+                    .collect(),
+            },
+        ),
     };
+    if let Err(json_err) = json_result {
+        io.error(
+            format!(
+                "Woops, I could not encode the passwords into JSON (reason: {:?}).",
+                json_err
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    Ok(into_safe_string(passwords_json))
+}
+
+/// Exports passwords in the KeePass 2 XML format, which KeePass and KeePassXC can import
+fn export_to_keepass_xml(store: &mut password::v2::PasswordStore) -> SafeString {
+    let mut xml = SafeVec::new(Vec::new());
+    xml.write_all(
+        b"<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n\
+         <KeePassFile>\n\
+         \t<Meta>\n\
+         \t\t<Generator>Rooster</Generator>\n\
+         \t</Meta>\n\
+         \t<Root>\n\
+         \t\t<Group>\n\
+         \t\t\t<Name>Rooster</Name>\n",
+    )
+    .unwrap();
+    for password in store.get_all_passwords() {
+        let mut other_fields: Vec<(&str, &str)> = vec![];
+        if let Some(url) = &password.url {
+            other_fields.push(("URL", url));
+        }
+        if let Some(notes) = &password.notes {
+            other_fields.push(("Notes", notes.deref()));
+        }
+        for (key, value) in password.fields.iter() {
+            other_fields.push((key, value.deref()));
+        }
+        let other_fields = SafeString::from_string(
+            other_fields
+                .into_iter()
+                .map(|(key, value)| {
+                    format!(
+                        "\t\t\t\t<String><Key>{}</Key><Value>{}</Value></String>\n",
+                        escape_xml(key),
+                        SafeString::from_string(escape_xml(value)).deref()
+                    )
+                })
+                .collect(),
+        );
+        let tags = if password.tags.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\t\t\t\t<Tags>{}</Tags>\n",
+                escape_xml(&password.tags.join(";"))
+            )
+        };
 
-    let passwords = SafeString::from_string(passwords_json);
-    io.write(format!("{}", passwords.deref()), OutputType::Standard);
-    return Ok(());
+        write!(
+            xml,
+            "\t\t\t<Entry>\n\
+             \t\t\t\t<Times>\n\
+             \t\t\t\t\t<CreationTime>{}</CreationTime>\n\
+             \t\t\t\t\t<LastModificationTime>{}</LastModificationTime>\n\
+             \t\t\t\t</Times>\n\
+             \t\t\t\t<String><Key>Title</Key><Value>{}</Value></String>\n\
+             \t\t\t\t<String><Key>UserName</Key><Value>{}</Value></String>\n\
+             \t\t\t\t<String><Key>Password</Key><Value ProtectInMemory=\"True\">{}</Value></String>\n\
+             {}\
+             {}\
+             \t\t\t</Entry>\n",
+            format_xml_time(password.created_at),
+            format_xml_time(password.updated_at),
+            escape_xml(&password.name),
+            escape_xml(&password.username),
+            SafeString::from_string(escape_xml(password.password.deref())).deref(),
+            other_fields.deref(),
+            tags,
+        )
+        .unwrap();
+    }
+    xml.write_all(b"\t\t</Group>\n\t</Root>\n</KeePassFile>\n")
+        .unwrap();
+
+    into_safe_string(xml)
+}
+
+/// Turns what was written to a `SafeVec` into a string, without copying it
+fn into_safe_string(mut bytes: SafeVec) -> SafeString {
+    SafeString::from_string(String::from_utf8(std::mem::take(bytes.inner_mut())).unwrap())
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats a timestamp the way KeePass does, ie: `2021-03-04T05:06:07Z`
+pub fn format_xml_time(timestamp: ffi::time_t) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+
+    // Converts days since 1970-01-01 to a date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Returns the columns to export, or `None` to export passwords the way `import` expects them
+fn get_export_columns(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<Option<Vec<ExportColumn>>, i32> {
+    let profile_name = matches.get_one::<String>("profile");
+
+    if !matches.get_flag("mapping") {
+        return match profile_name {
+            Some(profile_name) => match store.get_export_profile(profile_name) {
+                Some(profile) => Ok(Some(profile.columns.clone())),
+                None => {
+                    io.error(
+                        format!(
+                            "Woops, there is no export profile named \"{}\". You can create \
+                             it with `--mapping --profile {}`.",
+                            profile_name, profile_name
+                        ),
+                        OutputType::Error,
+                    );
+                    Err(1)
+                }
+            },
+            None => Ok(None),
+        };
+    }
+
+    let columns = ask_export_columns(io)?;
+    if columns.is_empty() {
+        io.error(
+            "You need to export at least one field. Aborting.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    if let Some(profile_name) = profile_name {
+        store.set_export_profile(ExportProfile {
+            name: profile_name.clone(),
+            columns: columns.clone(),
+        });
+    }
+
+    Ok(Some(columns))
+}
+
+fn ask_export_columns(io: &mut impl CliInputOutput) -> Result<Vec<ExportColumn>, i32> {
+    let mut columns = vec![];
+
+    for field in EXPORT_FIELDS.iter() {
+        let mut prompt = format!("Export \"{}\"? [y/n/m] (m: export it masked) ", field);
+        let masked = loop {
+            let answer = io.prompt_line(prompt).map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, an error occured while reading your response (reason: {:?}).",
+                        err
+                    ),
+                    OutputType::Error,
+                );
+                1
+            })?;
+
+            if answer.starts_with('y') {
+                break Some(false);
+            } else if answer.starts_with('m') {
+                break Some(true);
+            } else if answer.starts_with('n') {
+                break None;
+            }
+
+            prompt = format!("I did not get that. Export \"{}\"? [y/n/m] ", field);
+        };
+
+        let masked = match masked {
+            Some(masked) => masked,
+            None => continue,
+        };
+
+        let header = io
+            .prompt_line(format!("Column name for \"{}\" [{}]: ", field, field))
+            .map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, an error occured while reading your response (reason: {:?}).",
+                        err
+                    ),
+                    OutputType::Error,
+                );
+                1
+            })?;
+        let header = header.trim();
+
+        columns.push(ExportColumn {
+            field: field.to_string(),
+            header: if header.is_empty() {
+                field.to_string()
+            } else {
+                header.to_string()
+            },
+            masked,
+        });
+    }
+
+    Ok(columns)
+}
+
+fn export_value(password: &Password, column: &ExportColumn) -> Value {
+    if column.masked {
+        return Value::from(MASK);
+    }
+
+    match column.field.as_str() {
+        "name" => Value::from(password.name.as_str()),
+        "username" => Value::from(password.username.as_str()),
+        "password" => Value::from(password.password.deref().as_str()),
+        "created_at" => Value::from(password.created_at),
+        "updated_at" => Value::from(password.updated_at),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_xml, format_xml_time};
+
+    #[test]
+    fn test_format_xml_time() {
+        assert_eq!(format_xml_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_xml_time(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_xml_time(1614834367), "2021-03-04T05:06:07Z");
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("a<b>&\"c'"), "a&lt;b&gt;&amp;&quot;c&apos;");
+    }
 }
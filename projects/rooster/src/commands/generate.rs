@@ -1,4 +1,4 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
+use crate::clip::{self, copy_to_clipboard_and_clear, paste_keys};
 use crate::generate::{check_password_len, PasswordSpec};
 use crate::password;
 use rclio::CliInputOutput;
@@ -22,17 +22,23 @@ pub fn callback_exec(
         return Err(1);
     }
 
-    let pwspec = PasswordSpec::new(
-        matches.get_flag("alnum"),
-        check_password_len(*matches.get_one::<usize>("length").unwrap(), io),
-    );
+    let pwspec = PasswordSpec {
+        charset: matches.get_one::<String>("charset").cloned(),
+        no_ambiguous: matches.get_flag("no-ambiguous"),
+        words: matches.get_one::<usize>("words").copied(),
+        pattern: matches.get_one::<String>("pattern").cloned(),
+        ..PasswordSpec::new(
+            matches.get_flag("alnum"),
+            check_password_len(*matches.get_one::<usize>("length").unwrap(), io),
+        )
+    };
 
     let password_as_string = match pwspec.generate_hard_password() {
         Ok(password_as_string) => password_as_string,
         Err(io_err) => {
             io.error(
                 format!(
-                    "Woops, I could not generate the password (reason: {:?}).",
+                    "Woops, I could not generate the password (reason: {}).",
                     io_err
                 ),
                 OutputType::Error,
@@ -58,7 +64,12 @@ pub fn callback_exec(
                 return Ok(());
             }
 
-            if copy_to_clipboard(&password_as_string_clipboard).is_err() {
+            let copied = copy_to_clipboard_and_clear(
+                &password_as_string_clipboard,
+                clip::clear_after(matches),
+                io,
+            );
+            if let Err(()) = copied {
                 io.success(
                     format!(
                         "Hmm, I tried to copy your new password to your clipboard, but \
@@ -71,8 +82,9 @@ pub fn callback_exec(
             } else {
                 io.success(
                     format!(
-                        "Alright! I've saved your new password. You can paste it anywhere with {}.",
-                        paste_keys()
+                        "Alright! I've saved your new password. You can paste it anywhere with {}{}.",
+                        paste_keys(),
+                        copied.unwrap_or_default()
                     ),
                     OutputType::Standard,
                 );
@@ -1,20 +1,25 @@
 use crate::clip;
 
 use crate::list;
+use crate::output;
 use crate::password;
-use rclio::CliInputOutput;
+use crate::reveal;
+use rclio::{CliInputOutput, OutputType};
+use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
-    let show = matches.get_flag("show");
+    let pass_format = matches.get_flag("pass-format");
+    let json = output::is_json(matches);
+    let show = matches.get_flag("show") || pass_format || json;
     let query = matches.get_one::<String>("app").unwrap();
 
     let prompt = format!(
         "Which password would you like {}? ",
-        if show {
+        if show || matches.get_flag("hold") {
             "to see"
         } else {
             "to copy to your clipboard"
@@ -23,7 +28,76 @@ pub fn callback_exec(
     let password =
         list::search_and_choose_password(store, query, list::WITH_NUMBERS, &prompt, io).ok_or(1)?;
 
-    clip::confirm_password_retrieved(show, &password, io);
+    if json {
+        let password = output::PasswordOutput::new(password, output::show_secrets(matches));
+        return output::print_json(&password, io);
+    }
+
+    if pass_format {
+        print_pass_format(password, io);
+        return Ok(());
+    }
+
+    if matches.get_flag("hold") {
+        io.success(
+            format!("Alright! Here is your password for {}:", password.name),
+            OutputType::Standard,
+        );
+        io.success(
+            format!("Username: {}", password.username),
+            OutputType::Standard,
+        );
+        return reveal::reveal_on_tty(password.password.deref()).map_err(|err| {
+            io.error(
+                format!(
+                    "Woops, I couldn't show the password in the terminal (reason: {}). Try `--show` instead.",
+                    err
+                ),
+                OutputType::Error,
+            );
+            1
+        });
+    }
+
+    clip::confirm_password_retrieved(show, password, clip::clear_after(matches), io);
+    if matches.get_flag("full") {
+        print_details(password, io);
+    }
 
     Ok(())
 }
+
+/// Prints what is stored along with the username and password
+fn print_details(password: &password::v2::Password, io: &mut impl CliInputOutput) {
+    if let Some(url) = &password.url {
+        io.success(format!("URL: {}", url), OutputType::Standard);
+    }
+    for (name, value) in password.fields.iter() {
+        io.success(format!("{}: {}", name, value.deref()), OutputType::Standard);
+    }
+    if password.totp.is_some() {
+        io.success(
+            format!("TOTP: get a code with `rooster totp '{}'`", password.name),
+            OutputType::Standard,
+        );
+    }
+    if let Some(notes) = &password.notes {
+        io.success("Notes:", OutputType::Standard);
+        io.writeln(notes.deref(), OutputType::Standard);
+    }
+}
+
+/// Prints the password like pass(1) stores it, so that it can be piped to `pass insert -m`
+fn print_pass_format(password: &password::v2::Password, io: &mut impl CliInputOutput) {
+    io.writeln(password.password.deref(), OutputType::Standard);
+    if !password.username.is_empty() {
+        io.writeln(
+            format!("login: {}", password.username),
+            OutputType::Standard,
+        );
+    }
+    if let Some(totp) = &password.totp {
+        let label = format!("{}:{}", password.name, password.username);
+        io.writeln(totp.to_uri(&label).deref(), OutputType::Standard);
+    }
+}
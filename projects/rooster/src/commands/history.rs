@@ -0,0 +1,60 @@
+use crate::commands::export::format_xml_time;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like the previous passwords of?",
+        io,
+    )
+    .ok_or(1)?;
+
+    if password.history.is_empty() {
+        io.info(
+            format!(
+                "There are no previous passwords for {}, they are kept once it is changed.",
+                password.name
+            ),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    io.info(
+        format!(
+            "Previous passwords for {}, most recent first (restore one with `rooster restore '{}' --version <version>`):",
+            password.name, password.name
+        ),
+        OutputType::Standard,
+    );
+    let show = matches.get_flag("show");
+    for (version, previous) in password.history.iter().rev().enumerate() {
+        io.writeln(
+            format!(
+                "{}: {} (from {} until {})",
+                version + 1,
+                if show {
+                    previous.password.deref()
+                } else {
+                    "********"
+                },
+                format_xml_time(previous.created_at),
+                format_xml_time(previous.replaced_at)
+            ),
+            OutputType::Standard,
+        );
+    }
+    Ok(())
+}
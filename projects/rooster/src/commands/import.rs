@@ -1,16 +1,73 @@
 use crate::ffi;
 use crate::password;
 use crate::password::v2::{Password, PasswordStore};
+use crate::totp::Totp;
 use rclio::{CliInputOutput, OutputType};
+use rtoolbox::safe_string::SafeString;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::UNIX_EPOCH;
 
 #[derive(Serialize, Deserialize)]
 pub struct JsonExport {
     passwords: Vec<Password>,
 }
 
+#[derive(Deserialize)]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenItem {
+    #[serde(rename = "type")]
+    item_type: u32,
+    name: String,
+    login: Option<BitwardenLogin>,
+    notes: Option<String>,
+    #[serde(default)]
+    fields: Vec<BitwardenField>,
+    creation_date: Option<String>,
+    revision_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    username: Option<String>,
+    password: Option<String>,
+    #[serde(default)]
+    uris: Vec<BitwardenUri>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenField {
+    name: Option<String>,
+    value: Option<String>,
+}
+
+/// Fields of KeePass entries that Rooster has its own place for, the others are custom fields
+const KEEPASS_STANDARD_FIELDS: [&str; 5] = ["Title", "UserName", "Password", "URL", "Notes"];
+
+/// What to do with an imported password when the store already has one for the same app
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Resolution {
+    Merge,
+    Skip,
+    Overwrite,
+}
+
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
@@ -25,16 +82,33 @@ pub fn callback_exec(
         create_imported_passwords_from_csv(subcommand_matches, io)
     } else if subcommand_name == "1password" {
         create_imported_passwords_from_1password(subcommand_matches, io)
+    } else if subcommand_name == "keepass-xml" {
+        create_imported_passwords_from_keepass_xml(subcommand_matches, io)
+    } else if subcommand_name == "bitwarden-json" {
+        create_imported_passwords_from_bitwarden_json(subcommand_matches, io)
+    } else if subcommand_name == "pass" {
+        create_imported_passwords_from_pass(subcommand_matches, io)
     } else {
         unimplemented!("Invalid import source")
     }?;
 
-    import_passwords(valid, invalid, store, io)
+    let on_conflict = match subcommand_matches
+        .get_one::<String>("on-conflict")
+        .map(|s| s.as_str())
+    {
+        Some("merge") => Some(Resolution::Merge),
+        Some("skip") => Some(Resolution::Skip),
+        Some("overwrite") => Some(Resolution::Overwrite),
+        _ => None,
+    };
+
+    import_passwords(valid, invalid, on_conflict, store, io)
 }
 
 fn import_passwords(
     valid: Vec<Password>,
     invalid: Vec<Password>,
+    mut on_conflict: Option<Resolution>,
     store: &mut PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
@@ -49,16 +123,50 @@ fn import_passwords(
         errors += 1;
     }
     for password in valid {
-        if let Some(_) = store.get_password(&password.name) {
-            io.warning(
-                format!("{}, already in password store, skipping", password.name),
-                OutputType::Error,
-            );
-            warnings += 1;
-            continue;
-        }
+        let result = match store.get_password(&password.name) {
+            None => store.add_password(password.clone()),
+            Some(existing) => {
+                if existing.username == password.username && existing.password == password.password
+                {
+                    io.warning(
+                        format!("{}, already in password store, skipping", password.name),
+                        OutputType::Error,
+                    );
+                    warnings += 1;
+                    continue;
+                }
+
+                let resolution = match on_conflict {
+                    Some(resolution) => resolution,
+                    None => {
+                        let (resolution, for_all) = ask_resolution(&existing, &password, io)?;
+                        if for_all {
+                            on_conflict = Some(resolution);
+                        }
+                        resolution
+                    }
+                };
+                match resolution {
+                    Resolution::Skip => {
+                        io.warning(
+                            format!("{}, kept the password already in store", password.name),
+                            OutputType::Error,
+                        );
+                        warnings += 1;
+                        continue;
+                    }
+                    Resolution::Overwrite => {
+                        store.change_password(&existing.name, &|_| password.clone())
+                    }
+                    Resolution::Merge => store.change_password(&existing.name, &|existing| {
+                        merge_passwords(existing, &password)
+                    }),
+                }
+                .map(|_| ())
+            }
+        };
 
-        if let Err(err) = store.add_password(password.clone()) {
+        if let Err(err) = result {
             io.error(
                 format!("{}, error ({:?})", password.name, err),
                 OutputType::Error,
@@ -77,6 +185,90 @@ fn import_passwords(
     Ok(())
 }
 
+/// Asks what to do with an imported password that conflicts with one in the store, and whether
+/// to do the same for all remaining conflicts
+fn ask_resolution(
+    existing: &Password,
+    imported: &Password,
+    io: &mut impl CliInputOutput,
+) -> Result<(Resolution, bool), i32> {
+    io.info(
+        format!(
+            "{} is already in your password store, with username \"{}\" (\"{}\" in the import).",
+            existing.name, existing.username, imported.username
+        ),
+        OutputType::Standard,
+    );
+    loop {
+        let answer = io
+            .prompt_line(
+                "Merge, skip or overwrite? [m/s/o] (uppercase to do the same for all conflicts) ",
+            )
+            .map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, an error occured while reading your response (reason: {:?}).",
+                        err
+                    ),
+                    OutputType::Error,
+                );
+                1
+            })?;
+
+        let for_all = answer.starts_with(|c: char| c.is_uppercase());
+        match answer.trim().to_lowercase().as_str() {
+            "m" | "merge" => return Ok((Resolution::Merge, for_all)),
+            "s" | "skip" => return Ok((Resolution::Skip, for_all)),
+            "o" | "overwrite" => return Ok((Resolution::Overwrite, for_all)),
+            _ => io.error(
+                "Please answer with \"m\", \"s\" or \"o\".",
+                OutputType::Error,
+            ),
+        }
+    }
+}
+
+/// Keeps the most recently updated username and password, favoring the import on ties and falling
+/// back on the other username if it is empty, along with the earliest creation date
+fn merge_passwords(existing: Password, imported: &Password) -> Password {
+    let (newer, older) = if imported.updated_at >= existing.updated_at {
+        (imported.clone(), existing.clone())
+    } else {
+        (existing.clone(), imported.clone())
+    };
+
+    // An imported password that replaces the existing one keeps it in its history
+    let history = if newer.password == older.password {
+        newer.history.clone()
+    } else {
+        older.history_with_current(newer.updated_at)
+    };
+
+    Password {
+        name: existing.name,
+        username: if newer.username.is_empty() {
+            older.username
+        } else {
+            newer.username
+        },
+        password: newer.password,
+        created_at: existing.created_at.min(imported.created_at),
+        updated_at: newer.updated_at,
+        totp: newer.totp.or(older.totp),
+        url: newer.url.or(older.url),
+        notes: newer.notes.or(older.notes),
+        fields: older.fields.into_iter().chain(newer.fields).collect(),
+        history,
+        tags: if newer.tags.is_empty() {
+            older.tags
+        } else {
+            newer.tags
+        },
+        policy: newer.policy.or(older.policy),
+        expiry: newer.expiry.or(older.expiry),
+    }
+}
+
 fn create_imported_passwords_from_csv(
     matches: &clap::ArgMatches,
     io: &mut impl CliInputOutput,
@@ -101,6 +293,14 @@ fn create_imported_passwords_from_csv(
                 password: record[2].into(),
                 created_at: ffi::time(),
                 updated_at: ffi::time(),
+                totp: None,
+                url: None,
+                notes: None,
+                fields: BTreeMap::new(),
+                history: Vec::new(),
+                tags: Vec::new(),
+                policy: None,
+                expiry: None,
             });
         } else {
             return Err(1);
@@ -135,6 +335,14 @@ fn create_imported_passwords_from_1password(
                     password: record[1].into(),
                     created_at: ffi::time(),
                     updated_at: ffi::time(),
+                    totp: None,
+                    url: None,
+                    notes: None,
+                    fields: BTreeMap::new(),
+                    history: Vec::new(),
+                    tags: Vec::new(),
+                    policy: None,
+                    expiry: None,
                 });
                 continue;
             }
@@ -146,6 +354,14 @@ fn create_imported_passwords_from_1password(
                 password: record[1].into(),
                 created_at: ffi::time(),
                 updated_at: ffi::time(),
+                totp: None,
+                url: non_empty(&record[4]),
+                notes: non_empty(&record[0]).map(SafeString::from_string),
+                fields: BTreeMap::new(),
+                history: Vec::new(),
+                tags: Vec::new(),
+                policy: None,
+                expiry: None,
             });
         } else {
             return Err(1);
@@ -178,3 +394,399 @@ fn create_imported_passwords_from_json(
     })?;
     Ok((export.passwords, vec![]))
 }
+
+fn create_imported_passwords_from_keepass_xml(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) -> Result<(Vec<Password>, Vec<Password>), i32> {
+    let path_str = matches.get_one::<String>("path").unwrap();
+    let xml = std::fs::read_to_string(path_str).map_err(|err| {
+        io.error(
+            format!("Uh oh, could not open or read the file (reason: {})", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+    let document = roxmltree::Document::parse(&xml).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not import the passwords from KeePass XML (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    let mut valid = vec![];
+    let mut invalid = vec![];
+    // Older versions of entries are kept in a <History> within the entry, we only want the latest
+    let entries = document.descendants().filter(|node| {
+        node.has_tag_name("Entry") && !node.ancestors().any(|node| node.has_tag_name("History"))
+    });
+    for entry in entries {
+        let field = |key: &str| {
+            entry
+                .children()
+                .filter(|node| node.has_tag_name("String"))
+                .find(|node| child_text(*node, "Key") == Some(key))
+                .and_then(|node| child_text(node, "Value"))
+                .unwrap_or("")
+                .to_string()
+        };
+        let times = entry.children().find(|node| node.has_tag_name("Times"));
+        let time = |tag: &str| {
+            times
+                .and_then(|times| child_text(times, tag))
+                .and_then(parse_iso_time)
+                .unwrap_or_else(ffi::time)
+        };
+
+        let password = Password {
+            name: field("Title"),
+            username: field("UserName"),
+            password: field("Password").into(),
+            created_at: time("CreationTime"),
+            updated_at: time("LastModificationTime"),
+            totp: None,
+            url: non_empty(&field("URL")),
+            notes: non_empty(&field("Notes")).map(SafeString::from_string),
+            fields: entry
+                .children()
+                .filter(|node| node.has_tag_name("String"))
+                .filter_map(|node| Some((child_text(node, "Key")?, child_text(node, "Value")?)))
+                .filter(|(key, _)| !KEEPASS_STANDARD_FIELDS.contains(key))
+                .map(|(key, value)| (key.to_string(), SafeString::from_string(value.to_string())))
+                .collect(),
+            history: Vec::new(),
+            // KeePass separates tags with semicolons, and older versions with commas
+            tags: child_text(entry, "Tags")
+                .unwrap_or("")
+                .split([';', ','])
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            policy: None,
+            expiry: None,
+        };
+        if password.name.is_empty() || password.password.is_empty() {
+            invalid.push(password);
+        } else {
+            valid.push(password);
+        }
+    }
+    Ok((valid, invalid))
+}
+
+/// Returns the text of the first child of `node` with the given tag
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(tag))
+        .and_then(|child| child.text())
+}
+
+fn create_imported_passwords_from_bitwarden_json(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) -> Result<(Vec<Password>, Vec<Password>), i32> {
+    let path_str = matches.get_one::<String>("path").unwrap();
+    let dump_file = File::open(path_str).map_err(|err| {
+        io.error(
+            format!("Uh oh, could not open the file (reason: {})", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+    let export: BitwardenExport = serde_json::from_reader(dump_file).map_err(|json_err| {
+        io.error(
+            format!(
+                "Woops, I could not import the passwords from Bitwarden JSON (reason: {}).",
+                json_err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+    if export.encrypted {
+        io.error(
+            "Woops, this Bitwarden export is encrypted. Please export your vault in the unencrypted JSON format.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    let mut valid = vec![];
+    let mut invalid = vec![];
+    for item in export.items {
+        let time = |date: &Option<String>| {
+            date.as_deref()
+                .and_then(parse_iso_time)
+                .unwrap_or_else(ffi::time)
+        };
+        let login = item.login.unwrap_or(BitwardenLogin {
+            username: None,
+            password: None,
+            uris: vec![],
+        });
+        let password = Password {
+            name: item.name,
+            username: login.username.unwrap_or_default(),
+            password: login.password.unwrap_or_default().into(),
+            created_at: time(&item.creation_date),
+            updated_at: time(&item.revision_date),
+            totp: None,
+            url: login.uris.into_iter().find_map(|uri| uri.uri),
+            notes: item.notes.map(SafeString::from_string),
+            fields: item
+                .fields
+                .into_iter()
+                .filter_map(|field| Some((field.name?, SafeString::from_string(field.value?))))
+                .collect(),
+            history: Vec::new(),
+            tags: Vec::new(),
+            policy: None,
+            expiry: None,
+        };
+
+        // Bitwarden items of type 1 are logins, the others are notes, cards and identities
+        if item.item_type != 1 || password.password.is_empty() {
+            invalid.push(password);
+        } else {
+            valid.push(password);
+        }
+    }
+    Ok((valid, invalid))
+}
+
+fn create_imported_passwords_from_pass(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) -> Result<(Vec<Password>, Vec<Password>), i32> {
+    let store_dir = match matches.get_one::<String>("path") {
+        Some(path) => PathBuf::from(path),
+        None => match std::env::var_os("PASSWORD_STORE_DIR") {
+            Some(path) => PathBuf::from(path),
+            None => dirs::home_dir().unwrap_or_default().join(".password-store"),
+        },
+    };
+
+    let mut files = vec![];
+    find_pass_files(&store_dir, &mut files).map_err(|err| {
+        io.error(
+            format!(
+                "Uh oh, could not read the password store in {} (reason: {})",
+                store_dir.display(),
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+    files.sort();
+
+    let mut valid = vec![];
+    let mut invalid = vec![];
+    for file in files {
+        let name = file
+            .strip_prefix(&store_dir)
+            .unwrap_or(&file)
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        // gpg asks for the passphrase of the key itself, through gpg-agent, if needed
+        let output = Command::new("gpg")
+            .args(["--quiet", "--batch", "--decrypt"])
+            .arg(&file)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|err| {
+                io.error(
+                    format!("Woops, I could not run gpg (reason: {}).", err),
+                    OutputType::Error,
+                );
+                1
+            })?;
+        if !output.status.success() {
+            io.error(
+                format!(
+                    "Woops, gpg could not decrypt {} ({}).",
+                    name,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+
+        let updated_at = std::fs::metadata(&file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|age| age.as_secs() as ffi::time_t)
+            .unwrap_or_else(ffi::time);
+        let contents = String::from_utf8_lossy(&output.stdout);
+        let password = parse_pass_entry(name, &contents, updated_at);
+        if password.password.is_empty() {
+            invalid.push(password);
+        } else {
+            valid.push(password);
+        }
+    }
+    Ok((valid, invalid))
+}
+
+/// Finds the encrypted files of a password store, skipping its git repository
+fn find_pass_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path.file_name() != Some(".git".as_ref()) {
+                find_pass_files(&path, files)?;
+            }
+        } else if path.extension() == Some("gpg".as_ref()) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Reads an entry in the pass(1) format: the password on the first line, then optional lines
+/// like `login: me@example.com`, and an otpauth:// URI for pass-otp
+fn parse_pass_entry(name: String, contents: &str, updated_at: ffi::time_t) -> Password {
+    let mut lines = contents.lines();
+    let mut password = Password {
+        name,
+        username: String::new(),
+        password: lines.next().unwrap_or("").into(),
+        created_at: updated_at,
+        updated_at,
+        totp: None,
+        url: None,
+        notes: None,
+        fields: BTreeMap::new(),
+        history: Vec::new(),
+        tags: Vec::new(),
+        policy: None,
+        expiry: None,
+    };
+    let mut notes = vec![];
+    for line in lines {
+        if line.starts_with("otpauth://") {
+            password.totp = Totp::from_uri(line).ok();
+            continue;
+        }
+        // URLs have a colon too, but no space after it
+        let field = line
+            .split_once(": ")
+            .filter(|(key, _)| !key.is_empty() && !key.contains(' '));
+        match field {
+            Some((key, value)) => match key.to_lowercase().as_str() {
+                "login" | "username" | "user" if password.username.is_empty() => {
+                    password.username = value.trim().to_string()
+                }
+                "url" if password.url.is_none() => password.url = Some(value.trim().to_string()),
+                _ => {
+                    password
+                        .fields
+                        .insert(key.to_string(), SafeString::from_string(value.to_string()));
+                }
+            },
+            None => notes.push(line),
+        }
+    }
+    password.notes = non_empty(notes.join("\n").trim()).map(SafeString::from_string);
+    password
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    match s.is_empty() {
+        true => None,
+        false => Some(s.to_string()),
+    }
+}
+
+/// Parses a UTC date like `2021-03-04T05:06:07Z`, as found in KeePass and Bitwarden exports,
+/// ignoring fractions of seconds
+fn parse_iso_time(date: &str) -> Option<ffi::time_t> {
+    let date = date.trim().trim_end_matches('Z');
+    let date = date.split('.').next()?;
+    let (day, time) = date.split_once('T')?;
+
+    let mut day = day.splitn(3, '-').map(|n| n.parse::<i64>().ok());
+    let (year, month, day) = (day.next()??, day.next()??, day.next()??);
+    let mut time = time.splitn(3, ':').map(|n| n.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Converts a date to days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some((days * 86400 + hours * 3600 + minutes * 60 + seconds) as ffi::time_t)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{merge_passwords, parse_iso_time, parse_pass_entry};
+    use crate::password::v2::Password;
+
+    #[test]
+    fn test_parse_iso_time() {
+        assert_eq!(parse_iso_time("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_iso_time("2000-02-29T00:00:00Z"), Some(951782400));
+        assert_eq!(parse_iso_time("2021-03-04T05:06:07.123Z"), Some(1614834367));
+        assert_eq!(parse_iso_time("2021-13-04T05:06:07Z"), None);
+        assert_eq!(parse_iso_time("yesterday"), None);
+    }
+
+    #[test]
+    fn test_parse_pass_entry() {
+        let password = parse_pass_entry(
+            "web/github.com".to_string(),
+            "hunter2\nurl: https://github.com\nLogin: me@example.com\nPIN: 1234\notpauth://totp/Github?secret=JBSWY3DP\nRecovery codes:\nabcd efgh\n",
+            100,
+        );
+        assert_eq!(password.name, "web/github.com");
+        assert_eq!(password.username, "me@example.com");
+        assert_eq!(password.password, "hunter2".into());
+        assert_eq!((password.created_at, password.updated_at), (100, 100));
+        assert!(password.totp.is_some());
+        assert_eq!(password.url, Some("https://github.com".to_string()));
+        assert_eq!(password.fields.get("PIN"), Some(&"1234".into()));
+        assert_eq!(password.notes, Some("Recovery codes:\nabcd efgh".into()));
+
+        let password = parse_pass_entry("empty".to_string(), "", 100);
+        assert!(password.password.is_empty());
+    }
+
+    #[test]
+    fn test_merge_passwords() {
+        let mut existing = Password::new("Youtube", "yt@example.com", "old");
+        existing.created_at = 100;
+        existing.updated_at = 200;
+        let mut imported = Password::new("youtube", "", "new");
+        imported.created_at = 50;
+        imported.updated_at = 300;
+
+        let merged = merge_passwords(existing.clone(), &imported);
+        assert_eq!(merged.name, "Youtube");
+        assert_eq!(merged.username, "yt@example.com");
+        assert_eq!(merged.password, "new".into());
+        assert_eq!((merged.created_at, merged.updated_at), (50, 300));
+        assert_eq!(merged.history.len(), 1);
+        assert_eq!(merged.history[0].password, "old".into());
+
+        imported.updated_at = 150;
+        let merged = merge_passwords(existing, &imported);
+        assert_eq!(merged.password, "old".into());
+        assert_eq!(merged.history[0].password, "new".into());
+    }
+}
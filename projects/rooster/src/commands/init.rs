@@ -1,3 +1,4 @@
+use crate::password::v2::KeyFile;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 use std::path::PathBuf;
@@ -6,6 +7,7 @@ pub fn callback_exec(
     matches: &clap::ArgMatches,
     io: &mut impl CliInputOutput,
     rooster_file_path: &PathBuf,
+    key_file: Option<KeyFile>,
 ) -> Result<(), i32> {
     let filename_as_string = rooster_file_path.to_string_lossy().into_owned();
     if rooster_file_path.exists() && !matches.get_flag("force-for-tests") {
@@ -58,7 +60,7 @@ pub fn callback_exec(
         return Err(1);
     }
 
-    let store = match crate::password::v2::PasswordStore::new(master_password) {
+    let mut store = match crate::password::v2::PasswordStore::new(master_password) {
         Ok(store) => store,
         Err(err) => {
             io.error(
@@ -73,6 +75,10 @@ pub fn callback_exec(
         }
     };
 
+    if key_file.is_some() {
+        store.set_key_file(key_file);
+    }
+
     let mut file = match crate::create_password_file(filename_as_string.as_str()).map_err(|_| 1) {
         Ok(file) => file,
         Err(err) => {
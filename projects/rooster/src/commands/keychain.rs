@@ -0,0 +1,53 @@
+use crate::keychain;
+use crate::password;
+use rclio::{CliInputOutput, OutputType};
+use std::path::Path;
+
+pub fn callback_exec_enroll(
+    store: &password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    if let Err(err) = keychain::store_master_password(rooster_file_path, &store.master_password()) {
+        io.error(
+            format!(
+                "Woops, I could not save your master password in the keychain (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    io.success(
+        "Your master password has been saved in the keychain.",
+        OutputType::Standard,
+    );
+    io.info(
+        "Use `rooster --from-keychain <command>` to unlock Rooster without typing it.",
+        OutputType::Standard,
+    );
+    Ok(())
+}
+
+pub fn callback_exec_remove(
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    if let Err(err) = keychain::remove_master_password(rooster_file_path) {
+        io.error(
+            format!(
+                "Woops, I could not remove your master password from the keychain (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    io.success(
+        "Your master password has been removed from the keychain.",
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use crate::password;
+use crate::password::v2::KeyFile;
+use rclio::{CliInputOutput, OutputType};
+use std::path::Path;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    match matches.subcommand() {
+        Some(("add", add_matches)) => {
+            let path = Path::new(add_matches.get_one::<String>("path").unwrap());
+            let key_file = KeyFile::read(path).map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, I could not read the key file {} (reason: {}).",
+                        path.display(),
+                        err
+                    ),
+                    OutputType::Error,
+                );
+                1
+            })?;
+            store.set_key_file(Some(key_file));
+
+            io.success(
+                "Your password file now needs this key file along with your master password.",
+                OutputType::Standard,
+            );
+            io.info(
+                format!(
+                    "Use `rooster --keyfile {} <command>`, or set `keyfile` in your config. \
+                     Keep a copy of the key file somewhere safe: without it, your passwords are lost.",
+                    path.display()
+                ),
+                OutputType::Standard,
+            );
+        }
+        Some(("remove", _)) => {
+            if store.key_file().is_none() {
+                io.error(
+                    "Woops, your password file doesn't need a key file.",
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+            store.set_key_file(None);
+
+            io.success(
+                "Your password file only needs your master password now.",
+                OutputType::Standard,
+            );
+        }
+        _ => unreachable!("Validation should have been done by `clap` before"),
+    }
+
+    Ok(())
+}
@@ -1,16 +1,42 @@
 use crate::list;
+use crate::output;
 use crate::password;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 
 pub fn callback_exec(
-    _matches: &clap::ArgMatches,
+    matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
-    let passwords = store.get_all_passwords();
+    let tags = matches
+        .get_many::<String>("tag")
+        .unwrap_or_default()
+        .collect::<Vec<&String>>();
+    let passwords = store
+        .get_all_passwords()
+        .into_iter()
+        .filter(|p| tags.iter().all(|tag| p.has_tag(tag)))
+        .collect::<Vec<_>>();
 
-    if passwords.len() == 0 {
+    // For completion scripts, which can't tell colors and padding from names
+    if matches.get_flag("names-only") {
+        for p in passwords {
+            io.writeln(&p.name, OutputType::Standard);
+        }
+        return Ok(());
+    }
+
+    if output::is_json(matches) {
+        return output::print_passwords(&passwords, output::show_secrets(matches), io);
+    }
+
+    if passwords.is_empty() && !tags.is_empty() {
+        io.info(
+            "No passwords have these tags. Tag one with `rooster tag <app> <tag>`.",
+            OutputType::Standard,
+        );
+    } else if passwords.is_empty() {
         io.info(
             "No passwords on record yet. Add one with `rooster add <app> <username>`.",
             OutputType::Standard,
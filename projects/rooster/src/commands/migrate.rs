@@ -0,0 +1,85 @@
+use crate::migrations;
+use crate::password;
+use rclio::{CliInputOutput, OutputType};
+use std::fs::File;
+use std::path::Path;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    file: &mut File,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let pending = migrations::pending(store.version());
+    if pending.is_empty() {
+        io.success(
+            format!(
+                "Your Rooster file is up to date (version {}).",
+                store.version()
+            ),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    for migration in pending.iter() {
+        io.info(
+            format!(
+                "Version {} to {}: {}",
+                migration.from, migration.to, migration.description
+            ),
+            OutputType::Standard,
+        );
+    }
+
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!(
+                "Dry run: your Rooster file would be backed up to {} first. Nothing was changed.",
+                migrations::backup_path(rooster_file_path, store.version()).display()
+            ),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    migrate(store, io, rooster_file_path)?;
+    crate::sync_password_store(store, file, io)
+}
+
+/// Applies the pending migrations, if any, after backing up the file
+///
+/// This runs before every command, so that files are always upgraded the same way.
+pub fn migrate(
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let version = store.version();
+    match migrations::migrate(store, rooster_file_path) {
+        Ok(None) => Ok(()),
+        Ok(Some(backup)) => {
+            io.info(
+                format!(
+                    "Your Rooster file was upgraded from version {} to {}. The old file was backed up to {}.",
+                    version,
+                    store.version(),
+                    backup.display()
+                ),
+                OutputType::Error,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I could not back up your Rooster file before upgrading it (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
@@ -1,14 +1,41 @@
 pub mod add;
+pub mod agent;
+pub mod audit;
+pub mod backup;
+pub mod backups;
 pub mod change;
+pub mod clear_clipboard;
+pub mod completions;
+pub mod copy;
 pub mod delete;
+pub mod derive;
+pub mod expire;
+pub mod expiring;
 pub mod export;
 pub mod generate;
 pub mod get;
+pub mod history;
 pub mod import;
 pub mod init;
+pub mod keychain;
+pub mod keyfile;
 pub mod list;
+pub mod migrate;
+pub mod note;
+pub mod policy;
 pub mod regenerate;
+pub mod rekey;
 pub mod rename;
+pub mod restore;
+pub mod search;
 pub mod set_master_password;
+pub mod set_field;
 pub mod set_scrypt_params;
+pub mod set_totp;
+pub mod shell;
+pub mod sync;
+pub mod tag;
+pub mod totp;
 pub mod transfer;
+pub mod verify;
+pub mod where_used;
@@ -0,0 +1,65 @@
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like the notes of?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    let notes = match matches.get_one::<String>("note") {
+        Some(note) => Some(SafeString::from_string(note.clone())),
+        None if matches.get_flag("clear") => None,
+        None => {
+            match &password.notes {
+                Some(notes) => io.writeln(notes.deref(), OutputType::Standard),
+                None => io.info(
+                    format!(
+                        "There are no notes for {}. Add some with `rooster note '{}' <note>`.",
+                        password.name, password.name
+                    ),
+                    OutputType::Standard,
+                ),
+            }
+            return Ok(());
+        }
+    };
+
+    store
+        .change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                updated_at: ffi::time(),
+                notes: notes.clone(),
+                ..old_password
+            }
+        })
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't save the notes (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    io.success(
+        format!("Done! The notes for {} are saved.", password.name),
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use crate::generate::{check_password_len, PasswordSpec};
+use crate::list;
+use crate::password;
+use crate::password::v2::Policy;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+/// Describes a policy in a few words, ie: "16 characters, letters and digits only"
+pub fn describe(policy: &Policy) -> String {
+    let mut parts = Vec::new();
+    if let Some(pattern) = &policy.pattern {
+        parts.push(format!("looks like {}", pattern));
+    }
+    if let Some(words) = policy.words {
+        parts.push(format!("{} words", words));
+    }
+    if let Some(length) = policy.length {
+        parts.push(format!("{} characters", length));
+    }
+    if policy.alnum {
+        parts.push(String::from("letters and digits only"));
+    }
+    if let Some(charset) = &policy.charset {
+        parts.push(format!("only {}", charset));
+    }
+    if policy.no_ambiguous {
+        parts.push(String::from("no ambiguous characters"));
+    }
+    if parts.is_empty() {
+        parts.push(String::from("like for other apps"));
+    }
+    parts.join(", ")
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like the policy of?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    let policy = Policy {
+        length: matches.get_one::<usize>("length").copied(),
+        alnum: matches.get_flag("alnum"),
+        charset: matches.get_one::<String>("charset").cloned(),
+        no_ambiguous: matches.get_flag("no-ambiguous"),
+        words: matches.get_one::<usize>("words").copied(),
+        pattern: matches.get_one::<String>("pattern").cloned(),
+    };
+    let policy = match policy == Policy::default() {
+        true if matches.get_flag("clear") => None,
+        true => {
+            match &password.policy {
+                Some(policy) => io.writeln(describe(policy), OutputType::Standard),
+                None => io.info(
+                    format!(
+                        "There is no policy for {}. Set one with `rooster policy '{}' --length 16`.",
+                        password.name, password.name
+                    ),
+                    OutputType::Standard,
+                ),
+            }
+            return Ok(());
+        }
+        false => Some(policy),
+    };
+
+    if let Some(policy) = &policy {
+        if let Some(length) = policy.length {
+            check_password_len(length, io).ok_or(1)?;
+        }
+        // Policies that can't generate passwords, ie: with invalid characters, aren't saved
+        if let Err(err) = PasswordSpec::from_policy(policy).generate_hard_password() {
+            io.error(
+                format!("Woops, this policy doesn't work (reason: {}).", err),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    }
+
+    store
+        .change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                policy: policy.clone(),
+                ..old_password
+            }
+        })
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't save the policy (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    match &policy {
+        Some(policy) => io.success(
+            format!(
+                "Done! New passwords for {} will be generated like this: {}.",
+                password.name,
+                describe(policy)
+            ),
+            OutputType::Standard,
+        ),
+        None => io.success(
+            format!("Done! The policy for {} is removed.", password.name),
+            OutputType::Standard,
+        ),
+    }
+    Ok(())
+}
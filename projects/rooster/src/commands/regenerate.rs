@@ -1,8 +1,10 @@
 use crate::clip;
+use crate::commands::policy;
 use crate::ffi;
 use crate::generate::{check_password_len, PasswordSpec};
 use crate::list;
 use crate::password;
+use clap::parser::ValueSource;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 
@@ -23,17 +25,32 @@ pub fn callback_exec(
     .ok_or(1)?
     .clone();
 
-    let pwspec = PasswordSpec::new(
-        matches.get_flag("alnum"),
-        check_password_len(*matches.get_one::<usize>("length").unwrap(), io),
-    );
+    // The policy of the app is followed, unless other options are given
+    let given = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let pwspec = match &password.policy {
+        Some(policy) if !given("alnum") && !given("length") => {
+            io.info(
+                format!(
+                    "Following the policy of {}: {}.",
+                    password.name,
+                    policy::describe(policy)
+                ),
+                OutputType::Standard,
+            );
+            PasswordSpec::from_policy(policy)
+        }
+        _ => PasswordSpec::new(
+            matches.get_flag("alnum"),
+            check_password_len(*matches.get_one::<usize>("length").unwrap(), io),
+        ),
+    };
 
     let password_as_string = match pwspec.generate_hard_password() {
         Ok(password_as_string) => password_as_string,
         Err(io_err) => {
             io.error(
                 format!(
-                    "Woops, I could not generate the password (reason: {:?}).",
+                    "Woops, I could not generate the password (reason: {}).",
                     io_err
                 ),
                 OutputType::Error,
@@ -50,13 +67,21 @@ pub fn callback_exec(
                 password: password_as_string.clone(),
                 created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                totp: old_password.totp.clone(),
+                url: old_password.url.clone(),
+                notes: old_password.notes.clone(),
+                fields: old_password.fields.clone(),
+                history: old_password.history_with_current(ffi::time()),
+                tags: old_password.tags.clone(),
+                policy: old_password.policy.clone(),
+                expiry: old_password.renewed_expiry(ffi::time()),
             }
         });
 
     match change_result {
         Ok(password) => {
             let show = matches.get_flag("show");
-            clip::confirm_password_retrieved(show, &password, io);
+            clip::confirm_password_retrieved(show, &password, clip::clear_after(matches), io);
             Ok(())
         }
         Err(err) => {
@@ -0,0 +1,57 @@
+use crate::password;
+use crate::password::v2::Kdf;
+use rclio::{CliInputOutput, OutputType};
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let kdf = match matches.get_one::<String>("kdf").map(|kdf| kdf.as_str()) {
+        Some("argon2id") => Some(argon2id_from_matches(matches, io)?),
+        Some("scrypt") => Some(Kdf::SCRYPT_DEFAULT),
+        _ => None,
+    };
+
+    store.rekey();
+    if let Some(kdf) = kdf {
+        store.set_kdf(kdf);
+    }
+
+    io.success(
+        format!(
+            "Your passwords have been encrypted with a new key, which your master password unlocks with {}.",
+            store.kdf()
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
+
+fn argon2id_from_matches(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) -> Result<Kdf, i32> {
+    let memory_kib = *matches.get_one::<u32>("memory").unwrap();
+    let iterations = *matches.get_one::<u32>("iterations").unwrap();
+    let parallelism = *matches.get_one::<u32>("parallelism").unwrap();
+
+    let kdf = Kdf::argon2id(memory_kib, iterations, parallelism).map_err(|err| {
+        io.error(
+            format!("Woops, these parameters can't be used (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    if !matches.get_flag("force") && (memory_kib > 1024 * 1024 || iterations > 10) {
+        io.error("These parameters seem very high. You might be unable to open your password file ever again. Aborting.", OutputType::Error);
+        io.error(
+            "Run with --force to force, but make a backup of your password file first.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    Ok(kdf)
+}
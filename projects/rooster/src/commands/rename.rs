@@ -30,6 +30,14 @@ pub fn callback_exec(
                 password: old_password.password.clone(),
                 created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                totp: old_password.totp.clone(),
+                url: old_password.url.clone(),
+                notes: old_password.notes.clone(),
+                fields: old_password.fields.clone(),
+                history: old_password.history.clone(),
+                tags: old_password.tags.clone(),
+                policy: old_password.policy.clone(),
+                expiry: old_password.expiry.clone(),
             }
         });
 
@@ -0,0 +1,72 @@
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+    let version = *matches.get_one::<usize>("version").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like to restore a previous password of?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    // Versions count from the most recent previous password, like `rooster history` shows them
+    if version == 0 || version > password.history.len() {
+        io.error(
+            format!(
+                "Woops, {} has no version {}. See its previous passwords with `rooster history '{}'`.",
+                password.name, version, password.name
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+    let index = password.history.len() - version;
+
+    store
+        .change_password(
+            &password.name,
+            &|mut old_password: password::v2::Password| {
+                // The password being replaced goes to the history, so that restoring can be undone
+                let restored = old_password.history.remove(index);
+                password::v2::Password {
+                    password: restored.password,
+                    updated_at: ffi::time(),
+                    history: old_password.history_with_current(ffi::time()),
+                    expiry: old_password.renewed_expiry(ffi::time()),
+                    ..old_password
+                }
+            },
+        )
+        .map_err(|err| {
+            io.error(
+                format!(
+                    "Woops, I couldn't save the restored password (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    io.success(
+        format!(
+            "Done! {} has its previous password back, and the one it replaced is now version 1.",
+            password.name
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use crate::list;
+use crate::output;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("query").unwrap();
+
+    let passwords = store.search_passwords(query);
+    if output::is_json(matches) {
+        output::print_passwords(&passwords, output::show_secrets(matches), io)?;
+        return match passwords.is_empty() {
+            true => Err(1),
+            false => Ok(()),
+        };
+    }
+
+    if passwords.is_empty() {
+        io.error(
+            format!("Woops, I can't find any passwords for \"{}\".", query),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    list::print_list_of_passwords(&passwords, list::WITHOUT_NUMBERS, io);
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+    let name = matches.get_one::<String>("name").unwrap().trim();
+    if name.is_empty() {
+        io.error(
+            "Woops, the name of the field can't be empty.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like to set a field of?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    let value = match matches.get_one::<String>("value") {
+        Some(value) => Some(SafeString::from_string(value.clone())),
+        None if matches.get_flag("delete") => None,
+        // Values may be secret, ie: a PIN, so they are read like passwords
+        None => Some(
+            io.prompt_password(format!("What is the {} for \"{}\"? ", name, password.name))
+                .map_err(|err| {
+                    io.error(
+                        format!("\nI couldn't read the value (reason: {:?}).", err),
+                        OutputType::Error,
+                    );
+                    1
+                })?,
+        ),
+    };
+
+    store
+        .change_password(&password.name, &|old_password: password::v2::Password| {
+            let mut new_password = password::v2::Password {
+                updated_at: ffi::time(),
+                ..old_password
+            };
+            if name.eq_ignore_ascii_case("url") {
+                new_password.url = value.as_ref().map(|url| url.deref().clone());
+            } else {
+                match &value {
+                    Some(value) => new_password.fields.insert(name.to_string(), value.clone()),
+                    None => new_password.fields.remove(name),
+                };
+            }
+            new_password
+        })
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't save the field (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    io.success(
+        format!(
+            "Done! You can see it with `rooster get --full '{}'`.",
+            password.name
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use crate::password;
+use crate::password::v2::Kdf;
 use rclio::{CliInputOutput, OutputType};
 
 pub fn callback_exec(
@@ -27,7 +28,7 @@ pub fn callback_exec(
         return Err(1);
     }
 
-    store.change_scrypt_params(log2_n, r, p);
+    store.set_kdf(Kdf::Scrypt { log2_n, r, p });
 
     Ok(())
 }
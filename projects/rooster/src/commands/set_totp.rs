@@ -0,0 +1,75 @@
+use crate::ffi;
+use crate::list;
+use crate::password;
+use crate::totp::Totp;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like to add a TOTP secret to?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    // The URI contains the secret, so it is read like a password
+    let uri = io
+        .prompt_password(format!(
+            "What is the otpauth:// URI (or the secret) for \"{}\"? ",
+            password.name
+        ))
+        .map_err(|err| {
+            io.error(
+                format!("\nI couldn't read the TOTP URI (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    let totp = Totp::from_uri(uri.deref()).map_err(|err| {
+        io.error(
+            format!("Woops, this TOTP URI is not valid (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    store
+        .change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                updated_at: ffi::time(),
+                totp: Some(totp.clone()),
+                ..old_password
+            }
+        })
+        .map_err(|err| {
+            io.error(
+                format!(
+                    "Woops, I couldn't save the TOTP secret (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    io.success(
+        format!(
+            "Done! You can now get 2FA codes for {} with `rooster totp '{}'`.",
+            password.name, password.name
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -0,0 +1,305 @@
+use crate::config::Config;
+use crate::password::v2::{KeyFile, PasswordStore};
+use crate::unattended;
+use clap::{Arg, Command};
+use rclio::{CliInputOutput, History, OutputType};
+use rtoolbox::safe_string::SafeString;
+use std::fs::File;
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The commands of `rooster` that can be run in the shell
+const ROOSTER_COMMANDS: [&str; 3] = ["get", "generate", "list"];
+
+/// Builds the parser for the commands typed in the shell, out of the ones of `rooster`
+fn shell_command(rooster: &Command, matches: &clap::ArgMatches) -> Command {
+    // Copied passwords are cleared like the `rooster shell` command says
+    let clear_clipboard_after = rooster
+        .get_arguments()
+        .find(|arg| arg.get_id() == "clear-clipboard-after")
+        .cloned()
+        .map(|arg: Arg| {
+            arg.default_value(
+                matches
+                    .get_one::<u64>("clear-clipboard-after")
+                    .unwrap()
+                    .to_string(),
+            )
+        })
+        .unwrap();
+    // `--output` is global in `rooster`, so commands only have it if the shell has it too
+    let output = rooster
+        .get_arguments()
+        .find(|arg| arg.get_id() == "output")
+        .cloned()
+        .unwrap();
+
+    let mut command = Command::new("rooster")
+        .no_binary_name(true)
+        .subcommand_required(true)
+        .disable_version_flag(true)
+        .color(clap::ColorChoice::Never)
+        .arg(clear_clipboard_after)
+        .arg(output)
+        .subcommand(
+            Command::new("quit")
+                .visible_alias("exit")
+                .about("Forget your passwords and leave the shell"),
+        );
+    for name in ROOSTER_COMMANDS {
+        command = command.subcommand(rooster.find_subcommand(name).unwrap().clone());
+    }
+    command
+}
+
+/// Splits a command into words, keeping words between double quotes together, ie: app names with
+/// spaces
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Completes the name of a command, then the name of an app
+fn complete(line: &str, commands: &[String], apps: &[String]) -> Vec<String> {
+    let (command, typed) = match line.rsplit_once(' ') {
+        Some(split) => split,
+        None => {
+            return commands
+                .iter()
+                .filter(|name| name.starts_with(line))
+                .map(|name| format!("{} ", name))
+                .collect()
+        }
+    };
+
+    let typed = typed.trim_start_matches('"').to_lowercase();
+    apps.iter()
+        .filter(|app| app.to_lowercase().starts_with(&typed))
+        .map(|app| match app.contains(' ') {
+            true => format!("{} \"{}\"", command, app),
+            false => format!("{} {}", command, app),
+        })
+        .collect()
+}
+
+/// Forgets the passwords, until the master password is typed again
+fn lock(
+    store: &mut Option<PasswordStore>,
+    lock_after: Option<Duration>,
+    io: &mut impl CliInputOutput,
+) {
+    if store.take().is_some() {
+        io.info(
+            format!(
+                "Rooster locked after {} seconds without commands.",
+                lock_after.unwrap_or_default().as_secs()
+            ),
+            OutputType::Standard,
+        );
+    }
+}
+
+fn open(rooster_file_path: &Path, io: &mut impl CliInputOutput) -> Result<File, i32> {
+    crate::open_password_file(&rooster_file_path.to_string_lossy()).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not open your password file (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+/// Asks for the master password again, once the shell locked, unless it came from a file
+/// descriptor, which can't be read again
+fn unlock(
+    rooster_file_path: &Path,
+    config: &Config,
+    key_file: Option<&KeyFile>,
+    fd_master_password: Option<&SafeString>,
+    io: &mut impl CliInputOutput,
+) -> Result<PasswordStore, i32> {
+    let mut file = open(rooster_file_path, io)?;
+    match fd_master_password {
+        Some(master_password) => crate::get_password_store_with_master_password(
+            &mut file,
+            unattended::MASTER_PASSWORD_FD_ENV_VAR,
+            master_password.clone(),
+            key_file,
+            config,
+            io,
+        ),
+        None => crate::get_password_store(&mut file, config, None, None, key_file, io),
+    }
+}
+
+/// Saves the store after a command changed it
+fn save(
+    store: &mut PasswordStore,
+    subcommand: &str,
+    rooster_file_path: &Path,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let mut file = open(rooster_file_path, io)?;
+    crate::sync_password_store(store, &mut file, io)?;
+    crate::commit_password_file(rooster_file_path, subcommand, io);
+    Ok(())
+}
+
+/// Runs commands with the password file unlocked once, until `quit`, and forgets the passwords
+/// when no command was typed for `shell_lock_secs`, until the master password is typed again
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    rooster: &Command,
+    store: PasswordStore,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+    config: &Config,
+    key_file: Option<&KeyFile>,
+) -> Result<(), i32> {
+    let mut command = shell_command(rooster, matches);
+    let commands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    let lock_after = config.shell_lock_after();
+    let read_only = matches.get_flag("read-only");
+    let fd_master_password = match unattended::master_password_fd_was_read() {
+        true => Some(store.master_password()),
+        false => None,
+    };
+    let mut store = Some(store);
+    let mut history = History::new();
+    let mut last_command_at = Instant::now();
+
+    io.info(
+        "Type `help` to see the commands, and `quit` when you're done.",
+        OutputType::Standard,
+    );
+    loop {
+        let apps: Vec<String> = match &store {
+            Some(store) => store
+                .get_all_passwords()
+                .iter()
+                .map(|password| password.name.clone())
+                .collect(),
+            None => vec![],
+        };
+        let completer = |line: &str| complete(line, &commands, &apps);
+        let idle = store.as_ref().and(lock_after);
+        let line = match io.prompt_command("rooster> ", &completer, &mut history, idle) {
+            Ok(line) => line,
+            Err(err) if err.kind() == IoErrorKind::TimedOut => {
+                lock(&mut store, lock_after, io);
+                continue;
+            }
+            Err(err)
+                if err.kind() == IoErrorKind::UnexpectedEof
+                    || err.kind() == IoErrorKind::Interrupted =>
+            {
+                return Ok(());
+            }
+            Err(err) => {
+                io.error(
+                    format!("Woops, I could not read your command (reason: {}).", err),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        };
+
+        // A command that is typed slowly, or read from somewhere else than the TTY, doesn't time
+        // out, so we also check how long it's been.
+        if matches!(lock_after, Some(lock_after) if last_command_at.elapsed() >= lock_after) {
+            lock(&mut store, lock_after, io);
+        }
+
+        let words = split_words(&line);
+        if words.is_empty() {
+            continue;
+        }
+        let matches = match command.try_get_matches_from_mut(words) {
+            Ok(matches) => matches,
+            Err(err) => {
+                match err.use_stderr() {
+                    true => io.error(err.to_string().trim_end(), OutputType::Error),
+                    false => io.info(err.to_string().trim_end(), OutputType::Standard),
+                }
+                continue;
+            }
+        };
+        let (subcommand, command_matches) = matches.subcommand().unwrap();
+        if subcommand == "quit" {
+            return Ok(());
+        }
+
+        if crate::check_read_only(read_only, subcommand, command_matches, io).is_err() {
+            continue;
+        }
+
+        let store = match &mut store {
+            Some(store) => store,
+            None => store.insert(unlock(
+                rooster_file_path,
+                config,
+                key_file,
+                fd_master_password.as_ref(),
+                io,
+            )?),
+        };
+        let callback = match subcommand {
+            "get" => crate::commands::get::callback_exec,
+            "generate" => crate::commands::generate::callback_exec,
+            "list" => crate::commands::list::callback_exec,
+            _ => unreachable!("Validation should have been done by `clap` before"),
+        };
+        let changes = crate::changes_password_file(subcommand);
+        if changes {
+            crate::back_up_password_file(rooster_file_path, config, io);
+        }
+        // Errors are reported by the command, and the shell goes on
+        if callback(command_matches, store, io).is_ok() && changes {
+            save(store, subcommand, rooster_file_path, io)?;
+        }
+        last_command_at = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{complete, split_words};
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("  get  -s github "), ["get", "-s", "github"]);
+        assert_eq!(split_words("get \"My Bank\""), ["get", "My Bank"]);
+        assert_eq!(split_words("get \"\""), ["get", ""]);
+        assert!(split_words("  ").is_empty());
+    }
+
+    #[test]
+    fn test_complete() {
+        let commands = vec!["get".to_string(), "generate".to_string()];
+        let apps = vec!["Github".to_string(), "My Bank".to_string()];
+        assert_eq!(complete("ge", &commands, &apps), ["get ", "generate "]);
+        assert_eq!(complete("get -s git", &commands, &apps), ["get -s Github"]);
+        assert_eq!(complete("get m", &commands, &apps), ["get \"My Bank\""]);
+        assert!(complete("get x", &commands, &apps).is_empty());
+    }
+}
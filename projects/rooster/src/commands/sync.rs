@@ -0,0 +1,290 @@
+use crate::backup;
+use crate::config::Config;
+use crate::password::v2::{KeyFile, Password, PasswordStore};
+use crate::sync::{self, Relation};
+use rclio::{CliInputOutput, OutputType};
+use rtoolbox::safe_vec::SafeVec;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+fn report_error(io: &mut impl CliInputOutput, action: &str, err: std::io::Error) -> i32 {
+    io.error(
+        format!("Woops, I could not {} (reason: {}).", action, err),
+        OutputType::Error,
+    );
+    1
+}
+
+fn check_enabled(io: &mut impl CliInputOutput, rooster_file_path: &Path) -> Result<(), i32> {
+    if !sync::is_enabled(rooster_file_path) {
+        io.error(
+            "Woops, sync isn't set up for this password file. Run `rooster sync init <remote>` first.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+    Ok(())
+}
+
+pub fn callback_exec_init(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let remote = matches.get_one::<String>("remote").unwrap();
+    sync::init(rooster_file_path, remote).map_err(|err| report_error(io, "set up sync", err))?;
+
+    io.success(
+        format!(
+            "Your password file is now kept in the git repository {}, and committed after each change.",
+            sync::sync_dir(rooster_file_path).display()
+        ),
+        OutputType::Standard,
+    );
+    io.info(
+        "Run `rooster sync pull` to get the password file from the remote, and `rooster sync push` to send yours.",
+        OutputType::Standard,
+    );
+    Ok(())
+}
+
+pub fn callback_exec_push(
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    check_enabled(io, rooster_file_path)?;
+    if rooster_file_path.exists() {
+        sync::commit(rooster_file_path, "Update password file")
+            .map_err(|err| report_error(io, "commit your password file", err))?;
+    }
+
+    let relation =
+        sync::fetch(rooster_file_path).map_err(|err| report_error(io, "fetch the remote", err))?;
+    match relation {
+        Relation::UpToDate => {
+            io.success("The remote is already up to date.", OutputType::Standard);
+            Ok(())
+        }
+        Relation::Ahead => {
+            sync::push(rooster_file_path)
+                .map_err(|err| report_error(io, "push your password file", err))?;
+            io.success("Your password file was pushed.", OutputType::Standard);
+            Ok(())
+        }
+        Relation::Behind | Relation::Diverged { .. } => {
+            io.error(
+                "Woops, the remote has changes that you don't have. Run `rooster sync pull` first.",
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+/// Gets the remote password file, merging it with the local one entry by entry if both changed
+pub fn callback_exec_pull(
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+    config: &Config,
+    key_file: Option<KeyFile>,
+) -> Result<(), i32> {
+    check_enabled(io, rooster_file_path)?;
+    if rooster_file_path.exists() {
+        sync::commit(rooster_file_path, "Update password file")
+            .map_err(|err| report_error(io, "commit your password file", err))?;
+    }
+
+    let relation =
+        sync::fetch(rooster_file_path).map_err(|err| report_error(io, "fetch the remote", err))?;
+    let base = match relation {
+        Relation::UpToDate | Relation::Ahead => {
+            io.success(
+                "Your password file already has the changes of the remote.",
+                OutputType::Standard,
+            );
+            return Ok(());
+        }
+        Relation::Behind => {
+            back_up(io, rooster_file_path, config);
+            sync::fast_forward(rooster_file_path)
+                .map_err(|err| report_error(io, "get the remote password file", err))?;
+            io.success(
+                "Your password file was updated from the remote.",
+                OutputType::Standard,
+            );
+            return Ok(());
+        }
+        Relation::Diverged { base } => base,
+    };
+
+    io.info(
+        "Your password file and the remote one both changed, so I'll merge them password by password.",
+        OutputType::Standard,
+    );
+    let mut file = crate::open_password_file(&rooster_file_path.to_string_lossy())
+        .map_err(|err| report_error(io, "open your password file", err))?;
+    let mut store =
+        crate::get_password_store(&mut file, config, None, None, key_file.as_ref(), io)?;
+
+    let remote = sync::read_remote_file(rooster_file_path)
+        .map_err(|err| report_error(io, "read the remote password file", err))?;
+    let remote = unlock(&store, remote, "the remote password file", io)?;
+    let base = match base {
+        Some(base) => {
+            let input = sync::read_file(rooster_file_path, &base)
+                .map_err(|err| report_error(io, "read the previous password file", err))?;
+            Some(unlock(&store, input, "the previous password file", io)?)
+        }
+        None => None,
+    };
+
+    let changes = merge(base.as_ref(), &mut store, &remote, io)?;
+
+    back_up(io, rooster_file_path, config);
+    crate::sync_password_store(&mut store, &mut file, io)?;
+    sync::commit_merge(rooster_file_path)
+        .map_err(|err| report_error(io, "commit the merged password file", err))?;
+    io.success(
+        format!(
+            "Your password file was merged with the remote one, which changed {} password(s). \
+             Run `rooster sync push` to send the result.",
+            changes
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
+
+fn back_up(io: &mut impl CliInputOutput, rooster_file_path: &Path, config: &Config) {
+    if config.backups_keep == 0 || !rooster_file_path.exists() {
+        return;
+    }
+    if let Err(err) = backup::write_backup(rooster_file_path, config.backups_keep) {
+        io.warning(
+            format!(
+                "I could not back up your password file to {} (reason: {}).",
+                backup::backups_dir(rooster_file_path).display(),
+                err
+            ),
+            OutputType::Error,
+        );
+    }
+}
+
+/// Opens another version of the password file with the same master password and key file
+fn unlock(
+    store: &PasswordStore,
+    input: Vec<u8>,
+    what: &str,
+    io: &mut impl CliInputOutput,
+) -> Result<PasswordStore, i32> {
+    PasswordStore::from_input(
+        store.master_password(),
+        store.key_file(),
+        SafeVec::new(input),
+    )
+    .map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not unlock {} with your master password, which may have changed \
+                 on another machine (reason: {:?}).",
+                what, err
+            ),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+fn by_name(store: &PasswordStore) -> BTreeMap<String, Password> {
+    store
+        .get_all_passwords()
+        .into_iter()
+        .map(|password| (password.name.clone(), password.clone()))
+        .collect()
+}
+
+/// Applies the remote changes since `base` to the store, and asks which side to keep for
+/// passwords that both sides changed. Returns the number of passwords that changed in the store.
+fn merge(
+    base: Option<&PasswordStore>,
+    store: &mut PasswordStore,
+    remote: &PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<usize, i32> {
+    let base = base.map(by_name).unwrap_or_default();
+    let local = by_name(store);
+    let remote = by_name(remote);
+    let names: BTreeSet<&String> = base
+        .keys()
+        .chain(local.keys())
+        .chain(remote.keys())
+        .collect();
+
+    let mut changes = 0;
+    for name in names {
+        let (base, local, remote) = (base.get(name), local.get(name), remote.get(name));
+        if local == remote || remote == base {
+            continue;
+        }
+        if local != base && !ask_keep_remote(name, local, remote, io)? {
+            continue;
+        }
+
+        let result = match (local, remote) {
+            (_, None) => store.delete_password(name).map(|_| ()),
+            (None, Some(remote)) => store.add_password(remote.clone()),
+            (Some(_), Some(remote)) => store.change_password(name, &|_| remote.clone()).map(|_| ()),
+        };
+        if let Err(err) = result {
+            io.error(
+                format!("Woops, I could not merge {} (reason: {:?}).", name, err),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+        changes += 1;
+    }
+    Ok(changes)
+}
+
+/// Asks whether to keep the remote version of a password that both sides changed
+fn ask_keep_remote(
+    name: &str,
+    local: Option<&Password>,
+    remote: Option<&Password>,
+    io: &mut impl CliInputOutput,
+) -> Result<bool, i32> {
+    let describe = |password: Option<&Password>| match password {
+        Some(password) => format!("changed, with username \"{}\"", password.username),
+        None => "deleted".to_string(),
+    };
+    io.info(
+        format!(
+            "{} was {} here, and {} on the remote.",
+            name,
+            describe(local),
+            describe(remote)
+        ),
+        OutputType::Standard,
+    );
+    loop {
+        let answer = io
+            .prompt_line("Keep yours or the remote one? [y/r] ")
+            .map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, an error occured while reading your response (reason: {:?}).",
+                        err
+                    ),
+                    OutputType::Error,
+                );
+                1
+            })?;
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yours" => return Ok(false),
+            "r" | "remote" => return Ok(true),
+            _ => io.error("Please answer with \"y\" or \"r\".", OutputType::Error),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like the tags of?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    let changed_tags = match matches.get_many::<String>("tags") {
+        Some(tags) => tags.collect::<Vec<&String>>(),
+        None => {
+            if password.tags.is_empty() {
+                io.info(
+                    format!(
+                        "There are no tags for {}. Add some with `rooster tag '{}' <tag>...`.",
+                        password.name, password.name
+                    ),
+                    OutputType::Standard,
+                );
+            } else {
+                io.writeln(password.tags.join("\n"), OutputType::Standard);
+            }
+            return Ok(());
+        }
+    };
+    let remove = matches.get_flag("remove");
+
+    let mut tags = password.tags.clone();
+    if remove {
+        tags.retain(|tag| {
+            !changed_tags
+                .iter()
+                .any(|changed| changed.to_lowercase() == tag.to_lowercase())
+        });
+    } else {
+        for tag in changed_tags {
+            if !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+
+    store
+        .change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                tags: tags.clone(),
+                ..old_password
+            }
+        })
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't save the tags (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    io.success(
+        format!("Done! The tags for {} are saved.", password.name),
+        OutputType::Standard,
+    );
+    Ok(())
+}
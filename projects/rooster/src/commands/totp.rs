@@ -0,0 +1,54 @@
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which app would you like a 2FA code for?",
+        io,
+    )
+    .ok_or(1)?;
+
+    let totp = match password.totp.as_ref() {
+        Some(totp) => totp,
+        None => {
+            io.error(
+                format!(
+                    "Woops, there is no TOTP secret for {}. You can add one with `rooster set-totp '{}'`.",
+                    password.name, password.name
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    let now = ffi::time() as u64;
+    let code = totp.code(now).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't generate the code (reason: {:?}).", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    // Only the code goes to stdout, so that it can be piped
+    io.writeln(code, OutputType::Standard);
+    io.info(
+        format!("Valid for {} more seconds.", totp.seconds_left(now)),
+        OutputType::Error,
+    );
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::migrations;
+use crate::password::v2::{self, PasswordStore};
+use crate::password::PasswordError;
+use rclio::{CliInputOutput, OutputType};
+use std::path::Path;
+
+/// Why no master password can unlock a file, if it's truncated or malformed
+fn damage(err: &PasswordError, len: usize) -> Option<String> {
+    match err {
+        PasswordError::TruncatedError => Some(format!(
+            "it ends after {} bytes, before all of its contents",
+            len
+        )),
+        PasswordError::CorruptionError => {
+            Some(String::from("its header or contents are malformed"))
+        }
+        _ => None,
+    }
+}
+
+fn report_damage(reason: String, io: &mut impl CliInputOutput) {
+    io.error(
+        format!("Your Rooster file is corrupted: {}.", reason),
+        OutputType::Error,
+    );
+    io.info(
+        "Restore it from a backup, see `rooster backups list`.",
+        OutputType::Error,
+    );
+}
+
+/// Refuses files that are truncated or malformed, before the master password is asked for
+pub fn check_structure(input: &[u8], io: &mut impl CliInputOutput) -> Result<(), i32> {
+    match v2::inspect(input)
+        .err()
+        .and_then(|err| damage(&err, input.len()))
+    {
+        Some(reason) => {
+            report_damage(reason, io);
+            Err(1)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Checks the header of the file, which doesn't need the master password, and reports its format
+pub fn callback_exec_header(
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let input = std::fs::read(rooster_file_path).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not read your Rooster file (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    let info = match v2::inspect(&input) {
+        Ok(info) => info,
+        Err(PasswordError::NeedUpgradeErrorFromV1) => {
+            io.info(
+                "Format version: 1, which is upgraded once you unlock it",
+                OutputType::Standard,
+            );
+            return Ok(());
+        }
+        Err(PasswordError::OutdatedRoosterBinaryError) => {
+            io.error(
+                "Your Rooster file was made by a newer version of Rooster. Try upgrading Rooster to the latest version.",
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+        Err(err) => {
+            let reason = damage(&err, input.len()).unwrap_or_else(|| format!("{:?}", err));
+            report_damage(reason, io);
+            return Err(1);
+        }
+    };
+
+    let format = match migrations::pending(info.version).last() {
+        None => format!("Format version: {} (the latest)", info.version),
+        Some(migration) => format!(
+            "Format version: {}, upgraded to {} the next time it changes (see `rooster migrate --dry-run`)",
+            info.version, migration.to
+        ),
+    };
+    io.info(format, OutputType::Standard);
+    io.info(
+        format!("Key derivation: {}", info.kdf),
+        OutputType::Standard,
+    );
+    io.info(
+        format!(
+            "Key file: {}",
+            match info.needs_key_file {
+                true => "needed",
+                false => "not needed",
+            }
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
+
+/// Reports on a file that was unlocked, which means its signature matched
+pub fn callback_exec(store: &PasswordStore, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    io.success(
+        format!(
+            "The signature matches: your Rooster file is intact, and has {} password(s).",
+            store.get_all_passwords().len()
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
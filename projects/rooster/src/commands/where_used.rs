@@ -0,0 +1,37 @@
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let password = io
+        .prompt_password("What password are you looking for? ")
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't read the password (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    let passwords = store.find_passwords_by_value(&password);
+    if passwords.is_empty() {
+        io.success("No app uses this password.", OutputType::Standard);
+        return Ok(());
+    }
+
+    io.warning(
+        format!(
+            "{} app(s) use this password. If it was leaked, change it with `rooster change` or `rooster regenerate`:",
+            passwords.len()
+        ),
+        OutputType::Standard,
+    );
+    list::print_list_of_passwords(&passwords, list::WITHOUT_NUMBERS, io);
+
+    Ok(())
+}
@@ -0,0 +1,492 @@
+// Completion scripts for shells, as written by `rooster completions`.
+//
+// Commands and options come from the parser, and app names from `rooster list --names-only`,
+// which never asks for the master password.
+
+use clap::{ArgAction, Command};
+
+pub const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "powershell"];
+
+/// An option of a command, ie: `-s`/`--show`
+struct Flag {
+    long: Option<String>,
+    short: Option<char>,
+    help: String,
+    takes_value: bool,
+}
+
+impl Flag {
+    fn names(&self) -> Vec<String> {
+        let mut names = vec![];
+        names.extend(self.long.iter().map(|long| format!("--{}", long)));
+        names.extend(self.short.iter().map(|short| format!("-{}", short)));
+        names
+    }
+}
+
+/// What can be typed after a command, ie: `rooster backup` or `rooster backup now`
+struct Spec {
+    /// The names of the command and its parents, ie: `backup now`, empty for `rooster` itself
+    path: String,
+    about: String,
+    flags: Vec<Flag>,
+    /// Whether the command is given the name of an app, which is completed from the password file
+    takes_app: bool,
+    subcommands: Vec<Spec>,
+}
+
+impl Spec {
+    fn new(command: &Command, path: String) -> Spec {
+        let flags = command
+            .get_arguments()
+            .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+            .map(|arg| Flag {
+                long: arg.get_long().map(String::from),
+                short: arg.get_short(),
+                help: first_line(arg.get_help().map(|help| help.to_string())),
+                takes_value: matches!(arg.get_action(), ArgAction::Set | ArgAction::Append),
+            })
+            .collect();
+        let subcommands = command
+            .get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| {
+                let name = subcommand.get_name();
+                match path.is_empty() {
+                    true => Spec::new(subcommand, name.to_string()),
+                    false => Spec::new(subcommand, format!("{} {}", path, name)),
+                }
+            })
+            .collect();
+
+        Spec {
+            path,
+            about: first_line(command.get_about().map(|about| about.to_string())),
+            flags,
+            takes_app: command
+                .get_positionals()
+                .next()
+                .is_some_and(|arg| arg.get_id() == "app"),
+            subcommands,
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.path.rsplit(' ').next().unwrap_or_default()
+    }
+
+    /// Returns this command and all the ones below it
+    fn all(&self) -> Vec<&Spec> {
+        let mut all = vec![self];
+        for subcommand in self.subcommands.iter() {
+            all.extend(subcommand.all());
+        }
+        all
+    }
+
+    /// Returns the options which are followed by a value, whichever command they belong to
+    fn options_with_values(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .all()
+            .iter()
+            .flat_map(|spec| spec.flags.iter())
+            .filter(|flag| flag.takes_value)
+            .flat_map(|flag| flag.names())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn flag_names(&self) -> Vec<String> {
+        self.flags.iter().flat_map(|flag| flag.names()).collect()
+    }
+
+    fn subcommand_names(&self) -> Vec<&str> {
+        self.subcommands.iter().map(|spec| spec.name()).collect()
+    }
+}
+
+fn first_line(s: Option<String>) -> String {
+    s.unwrap_or_default()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Quotes for bash and zsh, which don't expand anything between single quotes
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+fn quote_fish(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn quote_powershell(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Returns the completion script of `shell`, one of `SHELLS`
+pub fn script(shell: &str, rooster: &Command) -> String {
+    let mut rooster = rooster.clone();
+    // Adds `--help`, and the global options to each command
+    rooster.build();
+    let spec = Spec::new(&rooster, String::new());
+
+    match shell {
+        "bash" => bash(&spec),
+        "zsh" => zsh(&spec),
+        "fish" => fish(&spec),
+        "powershell" => powershell(&spec),
+        _ => unreachable!("Validation should have been done by `clap` before"),
+    }
+}
+
+/// Returns the commands below `rooster` as a `case` pattern, ie: `'get'|'backup now'`
+fn command_paths(spec: &Spec) -> String {
+    spec.all()
+        .iter()
+        .skip(1)
+        .map(|spec| quote(&spec.path))
+        .collect::<Vec<String>>()
+        .join("|")
+}
+
+fn bash(spec: &Spec) -> String {
+    let mut cases = String::new();
+    for spec in spec.all() {
+        let other = match (spec.subcommands.is_empty(), spec.takes_app) {
+            (false, _) => format!(
+                "COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+                quote(&spec.subcommand_names().join(" "))
+            ),
+            (true, true) => String::from("_rooster_apps \"$cur\""),
+            (true, false) => String::from(":"),
+        };
+        cases.push_str(&format!(
+            "        {})\n            case \"$cur\" in\n                -*) COMPREPLY=($(compgen -W {} -- \"$cur\")) ;;\n                *) {} ;;\n            esac\n            ;;\n",
+            quote(&spec.path),
+            quote(&spec.flag_names().join(" ")),
+            other
+        ));
+    }
+
+    format!(
+        r#"_rooster_apps() {{
+    local IFS=$'\n'
+    compopt -o filenames 2>/dev/null
+    COMPREPLY=($(compgen -W "$(rooster list --names-only 2>/dev/null)" -- "$1"))
+}}
+
+_rooster() {{
+    local cur=${{COMP_WORDS[COMP_CWORD]}}
+    local command_path="" word i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word=${{COMP_WORDS[i]}}
+        case "$word" in
+            {options}) ((i++)) ;;
+            -*) ;;
+            *)
+                case "${{command_path:+$command_path }}$word" in
+                    {paths}) command_path="${{command_path:+$command_path }}$word" ;;
+                esac
+                ;;
+        esac
+    done
+
+    case "$command_path" in
+{cases}    esac
+}}
+
+complete -o default -F _rooster rooster
+"#,
+        options = spec.options_with_values().join("|"),
+        paths = command_paths(spec),
+        cases = cases,
+    )
+}
+
+fn zsh(spec: &Spec) -> String {
+    let mut cases = String::new();
+    for spec in spec.all() {
+        let options: Vec<String> = spec
+            .flags
+            .iter()
+            .flat_map(|flag| {
+                flag.names()
+                    .into_iter()
+                    .map(move |name| quote(&format!("{}:{}", name, flag.help)))
+            })
+            .collect();
+        let subcommands: Vec<String> = spec
+            .subcommands
+            .iter()
+            .map(|spec| quote(&format!("{}:{}", spec.name(), spec.about)))
+            .collect();
+        cases.push_str(&format!(
+            "        ({})\n            options=({})\n            subcommands=({})\n            takes_app={}\n            ;;\n",
+            quote(&spec.path),
+            options.join(" "),
+            subcommands.join(" "),
+            spec.takes_app as u8
+        ));
+    }
+
+    format!(
+        r#"#compdef rooster
+
+_rooster_apps() {{
+    local -a apps
+    apps=("${{(@f)$(rooster list --names-only 2>/dev/null)}}")
+    compadd -a apps
+}}
+
+_rooster() {{
+    local cur=${{words[CURRENT]}} command_path="" word
+    local -a options subcommands
+    local -i i takes_app=0
+    for ((i = 2; i < CURRENT; i++)); do
+        word=${{words[i]}}
+        case "$word" in
+            ({options}) ((i++)) ;;
+            (-*) ;;
+            (*)
+                case "${{command_path:+$command_path }}$word" in
+                    ({paths}) command_path="${{command_path:+$command_path }}$word" ;;
+                esac
+                ;;
+        esac
+    done
+
+    case "$command_path" in
+{cases}    esac
+
+    if [[ "$cur" == -* ]]; then
+        _describe 'option' options
+    elif (( ${{#subcommands}} )); then
+        _describe 'command' subcommands
+    elif (( takes_app )); then
+        _rooster_apps
+    else
+        _files
+    fi
+}}
+
+if [ "$funcstack[1]" = "_rooster" ]; then
+    _rooster "$@"
+else
+    compdef _rooster rooster
+fi
+"#,
+        options = spec.options_with_values().join("|"),
+        paths = command_paths(spec),
+        cases = cases,
+    )
+}
+
+fn fish(spec: &Spec) -> String {
+    let paths: Vec<String> = spec
+        .all()
+        .iter()
+        .skip(1)
+        .map(|spec| quote_fish(&spec.path))
+        .collect();
+    let mut completions = String::new();
+    for spec in spec.all() {
+        let condition = quote_fish(&format!("__rooster_at {}", quote_fish(&spec.path)));
+        for subcommand in spec.subcommands.iter() {
+            completions.push_str(&format!(
+                "complete -c rooster -n {} -a {} -d {}\n",
+                condition,
+                quote_fish(subcommand.name()),
+                quote_fish(&subcommand.about)
+            ));
+        }
+        for flag in spec.flags.iter() {
+            let mut line = format!("complete -c rooster -n {}", condition);
+            if let Some(long) = &flag.long {
+                line.push_str(&format!(" -l {}", long));
+            }
+            if let Some(short) = flag.short {
+                line.push_str(&format!(" -s {}", short));
+            }
+            if flag.takes_value {
+                line.push_str(" -r");
+            }
+            completions.push_str(&format!("{} -d {}\n", line, quote_fish(&flag.help)));
+        }
+        if spec.takes_app {
+            completions.push_str(&format!(
+                "complete -c rooster -n {} -a '(__rooster_apps)'\n",
+                condition
+            ));
+        }
+    }
+
+    format!(
+        r#"function __rooster_apps
+    rooster list --names-only 2>/dev/null
+end
+
+# Prints the commands typed so far, ie: `backup now`
+function __rooster_path
+    set -l command_path
+    set -l skip 0
+    for word in (commandline -opc)[2..-1]
+        if test $skip -eq 1
+            set skip 0
+        else if contains -- $word {options}
+            set skip 1
+        else if not string match -q -- '-*' $word; and contains -- (string join ' ' $command_path $word) {paths}
+            set -a command_path $word
+        end
+    end
+    string join ' ' $command_path
+end
+
+function __rooster_at
+    set -l command_path (__rooster_path)
+    test "$command_path" = "$argv[1]"
+end
+
+complete -c rooster -f
+{completions}"#,
+        options = spec.options_with_values().join(" "),
+        paths = paths.join(" "),
+        completions = completions,
+    )
+}
+
+fn powershell(spec: &Spec) -> String {
+    let paths: Vec<String> = spec
+        .all()
+        .iter()
+        .skip(1)
+        .map(|spec| quote_powershell(&spec.path))
+        .collect();
+    let options: Vec<String> = spec
+        .options_with_values()
+        .iter()
+        .map(|name| quote_powershell(name))
+        .collect();
+    let mut cases = String::new();
+    for spec in spec.all() {
+        let words: Vec<String> = spec
+            .subcommand_names()
+            .into_iter()
+            .map(String::from)
+            .chain(spec.flag_names())
+            .map(|word| quote_powershell(&word))
+            .collect();
+        cases.push_str(&format!(
+            "        {} {{ $words = @({}); $takesApp = ${} }}\n",
+            quote_powershell(&spec.path),
+            words.join(", "),
+            spec.takes_app
+        ));
+    }
+
+    format!(
+        r#"Register-ArgumentCompleter -Native -CommandName rooster -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $optionsWithValues = @({options})
+    $commandPaths = @({paths})
+    $typed = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object {{ $_.ToString() }})
+    if ($wordToComplete -ne '') {{
+        $typed = @($typed | Select-Object -First ($typed.Count - 1))
+    }}
+
+    $commandPath = ''
+    $skip = $false
+    foreach ($word in $typed) {{
+        if ($skip) {{
+            $skip = $false
+        }} elseif ($optionsWithValues -contains $word) {{
+            $skip = $true
+        }} elseif (-not $word.StartsWith('-')) {{
+            $next = "$commandPath $word".Trim()
+            if ($commandPaths -contains $next) {{
+                $commandPath = $next
+            }}
+        }}
+    }}
+
+    $words = @()
+    $takesApp = $false
+    switch -exact ($commandPath) {{
+{cases}    }}
+    if ($takesApp -and -not $wordToComplete.StartsWith('-')) {{
+        $words = @(rooster list --names-only 2>$null)
+    }}
+
+    $words | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        $completion = if ($_ -match '\s') {{ "'" + ($_ -replace "'", "''") + "'" }} else {{ $_ }}
+        [System.Management.Automation.CompletionResult]::new($completion, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        options = options.join(", "),
+        paths = paths.join(", "),
+        cases = cases,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{quote, quote_fish, Spec};
+    use clap::{Arg, ArgAction, Command};
+
+    fn rooster() -> Command {
+        Command::new("rooster")
+            .disable_help_subcommand(true)
+            .arg(
+                Arg::new("keyfile")
+                    .long("keyfile")
+                    .global(true)
+                    .help("The key file"),
+            )
+            .subcommand(
+                Command::new("get")
+                    .about("Retrieve a password")
+                    .arg(Arg::new("app").required(true))
+                    .arg(
+                        Arg::new("show")
+                            .action(ArgAction::SetTrue)
+                            .short('s')
+                            .long("show")
+                            .help("Show the password"),
+                    ),
+            )
+            .subcommand(
+                Command::new("backup")
+                    .about("Back up")
+                    .subcommand(Command::new("now").about("Now"))
+                    .subcommand(Command::new("secret").hide(true)),
+            )
+    }
+
+    #[test]
+    fn test_spec() {
+        let mut rooster = rooster();
+        rooster.build();
+        let spec = Spec::new(&rooster, String::new());
+
+        let paths: Vec<&str> = spec.all().iter().map(|spec| spec.path.as_str()).collect();
+        assert_eq!(paths, vec!["", "get", "backup", "backup now"]);
+        assert!(spec.subcommands[0].takes_app);
+        assert!(!spec.subcommands[1].takes_app);
+        assert!(spec.subcommands[0]
+            .flag_names()
+            .contains(&String::from("-s")));
+        assert_eq!(spec.options_with_values(), vec!["--keyfile"]);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote_fish("it's"), "'it\\'s'");
+    }
+}
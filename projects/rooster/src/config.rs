@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Minimum time a failed unlock takes by default, in milliseconds
+const UNLOCK_MIN_DELAY_MS_DEFAULT: u64 = 500;
+
+/// Time copied passwords stay on the clipboard by default, in seconds
+const CLIPBOARD_CLEAR_SECS_DEFAULT: u64 = 30;
+
+/// Age after which `rooster audit` reports passwords as stale by default, in days
+const AUDIT_MAX_AGE_DAYS_DEFAULT: u64 = 365;
+
+/// Days between automatic snapshots of the password file in `backup_dir` by default
+const BACKUP_INTERVAL_DAYS_DEFAULT: u64 = 7;
+
+/// Backups kept next to the password file by default
+const BACKUPS_KEEP_DEFAULT: usize = 10;
+
+/// Time without commands after which `rooster shell` locks by default, in seconds
+const SHELL_LOCK_SECS_DEFAULT: u64 = 300;
+
+/// Time `rooster agent start` keeps the key in memory by default, in seconds
+const AGENT_TTL_SECS_DEFAULT: u64 = 900;
+
+/// Settings that live outside of the password file, in a JSON file
+///
+/// Missing fields take their default value, so an empty object is a valid config.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Minimum time, in milliseconds, that a failed unlock takes, whether the master password is
+    /// wrong or the file is corrupted, so that timing doesn't tell the two apart
+    pub unlock_min_delay_ms: u64,
+    /// Time, in seconds, after which passwords copied to the clipboard are cleared from it, or 0
+    /// to leave them there
+    pub clipboard_clear_secs: u64,
+    /// Age, in days, after which `rooster audit` reports a password as stale, or 0 to never
+    /// report passwords as stale
+    pub audit_max_age_days: u64,
+    /// Directory where snapshots of the password file are saved, ie: on an external drive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<PathBuf>,
+    /// Days between automatic snapshots in `backup_dir`, or 0 to only make them with
+    /// `rooster backup now`
+    pub backup_interval_days: u64,
+    /// Number of backups kept next to the password file, which are made before each command that
+    /// changes it, or 0 to make none
+    pub backups_keep: usize,
+    /// Shortcuts for commands, ie: `"g": "get --copy"` makes `rooster g github` run
+    /// `rooster get --copy github`
+    pub aliases: BTreeMap<String, String>,
+    /// Command to run when none is given, ie: `"list"`, which can also be an alias
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_command: Option<String>,
+    /// Key file that the password file needs along with the master password, unless `--keyfile`
+    /// gives another one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyfile: Option<PathBuf>,
+    /// Time, in seconds, without commands after which `rooster shell` forgets the passwords and
+    /// asks for the master password again, or 0 to never lock
+    pub shell_lock_secs: u64,
+    /// Time, in seconds, after which the agent started by `rooster agent start` forgets the key
+    /// and stops
+    pub agent_ttl_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            unlock_min_delay_ms: UNLOCK_MIN_DELAY_MS_DEFAULT,
+            clipboard_clear_secs: CLIPBOARD_CLEAR_SECS_DEFAULT,
+            audit_max_age_days: AUDIT_MAX_AGE_DAYS_DEFAULT,
+            backup_dir: None,
+            backup_interval_days: BACKUP_INTERVAL_DAYS_DEFAULT,
+            backups_keep: BACKUPS_KEEP_DEFAULT,
+            aliases: BTreeMap::new(),
+            default_command: None,
+            keyfile: None,
+            shell_lock_secs: SHELL_LOCK_SECS_DEFAULT,
+            agent_ttl_secs: AGENT_TTL_SECS_DEFAULT,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config from a JSON file, using the defaults if the file doesn't exist
+    pub fn from_file(path: &Path) -> Result<Config, IoError> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(bytes.as_slice())
+                .map_err(|err| IoError::new(IoErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == IoErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn unlock_min_delay(&self) -> Duration {
+        Duration::from_millis(self.unlock_min_delay_ms)
+    }
+
+    pub fn shell_lock_after(&self) -> Option<Duration> {
+        match self.shell_lock_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+}
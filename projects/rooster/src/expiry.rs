@@ -0,0 +1,116 @@
+// Password expiry, as set by `rooster expire` and reported by `rooster expiring`.
+
+use crate::ffi;
+use crate::password::v2::Password;
+
+/// Passwords which expire within this many days are highlighted, ie: by `rooster list`
+pub const WARNING_DAYS: u32 = 14;
+
+const SECONDS_PER_DAY: ffi::time_t = 24 * 60 * 60;
+
+/// Parses how long a password lasts in days, ie: `90d`, `12w`, `6m` or `1y`, where a month is 30
+/// days and a year 365, and a number alone is in days
+pub fn parse_days(s: &str) -> Result<u32, String> {
+    let (number, days_per_unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let days_per_unit = match c.to_ascii_lowercase() {
+                'd' => 1,
+                'w' => 7,
+                'm' => 30,
+                'y' => 365,
+                _ => return Err(format!("Unknown unit \"{}\", use d, w, m or y", c)),
+            };
+            (&s[..i], days_per_unit)
+        }
+        _ => (s, 1),
+    };
+
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return Err(String::from("The value must look like 90d, 12w, 6m or 1y"));
+    }
+    number
+        .parse::<u32>()
+        .ok()
+        .and_then(|number| number.checked_mul(days_per_unit))
+        .filter(|days| *days > 0)
+        .ok_or_else(|| String::from("The value must be at least a day and at most a few years"))
+}
+
+/// Whether a password must be changed soon, see `status`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status {
+    Expired { days_ago: u32 },
+    Expiring { days_left: u32 },
+}
+
+impl Status {
+    pub fn describe(&self) -> String {
+        match self {
+            Status::Expired { days_ago: 0 } => String::from("expired today"),
+            Status::Expired { days_ago } => format!("expired {} ago", days(*days_ago)),
+            Status::Expiring { days_left } => format!("expires in {}", days(*days_left)),
+        }
+    }
+}
+
+fn days(n: u32) -> String {
+    match n {
+        1 => String::from("1 day"),
+        n => format!("{} days", n),
+    }
+}
+
+/// Returns whether the password expired, or expires within `within_days`, or `None` if it has no
+/// expiry or doesn't need changing yet
+pub fn status(password: &Password, within_days: u32, now: ffi::time_t) -> Option<Status> {
+    let expires_at = password.expiry.as_ref()?.expires_at;
+    if expires_at <= now {
+        return Some(Status::Expired {
+            days_ago: (now - expires_at) / SECONDS_PER_DAY,
+        });
+    }
+
+    let left = expires_at - now;
+    (left <= within_days.saturating_mul(SECONDS_PER_DAY)).then(|| Status::Expiring {
+        days_left: left.div_ceil(SECONDS_PER_DAY),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_days, status, Status, SECONDS_PER_DAY};
+    use crate::password::v2::{Expiry, Password};
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("90d"), Ok(90));
+        assert_eq!(parse_days("90"), Ok(90));
+        assert_eq!(parse_days("2W"), Ok(14));
+        assert_eq!(parse_days("6m"), Ok(180));
+        assert_eq!(parse_days("1y"), Ok(365));
+        assert!(parse_days("0d").is_err());
+        assert!(parse_days("d").is_err());
+        assert!(parse_days("-1d").is_err());
+        assert!(parse_days("3h").is_err());
+        assert!(parse_days("99999999y").is_err());
+    }
+
+    #[test]
+    fn test_status() {
+        let now = 1000 * SECONDS_PER_DAY;
+        let mut password = Password::new("Github", "me", "hunter2");
+        assert_eq!(status(&password, 14, now), None);
+
+        password.expiry = Some(Expiry::new(90, now - 100 * SECONDS_PER_DAY));
+        assert_eq!(
+            status(&password, 14, now),
+            Some(Status::Expired { days_ago: 10 })
+        );
+
+        password.expiry = Some(Expiry::new(90, now - 80 * SECONDS_PER_DAY - 1));
+        let expiring = status(&password, 14, now).unwrap();
+        assert_eq!(expiring, Status::Expiring { days_left: 10 });
+        assert_eq!(expiring.describe(), "expires in 10 days");
+        assert_eq!(status(&password, 7, now), None);
+    }
+}
@@ -1,44 +1,241 @@
-use rand::{rngs::OsRng, Rng};
+use crate::password::v2::{crypto_pwhash_scryptsalsa208sha256_ll, Policy};
+use rand::{rngs::OsRng, seq::SliceRandom};
 use rclio::CliInputOutput;
 use rclio::OutputType;
 use rtoolbox::safe_string::SafeString;
-use std::io::Result as IoResult;
+use rtoolbox::safe_vec::SafeVec;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
 
-fn generate_password(alnum: bool, len: usize) -> IoResult<SafeString> {
+/// scrypt parameters for derived passwords, which can never change without changing every
+/// derived password: 32MiB of memory, about as slow as unlocking the password file
+const DERIVE_SCRYPT_LOG2_N: u8 = 15;
+const DERIVE_SCRYPT_R: u32 = 8;
+const DERIVE_SCRYPT_P: u32 = 1;
+
+const ALNUM_CHARACTERS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Characters that are easily mistaken for one another, left out with `--no-ambiguous`
+const AMBIGUOUS_CHARACTERS: &str = "0Oo1lI|`'\"";
+
+/// Words that passphrases are made of, one per line, see `--words`
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// Separates the words of passphrases, ie: `maple-rocket-tundra-violin`
+const WORD_SEPARATOR: &str = "-";
+
+/// The characters of generated passwords, when no other ones are given
+fn default_characters(alnum: bool) -> Vec<char> {
+    match alnum {
+        true => ALNUM_CHARACTERS.iter().map(|c| *c as char).collect(),
+        false => (33u8..127).map(char::from).collect(),
+    }
+}
+
+/// Returns the characters of a set like `a-z0-9_`, where `-` between two characters stands for
+/// all the characters in between
+fn parse_charset(charset: &str) -> IoResult<Vec<char>> {
+    let chars: Vec<char> = charset.chars().collect();
+    let mut characters = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if i + 2 < chars.len() && chars[i + 1] == '-' {
+            if chars[i] > chars[i + 2] {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidInput,
+                    format!("{}-{} is not a valid range", chars[i], chars[i + 2]),
+                ));
+            }
+            characters.extend(chars[i]..=chars[i + 2]);
+            i += 3;
+        } else {
+            characters.push(chars[i]);
+            i += 1;
+        }
+    }
+    // Characters given twice would come up more often
+    characters.sort_unstable();
+    characters.dedup();
+    Ok(characters)
+}
+
+/// Picks a character at random, from characters that are not ambiguous if `no_ambiguous`
+fn pick(characters: &[char], no_ambiguous: bool) -> IoResult<char> {
+    characters
+        .iter()
+        .filter(|c| !no_ambiguous || !AMBIGUOUS_CHARACTERS.contains(**c))
+        .copied()
+        .collect::<Vec<char>>()
+        .choose(&mut OsRng)
+        .copied()
+        .ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, "there are no characters to pick"))
+}
+
+fn generate_password(characters: &[char], len: usize) -> IoResult<SafeString> {
     let mut password_as_string = String::new();
-    let mut rng = OsRng::default();
     for _ in 0..len {
-        if alnum {
-            match rng.gen_range(0..3) {
-                // Numbers 0-9
-                0 => password_as_string.push(rng.gen_range(48..58) as u8 as char),
-                // Uppercase A-Z
-                1 => password_as_string.push(rng.gen_range(65..91) as u8 as char),
-                // Lowercase a-z
-                2 => password_as_string.push(rng.gen_range(97..123) as u8 as char),
-                _ => unreachable!(),
+        password_as_string.push(pick(characters, false)?);
+    }
+    Ok(SafeString::from_string(password_as_string))
+}
+
+/// A character of a pattern: either one of a class of characters, or itself
+enum PatternItem {
+    Class(Vec<char>),
+    Literal(char),
+}
+
+/// Reads a pattern, where `U` is an uppercase letter, `L` a lowercase letter, `9` a digit, `S` a
+/// symbol and `A` a letter or digit, and other characters stand for themselves, ie: the `-` of
+/// `ULLLL-9999`. `\` makes the next character stand for itself.
+fn parse_pattern(pattern: &str) -> Vec<PatternItem> {
+    let mut items = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        items.push(match c {
+            'U' => PatternItem::Class(('A'..='Z').collect()),
+            'L' => PatternItem::Class(('a'..='z').collect()),
+            '9' => PatternItem::Class(('0'..='9').collect()),
+            'S' => PatternItem::Class(
+                default_characters(false)
+                    .into_iter()
+                    .filter(|c| !c.is_ascii_alphanumeric())
+                    .collect(),
+            ),
+            'A' => PatternItem::Class(default_characters(true)),
+            '\\' => match chars.next() {
+                Some(c) => PatternItem::Literal(c),
+                None => continue,
+            },
+            c => PatternItem::Literal(c),
+        });
+    }
+    items
+}
+
+/// Generates a password that looks like `pattern`, ie: `ULLLL-9999` gives `Kxqvd-4821`, see
+/// `parse_pattern`
+fn generate_password_from_pattern(pattern: &str, no_ambiguous: bool) -> IoResult<SafeString> {
+    let mut password_as_string = String::new();
+    for item in parse_pattern(pattern) {
+        match item {
+            PatternItem::Class(characters) => {
+                password_as_string.push(pick(&characters, no_ambiguous)?)
             }
-        } else {
-            password_as_string.push(rng.gen_range(33..127) as u8 as char);
+            PatternItem::Literal(c) => password_as_string.push(c),
         }
     }
     Ok(SafeString::from_string(password_as_string))
 }
 
-/// Returns true if the password contains at least one digit, one uppercase letter and one
-/// lowercase letter.
-fn password_is_hard(password: &str, alnum: bool) -> bool {
-    let is_punctuation = |c| -> bool { "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~".find(c).is_some() };
+fn matches_pattern(password: &str, pattern: &str) -> bool {
+    let items = parse_pattern(pattern);
+    password.chars().count() == items.len()
+        && password.chars().zip(items).all(|(c, item)| match item {
+            PatternItem::Class(characters) => characters.contains(&c),
+            PatternItem::Literal(literal) => c == literal,
+        })
+}
 
-    password.find(char::is_numeric).is_some()
-        && password.find(char::is_lowercase).is_some()
-        && password.find(char::is_uppercase).is_some()
-        && (alnum || password.find(is_punctuation).is_some())
+/// Generates a passphrase of `words` random words from the word list, which is easier to type
+/// and remember than random characters
+fn generate_passphrase(words: usize) -> IoResult<SafeString> {
+    if words == 0 {
+        return Err(IoError::new(
+            IoErrorKind::InvalidInput,
+            "a passphrase needs at least one word",
+        ));
+    }
+    let wordlist: Vec<&str> = WORDLIST.lines().collect();
+    let mut password_as_string = String::new();
+    for i in 0..words {
+        if i > 0 {
+            password_as_string.push_str(WORD_SEPARATOR);
+        }
+        password_as_string.push_str(wordlist.choose(&mut OsRng).unwrap());
+    }
+    Ok(SafeString::from_string(password_as_string))
+}
+
+/// Derives a password from the master seed, the site and its counter, picking characters from
+/// the output of scrypt instead of a random number generator
+///
+/// `attempt` is incremented until the password is hard enough, which is deterministic too.
+fn derive_password(
+    seed: &str,
+    site: &str,
+    counter: u32,
+    attempt: u32,
+    alnum: bool,
+    len: usize,
+) -> IoResult<SafeString> {
+    let mut salt = b"rooster-derive".to_vec();
+    salt.extend_from_slice(site.as_bytes());
+    salt.extend_from_slice(&counter.to_be_bytes());
+    salt.extend_from_slice(&attempt.to_be_bytes());
+
+    // Some bytes are skipped to pick characters uniformly, so we need more than one per character
+    let mut output = SafeVec::new(vec![0u8; len * 4]);
+    let result = unsafe {
+        crypto_pwhash_scryptsalsa208sha256_ll(
+            seed.as_ptr(),
+            seed.len(),
+            salt.as_ptr(),
+            salt.len(),
+            1 << DERIVE_SCRYPT_LOG2_N as u64,
+            DERIVE_SCRYPT_R,
+            DERIVE_SCRYPT_P,
+            output.inner_mut().as_mut_ptr(),
+            len * 4,
+        )
+    };
+    if result != 0 {
+        return Err(IoError::new(
+            IoErrorKind::OutOfMemory,
+            "deriving the password with scrypt failed",
+        ));
+    }
+
+    let characters = default_characters(alnum);
+    // Bytes past the last multiple of the number of characters would favor the first characters
+    let limit = 256 - 256 % characters.len();
+    let password_as_string: String = output
+        .iter()
+        .filter(|byte| (**byte as usize) < limit)
+        .take(len)
+        .map(|byte| characters[*byte as usize % characters.len()])
+        .collect();
+    Ok(SafeString::from_string(password_as_string))
+}
+
+/// Returns true if the password contains at least one digit, one uppercase letter, one
+/// lowercase letter and one symbol, for each of them that is among `characters`.
+fn password_is_hard(password: &str, characters: &[char]) -> bool {
+    let classes: [fn(char) -> bool; 4] = [
+        |c| c.is_numeric(),
+        |c| c.is_lowercase(),
+        |c| c.is_uppercase(),
+        |c| !c.is_alphanumeric(),
+    ];
+
+    classes.iter().all(|is_in_class| {
+        !characters.iter().any(|c| is_in_class(*c)) || password.chars().any(is_in_class)
+    })
 }
 
 pub struct PasswordSpec {
     pub alnum: bool,
     pub len: usize,
+    /// Characters to pick from instead of the default ones, ie: `a-z0-9_`
+    pub charset: Option<String>,
+    /// Leave out characters that are easily mistaken for one another, ie: `O` and `0`
+    pub no_ambiguous: bool,
+    /// Generate a passphrase of this many words instead
+    pub words: Option<usize>,
+    /// Generate a password that looks like this instead, see `generate_password_from_pattern`
+    pub pattern: Option<String>,
+    /// Whether typed passwords must be `len` characters long, which policies without a length
+    /// leave to the user
+    pub check_len: bool,
 }
 
 impl PasswordSpec {
@@ -46,17 +243,108 @@ impl PasswordSpec {
         PasswordSpec {
             alnum,
             len: password_len.unwrap_or(32),
+            charset: None,
+            no_ambiguous: false,
+            words: None,
+            pattern: None,
+            check_len: true,
+        }
+    }
+
+    fn characters(&self) -> IoResult<Vec<char>> {
+        let mut characters = match &self.charset {
+            Some(charset) => parse_charset(charset)?,
+            None => default_characters(self.alnum),
+        };
+        if self.no_ambiguous {
+            characters.retain(|c| !AMBIGUOUS_CHARACTERS.contains(*c));
+        }
+        Ok(characters)
+    }
+
+    /// Generates passwords like the policy of an app says
+    pub fn from_policy(policy: &Policy) -> PasswordSpec {
+        PasswordSpec {
+            charset: policy.charset.clone(),
+            no_ambiguous: policy.no_ambiguous,
+            words: policy.words,
+            pattern: policy.pattern.clone(),
+            check_len: policy.length.is_some(),
+            ..PasswordSpec::new(policy.alnum, policy.length)
+        }
+    }
+
+    /// Returns why the password could not have been generated with this spec, if it couldn't,
+    /// ie: when it was typed for a site with rules
+    pub fn violation(&self, password: &str) -> Option<String> {
+        if let Some(pattern) = &self.pattern {
+            return match matches_pattern(password, pattern) {
+                true => None,
+                false => Some(format!("it doesn't look like {}", pattern)),
+            };
+        }
+        if let Some(words) = self.words {
+            return match password.split(WORD_SEPARATOR).count() == words {
+                true => None,
+                false => Some(format!(
+                    "it isn't {} words separated by \"{}\"",
+                    words, WORD_SEPARATOR
+                )),
+            };
+        }
+
+        let len = password.chars().count();
+        if self.check_len && len != self.len {
+            return Some(format!(
+                "it is {} characters long instead of {}",
+                len, self.len
+            ));
         }
+        let characters = match self.characters() {
+            Ok(characters) => characters,
+            Err(err) => return Some(err.to_string()),
+        };
+        password
+            .chars()
+            .find(|c| !characters.contains(c))
+            .map(|c| format!("it contains {}, which isn't allowed", c))
     }
 
     pub fn generate_hard_password(&self) -> IoResult<SafeString> {
+        if let Some(pattern) = &self.pattern {
+            return generate_password_from_pattern(pattern, self.no_ambiguous);
+        }
+        if let Some(words) = self.words {
+            return generate_passphrase(words);
+        }
+
+        let characters = self.characters()?;
         loop {
-            let password = generate_password(self.alnum, self.len)?;
-            if password_is_hard(password.as_ref(), self.alnum) {
+            let password = generate_password(&characters, self.len)?;
+            if password_is_hard(password.as_ref(), &characters) {
                 return Ok(password);
             }
         }
     }
+
+    /// Derives the same hard password every time for the same seed, site and counter
+    pub fn derive_hard_password(
+        &self,
+        seed: &str,
+        site: &str,
+        counter: u32,
+    ) -> IoResult<SafeString> {
+        let site = site.trim().to_lowercase();
+        for attempt in 0.. {
+            let password = derive_password(seed, &site, counter, attempt, self.alnum, self.len)?;
+            if password.chars().count() == self.len
+                && password_is_hard(password.as_ref(), &default_characters(self.alnum))
+            {
+                return Ok(password);
+            }
+        }
+        unreachable!()
+    }
 }
 
 pub fn check_password_len(len: usize, io: &mut impl CliInputOutput) -> Option<usize> {
@@ -75,9 +363,91 @@ pub fn check_password_len(len: usize, io: &mut impl CliInputOutput) -> Option<us
 
 #[cfg(test)]
 mod test {
-    use crate::generate::PasswordSpec;
+    use crate::generate::{parse_charset, PasswordSpec, WORDLIST};
+    use crate::password::v2::Policy;
     use std::ops::Deref;
 
+    #[test]
+    fn test_violation() {
+        let ps = PasswordSpec::from_policy(&Policy {
+            pattern: Some("ULL\\L-99".to_string()),
+            ..Policy::default()
+        });
+        assert_eq!(ps.violation("AbcL-12"), None);
+        assert!(ps.violation("abcL-12").is_some());
+        assert!(ps.violation("AbcL-123").is_some());
+
+        let ps = PasswordSpec::from_policy(&Policy {
+            length: Some(8),
+            alnum: true,
+            ..Policy::default()
+        });
+        assert_eq!(ps.violation("abcd1234"), None);
+        assert!(ps.violation("abcd123").is_some());
+        assert!(ps.violation("abcd123!").is_some());
+
+        // Without a length, only the characters are checked
+        let ps = PasswordSpec::from_policy(&Policy {
+            charset: Some("a-z0-9".to_string()),
+            ..Policy::default()
+        });
+        assert_eq!(ps.violation("abcd123"), None);
+        assert!(ps.violation("abcd123!").is_some());
+
+        let ps = PasswordSpec::from_policy(&Policy {
+            words: Some(3),
+            ..Policy::default()
+        });
+        assert_eq!(ps.violation("maple-rocket-tundra"), None);
+        assert!(ps.violation("maple-rocket").is_some());
+    }
+
+    #[test]
+    fn test_parse_charset() {
+        assert_eq!(parse_charset("a-d_").unwrap(), ['_', 'a', 'b', 'c', 'd']);
+        assert_eq!(parse_charset("-ab-").unwrap(), ['-', 'a', 'b']);
+        assert_eq!(parse_charset("aa").unwrap(), ['a']);
+        assert!(parse_charset("d-a").is_err());
+    }
+
+    #[test]
+    fn test_generate_password_styles() {
+        let ps = PasswordSpec {
+            no_ambiguous: true,
+            ..PasswordSpec::new(true, Some(64))
+        };
+        let pw = ps.generate_hard_password().unwrap();
+        assert!(!pw.deref().contains(['0', 'O', 'o', '1', 'l', 'I']));
+
+        let ps = PasswordSpec {
+            pattern: Some("\\U-U9S".to_string()),
+            ..PasswordSpec::new(false, None)
+        };
+        let pw = ps.generate_hard_password().unwrap();
+        assert!(pw.deref().starts_with("U-"));
+        let chars: Vec<char> = pw.deref().chars().collect();
+        assert!(chars[2].is_ascii_uppercase());
+        assert!(chars[3].is_ascii_digit());
+        assert!(chars[4].is_ascii_punctuation());
+
+        let ps = PasswordSpec {
+            words: Some(4),
+            ..PasswordSpec::new(false, None)
+        };
+        let pw = ps.generate_hard_password().unwrap();
+        let words: Vec<&str> = pw.deref().split('-').collect();
+        assert_eq!(words.len(), 4);
+        assert!(words
+            .iter()
+            .all(|word| WORDLIST.lines().any(|w| w == *word)));
+        assert!(PasswordSpec {
+            words: Some(0),
+            ..PasswordSpec::new(false, None)
+        }
+        .generate_hard_password()
+        .is_err());
+    }
+
     #[test]
     fn test_default_password_size_is_32() {
         assert_eq!(
@@ -96,6 +466,22 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_derive_hard_password() {
+        let ps = PasswordSpec::new(false, Some(16));
+        let pw = ps.derive_hard_password("seed", "Github", 1).unwrap();
+        assert_eq!(pw.len(), 16);
+        assert_eq!(pw, ps.derive_hard_password("seed", " github", 1).unwrap());
+        assert_ne!(pw, ps.derive_hard_password("seed", "github", 2).unwrap());
+        assert_ne!(pw, ps.derive_hard_password("seed", "gitlab", 1).unwrap());
+        assert_ne!(pw, ps.derive_hard_password("other", "github", 1).unwrap());
+
+        let ps = PasswordSpec::new(true, None);
+        let pw = ps.derive_hard_password("seed", "github", 1).unwrap();
+        assert_eq!(pw.len(), 32);
+        assert!(pw.deref().chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
     #[test]
     fn test_generate_password_alnum() {
         // All alnum
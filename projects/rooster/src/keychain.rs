@@ -0,0 +1,213 @@
+//! Keeps the master password in the OS keychain, which the user's login unlocks
+//!
+//! Like for the clipboard, we use the tools that come with the OS: `security` on macOS and
+//! `secret-tool`, from libsecret, on other UNIX systems. Entries are named after the Rooster file,
+//! so that each file can have its own.
+
+use rtoolbox::safe_string::SafeString;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, Write};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// The name of the service that keychain entries belong to
+const SERVICE: &str = "rooster";
+
+/// Runs a keychain tool, writing `input` to its stdin
+fn run(command: &mut Command, input: &[u8]) -> IoResult<Output> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            IoErrorKind::NotFound => IoError::new(
+                IoErrorKind::NotFound,
+                format!("{} is not installed", program),
+            ),
+            _ => err,
+        })?;
+
+    child.stdin.take().unwrap().write_all(input)?;
+    child.wait_with_output()
+}
+
+/// Turns the output of a keychain tool into an error if it failed
+fn check(output: Output) -> IoResult<Output> {
+    if output.status.success() {
+        return Ok(output);
+    }
+    Err(IoError::other(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
+/// Reads a password printed by a keychain tool, without its final newline
+fn password_from_stdout(stdout: Vec<u8>) -> IoResult<SafeString> {
+    let mut password = SafeString::from_string(
+        String::from_utf8(stdout).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?,
+    );
+    if password.ends_with('\n') {
+        password.pop();
+    }
+    Ok(password)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{check, password_from_stdout, run, SERVICE};
+    use rtoolbox::safe_string::SafeString;
+    use std::io::Result as IoResult;
+    use std::ops::Deref;
+    use std::process::Command;
+
+    /// `security find-generic-password` exits with this code when there is no such entry
+    const ERR_SEC_ITEM_NOT_FOUND: i32 = 44;
+
+    pub fn store(account: &str, password: &SafeString) -> IoResult<()> {
+        // Commands are read from stdin, so that the password doesn't show up in the process list.
+        let command = SafeString::from_string(format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            super::quote(SERVICE),
+            super::quote(account),
+            super::quote(password.deref())
+        ));
+        check(run(Command::new("security").arg("-i"), command.as_bytes())?)?;
+        Ok(())
+    }
+
+    pub fn retrieve(account: &str) -> IoResult<Option<SafeString>> {
+        let output = run(
+            Command::new("security").args([
+                "find-generic-password",
+                "-s",
+                SERVICE,
+                "-a",
+                account,
+                "-w",
+            ]),
+            &[],
+        )?;
+        if output.status.code() == Some(ERR_SEC_ITEM_NOT_FOUND) {
+            return Ok(None);
+        }
+        Ok(Some(password_from_stdout(check(output)?.stdout)?))
+    }
+
+    pub fn remove(account: &str) -> IoResult<()> {
+        check(run(
+            Command::new("security").args([
+                "delete-generic-password",
+                "-s",
+                SERVICE,
+                "-a",
+                account,
+            ]),
+            &[],
+        )?)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::{check, password_from_stdout, run, SERVICE};
+    use rtoolbox::safe_string::SafeString;
+    use std::io::Result as IoResult;
+    use std::process::Command;
+
+    pub fn store(account: &str, password: &SafeString) -> IoResult<()> {
+        // secret-tool reads the password from stdin, so it doesn't show up in the process list.
+        check(run(
+            Command::new("secret-tool").args([
+                "store",
+                "--label=Rooster master password",
+                "service",
+                SERVICE,
+                "account",
+                account,
+            ]),
+            password.as_bytes(),
+        )?)?;
+        Ok(())
+    }
+
+    pub fn retrieve(account: &str) -> IoResult<Option<SafeString>> {
+        let output = run(
+            Command::new("secret-tool").args(["lookup", "service", SERVICE, "account", account]),
+            &[],
+        )?;
+        // secret-tool fails silently when there is no such entry
+        if !output.status.success() && output.stdout.is_empty() && output.stderr.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(password_from_stdout(check(output)?.stdout)?))
+    }
+
+    pub fn remove(account: &str) -> IoResult<()> {
+        check(run(
+            Command::new("secret-tool").args(["clear", "service", SERVICE, "account", account]),
+            &[],
+        )?)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use rtoolbox::safe_string::SafeString;
+    use std::io::{Error as IoError, Result as IoResult};
+
+    fn unsupported() -> IoError {
+        IoError::other("the keychain is only supported on Linux, BSD and OSX")
+    }
+
+    pub fn store(_account: &str, _password: &SafeString) -> IoResult<()> {
+        Err(unsupported())
+    }
+
+    pub fn retrieve(_account: &str) -> IoResult<Option<SafeString>> {
+        Err(unsupported())
+    }
+
+    pub fn remove(_account: &str) -> IoResult<()> {
+        Err(unsupported())
+    }
+}
+
+/// Quotes an argument for `security -i`, which splits its input like a shell
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn account(rooster_file_path: &Path) -> String {
+    rooster_file_path.to_string_lossy().into_owned()
+}
+
+/// Saves the master password for a Rooster file in the keychain, replacing any previous one
+pub fn store_master_password(rooster_file_path: &Path, password: &SafeString) -> IoResult<()> {
+    platform::store(&account(rooster_file_path), password)
+}
+
+/// Returns the master password saved for a Rooster file, if any
+pub fn retrieve_master_password(rooster_file_path: &Path) -> IoResult<Option<SafeString>> {
+    platform::retrieve(&account(rooster_file_path))
+}
+
+/// Removes the master password saved for a Rooster file
+pub fn remove_master_password(rooster_file_path: &Path) -> IoResult<()> {
+    platform::remove(&account(rooster_file_path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::quote;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("simple"), "\"simple\"");
+        assert_eq!(quote("with \"quotes\""), "\"with \\\"quotes\\\"\"");
+        assert_eq!(quote("back\\slash"), "\"back\\\\slash\"");
+    }
+}
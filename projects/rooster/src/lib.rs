@@ -1,27 +1,46 @@
 // #![allow(useless_format, too_many_arguments)]
 
-use crate::password::v2::PasswordStore;
+use crate::password::v2::{KeyFile, PasswordStore};
 use clap::{Arg, ArgAction, Command};
 use rclio::CliInputOutput;
 use rclio::OutputType;
 use rtoolbox::safe_string::SafeString;
 use rtoolbox::safe_vec::SafeVec;
+use rtoolbox::secure_memory;
 use std::env;
 use std::fs::File;
+use std::io::Error as IoError;
 use std::io::Read;
 use std::io::Result as IoResult;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 mod aes;
+mod agent;
+mod audit;
+mod backup;
 mod clip;
 mod commands;
+mod completions;
+mod config;
+mod expiry;
 mod ffi;
 mod generate;
+mod keychain;
 mod list;
+mod memory;
+mod migrations;
+mod output;
 mod password;
 mod quale;
+mod reveal;
 mod shell_escape;
+mod sync;
+mod totp;
+mod unattended;
+
+pub use crate::config::Config;
 
 fn only_digits(s: &str) -> bool {
     s.chars()
@@ -51,6 +70,219 @@ fn validate_arg_u32(v: &str) -> Result<u32, String> {
     Ok(v.parse::<u32>().unwrap())
 }
 
+fn validate_arg_u64(v: &str) -> Result<u64, String> {
+    if only_digits(v) {
+        return Err(String::from("The value must be made of digits"));
+    }
+    v.parse::<u64>().map_err(|err| err.to_string())
+}
+
+/// Replaces an alias from the config by the command it stands for, and adds the default command
+/// from the config if there is none
+///
+/// Aliases can't replace Rooster's own commands, but the default command can be an alias.
+fn expand_command_args(args: &[&str], command: &Command, config: &Config) -> Vec<String> {
+    let mut expanded: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+    // Global options come before the command, ie: `rooster --from-keychain g github`
+    let mut position = 1;
+    while position < expanded.len() && expanded[position].starts_with('-') {
+        match expanded[position].as_str() {
+            "-h" | "--help" | "-V" | "--version" => return expanded,
+            "--clear-clipboard-after" | "--keyfile" => position += 2,
+            _ => position += 1,
+        }
+    }
+
+    if position >= expanded.len() {
+        if let Some(default_command) = &config.default_command {
+            expanded.extend(default_command.split_whitespace().map(String::from));
+        }
+    }
+
+    let alias = expanded
+        .get(position)
+        .filter(|name| command.find_subcommand(name).is_none())
+        .and_then(|name| config.aliases.get(name));
+    if let Some(alias) = alias {
+        let words: Vec<String> = alias.split_whitespace().map(String::from).collect();
+        expanded.splice(position..=position, words);
+    }
+    expanded
+}
+
+/// Whether `--read-only` allows a command, which must leave the password file, its backups and the
+/// keychain alone
+fn allowed_when_read_only(subcommand: &str, command_matches: &clap::ArgMatches) -> bool {
+    if !changes_password_file(subcommand) {
+        return true;
+    }
+    match subcommand {
+        "clear-clipboard" | "completions" | "agent" | "shell" => true,
+        "derive" => command_matches.get_flag("stateless"),
+        "backup" => command_matches.subcommand_name() == Some("verify"),
+        "backups" => command_matches.subcommand_name() != Some("restore"),
+        // Exports only change the file when they save a profile
+        "export" => !command_matches
+            .subcommand()
+            .is_some_and(|(_, format_matches)| saves_export_profile(format_matches)),
+        "migrate" => command_matches.get_flag("dry-run"),
+        // Pushing sends what was already saved
+        "sync" => command_matches.subcommand_name() == Some("push"),
+        _ => false,
+    }
+}
+
+/// Whether an export saves its mapping as a profile, which only some formats can
+fn saves_export_profile(format_matches: &clap::ArgMatches) -> bool {
+    let mapping = format_matches.try_get_one::<bool>("mapping");
+    let profile = format_matches.try_get_one::<String>("profile");
+    matches!(mapping, Ok(Some(true))) && matches!(profile, Ok(Some(_)))
+}
+
+/// Refuses commands that `--read-only` doesn't allow
+fn check_read_only(
+    read_only: bool,
+    subcommand: &str,
+    command_matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    if !read_only || allowed_when_read_only(subcommand, command_matches) {
+        return Ok(());
+    }
+    io.error(
+        format!(
+            "Woops, `rooster {}` can change your password file, which --read-only doesn't allow.",
+            subcommand
+        ),
+        OutputType::Error,
+    );
+    Err(1)
+}
+
+/// Whether a command can change the password file, which is backed up before it runs
+fn changes_password_file(subcommand: &str) -> bool {
+    !matches!(
+        subcommand,
+        "get"
+            | "copy"
+            | "list"
+            | "search"
+            | "where-used"
+            | "audit"
+            | "expiring"
+            | "verify"
+            | "totp"
+            | "history"
+    )
+}
+
+fn agent_ttl_arg(config: &Config) -> Arg {
+    Arg::new("ttl")
+        .long("ttl")
+        .value_name("SECONDS")
+        .default_value(config.agent_ttl_secs.to_string())
+        .value_parser(validate_arg_u64)
+        .help("Forget the key after this long")
+}
+
+fn show_secrets_arg() -> Arg {
+    Arg::new("show-secrets")
+        .action(ArgAction::SetTrue)
+        .long("show-secrets")
+        .help("With `--output json`, also write passwords, notes and custom fields")
+}
+
+/// How passwords are generated, besides `--alnum` and `--length`, for `generate` and `policy`
+fn generation_args() -> [Arg; 4] {
+    [
+        Arg::new("charset")
+            .long("charset")
+            .value_name("CHARACTERS")
+            .conflicts_with("alnum")
+            .help("Only use these characters, where a-z stands for all letters from a to z, ie: `a-z0-9_`"),
+        Arg::new("no-ambiguous")
+            .action(ArgAction::SetTrue)
+            .long("no-ambiguous")
+            .help("Leave out characters that are easily mistaken for one another, ie: O and 0"),
+        Arg::new("words")
+            .long("words")
+            .value_name("N")
+            .conflicts_with_all(["alnum", "length", "charset", "no-ambiguous"])
+            .help("Generate a passphrase of N random words instead, ie: maple-rocket-tundra-violin")
+            .value_parser(validate_arg_usize),
+        Arg::new("pattern")
+            .long("pattern")
+            .value_name("PATTERN")
+            .conflicts_with_all(["alnum", "length", "charset", "words"])
+            .help("Generate a password that looks like this, ie: `ULLLL-9999`, where U, L, 9, S and A stand for an uppercase letter, a lowercase letter, a digit, a symbol, and a letter or digit"),
+    ]
+}
+
+fn export_mapping_arg() -> Arg {
+    Arg::new("mapping")
+        .action(ArgAction::SetTrue)
+        .short('m')
+        .long("mapping")
+        .help("Choose, rename and mask the exported fields interactively")
+}
+
+fn export_file_arg() -> Arg {
+    Arg::new("file")
+        .short('o')
+        .long("file")
+        .value_name("FILE")
+        .help("Write the export to this file instead of the standard output")
+}
+
+fn import_conflict_arg() -> Arg {
+    Arg::new("on-conflict")
+        .long("on-conflict")
+        .value_parser(["merge", "skip", "overwrite"])
+        .help("What to do with apps that are already in the store, instead of asking each time")
+}
+
+fn export_profile_arg() -> Arg {
+    Arg::new("profile")
+        .short('p')
+        .long("profile")
+        .help("Save the mapping under this name with --mapping, or reuse a saved mapping")
+}
+
+/// Backs up the password file before a command changes it, warning if that fails
+fn back_up_password_file(rooster_file_path: &Path, config: &Config, io: &mut impl CliInputOutput) {
+    if config.backups_keep == 0 {
+        return;
+    }
+    if let Err(err) = backup::write_backup(rooster_file_path, config.backups_keep) {
+        io.warning(
+            format!(
+                "I could not back up your password file to {} (reason: {}).",
+                backup::backups_dir(rooster_file_path).display(),
+                err
+            ),
+            OutputType::Error,
+        );
+    }
+}
+
+/// Commits the password file after a command changed it, if sync is set up
+fn commit_password_file(rooster_file_path: &Path, subcommand: &str, io: &mut impl CliInputOutput) {
+    if !sync::is_enabled(rooster_file_path) {
+        return;
+    }
+    if let Err(err) = sync::commit(rooster_file_path, &format!("rooster {}", subcommand)) {
+        io.warning(
+            format!(
+                "I could not commit your password file to {} (reason: {}).",
+                sync::sync_dir(rooster_file_path).display(),
+                err
+            ),
+            OutputType::Error,
+        );
+    }
+}
+
 fn open_password_file(filename: &str) -> IoResult<File> {
     let mut options = std::fs::OpenOptions::new();
     options.read(true);
@@ -85,20 +317,256 @@ fn sync_password_store(
 
 fn get_password_store(
     file: &mut File,
+    config: &Config,
+    agent_path: Option<&Path>,
+    keychain_path: Option<&Path>,
+    key_file: Option<&KeyFile>,
+    io: &mut impl CliInputOutput,
+) -> Result<password::v2::PasswordStore, i32> {
+    let input = read_password_file(file, io)?;
+
+    if let Some(path) = agent_path {
+        if let Some(store) = get_password_store_from_agent(&input, path, io) {
+            return Ok(store);
+        }
+    }
+
+    if let Some(path) = keychain_path {
+        if let Some(store) = get_password_store_from_keychain(&input, path, key_file, io) {
+            return Ok(store);
+        }
+    }
+
+    if let Some((source, master_password)) = unattended::master_password_from_env() {
+        return get_password_store_unattended(
+            &input,
+            source,
+            master_password,
+            key_file,
+            config,
+            io,
+        );
+    }
+
+    return get_password_store_from_input_interactive(
+        &input,
+        key_file,
+        3,
+        false,
+        false,
+        config.unlock_min_delay(),
+        io,
+    )
+    .map_err(|_| 1);
+}
+
+/// Opens the password file with a master password that was read before, from `source`
+///
+/// The shell unlocks again with it, since `ROOSTER_MASTER_PASSWORD_FD` can only be read once.
+fn get_password_store_with_master_password(
+    file: &mut File,
+    source: &str,
+    master_password: SafeString,
+    key_file: Option<&KeyFile>,
+    config: &Config,
     io: &mut impl CliInputOutput,
 ) -> Result<password::v2::PasswordStore, i32> {
+    let input = read_password_file(file, io)?;
+    get_password_store_unattended(&input, source, Ok(master_password), key_file, config, io)
+}
+
+/// Reads the encrypted contents of the password file, once it's checked that they aren't truncated
+fn read_password_file(file: &mut File, io: &mut impl CliInputOutput) -> Result<SafeVec, i32> {
     // Read the Rooster file contents.
     let mut input: SafeVec = SafeVec::new(Vec::new());
     file.read_to_end(input.inner_mut()).map_err(|_| 1)?;
+    disable_core_dumps(io);
+
+    // No master password unlocks a truncated file, so don't ask for one
+    commands::verify::check_structure(&input, io)?;
+    Ok(input)
+}
+
+/// Reads the key file given with `--keyfile`, or else the one in the config, if any
+fn read_key_file(
+    matches: &clap::ArgMatches,
+    config: &Config,
+    io: &mut impl CliInputOutput,
+) -> Result<Option<KeyFile>, i32> {
+    let path = match matches.get_one::<String>("keyfile") {
+        Some(path) => PathBuf::from(path),
+        None => match &config.keyfile {
+            Some(path) => path.clone(),
+            None => return Ok(None),
+        },
+    };
+
+    KeyFile::read(&path).map(Some).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not read your key file {} (reason: {}).",
+                path.display(),
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+/// Unlocks the password store with the key kept by `rooster agent start`, if one is running
+///
+/// The store doesn't know the master password, so it's only used by commands that don't change
+/// the file, and not if the file must be upgraded first.
+fn get_password_store_from_agent(
+    input: &SafeVec,
+    rooster_file_path: &Path,
+    io: &mut impl CliInputOutput,
+) -> Option<password::v2::PasswordStore> {
+    let warning = match agent::request_secret(rooster_file_path) {
+        Ok(Some(secret)) => match password::v2::PasswordStore::from_input_with_key(
+            secret.key,
+            secret.key_file.as_ref(),
+            input.clone(),
+        ) {
+            Ok(store) if migrations::pending(store.version()).is_empty() => return Some(store),
+            Ok(_) => return None,
+            Err(_) => String::from(
+                "The key kept by the agent does not unlock your Rooster file. \
+                 Run `rooster agent stop` and `rooster agent start` to update it.",
+            ),
+        },
+        Ok(None) => return None,
+        Err(err) => format!("I could not reach the agent (reason: {}).", err),
+    };
+
+    io.warning(warning, OutputType::Error);
+    None
+}
+
+/// Keeps decrypted passwords from being written to disk if Rooster crashes once the file is unlocked
+fn disable_core_dumps(io: &mut impl CliInputOutput) {
+    if let Err(err) = secure_memory::disable_core_dumps() {
+        io.warning(
+            format!("I could not disable core dumps (reason: {}).", err),
+            OutputType::Error,
+        );
+    }
+}
+
+/// Explains how to unlock the file when its passwords can't be kept from being swapped to disk
+fn memory_lock_error(err: &IoError) -> String {
+    format!(
+        "Woops, I could not keep your passwords from being swapped to disk (reason: {}). \
+         Raise the limit of locked memory, ie: with `ulimit -l`, or use `rooster --no-mlock`.",
+        err
+    )
+}
+
+/// Unlocks the password store with the agent only, and without saving it afterwards
+fn get_password_store_without_asking(
+    rooster_file_path: &Path,
+    io: &mut impl CliInputOutput,
+) -> Option<password::v2::PasswordStore> {
+    let mut input: SafeVec = SafeVec::new(Vec::new());
+    File::open(rooster_file_path)
+        .and_then(|mut file| file.read_to_end(input.inner_mut()))
+        .ok()?;
+    disable_core_dumps(io);
+
+    let store = get_password_store_from_agent(&input, rooster_file_path, io);
+    if store.is_none() {
+        io.info(
+            "Start the agent with `rooster agent start` to list app names without your master password.",
+            OutputType::Error,
+        );
+    }
+    store
+}
+
+/// Unlocks the password store with the master password saved in the keychain, if it works
+fn get_password_store_from_keychain(
+    input: &SafeVec,
+    rooster_file_path: &Path,
+    key_file: Option<&KeyFile>,
+    io: &mut impl CliInputOutput,
+) -> Option<password::v2::PasswordStore> {
+    let warning = match keychain::retrieve_master_password(rooster_file_path) {
+        Ok(Some(master_password)) => {
+            match get_password_store_from_input(input, &master_password, key_file, false) {
+                Ok(store) => return Some(store),
+                Err(_) => String::from(
+                    "The master password in the keychain does not unlock your Rooster file. \
+                     Run `rooster keychain enroll` to update it.",
+                ),
+            }
+        }
+        Ok(None) => String::from(
+            "Your master password is not in the keychain. \
+             Run `rooster keychain enroll` to save it there.",
+        ),
+        Err(err) => format!(
+            "I could not read your master password from the keychain (reason: {}).",
+            err
+        ),
+    };
+
+    io.warning(warning, OutputType::Error);
+    None
+}
+
+/// Unlocks the password store with the master password given by a script, without asking again
+/// if it doesn't work
+fn get_password_store_unattended(
+    input: &SafeVec,
+    source: &str,
+    master_password: IoResult<SafeString>,
+    key_file: Option<&KeyFile>,
+    config: &Config,
+    io: &mut impl CliInputOutput,
+) -> Result<password::v2::PasswordStore, i32> {
+    let master_password = master_password.map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not read your master password from {} (reason: {}).",
+                source, err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
 
-    return get_password_store_from_input_interactive(&input, 3, false, false, io).map_err(|_| 1);
+    let started_at = Instant::now();
+    get_password_store_from_input(input, &master_password, key_file, false).map_err(|err| {
+        std::thread::sleep(config.unlock_min_delay().saturating_sub(started_at.elapsed()));
+        io.error(
+            match err {
+                password::PasswordError::NeedKeyFileError => format!(
+                    "Woops, your Rooster file needs a key file along with the master password from {}.",
+                    source
+                ),
+                password::PasswordError::CorruptionError => String::from(
+                    "Your Rooster file is corrupted: its signature doesn't match its contents.",
+                ),
+                password::PasswordError::MemoryLockError(err) => memory_lock_error(&err),
+                _ => format!(
+                    "Woops, the master password from {} does not unlock your Rooster file.",
+                    source
+                ),
+            },
+            OutputType::Error,
+        );
+        1
+    })
 }
 
 fn get_password_store_from_input_interactive(
     input: &SafeVec,
+    key_file: Option<&KeyFile>,
     retries: i32,
     force_upgrade: bool,
     retry: bool,
+    min_delay: Duration,
     io: &mut impl CliInputOutput,
 ) -> Result<password::v2::PasswordStore, password::PasswordError> {
     if retries == 0 {
@@ -112,7 +580,10 @@ fn get_password_store_from_input_interactive(
 
     if retry {
         io.error(
-            "Woops, that's not the right password. Let's try again.",
+            match key_file {
+                Some(_) => "Woops, that's not the right password or key file. Let's try again.",
+                None => "Woops, that's not the right password. Let's try again.",
+            },
             OutputType::Error,
         );
     }
@@ -131,12 +602,23 @@ fn get_password_store_from_input_interactive(
         }
     };
 
-    match get_password_store_from_input(&input, &master_password, force_upgrade) {
+    // Failures all take at least `min_delay`, so that how long they take doesn't tell whether the
+    // password was wrong or the file was corrupted, and where.
+    let started_at = Instant::now();
+    let result = get_password_store_from_input(&input, &master_password, key_file, force_upgrade);
+    if result.is_err() {
+        std::thread::sleep(min_delay.saturating_sub(started_at.elapsed()));
+    }
+
+    match result {
         Ok(store) => {
             return Ok(store);
         }
         Err(password::PasswordError::CorruptionError) => {
-            io.error("Your Rooster file is corrupted.", OutputType::Error);
+            io.error(
+                "Your Rooster file is corrupted: its signature doesn't match its contents.",
+                OutputType::Error,
+            );
             return Err(password::PasswordError::CorruptionError);
         }
         Err(password::PasswordError::OutdatedRoosterBinaryError) => {
@@ -150,6 +632,18 @@ fn get_password_store_from_input_interactive(
             );
             return Err(password::PasswordError::OutdatedRoosterBinaryError);
         }
+        Err(password::PasswordError::NeedKeyFileError) => {
+            io.error(
+                "Your Rooster file needs a key file along with your master password. \
+                 Use `rooster --keyfile <path> <command>`, or set `keyfile` in your config.",
+                OutputType::Error,
+            );
+            Err(password::PasswordError::NeedKeyFileError)
+        }
+        Err(password::PasswordError::MemoryLockError(err)) => {
+            io.error(memory_lock_error(&err), OutputType::Error);
+            Err(password::PasswordError::MemoryLockError(err))
+        }
         Err(password::PasswordError::Io(err)) => {
             io.error(
                 format!("I couldn't open your Rooster file (reason: {:?})", err),
@@ -167,7 +661,7 @@ fn get_password_store_from_input_interactive(
                         if line.starts_with('y') {
                             // This time we'll try to upgrade
                             return get_password_store_from_input_interactive(
-                                &input, retries, true, false, io,
+                                &input, key_file, retries, true, false, min_delay, io,
                             );
                         } else if line.starts_with('n') {
                             // The user doesn't want to upgrade, that's fine
@@ -191,7 +685,15 @@ fn get_password_store_from_input_interactive(
             }
         }
         _ => {
-            return get_password_store_from_input_interactive(&input, retries - 1, false, true, io);
+            return get_password_store_from_input_interactive(
+                &input,
+                key_file,
+                retries - 1,
+                false,
+                true,
+                min_delay,
+                io,
+            );
         }
     }
 }
@@ -199,10 +701,12 @@ fn get_password_store_from_input_interactive(
 fn get_password_store_from_input(
     input: &SafeVec,
     master_password: &SafeString,
+    key_file: Option<&KeyFile>,
     upgrade: bool,
 ) -> Result<password::v2::PasswordStore, password::PasswordError> {
     // Try to open the file as is.
-    match password::v2::PasswordStore::from_input(master_password.clone(), input.clone()) {
+    match password::v2::PasswordStore::from_input(master_password.clone(), key_file, input.clone())
+    {
         Ok(store) => {
             return Ok(store);
         }
@@ -242,13 +746,75 @@ pub fn main_with_args(
     io: &mut impl CliInputOutput,
     rooster_file_path: &PathBuf,
 ) -> i32 {
-    let matches = Command::new("rooster")
+    main_with_args_and_config(args, io, rooster_file_path, &Config::default())
+}
+
+pub fn main_with_args_and_config(
+    args: &[&str],
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+    config: &Config,
+) -> i32 {
+    let command = Command::new("rooster")
         .help_expected(true)
         .disable_help_subcommand(true)
         .subcommand_required(true)
         .arg_required_else_help(true)
         .about("Welcome to Rooster, the simple password manager for geeks :-)")
         .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("from-keychain")
+                .action(ArgAction::SetTrue)
+                .long("from-keychain")
+                .global(true)
+                .help("Unlock with the master password saved by `rooster keychain enroll`"),
+        )
+        .arg(
+            Arg::new("keyfile")
+                .long("keyfile")
+                .value_name("PATH")
+                .global(true)
+                .help("The key file your password file needs along with your master password, if any"),
+        )
+        .arg(
+            Arg::new("read-only")
+                .action(ArgAction::SetTrue)
+                .long("read-only")
+                .global(true)
+                .help("Refuse commands that can change the password file, and never save it"),
+        )
+        .arg(
+            Arg::new("non-interactive")
+                .action(ArgAction::SetTrue)
+                .long("non-interactive")
+                .global(true)
+                .help("Never ask anything, ie: in scripts, which give the master password with ROOSTER_MASTER_PASSWORD_FD, ROOSTER_MASTER_PASSWORD_FILE or ROOSTER_MASTER_PASSWORD"),
+        )
+        .arg(
+            Arg::new("no-mlock")
+                .action(ArgAction::SetTrue)
+                .long("no-mlock")
+                .global(true)
+                .help("Don't keep decrypted passwords from being swapped to disk, for systems that don't allow it"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .global(true)
+                .help("Write the result of `get`, `list`, `search` and `audit` as text, or as JSON for scripts"),
+        )
+        .arg(
+            Arg::new("clear-clipboard-after")
+                .long("clear-clipboard-after")
+                .value_name("SECONDS")
+                .global(true)
+                .default_value(config.clipboard_clear_secs.to_string())
+                .value_parser(validate_arg_u64)
+                .help("Clear copied passwords from the clipboard after this long, 0 to never clear them"),
+        )
         .subcommand(
             Command::new("init")
                 .about("Create a new password file")
@@ -329,6 +895,7 @@ pub fn main_with_args(
                         .action(ArgAction::SetTrue)
                         .short('a')
                         .long("alnum")
+                        .visible_alias("no-symbols")
                         .help("Only use alpha numeric (a-z, A-Z, 0-9) in generated passwords"),
                 )
                 .arg(
@@ -338,6 +905,64 @@ pub fn main_with_args(
                         .default_value("32")
                         .help("Set a custom length for the generated password")
                         .value_parser(validate_arg_usize),
+                )
+                .args(generation_args()),
+        )
+        .subcommand(
+            Command::new("derive")
+                .about("Derive a password from a master seed, without storing it")
+                .after_help(
+                    "The same seed, site and counter always give the same password, so you can \
+                     get it back anywhere. The counter of each site is saved in your password \
+                     file, unless you use --stateless.",
+                )
+                .arg(
+                    Arg::new("site")
+                        .required(true)
+                        .help("The name of the site, ie: github.com"),
+                )
+                .arg(
+                    Arg::new("counter")
+                        .short('c')
+                        .long("counter")
+                        .help("Derive the password for this counter (1 for the first password)")
+                        .value_parser(validate_arg_u32),
+                )
+                .arg(
+                    Arg::new("next")
+                        .action(ArgAction::SetTrue)
+                        .short('n')
+                        .long("next")
+                        .conflicts_with("counter")
+                        .help("Increment the counter, to get a new password for the site"),
+                )
+                .arg(
+                    Arg::new("stateless")
+                        .action(ArgAction::SetTrue)
+                        .long("stateless")
+                        .help("Don't open the password file, nor save the counter"),
+                )
+                .arg(
+                    Arg::new("show")
+                        .action(ArgAction::SetTrue)
+                        .short('s')
+                        .long("show")
+                        .help("Show the password instead of copying it to the clipboard"),
+                )
+                .arg(
+                    Arg::new("alnum")
+                        .action(ArgAction::SetTrue)
+                        .short('a')
+                        .long("alnum")
+                        .help("Only use alpha numeric (a-z, A-Z, 0-9) in derived passwords"),
+                )
+                .arg(
+                    Arg::new("length")
+                        .short('l')
+                        .long("length")
+                        .default_value("32")
+                        .help("Set a custom length for the derived password")
+                        .value_parser(validate_arg_usize),
                 ),
         )
         .subcommand(
@@ -385,59 +1010,346 @@ pub fn main_with_args(
                         .short('s')
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
+                )
+                .arg(
+                    Arg::new("full")
+                        .action(ArgAction::SetTrue)
+                        .short('f')
+                        .long("full")
+                        .conflicts_with("pass-format")
+                        .help("Also show the URL, notes and custom fields"),
+                )
+                .arg(
+                    Arg::new("pass-format")
+                        .action(ArgAction::SetTrue)
+                        .long("pass-format")
+                        .conflicts_with("copy")
+                        .help("Print the password like `pass show` does: the password first, then the username"),
+                )
+                .arg(
+                    Arg::new("copy")
+                        .action(ArgAction::SetTrue)
+                        .short('c')
+                        .long("copy")
+                        .conflicts_with("show")
+                        .help("Copy the password to the clipboard, which is the default"),
+                )
+                .arg(
+                    Arg::new("hold")
+                        .action(ArgAction::SetTrue)
+                        .long("hold")
+                        .conflicts_with_all(["show", "copy", "pass-format"])
+                        .help("Show the password in the terminal only while a key is held down"),
+                )
+                .arg(show_secrets_arg()),
+        )
+        .subcommand(
+            Command::new("copy")
+                .about("Copy a password to the clipboard, without ever showing it")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
                 ),
         )
         .subcommand(
-            Command::new("rename")
-                .about("Rename the app for a password")
+            Command::new("clear-clipboard")
+                .hide(true)
+                .about("Clear the clipboard after a delay, unless something else was copied")
+                .arg(
+                    Arg::new("seconds")
+                        .required(true)
+                        .value_parser(validate_arg_u64)
+                        .help("How long to wait before clearing the clipboard"),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("List the previous passwords of an app")
                 .arg(
                     Arg::new("app")
                         .required(true)
-                        .help("The current name of the app (fuzzy-matched)"),
+                        .help("The name of the app (fuzzy-matched)"),
                 )
                 .arg(
-                    Arg::new("new_name")
-                        .required(true)
-                        .help("The new name of the app"),
+                    Arg::new("show")
+                        .action(ArgAction::SetTrue)
+                        .short('s')
+                        .long("show")
+                        .help("Show the previous passwords instead of masking them"),
                 ),
         )
         .subcommand(
-            Command::new("transfer")
-                .about("Change the username for a password")
+            Command::new("restore")
+                .about("Go back to a previous password of an app")
                 .arg(
                     Arg::new("app")
                         .required(true)
-                        .help("The current name of the app (fuzzy-matched)"),
+                        .help("The name of the app (fuzzy-matched)"),
                 )
                 .arg(
-                    Arg::new("new_username")
+                    Arg::new("version")
+                        .long("version")
                         .required(true)
-                        .help("Your new username for this account"),
+                        .help("The previous password to go back to, as numbered by `rooster history`")
+                        .value_parser(validate_arg_usize),
                 ),
         )
-        .subcommand(Command::new("list").about("List all apps and usernames"))
         .subcommand(
-            Command::new("import")
-                .subcommand_required(true)
-                .arg_required_else_help(true)
-                .about("Import all your existing passwords from elsewhere")
-                .subcommand(
-                    Command::new("json")
-                        .about("Import a file generated with `rooster export json`")
-                        .arg(
-                            Arg::new("path")
-                                .required(true)
-                                .help("The path to the file you want to import"),
-                        ),
+            Command::new("tag")
+                .about("Show the tags of an app, or add or remove some")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
                 )
-                .subcommand(
-                    Command::new("csv")
-                        .about("Import a file generated with `rooster export csv`")
-                        .arg(
-                            Arg::new("path")
+                .arg(
+                    Arg::new("tags")
+                        .num_args(1..)
+                        .help("The tags to add, ie: `work banking`"),
+                )
+                .arg(
+                    Arg::new("remove")
+                        .action(ArgAction::SetTrue)
+                        .long("remove")
+                        .requires("tags")
+                        .help("Remove the tags instead of adding them"),
+                ),
+        )
+        .subcommand(
+            Command::new("note")
+                .about("Show the notes of an app, or change them")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(Arg::new("note").help("The new notes, which replace the current ones"))
+                .arg(
+                    Arg::new("clear")
+                        .action(ArgAction::SetTrue)
+                        .long("clear")
+                        .conflicts_with("note")
+                        .help("Remove the notes"),
+                ),
+        )
+        .subcommand(
+            Command::new("policy")
+                .about("Show how new passwords are generated for an app, or change it, ie: to fit the site's rules")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("alnum")
+                        .action(ArgAction::SetTrue)
+                        .short('a')
+                        .long("alnum")
+                        .visible_alias("no-symbols")
+                        .help("Only use alpha numeric (a-z, A-Z, 0-9) in generated passwords"),
+                )
+                .arg(
+                    Arg::new("length")
+                        .short('l')
+                        .long("length")
+                        .help("The length of generated passwords")
+                        .value_parser(validate_arg_usize),
+                )
+                .args(generation_args())
+                .arg(
+                    Arg::new("clear")
+                        .action(ArgAction::SetTrue)
+                        .long("clear")
+                        .conflicts_with_all(["alnum", "length", "charset", "no-ambiguous", "words", "pattern"])
+                        .help("Remove the policy, so that passwords are generated like for other apps"),
+                ),
+        )
+        .subcommand(
+            Command::new("expire")
+                .about("Show when the password of an app must be changed, or make it expire, ie: every 90 days")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("after")
+                        .value_name("DURATION")
+                        .value_parser(expiry::parse_days)
+                        .help("How long the password lasts, which starts over when it is changed, ie: 90d, 12w, 6m or 1y"),
+                )
+                .arg(
+                    Arg::new("clear")
+                        .action(ArgAction::SetTrue)
+                        .long("clear")
+                        .conflicts_with("after")
+                        .help("Remove the expiry"),
+                ),
+        )
+        .subcommand(
+            Command::new("set-field")
+                .about("Set a custom field of an app, ie: a PIN, or its URL with the name \"url\"")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .required(true)
+                        .help("The name of the field"),
+                )
+                .arg(Arg::new("value").help(
+                    "The value of the field, asked for without showing it if not given",
+                ))
+                .arg(
+                    Arg::new("delete")
+                        .action(ArgAction::SetTrue)
+                        .long("delete")
+                        .conflicts_with("value")
+                        .help("Remove the field"),
+                ),
+        )
+        .subcommand(
+            Command::new("totp")
+                .about("Print the current 2FA code for an app")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                ),
+        )
+        .subcommand(
+            Command::new("set-totp")
+                .about("Save the TOTP secret of an app, from an otpauth:// URI")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                ),
+        )
+        .subcommand(
+            Command::new("rename")
+                .about("Rename the app for a password")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The current name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("new_name")
+                        .required(true)
+                        .help("The new name of the app"),
+                ),
+        )
+        .subcommand(
+            Command::new("transfer")
+                .about("Change the username for a password")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The current name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("new_username")
+                        .required(true)
+                        .help("Your new username for this account"),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List all apps and usernames")
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .short('t')
+                        .value_name("TAG")
+                        .action(ArgAction::Append)
+                        .help("Only list the apps with this tag, which can be given more than once"),
+                )
+                .arg(show_secrets_arg())
+                .arg(
+                    Arg::new("names-only")
+                        .action(ArgAction::SetTrue)
+                        .long("names-only")
+                        .help("Only list app names, one per line, without asking for the master password, which needs `rooster agent start`"),
+                ),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Search apps by name, best matches first, and by tag with `tag:<tag>`")
+                .arg(
+                    Arg::new("query")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched), and tags like `tag:work`"),
+                )
+                .arg(show_secrets_arg()),
+        )
+        .subcommand(
+            Command::new("where-used")
+                .about("Find the apps that use a password, ie: after it was leaked in a breach"),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Report reused, weak and stale passwords, and exit with 1 if there are any")
+                .arg(
+                    Arg::new("max-age")
+                        .long("max-age")
+                        .value_name("DAYS")
+                        .default_value(config.audit_max_age_days.to_string())
+                        .value_parser(validate_arg_u64)
+                        .help("Report passwords older than this as stale, 0 to never report them"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .action(ArgAction::SetTrue)
+                        .long("json")
+                        .help("Write the report as JSON, for scripts, like `--output json`"),
+                ),
+        )
+        .subcommand(
+            Command::new("expiring")
+                .about("List the passwords which expired or expire soon, see `rooster expire`")
+                .arg(
+                    Arg::new("within")
+                        .long("within")
+                        .value_name("DAYS")
+                        .default_value(expiry::WARNING_DAYS.to_string())
+                        .value_parser(validate_arg_u32)
+                        .help("List the passwords which expire within this many days"),
+                )
+                .arg(
+                    Arg::new("fail-if-expired")
+                        .action(ArgAction::SetTrue)
+                        .long("fail-if-expired")
+                        .help("Exit with 1 if any password expired, ie: for reminders run by cron"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Import all your existing passwords from elsewhere")
+                .subcommand(
+                    Command::new("json")
+                        .about("Import a file generated with `rooster export json`")
+                        .arg(
+                            Arg::new("path")
                                 .required(true)
                                 .help("The path to the file you want to import"),
-                        ),
+                        )
+                        .arg(import_conflict_arg()),
+                )
+                .subcommand(
+                    Command::new("csv")
+                        .about("Import a file generated with `rooster export csv`")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        )
+                        .arg(import_conflict_arg()),
                 )
                 .subcommand(
                     Command::new("1password")
@@ -446,7 +1358,36 @@ pub fn main_with_args(
                             Arg::new("path")
                                 .required(true)
                                 .help("The path to the file you want to import"),
-                        ),
+                        )
+                        .arg(import_conflict_arg()),
+                )
+                .subcommand(
+                    Command::new("keepass-xml")
+                        .about("Import a KeePass 2 XML export")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        )
+                        .arg(import_conflict_arg()),
+                )
+                .subcommand(
+                    Command::new("bitwarden-json")
+                        .about("Import an unencrypted JSON export from Bitwarden")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        )
+                        .arg(import_conflict_arg()),
+                )
+                .subcommand(
+                    Command::new("pass")
+                        .about("Import a password store from pass, the standard unix password manager, with gpg")
+                        .arg(Arg::new("path").help(
+                            "The path to the password store, $PASSWORD_STORE_DIR or ~/.password-store by default",
+                        ))
+                        .arg(import_conflict_arg()),
                 ),
         )
         .subcommand(
@@ -454,14 +1395,230 @@ pub fn main_with_args(
                 .subcommand_required(true)
                 .arg_required_else_help(true)
                 .about("Export raw password data")
-                .subcommand(Command::new("json").about("Export raw password data in JSON format"))
-                .subcommand(Command::new("csv").about("Export raw password data in CSV format"))
+                .subcommand(
+                    Command::new("json")
+                        .about("Export raw password data in JSON format")
+                        .arg(export_mapping_arg())
+                        .arg(export_profile_arg())
+                        .arg(export_file_arg()),
+                )
+                .subcommand(
+                    Command::new("csv")
+                        .about("Export raw password data in CSV format")
+                        .arg(export_mapping_arg())
+                        .arg(export_profile_arg())
+                        .arg(export_file_arg()),
+                )
                 .subcommand(
                     Command::new("1password")
-                        .about("Export raw password data in 1Password compatible CSV format"),
+                        .about("Export raw password data in 1Password compatible CSV format")
+                        .arg(export_file_arg()),
+                )
+                .subcommand(
+                    Command::new("keepass-xml")
+                        .about("Export raw password data in KeePass XML format, for KeePass and KeePassXC")
+                        .arg(export_file_arg()),
+                ),
+        )
+        .subcommand(
+            Command::new("shell").about(
+                "Unlock your password file once, then type commands until `quit`, with completion and history",
+            ),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print the completion script of your shell, which completes commands, options and app names")
+                .after_help(
+                    "For instance, add `eval \"$(rooster completions bash)\"` to your ~/.bashrc. App \
+                     names are only completed while `rooster agent start` keeps your password file \
+                     unlocked.",
+                )
+                .arg(
+                    Arg::new("shell")
+                        .required(true)
+                        .value_parser(completions::SHELLS)
+                        .help("The shell to complete for"),
                 ),
         )
         .subcommand(Command::new("set-master-password").about("Set your master password"))
+        .subcommand(
+            Command::new("backup")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Back up your password file to a secondary location, set with `backup_dir` in your config")
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .global(true)
+                        .help("Use this directory instead of the one in your config"),
+                )
+                .subcommand(
+                    Command::new("now").about("Save an encrypted snapshot of your password file"),
+                )
+                .subcommand(
+                    Command::new("verify")
+                        .about("Check that a snapshot can be decrypted and has not been tampered with")
+                        .arg(
+                            Arg::new("snapshot")
+                                .help("The path to the snapshot, the latest one by default"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("backups")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Recover from the backups made before each command that changes your password file")
+                .subcommand(Command::new("list").about("List the backups, latest first"))
+                .subcommand(
+                    Command::new("restore")
+                        .about("Replace your password file by a backup")
+                        .arg(
+                            Arg::new("backup")
+                                .required(true)
+                                .help("The number of the backup in `rooster backups list`, 1 for the latest")
+                                .value_parser(validate_arg_usize),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("sync")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Sync your password file between machines through a git remote")
+                .subcommand(
+                    Command::new("init")
+                        .about("Keep your password file in a git repository, committed after each change")
+                        .arg(
+                            Arg::new("remote")
+                                .required(true)
+                                .help("The URL of the git remote, ie: git@example.com:me/passwords.git"),
+                        ),
+                )
+                .subcommand(Command::new("push").about("Send your password file to the remote"))
+                .subcommand(
+                    Command::new("pull")
+                        .about("Get the password file from the remote, merging it with yours if both changed"),
+                ),
+        )
+        .subcommand(
+            Command::new("keychain")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Manage your master password in the OS keychain")
+                .subcommand(
+                    Command::new("enroll").about("Save your master password in the OS keychain"),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove your master password from the OS keychain"),
+                ),
+        )
+        .subcommand(
+            Command::new("agent")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Keep your password file unlocked for commands that only read it, ie: in scripts")
+                .subcommand(
+                    Command::new("start")
+                        .about("Unlock your password file and keep its key in memory")
+                        .arg(agent_ttl_arg(config))
+                        .arg(
+                            Arg::new("foreground")
+                                .action(ArgAction::SetTrue)
+                                .long("foreground")
+                                .help("Keep the key in this process instead of in the background"),
+                        ),
+                )
+                .subcommand(Command::new("stop").about("Forget the key kept in memory"))
+                .subcommand(
+                    Command::new("serve")
+                        .hide(true)
+                        .about("Run the agent started by `rooster agent start`")
+                        .arg(agent_ttl_arg(config)),
+                ),
+        )
+        .subcommand(
+            Command::new("keyfile")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Require a key file to unlock your password file, along with your master password")
+                .subcommand(
+                    Command::new("add")
+                        .about("Require this key file from now on, instead of the previous one if any")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the key file, ie: a file of random bytes on a USB key"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Only require your master password from now on"),
+                ),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Upgrade your Rooster file to the latest format, after backing it up")
+                .arg(
+                    Arg::new("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .long("dry-run")
+                        .help("Show what would change without changing anything"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check that your Rooster file is intact, and show its format version")
+                .arg(
+                    Arg::new("header-only")
+                        .action(ArgAction::SetTrue)
+                        .long("header-only")
+                        .help("Only check the header, which doesn't need your master password"),
+                ),
+        )
+        .subcommand(
+            Command::new("rekey")
+                .about("Encrypt your passwords with a new key, keeping your master password")
+                .arg(
+                    Arg::new("kdf")
+                        .long("kdf")
+                        .value_parser(["argon2id", "scrypt"])
+                        .help("Also change how the key is derived from your master password"),
+                )
+                .arg(
+                    Arg::new("memory")
+                        .long("memory")
+                        .requires("kdf")
+                        .value_name("KIB")
+                        .default_value("19456")
+                        .help("The memory Argon2id uses, in KiB")
+                        .value_parser(validate_arg_u32),
+                )
+                .arg(
+                    Arg::new("iterations")
+                        .long("iterations")
+                        .requires("kdf")
+                        .default_value("2")
+                        .help("The number of iterations of Argon2id")
+                        .value_parser(validate_arg_u32),
+                )
+                .arg(
+                    Arg::new("parallelism")
+                        .long("parallelism")
+                        .requires("kdf")
+                        .default_value("1")
+                        .help("The number of lanes of Argon2id")
+                        .value_parser(validate_arg_u32),
+                )
+                .arg(
+                    Arg::new("force")
+                        .action(ArgAction::SetTrue)
+                        .short('f')
+                        .long("force")
+                        .help("Disable parameter checks"),
+                ),
+        )
         .subcommand(
             Command::new("set-scrypt-params")
                 .about("Set the key derivation parameters")
@@ -490,15 +1647,152 @@ pub fn main_with_args(
                         .long("force")
                         .help("Disable parameter checks"),
                 ),
-        )
-        .get_matches_from(args);
+        );
+    let args = expand_command_args(args, &command, config);
+    // The shell parses its commands with the same definitions
+    let matches = command.clone().get_matches_from(args);
+    memory::set_locking(!matches.get_flag("no-mlock"));
+
+    // Questions fail right away, instead of waiting for an answer that won't come
+    if matches.get_flag("non-interactive") {
+        let mut io = rclio::WithPrompts::new(
+            io,
+            Box::new(rclio::NoPrompts::new(
+                "--non-interactive doesn't let me ask questions",
+            )),
+        );
+        return run_command(&command, &matches, &mut io, rooster_file_path, config);
+    }
+    run_command(&command, &matches, io, rooster_file_path, config)
+}
 
+fn run_command(
+    command: &Command,
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+    config: &Config,
+) -> i32 {
     let subcommand = matches.subcommand_name().unwrap();
 
     let command_matches = matches.subcommand_matches(subcommand).unwrap();
 
+    let read_only = matches.get_flag("read-only");
+    if let Err(code) = check_read_only(read_only, subcommand, command_matches, io) {
+        return code;
+    }
+
     if subcommand == "init" {
-        match commands::init::callback_exec(command_matches, io, rooster_file_path) {
+        let key_file = match read_key_file(matches, config, io) {
+            Err(code) => return code,
+            Ok(key_file) => key_file,
+        };
+        match commands::init::callback_exec(command_matches, io, rooster_file_path, key_file) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "completions" {
+        match commands::completions::callback_exec(command_matches, command, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Completion scripts list app names while the user types, so this never asks anything
+    if subcommand == "list" && command_matches.get_flag("names-only") {
+        let mut store = match get_password_store_without_asking(rooster_file_path, io) {
+            Some(store) => store,
+            None => return 1,
+        };
+        match commands::list::callback_exec(command_matches, &mut store, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "clear-clipboard" {
+        match commands::clear_clipboard::callback_exec(command_matches) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Derived passwords only need the master seed, the password file just remembers counters
+    if subcommand == "derive" && command_matches.get_flag("stateless") {
+        match commands::derive::callback_exec_stateless(command_matches, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Snapshots are copies of the encrypted file, which don't need unlocking
+    if subcommand == "backup" && command_matches.subcommand_name() == Some("now") {
+        let now_matches = command_matches.subcommand_matches("now").unwrap();
+        match commands::backup::callback_exec_now(now_matches, io, rooster_file_path, config) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Removing the master password from the keychain doesn't need it, ie: if it was changed.
+    if subcommand == "keychain" && command_matches.subcommand_name() == Some("remove") {
+        match commands::keychain::callback_exec_remove(io, rooster_file_path) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // The agent is stopped, or runs with the key it was started with, without unlocking
+    if subcommand == "agent" && command_matches.subcommand_name() != Some("start") {
+        let result = match command_matches.subcommand() {
+            Some(("serve", serve_matches)) => {
+                commands::agent::callback_exec_serve(serve_matches, io, rooster_file_path)
+            }
+            _ => commands::agent::callback_exec_stop(io, rooster_file_path),
+        };
+        match result {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Backups are restored without unlocking the password file, which may be broken or gone
+    if subcommand == "backups" {
+        let result = match command_matches.subcommand() {
+            Some(("restore", restore_matches)) => match read_key_file(matches, config, io) {
+                Ok(key_file) => commands::backups::callback_exec_restore(
+                    restore_matches,
+                    io,
+                    rooster_file_path,
+                    key_file,
+                ),
+                Err(code) => Err(code),
+            },
+            _ => commands::backups::callback_exec_list(io, rooster_file_path, config),
+        };
+        match result {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Sync works on the encrypted file, which is only unlocked to merge it
+    if subcommand == "sync" {
+        let result = match command_matches.subcommand() {
+            Some(("init", init_matches)) => {
+                commands::sync::callback_exec_init(init_matches, io, rooster_file_path)
+            }
+            Some(("push", _)) => commands::sync::callback_exec_push(io, rooster_file_path),
+            _ => match read_key_file(matches, config, io) {
+                Ok(key_file) => {
+                    commands::sync::callback_exec_pull(io, rooster_file_path, config, key_file)
+                }
+                Err(code) => Err(code),
+            },
+        };
+        match result {
             Err(i) => return i,
             _ => return 0,
         }
@@ -524,7 +1818,21 @@ pub fn main_with_args(
         return 1;
     }
 
-    let mut file = match open_password_file(password_file_path_as_string.deref()) {
+    // The header is checked first, so that a truncated file is reported without the master password
+    if subcommand == "verify" {
+        if let Err(code) = commands::verify::callback_exec_header(io, rooster_file_path) {
+            return code;
+        }
+        if command_matches.get_flag("header-only") {
+            return 0;
+        }
+    }
+
+    let file = match read_only {
+        true => File::open(&password_file_path_as_string),
+        false => open_password_file(password_file_path_as_string.deref()),
+    };
+    let mut file = match file {
         Ok(file) => file,
         Err(err) => {
             match err.kind() {
@@ -548,25 +1856,137 @@ pub fn main_with_args(
         }
     };
 
-    let mut store = match get_password_store(&mut file, io) {
+    let keychain_path = match matches.get_flag("from-keychain") {
+        true => Some(rooster_file_path.as_path()),
+        false => None,
+    };
+    let key_file = match read_key_file(matches, config, io) {
+        Err(code) => return code,
+        Ok(key_file) => key_file,
+    };
+    // Stores unlocked by the agent can't be saved with another master password or key file
+    let agent_path = match changes_password_file(subcommand) {
+        true => None,
+        false => Some(rooster_file_path.as_path()),
+    };
+    let mut store = match get_password_store(
+        &mut file,
+        config,
+        agent_path,
+        keychain_path,
+        key_file.as_ref(),
+        io,
+    ) {
         Err(code) => return code,
         Ok(store) => store,
     };
 
+    if subcommand == "migrate" {
+        match commands::migrate::callback_exec(
+            command_matches,
+            &mut store,
+            &mut file,
+            io,
+            rooster_file_path,
+        ) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "verify" {
+        match commands::verify::callback_exec(&store, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "backup" {
+        let verify_matches = command_matches.subcommand_matches("verify").unwrap();
+        match commands::backup::callback_exec_verify(
+            verify_matches,
+            &store,
+            io,
+            rooster_file_path,
+            config,
+        ) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "keychain" {
+        match commands::keychain::callback_exec_enroll(&store, io, rooster_file_path) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "agent" {
+        let start_matches = command_matches.subcommand_matches("start").unwrap();
+        match commands::agent::callback_exec_start(start_matches, &store, io, rooster_file_path) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Read-only files are upgraded the next time they can be saved
+    if !read_only {
+        if let Err(code) = commands::migrate::migrate(&mut store, io, rooster_file_path) {
+            return code;
+        }
+    }
+
+    if subcommand == "shell" {
+        match commands::shell::callback_exec(
+            command_matches,
+            command,
+            store,
+            io,
+            rooster_file_path,
+            config,
+            key_file.as_ref(),
+        ) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if !read_only && changes_password_file(subcommand) {
+        back_up_password_file(rooster_file_path, config, io);
+    }
+
     let callback = match subcommand {
         "get" => commands::get::callback_exec,
+        "copy" => commands::copy::callback_exec,
         "add" => commands::add::callback_exec,
         "delete" => commands::delete::callback_exec,
         "generate" => commands::generate::callback_exec,
+        "derive" => commands::derive::callback_exec,
         "regenerate" => commands::regenerate::callback_exec,
         "list" => commands::list::callback_exec,
+        "search" => commands::search::callback_exec,
+        "where-used" => commands::where_used::callback_exec,
+        "audit" => commands::audit::callback_exec,
+        "expiring" => commands::expiring::callback_exec,
         "import" => commands::import::callback_exec,
         "export" => commands::export::callback_exec,
         "set-master-password" => commands::set_master_password::callback_exec,
+        "rekey" => commands::rekey::callback_exec,
+        "keyfile" => commands::keyfile::callback_exec,
         "set-scrypt-params" => commands::set_scrypt_params::callback_exec,
         "rename" => commands::rename::callback_exec,
         "transfer" => commands::transfer::callback_exec,
         "change" => commands::change::callback_exec,
+        "totp" => commands::totp::callback_exec,
+        "history" => commands::history::callback_exec,
+        "restore" => commands::restore::callback_exec,
+        "note" => commands::note::callback_exec,
+        "tag" => commands::tag::callback_exec,
+        "set-field" => commands::set_field::callback_exec,
+        "policy" => commands::policy::callback_exec,
+        "expire" => commands::expire::callback_exec,
+        "set-totp" => commands::set_totp::callback_exec,
         _ => unreachable!("Validation should have been done by `clap` before"),
     };
 
@@ -574,9 +1994,32 @@ pub fn main_with_args(
         return code;
     }
 
+    if read_only {
+        return 0;
+    }
+
     if let Err(code) = sync_password_store(&mut store, &mut file, io) {
         return code;
     }
 
+    if changes_password_file(subcommand) {
+        commit_password_file(rooster_file_path, subcommand, io);
+    }
+
+    if let Some(dir) = &config.backup_dir {
+        if let Err(err) =
+            backup::write_snapshot_if_due(rooster_file_path, dir, config.backup_interval_days)
+        {
+            io.warning(
+                format!(
+                    "I could not back up your password file to {} (reason: {}).",
+                    dir.display(),
+                    err
+                ),
+                OutputType::Error,
+            );
+        }
+    }
+
     return 0;
 }
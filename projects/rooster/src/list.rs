@@ -1,3 +1,5 @@
+use crate::expiry;
+use crate::ffi;
 use crate::password::v2::{Password, PasswordStore};
 use rclio::{CliInputOutput, OutputType};
 
@@ -7,7 +9,11 @@ pub const WITH_NUMBERS: bool = true;
 /// Used to indicate lists should not have a number, ie: Google my.account@gmail.com
 pub const WITHOUT_NUMBERS: bool = false;
 
-fn get_list_of_passwords(passwords: &Vec<&Password>, with_numbers: bool) -> Vec<String> {
+fn get_list_of_passwords(
+    passwords: &Vec<&Password>,
+    with_numbers: bool,
+    now: ffi::time_t,
+) -> Vec<String> {
     // Find the app name column length
     let longest_app_name = passwords.iter().fold(0, |acc, p| {
         if p.name.len() > acc {
@@ -51,7 +57,15 @@ fn get_list_of_passwords(passwords: &Vec<&Password>, with_numbers: bool) -> Vec<
             ),
         };
 
-        list.push(s);
+        let s = match p.tags.is_empty() {
+            true => s,
+            false => format!("{} [{}]", s, p.tags.join(", ")),
+        };
+
+        match expiry::status(p, expiry::WARNING_DAYS, now) {
+            Some(status) => list.push(format!("{} ({})", s, status.describe())),
+            None => list.push(s),
+        }
     }
 
     list
@@ -62,18 +76,25 @@ pub fn print_list_of_passwords(
     with_numbers: bool,
     io: &mut impl CliInputOutput,
 ) {
-    let list = get_list_of_passwords(passwords, with_numbers);
+    let now = ffi::time();
+    let list = get_list_of_passwords(passwords, with_numbers, now);
 
-    for s in list {
-        io.info(s, OutputType::Standard);
+    // Passwords which must be changed soon stand out
+    for (s, p) in list.into_iter().zip(passwords.iter()) {
+        match expiry::status(p, expiry::WARNING_DAYS, now) {
+            Some(_) => io.warning(s, OutputType::Standard),
+            None => io.info(s, OutputType::Standard),
+        }
     }
 }
 
+/// Asks which password to use, and returns its index, or `None` if the user cancels by answering
+/// nothing or if the answer can't be read
 fn request_password_index_from_stdin(
     passwords: &Vec<&Password>,
     prompt: &str,
     io: &mut impl CliInputOutput,
-) -> usize {
+) -> Option<usize> {
     assert!(!passwords.is_empty());
 
     // Read the index from the command line and convert to a number
@@ -81,7 +102,10 @@ fn request_password_index_from_stdin(
         if passwords.len() > 1 {
             io.info(prompt, OutputType::Standard);
             io.write(
-                format!("Type a number from 1 to {}: ", passwords.len()),
+                format!(
+                    "Type a number from 1 to {}, or nothing to cancel: ",
+                    passwords.len()
+                ),
                 OutputType::Standard,
             );
         } else if passwords.len() == 1 {
@@ -92,6 +116,10 @@ fn request_password_index_from_stdin(
         }
 
         match io.read_line() {
+            Ok(line) if line.trim().is_empty() => {
+                io.error("Alright, I won't do anything then.", OutputType::Error);
+                return None;
+            }
             Ok(line) => {
                 match line.trim().parse::<usize>() {
                     Ok(index) => {
@@ -106,7 +134,7 @@ fn request_password_index_from_stdin(
                             continue;
                         }
 
-                        return index - 1;
+                        return Some(index - 1);
                     }
                     Err(err) => {
                         io.write(
@@ -117,14 +145,12 @@ fn request_password_index_from_stdin(
                 };
             }
             Err(err) => {
-                io.write(
-                    format!(
-                        "I couldn't read that (reason: {}). Let's try again (1 to {}): ",
-                        err,
-                        passwords.len()
-                    ),
-                    OutputType::Standard,
+                io.nl(OutputType::Standard);
+                io.error(
+                    format!("Woops, I couldn't read your answer (reason: {}).", err),
+                    OutputType::Error,
                 );
+                return None;
             }
         }
     }
@@ -135,12 +161,14 @@ fn choose_password_in_list(
     with_numbers: bool,
     prompt: &str,
     io: &mut impl CliInputOutput,
-) -> usize {
+) -> Option<usize> {
     print_list_of_passwords(passwords, with_numbers, io);
     io.nl(OutputType::Standard);
     request_password_index_from_stdin(passwords, prompt, io)
 }
 
+/// Finds the password that a query is about, and asks the user to pick one if there are several
+/// matches, the best ones first
 pub fn search_and_choose_password<'a>(
     store: &'a PasswordStore,
     query: &str,
@@ -164,7 +192,7 @@ pub fn search_and_choose_password<'a>(
         return Some(&password);
     }
 
-    let index = choose_password_in_list(&passwords, with_numbers, prompt, io);
+    let index = choose_password_in_list(&passwords, with_numbers, prompt, io)?;
     Some(passwords[index])
 }
 
@@ -172,7 +200,7 @@ pub fn search_and_choose_password<'a>(
 mod test {
     use super::get_list_of_passwords;
     use crate::list::{WITHOUT_NUMBERS, WITH_NUMBERS};
-    use crate::password::v2::Password;
+    use crate::password::v2::{Expiry, Password};
     use rtoolbox::safe_string::SafeString;
 
     // Creates a list of at least two passwords, and more if specified
@@ -200,11 +228,42 @@ mod test {
         list
     }
 
+    #[test]
+    fn password_list_shows_tags() {
+        let mut passwords = get_passwords(0);
+        passwords[1].tags = vec![format!("work"), format!("search")];
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITHOUT_NUMBERS, 0);
+
+        assert_eq!(
+            list,
+            &[
+                "youtube.com that long username",
+                "google      short un           [work, search]",
+            ]
+        );
+    }
+
+    #[test]
+    fn password_list_shows_expiry() {
+        let mut passwords = get_passwords(0);
+        passwords[0].expiry = Some(Expiry::new(90, 0));
+        passwords[1].expiry = Some(Expiry::new(1, 0));
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITHOUT_NUMBERS, 86400 * 3);
+
+        assert_eq!(
+            list,
+            &[
+                "youtube.com that long username",
+                "google      short un           (expired 2 days ago)",
+            ]
+        );
+    }
+
     #[test]
     fn password_list_has_right_format_with_numbers() {
         // With 2 passwords (number width 1)
         let passwords = get_passwords(0);
-        let list = get_list_of_passwords(&passwords.iter().collect(), WITH_NUMBERS);
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITH_NUMBERS, 0);
 
         assert_eq!(
             list,
@@ -216,7 +275,7 @@ mod test {
 
         // Now with 10 passwords (number width 2)
         let passwords = get_passwords(8);
-        let list = get_list_of_passwords(&passwords.iter().collect(), WITH_NUMBERS);
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITH_NUMBERS, 0);
 
         assert_eq!(
             list,
@@ -238,7 +297,7 @@ mod test {
     #[test]
     fn password_list_has_right_format_without_numbers() {
         let passwords = get_passwords(0);
-        let list = get_list_of_passwords(&passwords.iter().collect(), WITHOUT_NUMBERS);
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITHOUT_NUMBERS, 0);
 
         assert_eq!(
             list,
@@ -1,9 +1,12 @@
-use rclio::RegularInputOutput;
+use rclio::{CliInputOutput, OutputType, RegularInputOutput};
+use rooster::Config;
 use std::env::VarError;
 use std::path::PathBuf;
 
 const ROOSTER_FILE_ENV_VAR: &'static str = "ROOSTER_FILE";
 const ROOSTER_FILE_DEFAULT: &'static str = ".passwords.rooster";
+const ROOSTER_CONFIG_ENV_VAR: &'static str = "ROOSTER_CONFIG";
+const ROOSTER_CONFIG_DEFAULT: &'static str = "rooster/config.json";
 
 fn get_password_file_path() -> Result<PathBuf, i32> {
     // First, look for the ROOSTER_FILE environment variable.
@@ -27,19 +30,53 @@ fn get_password_file_path() -> Result<PathBuf, i32> {
     }
 }
 
+fn get_config_file_path() -> Result<PathBuf, i32> {
+    // First, look for the ROOSTER_CONFIG environment variable.
+    match std::env::var(ROOSTER_CONFIG_ENV_VAR) {
+        Ok(filename) => Ok(PathBuf::from(filename)),
+        Err(VarError::NotPresent) => {
+            // If the environment variable is not there, we'll look in the default location:
+            // ~/.config/rooster/config.json on Linux
+            let mut file_default = dirs::config_dir().ok_or(1)?;
+            file_default.push(ROOSTER_CONFIG_DEFAULT);
+            Ok(file_default)
+        }
+        Err(VarError::NotUnicode(_)) => Err(1),
+    }
+}
+
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
     let args_refs = args.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
 
     let rooster_file_path = get_password_file_path().unwrap_or_else(|err| std::process::exit(err));
 
+    let rooster_config_path = get_config_file_path().unwrap_or_else(|err| std::process::exit(err));
+
     let stdin = std::io::stdin();
     let stdout = std::io::stdout();
     let stderr = std::io::stderr();
+    let mut io = RegularInputOutput::new(stdin.lock(), stdout.lock(), stderr.lock());
+
+    let config = match Config::from_file(&rooster_config_path) {
+        Ok(config) => config,
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I could not read your config file \"{}\" (reason: {}).",
+                    rooster_config_path.to_string_lossy(),
+                    err
+                ),
+                OutputType::Error,
+            );
+            std::process::exit(1);
+        }
+    };
 
-    std::process::exit(rooster::main_with_args(
+    std::process::exit(rooster::main_with_args_and_config(
         args_refs.as_slice(),
-        &mut RegularInputOutput::new(stdin.lock(), stdout.lock(), stderr.lock()),
+        &mut io,
         &rooster_file_path,
+        &config,
     ));
 }
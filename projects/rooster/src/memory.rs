@@ -0,0 +1,29 @@
+//! Keeps decrypted passwords out of swap, unless `--no-mlock` is given for systems that don't let
+//! Rooster lock memory
+
+use rtoolbox::secure_memory::{LockedMemory, LockedRegions};
+use std::io::Result as IoResult;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static LOCKING: AtomicBool = AtomicBool::new(true);
+
+/// Turns locking on or off for the rest of the process, see `--no-mlock`
+pub fn set_locking(enabled: bool) {
+    LOCKING.store(enabled, Ordering::SeqCst);
+}
+
+/// Keeps secrets from being swapped out to disk until the guard is dropped, unless locking is off
+pub fn lock(bytes: &[u8]) -> IoResult<Option<LockedMemory<'_>>> {
+    match LOCKING.load(Ordering::SeqCst) {
+        true => LockedMemory::new(bytes).map(Some),
+        false => Ok(None),
+    }
+}
+
+/// Keeps secrets from being swapped out to disk until `locked` is dropped, unless locking is off
+pub fn lock_with(locked: &mut LockedRegions, bytes: &[u8]) -> IoResult<()> {
+    match LOCKING.load(Ordering::SeqCst) {
+        true => locked.lock(bytes),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,137 @@
+//! Upgrades Rooster files from older versions of the format, one version at a time
+//!
+//! Reading a file of any supported version gives a `PasswordStore`, which is always saved in the
+//! latest version. Migrations make the other changes that a version needs, in order, after a
+//! backup of the file has been made.
+
+use crate::password::v2::{Kdf, PasswordStore};
+use std::io::Result as IoResult;
+use std::path::{Path, PathBuf};
+
+/// A step from one version of the Rooster file format to the next
+pub struct Migration {
+    pub from: u32,
+    pub to: u32,
+    /// What changes in the file, shown to the user
+    pub description: &'static str,
+    apply: fn(&mut PasswordStore),
+}
+
+/// All migrations, ordered by version
+static MIGRATIONS: [Migration; 7] = [
+    Migration {
+        from: 1,
+        to: 2,
+        description: "Derive the key with scrypt and authenticate passwords with HMAC-SHA512",
+        // Reading a version 1 file already converts its passwords.
+        apply: |_| {},
+    },
+    Migration {
+        from: 2,
+        to: 3,
+        description: "Encrypt passwords with a random data key that the master password unlocks",
+        apply: |store| store.rekey(),
+    },
+    Migration {
+        from: 3,
+        to: 4,
+        description: "Store a URL, notes and custom fields with each password",
+        // Passwords of older files have none, which is the default.
+        apply: |_| {},
+    },
+    Migration {
+        from: 4,
+        to: 5,
+        description: "Keep the previous passwords of each password",
+        // Passwords of older files have no history yet, which is the default.
+        apply: |_| {},
+    },
+    Migration {
+        from: 5,
+        to: 6,
+        description: "Tag passwords",
+        // Passwords of older files have no tags, which is the default.
+        apply: |_| {},
+    },
+    Migration {
+        from: 6,
+        to: 7,
+        description: "Derive the key from the master password with Argon2id instead of scrypt",
+        apply: |store| store.set_kdf(Kdf::ARGON2ID_DEFAULT),
+    },
+    Migration {
+        from: 7,
+        to: 8,
+        description: "Optionally require a key file to unlock, along with the master password",
+        // Older files don't need a key file, which is the default.
+        apply: |_| {},
+    },
+];
+
+/// Returns the migrations that a file of this version needs, in the order they apply
+pub fn pending(version: u32) -> Vec<&'static Migration> {
+    MIGRATIONS.iter().filter(|m| m.from >= version).collect()
+}
+
+/// Where the file is saved before migrating it from this version
+pub fn backup_path(rooster_file_path: &Path, version: u32) -> PathBuf {
+    let mut path = rooster_file_path.as_os_str().to_owned();
+    path.push(format!(".v{}.backup", version));
+    PathBuf::from(path)
+}
+
+/// Backs up the file and applies the pending migrations to the store, which the caller then saves
+///
+/// Returns the path of the backup, or `None` if the file is already in the latest version.
+pub fn migrate(store: &mut PasswordStore, rooster_file_path: &Path) -> IoResult<Option<PathBuf>> {
+    let pending = pending(store.version());
+    let latest = match pending.last() {
+        Some(migration) => migration.to,
+        None => return Ok(None),
+    };
+
+    let backup = backup_path(rooster_file_path, store.version());
+    std::fs::copy(rooster_file_path, &backup)?;
+
+    for migration in pending {
+        (migration.apply)(store);
+    }
+    store.set_version(latest);
+
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{migrate, pending, MIGRATIONS};
+    use crate::password::v2::PasswordStore;
+    use rtoolbox::safe_string::SafeString;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert_eq!(pair[0].to, pair[1].from);
+        }
+        assert_eq!(pending(1).len(), MIGRATIONS.len());
+
+        let store = PasswordStore::new(SafeString::from_string("xxxx".to_owned())).unwrap();
+        assert_eq!(MIGRATIONS.last().unwrap().to, store.version());
+        assert!(pending(store.version()).is_empty());
+    }
+
+    #[test]
+    fn test_migrate_backs_up_file() {
+        let path = std::env::temp_dir().join(format!("rooster-migrations-{}", std::process::id()));
+        std::fs::write(&path, b"version 2").unwrap();
+
+        let mut store = PasswordStore::new(SafeString::from_string("xxxx".to_owned())).unwrap();
+        store.set_version(2);
+        let backup = migrate(&mut store, &path).unwrap().unwrap();
+        assert_eq!(std::fs::read(&backup).unwrap(), b"version 2");
+        assert_eq!(store.version(), 8);
+        assert_eq!(migrate(&mut store, &path).unwrap(), None);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&backup).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+//! Machine-readable output for scripts, see `--output json`
+//!
+//! Passwords are written without their secrets, ie: the password, notes and custom fields,
+//! unless `--show-secrets` is also given.
+
+use crate::ffi;
+use crate::password::v2::Password;
+use rclio::{CliInputOutput, OutputType};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+/// A password as written in JSON
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PasswordOutput<'a> {
+    pub name: &'a str,
+    pub username: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<&'a str>,
+    pub tags: &'a [String],
+    pub created_at: ffi::time_t,
+    pub updated_at: ffi::time_t,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+impl<'a> PasswordOutput<'a> {
+    pub fn new(password: &'a Password, show_secrets: bool) -> PasswordOutput<'a> {
+        PasswordOutput {
+            name: &password.name,
+            username: &password.username,
+            url: password.url.as_deref(),
+            tags: &password.tags,
+            created_at: password.created_at,
+            updated_at: password.updated_at,
+            password: show_secrets.then(|| password.password.deref().as_str()),
+            notes: password
+                .notes
+                .as_ref()
+                .filter(|_| show_secrets)
+                .map(|notes| notes.deref().as_str()),
+            fields: show_secrets.then(|| {
+                password
+                    .fields
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.deref().as_str()))
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Whether the command was given `--output json`
+pub fn is_json(matches: &clap::ArgMatches) -> bool {
+    matches.get_one::<String>("output").map(String::as_str) == Some("json")
+}
+
+pub fn show_secrets(matches: &clap::ArgMatches) -> bool {
+    matches.get_flag("show-secrets")
+}
+
+/// Writes passwords as a JSON array
+pub fn print_passwords(
+    passwords: &[&Password],
+    show_secrets: bool,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let passwords: Vec<PasswordOutput> = passwords
+        .iter()
+        .map(|password| PasswordOutput::new(password, show_secrets))
+        .collect();
+    print_json(&passwords, io)
+}
+
+pub fn print_json(value: &impl Serialize, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => {
+            io.writeln(json, OutputType::Standard);
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't write the JSON (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PasswordOutput;
+    use crate::password::v2::Password;
+    use rtoolbox::safe_string::SafeString;
+
+    #[test]
+    fn test_password_output() {
+        let mut password = Password::new("Github", "me@example.com", "hunter2");
+        password.notes = Some(SafeString::from_string("recovery codes".to_owned()));
+        password
+            .fields
+            .insert("PIN".to_owned(), SafeString::from_string("1234".to_owned()));
+
+        let json = serde_json::to_value(PasswordOutput::new(&password, false)).unwrap();
+        assert_eq!(json["name"], "Github");
+        assert_eq!(json["username"], "me@example.com");
+        assert!(json.get("password").is_none());
+        assert!(json.get("notes").is_none());
+        assert!(json.get("fields").is_none());
+
+        let json = serde_json::to_value(PasswordOutput::new(&password, true)).unwrap();
+        assert_eq!(json["password"], "hunter2");
+        assert_eq!(json["notes"], "recovery codes");
+        assert_eq!(json["fields"]["PIN"], "1234");
+    }
+}
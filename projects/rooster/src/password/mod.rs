@@ -18,9 +18,14 @@ pub enum PasswordError {
     InvalidJsonError,
     CorruptionError,
     CorruptionLikelyError,
+    /// The file ends before its header or password data does
+    TruncatedError,
     NeedUpgradeErrorFromV1,
     NoUpgradeError,
     EmptyPasswordError,
+    NeedKeyFileError,
+    /// The decrypted passwords couldn't be kept from being swapped out to disk, see `--no-mlock`
+    MemoryLockError(IoError),
 }
 
 impl From<IoError> for PasswordError {
@@ -40,6 +45,14 @@ fn upgrade_v1_v2(
             password: p.password.clone(),
             created_at: p.created_at,
             updated_at: p.updated_at,
+            totp: None,
+            url: None,
+            notes: None,
+            fields: Default::default(),
+            history: Vec::new(),
+            tags: Vec::new(),
+            policy: None,
+            expiry: None,
         };
         v2_store.add_password(v2_password)?;
     }
@@ -58,6 +71,7 @@ pub fn upgrade(
     // Upgrade from v1 to v2 if we could read v1 passwords.
     let mut v2_store = v2::PasswordStore::new(master_password.clone())?;
     upgrade_v1_v2(v1_passwords.deref(), &mut v2_store)?;
+    v2_store.set_version(1);
 
     Ok(v2_store)
 }
@@ -1,13 +1,16 @@
 use crate::aes;
 use crate::ffi;
+use crate::memory;
 use crate::password::PasswordError;
+use crate::totp::Totp;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use rand::{rngs::OsRng, RngCore};
 use rtoolbox::safe_string::SafeString;
 use rtoolbox::safe_vec::SafeVec;
+use rtoolbox::secure_memory::LockedRegions;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use serde_json::Error;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{
     Cursor, Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Seek, SeekFrom,
@@ -15,6 +18,7 @@ use std::io::{
 };
 use std::ops::Deref;
 use std::os::raw::{c_uchar, c_ulonglong};
+use std::path::Path;
 
 extern "C" {
     pub fn crypto_pwhash_scryptsalsa208sha256_ll(
@@ -42,6 +46,12 @@ extern "C" {
         inlen: libc::c_ulonglong,
         k: *const libc::c_uchar,
     ) -> libc::c_int;
+
+    pub fn crypto_hash_sha512(
+        out: *mut libc::c_uchar,
+        in_: *const libc::c_uchar,
+        inlen: libc::c_ulonglong,
+    ) -> libc::c_int;
 }
 
 /// The schema of the JSON content in the password file.
@@ -75,14 +85,219 @@ const SALT_LEN: usize = 32;
 /// Length of the HMAC signature
 const SIGNATURE_LEN: usize = 64;
 
-/// Scrypt parameters
-/// TODO: increase parameter strength by putting the parameters inside the Rooster file
+/// Length of the data key once encrypted, which adds a full block of padding
+const ENCRYPTED_KEY_LEN: usize = KEY_LEN + 16;
+
+/// Scrypt parameters, which files used before Argon2id
 const SCRYPT_PARAM_LOG2_N: u8 = 12;
 const SCRYPT_PARAM_R: u32 = 8;
 const SCRYPT_PARAM_P: u32 = 1;
 
 /// The version of this lib
-const VERSION: u32 = 2;
+const VERSION: u32 = 8;
+
+/// The first version with a data key, wrapped with the key derived from the master password
+const VERSION_WITH_DATA_KEY: u32 = 3;
+
+/// The first version that says which key derivation function the file uses, before its parameters
+const VERSION_WITH_KDF_ID: u32 = 7;
+
+/// The first version that says whether a key file is needed to unlock it
+const VERSION_WITH_KEY_FILE: u32 = 8;
+
+/// Length of the SHA-512 digest of a key file
+const KEY_FILE_DIGEST_LEN: usize = 64;
+
+/// A file whose contents are needed, along with the master password, to derive the key
+///
+/// Only the SHA-512 digest of the contents is kept, so that key files can be of any size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyFile {
+    digest: SafeVec,
+}
+
+impl KeyFile {
+    pub fn new(contents: &[u8]) -> KeyFile {
+        let mut digest = SafeVec::new(vec![0u8; KEY_FILE_DIGEST_LEN]);
+        unsafe {
+            crypto_hash_sha512(
+                digest.inner_mut().as_mut_ptr(),
+                contents.as_ptr(),
+                contents.len() as c_ulonglong,
+            );
+        }
+        KeyFile { digest }
+    }
+
+    /// Reads a key file, which can't be empty
+    pub fn read(path: &Path) -> IoResult<KeyFile> {
+        let contents = SafeVec::new(std::fs::read(path)?);
+        if contents.is_empty() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidData,
+                "the key file is empty",
+            ));
+        }
+        Ok(KeyFile::new(contents.deref()))
+    }
+
+    /// Returns a key file from its digest, ie: the one `digest` returned
+    pub fn from_digest(digest: &[u8]) -> Option<KeyFile> {
+        match digest.len() {
+            KEY_FILE_DIGEST_LEN => Some(KeyFile {
+                digest: SafeVec::new(digest.to_vec()),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn digest(&self) -> &[u8] {
+        self.digest.deref()
+    }
+}
+
+/// How the key that wraps the data key is derived from the master password
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kdf {
+    Scrypt {
+        log2_n: u8,
+        r: u32,
+        p: u32,
+    },
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Kdf {
+    /// The parameters that OWASP recommends at least: 19 MiB of memory, 2 iterations and 1 lane
+    pub const ARGON2ID_DEFAULT: Kdf = Kdf::Argon2id {
+        memory_kib: 19 * 1024,
+        iterations: 2,
+        parallelism: 1,
+    };
+
+    pub const SCRYPT_DEFAULT: Kdf = Kdf::Scrypt {
+        log2_n: SCRYPT_PARAM_LOG2_N,
+        r: SCRYPT_PARAM_R,
+        p: SCRYPT_PARAM_P,
+    };
+
+    const SCRYPT_ID: u8 = 1;
+    const ARGON2ID_ID: u8 = 2;
+
+    /// Returns Argon2id with these parameters, or why they can't be used
+    pub fn argon2id(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Kdf, String> {
+        argon2::Params::new(memory_kib, iterations, parallelism, Some(KEY_LEN))
+            .map_err(|err| err.to_string())?;
+        Ok(Kdf::Argon2id {
+            memory_kib,
+            iterations,
+            parallelism,
+        })
+    }
+
+    /// Reads the parameters from a file header, which only has scrypt ones before
+    /// `VERSION_WITH_KDF_ID`
+    fn read(version: u32, reader: &mut impl Read) -> Result<Kdf, PasswordError> {
+        let id = match version >= VERSION_WITH_KDF_ID {
+            true => reader.read_u8()?,
+            false => Kdf::SCRYPT_ID,
+        };
+        match id {
+            Kdf::SCRYPT_ID => Ok(Kdf::Scrypt {
+                log2_n: reader.read_u8()?,
+                r: reader.read_u32::<BigEndian>()?,
+                p: reader.read_u32::<BigEndian>()?,
+            }),
+            Kdf::ARGON2ID_ID => {
+                let memory_kib = reader.read_u32::<BigEndian>()?;
+                let iterations = reader.read_u32::<BigEndian>()?;
+                let parallelism = reader.read_u32::<BigEndian>()?;
+                Kdf::argon2id(memory_kib, iterations, parallelism)
+                    .map_err(|_| PasswordError::CorruptionError)
+            }
+            _ => Err(PasswordError::CorruptionError),
+        }
+    }
+
+    /// Returns the parameters as they are written in the header of a file of this version
+    fn header(&self, version: u32) -> IoResult<Vec<u8>> {
+        let mut header = Vec::new();
+        match *self {
+            Kdf::Scrypt { log2_n, r, p } => {
+                if version >= VERSION_WITH_KDF_ID {
+                    header.write_u8(Kdf::SCRYPT_ID)?;
+                }
+                header.write_u8(log2_n)?;
+                header.write_u32::<BigEndian>(r)?;
+                header.write_u32::<BigEndian>(p)?;
+            }
+            Kdf::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                header.write_u8(Kdf::ARGON2ID_ID)?;
+                header.write_u32::<BigEndian>(memory_kib)?;
+                header.write_u32::<BigEndian>(iterations)?;
+                header.write_u32::<BigEndian>(parallelism)?;
+            }
+        }
+        Ok(header)
+    }
+
+    /// Derives a 256 bits encryption key from the password, followed by the digest of the key
+    /// file if there is one.
+    fn derive_key(
+        &self,
+        master_password: &str,
+        key_file: Option<&KeyFile>,
+        salt: [u8; SALT_LEN],
+    ) -> SafeVec {
+        let mut secret = SafeVec::new(Vec::with_capacity(
+            master_password.len() + KEY_FILE_DIGEST_LEN,
+        ));
+        secret
+            .inner_mut()
+            .extend_from_slice(master_password.as_bytes());
+        if let Some(key_file) = key_file {
+            secret
+                .inner_mut()
+                .extend_from_slice(key_file.digest.deref());
+        }
+
+        match *self {
+            Kdf::Scrypt { log2_n, r, p } => generate_encryption_key(&secret, salt, log2_n, r, p),
+            Kdf::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => generate_argon2id_key(&secret, salt, memory_kib, iterations, parallelism),
+        }
+    }
+}
+
+impl std::fmt::Display for Kdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kdf::Scrypt { log2_n, r, p } => {
+                write!(f, "scrypt (log2n {}, r {}, p {})", log2_n, r, p)
+            }
+            Kdf::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => write!(
+                f,
+                "Argon2id ({} KiB of memory, {} iterations, parallelism {})",
+                memory_kib, iterations, parallelism
+            ),
+        }
+    }
+}
 
 // Create a random IV.
 fn generate_random_iv() -> IoResult<[u8; IV_LEN]> {
@@ -100,9 +315,16 @@ fn generate_random_salt() -> IoResult<[u8; SALT_LEN]> {
     Ok(bytes)
 }
 
+// Create a random 256 bits key to encrypt the password data with.
+fn generate_random_data_key() -> SafeVec {
+    let mut output = SafeVec::new(vec![0u8; KEY_LEN]);
+    OsRng.fill_bytes(output.inner_mut());
+    output
+}
+
 /// Derives a 256 bits encryption key from the password.
 fn generate_encryption_key(
-    master_password: &str,
+    master_password: &[u8],
     salt: [u8; SALT_LEN],
     scrypt_log2_n: u8,
     scrypt_r: u32,
@@ -142,19 +364,42 @@ fn generate_encryption_key(
     output
 }
 
+/// Derives a 256 bits encryption key from the password with Argon2id.
+fn generate_argon2id_key(
+    master_password: &[u8],
+    salt: [u8; SALT_LEN],
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+) -> SafeVec {
+    let mut output = SafeVec::new(vec![0u8; KEY_LEN]);
+
+    let result = argon2::Params::new(memory_kib, iterations, parallelism, Some(KEY_LEN)).and_then(
+        |params| {
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                .hash_password_into(master_password, &salt, output.inner_mut())
+        },
+    );
+
+    if let Err(err) = result {
+        panic!("Deriving Argon2id key failed: {}", err);
+    }
+
+    output
+}
+
 /// Creates a HMAC signature
 fn digest(
     key: &[u8],
     version: u32,
-    scrypt_log2_n: u8,
-    scrypt_r: u32,
-    scrypt_p: u32,
+    kdf_header: &[u8],
     iv: &[u8],
     salt: &[u8],
+    wrapped_key: &[u8],
     blob: &[u8],
 ) -> Result<Vec<u8>, PasswordError> {
     let blob_with_metadata =
-        digest_blob_with_metadata(version, scrypt_log2_n, scrypt_r, scrypt_p, iv, salt, blob)?;
+        digest_blob_with_metadata(version, kdf_header, iv, salt, wrapped_key, blob)?;
 
     let mut digest: Vec<u8> = Vec::with_capacity(512 / 8);
 
@@ -179,44 +424,232 @@ fn digest(
 }
 
 /// Creates the data that is signed with HMAC
+///
+/// The wrapped data key is empty for files older than `VERSION_WITH_DATA_KEY`.
 fn digest_blob_with_metadata(
     version: u32,
-    scrypt_log2_n: u8,
-    scrypt_r: u32,
-    scrypt_p: u32,
+    kdf_header: &[u8],
     iv: &[u8],
     salt: &[u8],
+    wrapped_key: &[u8],
     blob: &[u8],
 ) -> Result<Vec<u8>, PasswordError> {
     let mut version_bytes_cursor: Vec<u8> = Vec::new();
     version_bytes_cursor.write_u32::<BigEndian>(version)?;
-    let mut scrypt_bytes_cursor: Vec<u8> = Vec::new();
-    scrypt_bytes_cursor.write_u8(scrypt_log2_n)?;
-    scrypt_bytes_cursor.write_u32::<BigEndian>(scrypt_r)?;
-    scrypt_bytes_cursor.write_u32::<BigEndian>(scrypt_p)?;
     let mut blob_with_metadata: Vec<u8> = Vec::new();
     blob_with_metadata.write_all(version_bytes_cursor.deref())?;
-    blob_with_metadata.write_all(scrypt_bytes_cursor.deref())?;
+    blob_with_metadata.write_all(kdf_header)?;
     blob_with_metadata.write_all(iv)?;
     blob_with_metadata.write_all(salt)?;
+    blob_with_metadata.write_all(wrapped_key)?;
     blob_with_metadata.write_all(blob.deref())?;
     Ok(blob_with_metadata)
 }
 
+/// The part of a Rooster file before the encrypted blob, see `PasswordStore` for its format
+struct Header {
+    version: u32,
+    kdf: Kdf,
+    /// The key derivation params and whether a key file is needed, as they are signed
+    key_header: Vec<u8>,
+    needs_key_file: bool,
+    salt: [u8; SALT_LEN],
+    /// Empty for files older than `VERSION_WITH_DATA_KEY`
+    wrapped_key: Vec<u8>,
+    iv: [u8; IV_LEN],
+    signature: [u8; SIGNATURE_LEN],
+}
+
+impl Header {
+    /// Reads the header of a file, and returns it along with the encrypted blob
+    ///
+    /// This only needs the file, so truncated files are told apart from wrong passwords.
+    fn read(input: &[u8]) -> Result<(Header, &[u8]), PasswordError> {
+        let mut reader = Cursor::new(input);
+        let header = Header::read_from(&mut reader).map_err(|err| match err {
+            PasswordError::Io(err) if err.kind() == IoErrorKind::UnexpectedEof => {
+                PasswordError::TruncatedError
+            }
+            err => err,
+        })?;
+        let blob = &input[reader.position() as usize..];
+
+        // The blob is encrypted with AES-CBC, so it's made of whole blocks, with at least one for
+        // the padding.
+        if blob.is_empty() {
+            return Err(PasswordError::TruncatedError);
+        } else if !blob.len().is_multiple_of(IV_LEN) {
+            return Err(PasswordError::CorruptionError);
+        }
+
+        Ok((header, blob))
+    }
+
+    fn read_from(reader: &mut Cursor<&[u8]>) -> Result<Header, PasswordError> {
+        // Version taken from network byte order (big endian).
+        let version = reader.read_u32::<BigEndian>()?;
+        if version > VERSION {
+            return Err(PasswordError::OutdatedRoosterBinaryError);
+        } else if version < 2 {
+            return Err(PasswordError::NeedUpgradeErrorFromV1);
+        }
+
+        // Read the key derivation params.
+        let kdf = Kdf::read(version, reader)?;
+        let mut key_header = kdf.header(version)?;
+
+        // Read whether a key file is needed.
+        let needs_key_file = match version >= VERSION_WITH_KEY_FILE {
+            true => reader.read_u8()?,
+            false => 0,
+        };
+        if version >= VERSION_WITH_KEY_FILE {
+            key_header.write_u8(needs_key_file)?;
+        }
+        let needs_key_file = match needs_key_file {
+            0 => false,
+            1 => true,
+            _ => return Err(PasswordError::CorruptionError),
+        };
+
+        // Read the old salt.
+        let mut salt: [u8; SALT_LEN] = [0u8; SALT_LEN];
+        reader.read_exact(&mut salt)?;
+
+        // Read the wrapped data key, if the file has one.
+        let mut wrapped_key: Vec<u8> = Vec::new();
+        if version >= VERSION_WITH_DATA_KEY {
+            wrapped_key.resize(IV_LEN + ENCRYPTED_KEY_LEN, 0u8);
+            reader.read_exact(&mut wrapped_key)?;
+        }
+
+        // Read the old IV.
+        let mut iv: [u8; IV_LEN] = [0u8; IV_LEN];
+        reader.read_exact(&mut iv)?;
+
+        // Read the HMAC signature.
+        let mut signature: [u8; SIGNATURE_LEN] = [0u8; SIGNATURE_LEN];
+        reader.read_exact(&mut signature)?;
+
+        Ok(Header {
+            version,
+            kdf,
+            key_header,
+            needs_key_file,
+            salt,
+            wrapped_key,
+            iv,
+            signature,
+        })
+    }
+}
+
+/// What can be told about a file without the master password, see `inspect`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileInfo {
+    /// The version of the format the file is in
+    pub version: u32,
+    pub kdf: Kdf,
+    pub needs_key_file: bool,
+    /// The length of the encrypted password data, in bytes
+    pub blob_len: usize,
+}
+
+/// Checks that a file is complete and well formed, before it's decrypted
+///
+/// Whether it was tampered with can only be told with the master password, which the signature
+/// is made with.
+pub fn inspect(input: &[u8]) -> Result<FileInfo, PasswordError> {
+    let (header, blob) = Header::read(input)?;
+    Ok(FileInfo {
+        version: header.version,
+        kdf: header.kdf,
+        needs_key_file: header.needs_key_file,
+        blob_len: blob.len(),
+    })
+}
+
 /// The format of the encrypted JSON content in the password file v1.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Schema {
     passwords: Vec<Password>,
+    #[serde(default)]
+    export_profiles: Vec<ExportProfile>,
+    #[serde(default)]
+    derived_sites: Vec<DerivedSite>,
 }
 
 impl Schema {
     fn new() -> Schema {
         Schema {
             passwords: Vec::new(),
+            export_profiles: Vec::new(),
+            derived_sites: Vec::new(),
+        }
+    }
+}
+
+/// A saved choice of fields to export, so that repeat exports don't need to be configured again
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportProfile {
+    pub name: String,
+    pub columns: Vec<ExportColumn>,
+}
+
+/// A site whose password is derived from the master seed rather than stored, see `rooster derive`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DerivedSite {
+    pub site: String,
+    /// Incremented to get a new password for the site, ie: when it must be changed
+    pub counter: u32,
+}
+
+/// How the password of an app is generated, so that it fits the site's rules, see `rooster policy`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Policy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>,
+    /// Only letters and digits
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub alnum: bool,
+    /// The characters to pick from, ie: `a-z0-9_`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charset: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_ambiguous: bool,
+    /// The number of words of a passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<usize>,
+    /// What the password looks like, ie: `ULLLL-9999`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+/// When a password must be changed, see `rooster expire`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Expiry {
+    /// How long a password lasts, which starts over when it is changed
+    pub days: u32,
+    pub expires_at: ffi::time_t,
+}
+
+impl Expiry {
+    pub fn new(days: u32, now: ffi::time_t) -> Expiry {
+        Expiry {
+            days,
+            expires_at: now.saturating_add(days.saturating_mul(24 * 60 * 60)),
         }
     }
 }
 
+/// A field of a password, as it appears in an export
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportColumn {
+    pub field: String,
+    pub header: String,
+    pub masked: bool,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Password {
     pub name: String,
@@ -224,6 +657,28 @@ pub struct Password {
     pub password: SafeString,
     pub created_at: ffi::time_t,
     pub updated_at: ffi::time_t,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<Totp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Free text, ie: recovery codes or security questions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<SafeString>,
+    /// Anything else worth keeping with the password, ie: `PIN` or `Account number`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, SafeString>,
+    /// The passwords this one replaced, oldest first, see `rooster history`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PreviousPassword>,
+    /// Labels to organize passwords with, ie: `work` or `banking`, see `rooster tag`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How new passwords are generated for this app, see `rooster policy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<Policy>,
+    /// When this password must be changed, see `rooster expire`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<Expiry>,
 }
 
 impl Password {
@@ -239,193 +694,321 @@ impl Password {
             password: password.into(),
             created_at: timestamp,
             updated_at: timestamp,
+            totp: None,
+            url: None,
+            notes: None,
+            fields: BTreeMap::new(),
+            history: Vec::new(),
+            tags: Vec::new(),
+            policy: None,
+            expiry: None,
         }
     }
+
+    /// Returns whether the password has this tag, which is case insensitive
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|t| t.to_lowercase() == tag.to_lowercase())
+    }
+
+    /// Returns the expiry once this password is replaced at `replaced_at`, which starts over
+    pub fn renewed_expiry(&self, replaced_at: ffi::time_t) -> Option<Expiry> {
+        self.expiry
+            .as_ref()
+            .map(|expiry| Expiry::new(expiry.days, replaced_at))
+    }
+
+    /// Returns the secrets of this password, which the store keeps in locked memory
+    fn secrets(&self) -> Vec<&[u8]> {
+        let mut secrets = vec![self.password.as_bytes()];
+        secrets.extend(self.totp.iter().map(|totp| totp.secret.as_bytes()));
+        secrets.extend(self.notes.iter().map(|notes| notes.as_bytes()));
+        secrets.extend(self.fields.values().map(|value| value.as_bytes()));
+        secrets.extend(
+            self.history
+                .iter()
+                .map(|previous| previous.password.as_bytes()),
+        );
+        secrets
+    }
+
+    /// Returns the history once this password is replaced at `replaced_at`, which keeps at most
+    /// `MAX_HISTORY_LEN` passwords
+    pub fn history_with_current(&self, replaced_at: ffi::time_t) -> Vec<PreviousPassword> {
+        let mut history = self.history.clone();
+        history.push(PreviousPassword {
+            password: self.password.clone(),
+            created_at: self.updated_at,
+            replaced_at,
+        });
+        let excess = history.len().saturating_sub(MAX_HISTORY_LEN);
+        history.drain(..excess);
+        history
+    }
+}
+
+/// Ranks how well an app name matches a search query, both lowercase, lower being better, or
+/// returns `None` if it doesn't match
+///
+/// The query matches if its characters are in the name in the right order, so it's fine to leave
+/// some out, ie: "fcbk" finds "Facebook". Exact names come first, then names that start with the
+/// query, then names that contain it, then the others by how spread out the characters are.
+fn match_rank(name: &str, query: &str) -> Option<(u8, usize)> {
+    if name == query {
+        return Some((0, 0));
+    }
+    if name.starts_with(query) {
+        return Some((1, 0));
+    }
+    if let Some(position) = name.find(query) {
+        return Some((2, position));
+    }
+
+    let mut name_chars = name.char_indices();
+    let mut first = None;
+    let mut last = 0;
+    for c in query.chars() {
+        let (i, _) = name_chars.find(|(_, name_c)| *name_c == c)?;
+        first.get_or_insert(i);
+        last = i;
+    }
+    Some((3, last - first.unwrap_or(0)))
+}
+
+/// Starts the words of a search query that match tags rather than app names, ie: `tag:work`
+const TAG_QUERY_PREFIX: &str = "tag:";
+
+/// How many previous passwords are kept with each password
+const MAX_HISTORY_LEN: usize = 20;
+
+/// A password that was replaced, ie: by `rooster change` or `rooster regenerate`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PreviousPassword {
+    pub password: SafeString,
+    pub created_at: ffi::time_t,
+    pub replaced_at: ffi::time_t,
+}
+
+/// Keeps the secrets of a password out of swap until they're unlocked, unless locking is off
+fn lock_secrets(locked: &mut LockedRegions, password: &Password) -> Result<(), PasswordError> {
+    for secret in password.secrets() {
+        if let Err(err) = memory::lock_with(locked, secret) {
+            unlock_secrets(locked, password);
+            return Err(PasswordError::MemoryLockError(err));
+        }
+    }
+    Ok(())
+}
+
+/// Lets the secrets of a password that the store doesn't hold anymore be swapped out
+fn unlock_secrets(locked: &mut LockedRegions, password: &Password) {
+    for secret in password.secrets() {
+        locked.unlock(secret);
+    }
 }
 
 pub struct PasswordStore {
+    /// Keeps the secrets of the passwords out of swap for as long as the store holds them, which
+    /// comes first so that it's dropped before them
+    locked: LockedRegions,
+    /// The key derived from the master password, which wraps `data_key`
     key: SafeVec,
-    scrypt_log2_n: u8,
-    scrypt_r: u32,
-    scrypt_p: u32,
+    /// The key the password data is encrypted and signed with
+    data_key: SafeVec,
+    kdf: Kdf,
+    key_file: Option<KeyFile>,
     salt: [u8; SALT_LEN],
     schema: Schema,
-    master_password: String,
+    master_password: SafeString,
+    /// The version of the format the file was in when it was read
+    version: u32,
 }
 
 /// Read and writes to a Rooster file
 ///
 /// The Rooster file has the following format:
 /// - rooster version: u32, big endian
-/// - scrypt log2n:    u8
-/// - scrypt r:        u32, big endian
-/// - scrypt p:        u32, big endian
+/// - kdf:             u8, 1 for scrypt or 2 for Argon2id (since version 7)
+/// - kdf params:      for scrypt, log2n: u8, r: u32 and p: u32, big endian
+///   for Argon2id, memory in KiB, iterations and parallelism: u32, big endian
+/// - key file:        u8, 1 if the key is also derived from a key file, 0 otherwise (since version 8)
 /// - salt:            256 bits
-/// - iv:              256 bits
+/// - data key iv:     128 bits (since version 3)
+/// - data key:        384 bits, encrypted with the derived key (since version 3)
+/// - iv:              128 bits
 /// - signature:       512 bits HMAC-SHA512
 /// - encrypted blob:  variable length
+///
+/// Before version 3, the key derived from the master password encrypted the blob directly. Before
+/// version 7, it was always derived with scrypt.
 impl PasswordStore {
     pub fn new(master_password: SafeString) -> IoResult<PasswordStore> {
         let salt = generate_random_salt()?;
-        let key = generate_encryption_key(
-            master_password.deref(),
-            salt,
-            SCRYPT_PARAM_LOG2_N,
-            SCRYPT_PARAM_R,
-            SCRYPT_PARAM_P,
-        );
+        let kdf = Kdf::ARGON2ID_DEFAULT;
+        let key = kdf.derive_key(master_password.deref(), None, salt);
 
         Ok(PasswordStore {
             key: key,
-            scrypt_log2_n: SCRYPT_PARAM_LOG2_N,
-            scrypt_r: SCRYPT_PARAM_R,
-            scrypt_p: SCRYPT_PARAM_P,
+            data_key: generate_random_data_key(),
+            kdf,
+            key_file: None,
             salt: salt,
             schema: Schema::new(),
-            master_password: master_password.into_inner(),
+            master_password,
+            version: VERSION,
+            locked: LockedRegions::new(),
         })
     }
 
+    /// Opens a file, with the key file it needs if any, which is ignored by files that don't
     pub fn from_input(
         master_password: SafeString,
+        key_file: Option<&KeyFile>,
         input: SafeVec,
     ) -> Result<PasswordStore, PasswordError> {
-        let mut reader = Cursor::new(input.deref());
-
-        // Version taken from network byte order (big endian).
-        let version = reader.read_u32::<BigEndian>()?;
-        if version != VERSION {
-            if version > VERSION {
-                return Err(PasswordError::OutdatedRoosterBinaryError);
-            } else if version < VERSION {
-                return Err(PasswordError::NeedUpgradeErrorFromV1);
-            }
-        }
-
-        // Read the scrypt params.
-        let scrypt_log2_n = reader.read_u8()?;
-        let scrypt_r = reader.read_u32::<BigEndian>()?;
-        let scrypt_p = reader.read_u32::<BigEndian>()?;
-
-        // Read the old salt.
-        let mut salt: [u8; SALT_LEN] = [0u8; SALT_LEN];
-        reader.read(&mut salt).and_then(|num_bytes| {
-            if num_bytes == SALT_LEN {
-                Ok(())
-            } else {
-                Err(IoError::new(IoErrorKind::Other, "unexpected eof"))
-            }
-        })?;
-
-        // Read the old IV.
-        let mut iv: [u8; IV_LEN] = [0u8; IV_LEN];
-        reader.read(&mut iv).and_then(|num_bytes| {
-            if num_bytes == IV_LEN {
-                Ok(())
-            } else {
-                Err(IoError::new(IoErrorKind::Other, "unexpected eof"))
-            }
-        })?;
-
-        // Read the HMAC signature.
-        let mut old_signature_mac: [u8; SIGNATURE_LEN] = [0u8; SIGNATURE_LEN];
-        reader.read(&mut old_signature_mac).and_then(|num_bytes| {
-            if num_bytes == SIGNATURE_LEN {
-                Ok(())
-            } else {
-                Err(IoError::new(IoErrorKind::Other, "unexpected eof"))
-            }
-        })?;
+        PasswordStore::open(master_password, key_file, None, input)
+    }
 
-        // The encrypted password data.
-        let mut blob: Vec<u8> = Vec::new();
-        reader.read_to_end(&mut blob)?;
+    /// Opens a file with the key derived from the master password, and the key file it was
+    /// derived with if any, ie: the ones `derived_key` and `key_file` returned for this file
+    ///
+    /// The master password isn't needed, so the store doesn't know it: it can't derive another
+    /// key, ie: with `set_kdf`.
+    pub fn from_input_with_key(
+        key: SafeVec,
+        key_file: Option<&KeyFile>,
+        input: SafeVec,
+    ) -> Result<PasswordStore, PasswordError> {
+        PasswordStore::open(SafeString::new(), key_file, Some(key), input)
+    }
 
-        // Derive a 256 bits encryption key from the password.
-        let key = generate_encryption_key(
-            master_password.deref(),
+    fn open(
+        master_password: SafeString,
+        key_file: Option<&KeyFile>,
+        key: Option<SafeVec>,
+        input: SafeVec,
+    ) -> Result<PasswordStore, PasswordError> {
+        let (header, blob) = Header::read(input.deref())?;
+        let Header {
+            version,
+            kdf,
+            key_header,
+            needs_key_file,
             salt,
-            scrypt_log2_n,
-            scrypt_r,
-            scrypt_p,
-        );
+            wrapped_key,
+            iv,
+            signature: old_signature_mac,
+        } = header;
+        let key_file = match (needs_key_file, key_file) {
+            (false, _) => None,
+            (true, Some(key_file)) => Some(key_file.clone()),
+            (true, None) => return Err(PasswordError::NeedKeyFileError),
+        };
+
+        // Derive a 256 bits encryption key from the password, unless it was given.
+        let key = match key {
+            Some(key) if key.len() == KEY_LEN => key,
+            Some(_) => return Err(PasswordError::DecryptionError),
+            None => kdf.derive_key(master_password.deref(), key_file.as_ref(), salt),
+        };
 
-        // Decrypt the data.
-        let passwords = match aes::decrypt(blob.deref(), key.as_ref(), iv.as_ref()) {
-            Ok(decrypted) => {
-                let encoded = SafeString::from_string(
-                    String::from_utf8_lossy(decrypted.as_ref()).into_owned(),
-                );
-                let s: Result<Schema, Error> = serde_json::from_str(encoded.deref());
-                match s {
-                    Ok(json) => json.passwords,
-                    Err(_) => {
-                        return Err(PasswordError::InvalidJsonError);
-                    }
+        // Unwrap the data key, or use the derived key for files that don't have one. Like below,
+        // errors are only returned at the end, so that all failures go through the same steps.
+        let data_key = match wrapped_key.is_empty() {
+            true => Ok(key.clone()),
+            false => {
+                let (key_iv, encrypted_key) = wrapped_key.split_at(IV_LEN);
+                match aes::decrypt(encrypted_key, key.as_ref(), key_iv) {
+                    Ok(data_key) if data_key.len() == KEY_LEN => Ok(data_key),
+                    _ => Err(PasswordError::DecryptionError),
                 }
             }
-            Err(_) => {
-                return Err(PasswordError::DecryptionError);
-            }
+        };
+        let (data_key, data_key_error) = match data_key {
+            Ok(data_key) => (data_key, None),
+            Err(err) => (key.clone(), Some(err)),
+        };
+
+        // Decrypt the data. Errors are only returned once the signature has been checked too, so
+        // that a wrong password and a corrupted file go through the same steps.
+        let schema = match aes::decrypt(blob, data_key.as_ref(), iv.as_ref()) {
+            // The passwords are locked once they're parsed, but the JSON holds them too until then
+            Ok(decrypted) => match memory::lock(&decrypted) {
+                Ok(_locked) => serde_json::from_slice::<Schema>(&decrypted)
+                    .map_err(|_| PasswordError::InvalidJsonError),
+                Err(err) => Err(PasswordError::MemoryLockError(err)),
+            },
+            Err(_) => Err(PasswordError::DecryptionError),
         };
 
         // Check the signature against what it should be.
-        let blob = digest_blob_with_metadata(
-            version,
-            scrypt_log2_n,
-            scrypt_r,
-            scrypt_p,
-            &iv,
-            &salt,
-            blob.deref(),
-        )?;
+        let blob = digest_blob_with_metadata(version, &key_header, &iv, &salt, &wrapped_key, blob)?;
 
         let verification = unsafe {
             crypto_auth_hmacsha512_verify(
                 old_signature_mac.as_ptr() as *const c_uchar,
                 blob.as_ptr() as *const c_uchar,
                 blob.len() as c_ulonglong,
-                key.as_ptr() as *const c_uchar,
+                data_key.as_ptr() as *const c_uchar,
             )
         };
+        if let Some(err) = data_key_error {
+            return Err(err);
+        }
+        // Once the data key is unwrapped, the password was right, so a signature that doesn't match
+        // means the file changed, even if it can't be decrypted anymore. Older files have no data
+        // key to tell.
+        if verification != 0 && !wrapped_key.is_empty() {
+            return Err(PasswordError::CorruptionError);
+        }
+        let schema = schema?;
         if verification != 0 {
             return Err(PasswordError::CorruptionError);
         }
 
+        let mut locked = LockedRegions::new();
+        for password in &schema.passwords {
+            lock_secrets(&mut locked, password)?;
+        }
+
         Ok(PasswordStore {
             key: key,
-            scrypt_log2_n: scrypt_log2_n,
-            scrypt_r: scrypt_r,
-            scrypt_p: scrypt_p,
+            data_key,
+            kdf,
+            key_file,
             salt: salt,
-            schema: Schema {
-                passwords: passwords,
-            },
-            master_password: master_password.deref().into(),
+            schema: schema,
+            master_password,
+            version,
+            locked,
         })
     }
 
     pub fn sync(&self, file: &mut File) -> Result<(), PasswordError> {
         // This should never fail. The structs are all encodable.
-        let json_schema = match serde_json::to_string(&self.schema) {
-            Ok(json_schema) => json_schema,
-            Err(_) => {
-                return Err(PasswordError::InvalidJsonError);
-            }
-        };
-        let json_schema = SafeString::from_string(json_schema);
+        let mut json_schema = SafeVec::new(Vec::new());
+        if serde_json::to_writer(&mut json_schema, &self.schema).is_err() {
+            return Err(PasswordError::InvalidJsonError);
+        }
+        let _locked = memory::lock(&json_schema).map_err(PasswordError::MemoryLockError)?;
 
         // Encrypt the data with a new salt and a new IV.
         let iv = generate_random_iv()?;
-        let encrypted = match aes::encrypt(
-            json_schema.deref().as_bytes(),
-            self.key.as_ref(),
-            iv.as_ref(),
-        ) {
+        let encrypted = match aes::encrypt(json_schema.deref(), self.data_key.as_ref(), iv.as_ref())
+        {
             Ok(val) => val,
             Err(_) => return Err(PasswordError::EncryptionError),
         };
 
+        // Wrap the data key with the key derived from the master password, with a new IV too.
+        let key_iv = generate_random_iv()?;
+        let mut wrapped_key = key_iv.to_vec();
+        match aes::encrypt(self.data_key.deref(), self.key.as_ref(), key_iv.as_ref()) {
+            Ok(val) => wrapped_key.extend_from_slice(val.as_ref()),
+            Err(_) => return Err(PasswordError::EncryptionError),
+        };
+
         // Reset the file pointer.
         file.seek(SeekFrom::Start(0))
             .and_then(|_| file.set_len(0))?;
@@ -433,26 +1016,28 @@ impl PasswordStore {
         // Write the file version.
         file.write_u32::<BigEndian>(VERSION)?;
 
-        // Write the scrypt params.
-        file.write_u8(self.scrypt_log2_n)?;
-        file.write_u32::<BigEndian>(self.scrypt_r)?;
-        file.write_u32::<BigEndian>(self.scrypt_p)?;
+        // Write the key derivation params, and whether a key file is needed.
+        let mut key_header = self.kdf.header(VERSION)?;
+        key_header.write_u8(self.key_file.is_some() as u8)?;
+        file.write_all(&key_header)?;
 
         // Write the key derivation salt.
         file.write_all(&self.salt)?;
 
+        // Write the wrapped data key.
+        file.write_all(&wrapped_key)?;
+
         // Write the encryption IV.
         file.write_all(&iv)?;
 
         // Write the file signature.
         let signature = digest(
-            self.key.deref(),
+            self.data_key.deref(),
             VERSION,
-            self.scrypt_log2_n,
-            self.scrypt_r,
-            self.scrypt_p,
+            &key_header,
             &iv,
             &self.salt,
+            &wrapped_key,
             encrypted.as_ref(),
         )?;
         file.write_all(signature.deref())?;
@@ -474,6 +1059,35 @@ impl PasswordStore {
         passwords
     }
 
+    /// Returns the passwords whose value is `password`, ie: one that was leaked in a breach
+    ///
+    /// The values aren't compared directly, but through their HMAC with a random key, checked in
+    /// constant time, so that how long this takes doesn't depend on how much of them matches.
+    pub fn find_passwords_by_value(&self, password: &SafeString) -> Vec<&Password> {
+        let key = generate_random_data_key();
+        let mut mac = [0u8; SIGNATURE_LEN];
+        unsafe {
+            crypto_auth_hmacsha512(
+                mac.as_mut_ptr(),
+                password.as_ptr(),
+                password.len() as c_ulonglong,
+                key.as_ptr(),
+            );
+        }
+
+        self.get_all_passwords()
+            .into_iter()
+            .filter(|p| unsafe {
+                crypto_auth_hmacsha512_verify(
+                    mac.as_ptr(),
+                    p.password.as_ptr(),
+                    p.password.len() as c_ulonglong,
+                    key.as_ptr(),
+                ) == 0
+            })
+            .collect()
+    }
+
     /// Adds a password to the file.
     pub fn add_password(&mut self, password: Password) -> Result<(), PasswordError> {
         if password.password.deref().len() == 0 {
@@ -482,6 +1096,7 @@ impl PasswordStore {
         if self.has_password(password.name.deref()) {
             return Err(PasswordError::AppExistsError);
         }
+        lock_secrets(&mut self.locked, &password)?;
         self.schema.passwords.push(password);
         Ok(())
     }
@@ -494,62 +1109,42 @@ impl PasswordStore {
         let mut i = 0;
         while i < self.schema.passwords.len() {
             if self.schema.passwords[i].name == p.name {
-                return Ok(self.schema.passwords.remove(i));
+                let password = self.schema.passwords.remove(i);
+                unlock_secrets(&mut self.locked, &password);
+                return Ok(password);
             }
             i += 1;
         }
         unreachable!();
     }
 
-    pub fn search_passwords(&self, name: &str) -> Vec<&Password> {
-        // Fuzzy search password app names.
-        let keys = self
+    /// Searches passwords by app name, and by tag with words like `tag:work` in the query
+    ///
+    /// The best matches come first, see `match_rank`.
+    pub fn search_passwords(&self, query: &str) -> Vec<&Password> {
+        let (tags, name_words): (Vec<&str>, Vec<&str>) = query
+            .split_whitespace()
+            .partition(|word| word.starts_with(TAG_QUERY_PREFIX));
+        let tags = tags
+            .iter()
+            .map(|word| &word[TAG_QUERY_PREFIX.len()..])
+            .collect::<Vec<&str>>();
+        let name = if tags.is_empty() {
+            query.to_lowercase()
+        } else {
+            name_words.join(" ").to_lowercase()
+        };
+
+        let mut passwords = self
             .schema
             .passwords
             .iter()
-            .map(|p| p.name.to_lowercase())
-            .collect::<Vec<String>>();
-
-        let mut search_results = vec![];
-        // Check if each app name can be matched against the search query.
-        //
-        // It's fine if there are some characters left out in the query. For instance, you can
-        // search for the app "Facebook" with just "fcbk".
-        for app_name in keys.iter().map(|s| s.as_str()) {
-            let mut matches_query = true;
-            let mut last_i = 0;
-            for c in name.chars() {
-                let c_lowercase = format!("{}", c).to_lowercase();
-                match app_name[last_i..].find(c_lowercase.as_str()) {
-                    // Query chars must be present in the app name in the right order.
-                    Some(ic) => {
-                        last_i += ic + 1;
-                    }
-                    // Query char is not present, no match.
-                    None => {
-                        matches_query = false;
-                        break;
-                    }
-                }
-            }
-
-            if matches_query {
-                search_results.push(app_name.to_owned());
-            }
-        }
-
-        let mut passwords = vec![];
-        for p in self.schema.passwords.iter() {
-            if search_results.contains(&p.name.to_lowercase()) {
-                passwords.push(p);
-            }
-        }
+            .filter(|p| tags.iter().all(|tag| p.has_tag(tag)))
+            .filter_map(|p| Some((match_rank(&p.name.to_lowercase(), &name)?, p)))
+            .collect::<Vec<_>>();
+        passwords.sort_by_key(|(rank, p)| (*rank, p.name.to_lowercase()));
 
-        passwords.sort_by_key(|p| {
-            return p.name.to_lowercase();
-        });
-
-        passwords
+        passwords.into_iter().map(|(_, p)| p).collect()
     }
 
     pub fn get_password(&self, name: &str) -> Option<Password> {
@@ -595,39 +1190,150 @@ impl PasswordStore {
         }
     }
 
+    pub fn get_export_profile(&self, name: &str) -> Option<&ExportProfile> {
+        self.schema.export_profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Saves an export profile, replacing any previous profile with the same name.
+    pub fn set_export_profile(&mut self, profile: ExportProfile) {
+        self.schema
+            .export_profiles
+            .retain(|p| p.name != profile.name);
+        self.schema.export_profiles.push(profile);
+    }
+
+    pub fn get_derived_site(&self, site: &str) -> Option<&DerivedSite> {
+        self.schema.derived_sites.iter().find(|s| s.site == site)
+    }
+
+    /// Saves the counter of a derived site, replacing any previous one for the same site.
+    pub fn set_derived_site(&mut self, derived_site: DerivedSite) {
+        self.schema
+            .derived_sites
+            .retain(|s| s.site != derived_site.site);
+        self.schema.derived_sites.push(derived_site);
+    }
+
     pub fn change_master_password(&mut self, master_password: &str) {
-        self.key = generate_encryption_key(
-            master_password,
+        self.key = self
+            .kdf
+            .derive_key(master_password, self.key_file.as_ref(), self.salt);
+    }
+
+    /// Returns the key derived from the master password and the key file, which opens the file
+    /// with `from_input_with_key` as long as its key derivation doesn't change
+    pub fn derived_key(&self) -> &SafeVec {
+        &self.key
+    }
+
+    pub fn master_password(&self) -> SafeString {
+        self.master_password.clone()
+    }
+
+    /// Returns the version of the format the file was in when it was read, which `sync` upgrades
+    /// to the latest
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    /// Replaces the key the password data is encrypted with by a new random one, which is wrapped
+    /// with the same key derived from the master password when the file is saved.
+    pub fn rekey(&mut self) {
+        self.data_key = generate_random_data_key();
+    }
+
+    pub fn kdf(&self) -> Kdf {
+        self.kdf
+    }
+
+    /// Derives the key that wraps the data key in another way, which is used once the file is saved
+    pub fn set_kdf(&mut self, kdf: Kdf) {
+        self.kdf = kdf;
+        self.key = self.kdf.derive_key(
+            self.master_password.deref(),
+            self.key_file.as_ref(),
             self.salt,
-            self.scrypt_log2_n,
-            self.scrypt_r,
-            self.scrypt_p,
         );
     }
 
-    pub fn change_scrypt_params(&mut self, scrypt_log2_n: u8, scrypt_r: u32, scrypt_p: u32) {
-        self.scrypt_log2_n = scrypt_log2_n;
-        self.scrypt_r = scrypt_r;
-        self.scrypt_p = scrypt_p;
+    pub fn key_file(&self) -> Option<&KeyFile> {
+        self.key_file.as_ref()
+    }
 
-        self.key = generate_encryption_key(
+    /// Requires this key file, or none, to unlock the file once it is saved
+    pub fn set_key_file(&mut self, key_file: Option<KeyFile>) {
+        self.key_file = key_file;
+        self.key = self.kdf.derive_key(
             self.master_password.deref(),
+            self.key_file.as_ref(),
             self.salt,
-            self.scrypt_log2_n,
-            self.scrypt_r,
-            self.scrypt_p,
         );
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::aes;
     use crate::password::v2::{
-        generate_encryption_key, generate_random_iv, generate_random_salt, Password, PasswordStore,
-        SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_P, SCRYPT_PARAM_R,
+        digest, generate_encryption_key, generate_random_iv, generate_random_salt, inspect, Kdf,
+        KeyFile, Password, PasswordStore, MAX_HISTORY_LEN, SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_P,
+        SCRYPT_PARAM_R, VERSION,
     };
     use crate::password::PasswordError;
+    use byteorder::{BigEndian, WriteBytesExt};
     use rtoolbox::safe_string::SafeString;
+    use rtoolbox::safe_vec::SafeVec;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::ops::Deref;
+
+    fn sync_to_input(store: &PasswordStore) -> SafeVec {
+        let mut file = tempfile::tempfile().unwrap();
+        store.sync(&mut file).unwrap();
+        let mut input = SafeVec::new(Vec::new());
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_end(input.inner_mut()).unwrap();
+        input
+    }
+
+    /// Creates a file in version 2 of the format, where the key derived from the master password
+    /// encrypts the password data directly
+    fn version_2_input(master_password: &str, json: &str) -> SafeVec {
+        let salt = generate_random_salt().unwrap();
+        let iv = generate_random_iv().unwrap();
+        let key = generate_encryption_key(
+            master_password.as_bytes(),
+            salt,
+            SCRYPT_PARAM_LOG2_N,
+            SCRYPT_PARAM_R,
+            SCRYPT_PARAM_P,
+        );
+        let blob = aes::encrypt(json.as_bytes(), key.deref(), &iv).unwrap();
+        let signature = digest(
+            key.deref(),
+            2,
+            &Kdf::SCRYPT_DEFAULT.header(2).unwrap(),
+            &iv,
+            &salt,
+            &[],
+            &blob,
+        )
+        .unwrap();
+
+        let mut input = Vec::new();
+        input.write_u32::<BigEndian>(2).unwrap();
+        input.write_u8(SCRYPT_PARAM_LOG2_N).unwrap();
+        input.write_u32::<BigEndian>(SCRYPT_PARAM_R).unwrap();
+        input.write_u32::<BigEndian>(SCRYPT_PARAM_P).unwrap();
+        input.write_all(&salt).unwrap();
+        input.write_all(&iv).unwrap();
+        input.write_all(&signature).unwrap();
+        input.write_all(&blob).unwrap();
+        SafeVec::new(input)
+    }
 
     #[test]
     fn test_generate_random_iv_has_right_length() {
@@ -643,7 +1349,7 @@ mod test {
     fn test_generate_encryption_key_returns_256_bits_key() {
         assert_eq!(
             generate_encryption_key(
-                "hello world",
+                b"hello world",
                 generate_random_salt().unwrap(),
                 SCRYPT_PARAM_LOG2_N,
                 SCRYPT_PARAM_R,
@@ -654,12 +1360,258 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_kdf_header() {
+        for kdf in [Kdf::SCRYPT_DEFAULT, Kdf::ARGON2ID_DEFAULT] {
+            let header = kdf.header(VERSION).unwrap();
+            assert_eq!(Kdf::read(VERSION, &mut header.as_slice()).unwrap(), kdf);
+        }
+
+        // Older files only have scrypt parameters, without saying so
+        let header = Kdf::SCRYPT_DEFAULT.header(6).unwrap();
+        assert_eq!(header.len(), 9);
+        assert_eq!(
+            Kdf::read(6, &mut header.as_slice()).unwrap(),
+            Kdf::SCRYPT_DEFAULT
+        );
+
+        // Unknown functions and parameters that Argon2id refuses
+        assert!(matches!(
+            Kdf::read(VERSION, &mut &[3u8, 0, 0, 0, 0][..]),
+            Err(PasswordError::CorruptionError)
+        ));
+        let header = Kdf::Argon2id {
+            memory_kib: 1,
+            iterations: 1,
+            parallelism: 1,
+        }
+        .header(VERSION)
+        .unwrap();
+        assert!(matches!(
+            Kdf::read(VERSION, &mut header.as_slice()),
+            Err(PasswordError::CorruptionError)
+        ));
+    }
+
+    #[test]
+    fn test_set_kdf() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert_eq!(store.kdf(), Kdf::ARGON2ID_DEFAULT);
+        store
+            .add_password(Password::new("name", "username", "password"))
+            .unwrap();
+
+        store.set_kdf(Kdf::SCRYPT_DEFAULT);
+        let input = sync_to_input(&store);
+        let store =
+            PasswordStore::from_input(SafeString::from_string("****".to_owned()), None, input)
+                .ok()
+                .unwrap();
+        assert_eq!(store.kdf(), Kdf::SCRYPT_DEFAULT);
+        assert_eq!(store.get_all_passwords()[0].password, "password".into());
+    }
+
+    #[test]
+    fn test_set_key_file() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("name", "username", "password"))
+            .unwrap();
+
+        let key_file = KeyFile::new(b"key file");
+        store.set_key_file(Some(key_file.clone()));
+        let input = sync_to_input(&store);
+        assert!(matches!(
+            PasswordStore::from_input(
+                SafeString::from_string("****".to_owned()),
+                None,
+                input.clone()
+            ),
+            Err(PasswordError::NeedKeyFileError)
+        ));
+        assert!(matches!(
+            PasswordStore::from_input(
+                SafeString::from_string("****".to_owned()),
+                Some(&KeyFile::new(b"other key file")),
+                input.clone()
+            ),
+            Err(PasswordError::DecryptionError)
+        ));
+        let mut store = PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            Some(&key_file),
+            input,
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(store.get_all_passwords()[0].password, "password".into());
+
+        // Files that don't need a key file ignore it
+        store.set_key_file(None);
+        let input = sync_to_input(&store);
+        let store = PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            Some(&key_file),
+            input,
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(store.key_file(), None);
+    }
+
+    #[test]
+    fn test_from_input_with_key() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("name", "username", "password"))
+            .unwrap();
+        store.set_key_file(Some(KeyFile::new(b"key file")));
+        let input = sync_to_input(&store);
+
+        let key = store.derived_key().clone();
+        let key_file = KeyFile::from_digest(store.key_file().unwrap().digest()).unwrap();
+        assert!(PasswordStore::from_input_with_key(
+            SafeVec::new(vec![0u8; 32]),
+            Some(&key_file),
+            input.clone()
+        )
+        .is_err());
+        let store = PasswordStore::from_input_with_key(key, Some(&key_file), input)
+            .ok()
+            .unwrap();
+        assert_eq!(store.get_all_passwords()[0].password, "password".into());
+
+        // The file still needs the key file once saved
+        let input = sync_to_input(&store);
+        assert!(matches!(
+            PasswordStore::from_input(SafeString::from_string("****".to_owned()), None, input),
+            Err(PasswordError::NeedKeyFileError)
+        ));
+    }
+
     #[test]
     fn test_create_password_store() {
         let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
         assert_eq!(store.get_all_passwords().len(), 0);
     }
 
+    #[test]
+    fn test_read_version_2_file() {
+        let input = version_2_input(
+            "****",
+            r#"{"passwords":[{"name":"name","username":"username","password":"password","created_at":0,"updated_at":0}]}"#,
+        );
+        let store =
+            PasswordStore::from_input(SafeString::from_string("****".to_owned()), None, input)
+                .ok()
+                .unwrap();
+        assert_eq!(store.get_all_passwords().len(), 1);
+
+        // Saving the file upgrades it to the latest version, with a wrapped data key
+        let input = sync_to_input(&store);
+        assert_eq!(&input[..4], &[0, 0, 0, 8]);
+        let store =
+            PasswordStore::from_input(SafeString::from_string("****".to_owned()), None, input)
+                .ok()
+                .unwrap();
+        assert_eq!(store.get_all_passwords()[0].password, "password".into());
+    }
+
+    #[test]
+    fn test_inspect() {
+        let input = version_2_input("****", r#"{"passwords":[]}"#);
+        let info = inspect(&input).unwrap();
+        assert_eq!(info.version, 2);
+        assert_eq!(info.kdf, Kdf::SCRYPT_DEFAULT);
+        assert!(!info.needs_key_file);
+
+        let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let input = sync_to_input(&store);
+        let info = inspect(&input).unwrap();
+        assert_eq!(info.version, VERSION);
+        assert_eq!(info.kdf, Kdf::ARGON2ID_DEFAULT);
+
+        // Files cut anywhere are never decrypted, and those cut in the header or in a block of the
+        // blob are found out without the master password
+        let header_len = input.len() - info.blob_len;
+        for len in 0..input.len() {
+            match inspect(&input[..len]) {
+                Ok(_) => assert!(len > header_len && (len - header_len) % 16 == 0),
+                Err(PasswordError::TruncatedError) | Err(PasswordError::CorruptionError) => {}
+                _ => panic!("{} bytes", len),
+            }
+            match PasswordStore::from_input(
+                SafeString::from_string("****".to_owned()),
+                None,
+                SafeVec::new(input[..len].to_vec()),
+            ) {
+                Err(PasswordError::TruncatedError) | Err(PasswordError::CorruptionError) => {}
+                _ => panic!("{} bytes", len),
+            }
+        }
+
+        // A changed blob has the right structure, but the signature tells
+        let mut changed = input.deref().to_vec();
+        let last = changed.len() - 1;
+        changed[last] ^= 1;
+        assert!(inspect(&changed).is_ok());
+        match PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            None,
+            SafeVec::new(changed),
+        ) {
+            Err(PasswordError::CorruptionError) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_rekey() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+
+        let old_data_key = store.data_key.clone();
+        let old_key = store.key.clone();
+        store.rekey();
+        assert_ne!(store.data_key.deref(), old_data_key.deref());
+        assert_eq!(store.key.deref(), old_key.deref());
+
+        let input = sync_to_input(&store);
+        let reopened = PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            None,
+            input.clone(),
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(reopened.data_key.deref(), store.data_key.deref());
+        assert_eq!(reopened.get_all_passwords()[0].password, "password".into());
+
+        match PasswordStore::from_input(SafeString::from_string("nope".to_owned()), None, input) {
+            Err(PasswordError::DecryptionError) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_find_passwords_by_value() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        for (name, password) in [("b", "shared"), ("a", "shared"), ("c", "sharedd")] {
+            assert!(store
+                .add_password(Password::new(name, "username", password))
+                .is_ok());
+        }
+
+        let found = store.find_passwords_by_value(&"shared".into());
+        assert_eq!(
+            found.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert!(store.find_passwords_by_value(&"share".into()).is_empty());
+    }
+
     #[test]
     fn test_add_password() {
         let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
@@ -742,6 +1694,80 @@ mod test {
         assert_eq!(store.get_all_passwords()[0].password, "password".into());
     }
 
+    #[test]
+    fn test_history_with_current() {
+        let mut password = Password::new("name", "username", "first");
+        password.updated_at = 10;
+        password.history = password.history_with_current(20);
+        password.password = "second".into();
+        password.updated_at = 20;
+
+        let history = password.history_with_current(30);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].password, "first".into());
+        assert_eq!((history[0].created_at, history[0].replaced_at), (10, 20));
+        assert_eq!(history[1].password, "second".into());
+        assert_eq!((history[1].created_at, history[1].replaced_at), (20, 30));
+
+        for _ in 0..MAX_HISTORY_LEN {
+            password.history = password.history_with_current(40);
+        }
+        assert_eq!(password.history.len(), MAX_HISTORY_LEN);
+        assert_eq!(password.history[0].replaced_at, 40);
+    }
+
+    #[test]
+    fn test_search_passwords_ranks_matches() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        for name in ["Facebook", "Boat Hook", "Book", "eBook store", "Books"] {
+            store
+                .add_password(Password::new(name, "me", "xxxx"))
+                .unwrap();
+        }
+
+        let names = |query: &str| {
+            store
+                .search_passwords(query)
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            names("book"),
+            vec!["Book", "Books", "eBook store", "Facebook", "Boat Hook"]
+        );
+        assert_eq!(names("fcbk"), vec!["Facebook"]);
+        assert_eq!(names("bk").last().unwrap(), "Boat Hook");
+        assert_eq!(names("kb"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_search_passwords_by_tag() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let mut github = Password::new("GitHub", "me", "xxxx");
+        github.tags = vec!["Work".to_string()];
+        let mut gitlab = Password::new("GitLab", "me", "xxxx");
+        gitlab.tags = vec!["personal".to_string()];
+        store.add_password(github).unwrap();
+        store.add_password(gitlab).unwrap();
+        store
+            .add_password(Password::new("Bank", "me", "xxxx"))
+            .unwrap();
+
+        let names = |query: &str| {
+            store
+                .search_passwords(query)
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(names("git"), vec!["GitHub", "GitLab"]);
+        assert_eq!(names("git tag:work"), vec!["GitHub"]);
+        assert_eq!(names("tag:WORK"), vec!["GitHub"]);
+        assert_eq!(names("tag:work tag:personal"), Vec::<String>::new());
+        assert_eq!(names("bank tag:work"), Vec::<String>::new());
+    }
+
     #[test]
     fn test_delete_password() {
         let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
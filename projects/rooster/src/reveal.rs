@@ -0,0 +1,199 @@
+// Shows a password only while a key is held down, see `rooster get --hold`.
+//
+// Terminals don't tell when a key is released, but a key that is held down repeats, so the
+// password is masked again as soon as the repeats stop coming.
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// How long a held key takes to start repeating, which is usually 250 to 600 ms
+const FIRST_REPEAT_DELAY: Duration = Duration::from_millis(700);
+
+/// How long a held key takes to repeat again, which is usually 30 to 50 ms
+const REPEAT_INTERVAL: Duration = Duration::from_millis(150);
+
+const PROMPT: &str = "Hold any key to show the password, Enter to finish: ";
+
+/// Shown while the password is hidden, with the same width whatever the password's length
+const MASK: &str = "********";
+
+const CTRL_C: u8 = 0x03;
+const ESCAPE: u8 = 0x1b;
+
+/// Shows the password while keys keep coming from `next_key`, and a mask otherwise, until Enter,
+/// Escape, q or Ctrl-C is typed
+///
+/// `next_key` returns the next key typed, waiting for it at most the given time if any, or `None`
+/// if no key was typed in time.
+pub fn reveal_while_held(
+    mut next_key: impl FnMut(Option<Duration>) -> io::Result<Option<u8>>,
+    output: &mut impl Write,
+    password: &str,
+) -> io::Result<()> {
+    let width = password.chars().count().max(MASK.len());
+    let is_done = |key: u8| matches!(key, b'\r' | b'\n' | b'q' | ESCAPE | CTRL_C);
+
+    write!(output, "{}{}", PROMPT, MASK)?;
+    output.flush()?;
+    let result = loop {
+        match next_key(None) {
+            Ok(Some(key)) if !is_done(key) => {}
+            Ok(_) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+
+        write!(output, "\r{}{}", PROMPT, password)?;
+        output.flush()?;
+        let mut timeout = FIRST_REPEAT_DELAY;
+        let key = loop {
+            match next_key(Some(timeout)) {
+                Ok(Some(key)) if !is_done(key) => timeout = REPEAT_INTERVAL,
+                other => break other,
+            }
+        };
+        // The password is erased first, since it can be longer than the mask
+        write!(output, "\r{0}{1:2$}\r{0}{3}", PROMPT, "", width, MASK)?;
+        output.flush()?;
+
+        match key {
+            Ok(None) => {}
+            Ok(Some(_)) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+
+    // The line is erased, so that nothing stays on screen
+    write!(output, "\r{}\r", " ".repeat(PROMPT.len() + width))?;
+    output.flush()?;
+    result
+}
+
+/// Runs `reveal_while_held` with the keys typed in the terminal
+#[cfg(unix)]
+pub fn reveal_on_tty(password: &str) -> io::Result<()> {
+    use rtoolbox::raw_mode::{wait_for_key, RawMode, RawModeGuard};
+    use rtoolbox::tty_lock;
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+
+    let _tty = tty_lock::acquire("rooster");
+    let mut input = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")?;
+    let mut output = input.try_clone()?;
+    let fd = input.as_raw_fd();
+    let _raw_mode = RawModeGuard::new(fd, RawMode::Raw)?;
+
+    let next_key = |timeout: Option<Duration>| {
+        if let Some(timeout) = timeout {
+            if !wait_for_key(fd, timeout)? {
+                return Ok(None);
+            }
+        }
+        let mut key = [0u8; 1];
+        match input.read(&mut key)? {
+            0 => Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => Ok(Some(key[0])),
+        }
+    };
+    reveal_while_held(next_key, &mut output, password)
+}
+
+#[cfg(not(unix))]
+pub fn reveal_on_tty(_password: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "holding a key to show the password only works on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{reveal_while_held, FIRST_REPEAT_DELAY, REPEAT_INTERVAL};
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct Screen(Rc<RefCell<Vec<u8>>>);
+
+    impl Screen {
+        fn line(&self) -> String {
+            let output = String::from_utf8(self.0.borrow().clone()).unwrap();
+            output.rsplit('\r').next().unwrap().to_string()
+        }
+    }
+
+    impl Write for Screen {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs `reveal_while_held` with the given keys and the timeouts they are expected with, and
+    /// returns what is shown each time a key is waited for, then once done
+    fn run(password: &str, keys: &[(Option<u8>, Option<Duration>)]) -> Vec<String> {
+        let mut screen = Screen::default();
+        let mut lines = vec![];
+        let mut keys = keys.iter();
+        let seen = screen.clone();
+        let next_key = |timeout: Option<Duration>| {
+            lines.push(seen.line());
+            let (key, expected_timeout) = keys.next().unwrap();
+            assert_eq!(timeout, *expected_timeout);
+            Ok(*key)
+        };
+        reveal_while_held(next_key, &mut screen, password).unwrap();
+        lines.push(screen.line());
+        lines
+    }
+
+    #[test]
+    fn test_reveal_while_held() {
+        let lines = run(
+            "abc",
+            &[
+                (Some(b' '), None),
+                (Some(b' '), Some(FIRST_REPEAT_DELAY)),
+                (Some(b' '), Some(REPEAT_INTERVAL)),
+                (None, Some(REPEAT_INTERVAL)),
+                (Some(b'\r'), None),
+            ],
+        );
+        assert!(lines[0].ends_with(": ********"));
+        assert!(lines[1].ends_with(": abc"));
+        assert!(lines[3].ends_with(": abc"));
+        assert!(lines[4].ends_with(": ********"));
+        assert_eq!(lines[5], "");
+    }
+
+    #[test]
+    fn test_reveal_while_held_hides_length() {
+        let keys = [
+            (Some(b' '), None),
+            (None, Some(FIRST_REPEAT_DELAY)),
+            (Some(b'\r'), None),
+        ];
+        let short = run("abc", &keys);
+        let long = run("correct horse battery staple", &keys);
+        assert_eq!(short[0], long[0]);
+        assert!(long[1].ends_with(": correct horse battery staple"));
+        assert_eq!(long[2], short[2]);
+    }
+
+    #[test]
+    fn test_reveal_while_held_done_while_shown() {
+        let lines = run(
+            "abc",
+            &[(Some(b'x'), None), (Some(b'q'), Some(FIRST_REPEAT_DELAY))],
+        );
+        assert!(lines[1].ends_with(": abc"));
+        assert_eq!(lines[2], "");
+    }
+}
@@ -0,0 +1,268 @@
+//! Keeps the password file in a git repository, to sync it between machines through a remote
+//!
+//! Like for the keychain, we use the `git` command that is already installed. The repository is
+//! a directory next to the password file, ie: `passwords.rooster.sync`, where a copy of the file
+//! is committed after each change. Only the encrypted file is ever committed.
+
+use std::fs;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// The name of the password file in the repository, whatever its name on each machine
+const FILE_NAME: &str = "passwords.rooster";
+
+const REMOTE: &str = "origin";
+
+const BRANCH: &str = "main";
+
+/// The remote branch, once fetched
+const REMOTE_BRANCH: &str = "origin/main";
+
+/// How the local copy of the password file relates to the remote one
+#[derive(Debug, PartialEq, Eq)]
+pub enum Relation {
+    UpToDate,
+    /// Only the local copy changed, which can be pushed
+    Ahead,
+    /// Only the remote copy changed, which can be pulled as is
+    Behind,
+    /// Both changed since `base`, which is `None` when they have no common history, ie: when
+    /// the remote already had a password file when sync was set up
+    Diverged {
+        base: Option<String>,
+    },
+}
+
+/// The directory of the repository, next to the password file
+pub fn sync_dir(rooster_file_path: &Path) -> PathBuf {
+    let mut path = rooster_file_path.as_os_str().to_owned();
+    path.push(".sync");
+    PathBuf::from(path)
+}
+
+/// Whether sync has been set up for this password file
+pub fn is_enabled(rooster_file_path: &Path) -> bool {
+    sync_dir(rooster_file_path).join(".git").exists()
+}
+
+/// Runs git in the repository, and returns what it printed if it succeeded
+fn git(dir: &Path, args: &[&str]) -> IoResult<Vec<u8>> {
+    let output = run(dir, args)?;
+    if output.status.success() {
+        return Ok(output.stdout);
+    }
+    Err(IoError::other(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
+/// Runs git in the repository, and returns whether it succeeded, ie: for yes/no questions
+fn git_succeeds(dir: &Path, args: &[&str]) -> IoResult<bool> {
+    run(dir, args).map(|output| output.status.success())
+}
+
+fn run(dir: &Path, args: &[&str]) -> IoResult<Output> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // Credentials can't be typed in, since Rooster reads the terminal itself
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| match err.kind() {
+            IoErrorKind::NotFound => IoError::new(IoErrorKind::NotFound, "git is not installed"),
+            _ => err,
+        })
+}
+
+/// Returns the commit a revision points to, if it exists
+fn rev_parse(dir: &Path, revision: &str) -> IoResult<Option<String>> {
+    match git_succeeds(dir, &["rev-parse", "--verify", "-q", revision])? {
+        true => {
+            let commit = git(dir, &["rev-parse", "--verify", "-q", revision])?;
+            Ok(Some(String::from_utf8_lossy(&commit).trim().to_string()))
+        }
+        false => Ok(None),
+    }
+}
+
+/// Creates the repository with this remote, and commits the password file to it if it exists
+pub fn init(rooster_file_path: &Path, remote: &str) -> IoResult<()> {
+    let dir = sync_dir(rooster_file_path);
+    if is_enabled(rooster_file_path) {
+        return Err(IoError::new(
+            IoErrorKind::AlreadyExists,
+            format!("{} is already a git repository", dir.display()),
+        ));
+    }
+
+    fs::create_dir_all(&dir)?;
+    git(&dir, &["init", "-q", "--initial-branch", BRANCH])?;
+    git(&dir, &["remote", "add", REMOTE, remote])?;
+    // Commits need an author, which git may not know about yet
+    if !git_succeeds(&dir, &["config", "user.email"])? {
+        git(&dir, &["config", "user.name", "Rooster"])?;
+        git(&dir, &["config", "user.email", "rooster@localhost"])?;
+    }
+
+    if rooster_file_path.exists() {
+        commit(rooster_file_path, "Add password file")?;
+    }
+    Ok(())
+}
+
+/// Commits the password file as it is now, if it changed
+pub fn commit(rooster_file_path: &Path, message: &str) -> IoResult<()> {
+    let dir = sync_dir(rooster_file_path);
+    fs::copy(rooster_file_path, dir.join(FILE_NAME))?;
+    git(&dir, &["add", FILE_NAME])?;
+    if git_succeeds(&dir, &["diff", "--cached", "--quiet"])? {
+        return Ok(());
+    }
+    git(&dir, &["commit", "-q", "-m", message])?;
+    Ok(())
+}
+
+/// Fetches the remote, and tells how the local copy relates to it
+pub fn fetch(rooster_file_path: &Path) -> IoResult<Relation> {
+    let dir = sync_dir(rooster_file_path);
+    git(&dir, &["fetch", "-q", REMOTE])?;
+
+    let local = rev_parse(&dir, "HEAD")?;
+    let remote = rev_parse(&dir, REMOTE_BRANCH)?;
+    let (local, remote) = match (local, remote) {
+        (_, None) => return Ok(Relation::Ahead),
+        (None, Some(_)) => return Ok(Relation::Behind),
+        (Some(local), Some(remote)) => (local, remote),
+    };
+
+    if local == remote {
+        Ok(Relation::UpToDate)
+    } else if git_succeeds(&dir, &["merge-base", "--is-ancestor", &remote, &local])? {
+        Ok(Relation::Ahead)
+    } else if git_succeeds(&dir, &["merge-base", "--is-ancestor", &local, &remote])? {
+        Ok(Relation::Behind)
+    } else {
+        let base = match git_succeeds(&dir, &["merge-base", &local, &remote])? {
+            true => Some(
+                String::from_utf8_lossy(&git(&dir, &["merge-base", &local, &remote])?)
+                    .trim()
+                    .to_string(),
+            ),
+            false => None,
+        };
+        Ok(Relation::Diverged { base })
+    }
+}
+
+/// Returns the password file as it is in a commit, ie: the remote one or the one both sides
+/// started from
+pub fn read_file(rooster_file_path: &Path, revision: &str) -> IoResult<Vec<u8>> {
+    let dir = sync_dir(rooster_file_path);
+    git(&dir, &["show", &format!("{}:{}", revision, FILE_NAME)])
+}
+
+/// Returns the remote password file, once fetched
+pub fn read_remote_file(rooster_file_path: &Path) -> IoResult<Vec<u8>> {
+    read_file(rooster_file_path, REMOTE_BRANCH)
+}
+
+/// Moves the local copy to the remote one, when only the remote one changed, and replaces the
+/// password file with it
+pub fn fast_forward(rooster_file_path: &Path) -> IoResult<()> {
+    let dir = sync_dir(rooster_file_path);
+    git(&dir, &["merge", "-q", "--ff-only", REMOTE_BRANCH])?;
+    fs::copy(dir.join(FILE_NAME), rooster_file_path)?;
+    Ok(())
+}
+
+/// Records that the password file, merged by the caller, has both the local and remote changes
+pub fn commit_merge(rooster_file_path: &Path) -> IoResult<()> {
+    let dir = sync_dir(rooster_file_path);
+    git(
+        &dir,
+        &[
+            "merge",
+            "-q",
+            "--no-commit",
+            "--allow-unrelated-histories",
+            "-s",
+            "ours",
+            REMOTE_BRANCH,
+        ],
+    )?;
+    fs::copy(rooster_file_path, dir.join(FILE_NAME))?;
+    git(&dir, &["add", FILE_NAME])?;
+    git(&dir, &["commit", "-q", "-m", "Merge remote password file"])?;
+    Ok(())
+}
+
+pub fn push(rooster_file_path: &Path) -> IoResult<()> {
+    let dir = sync_dir(rooster_file_path);
+    git(&dir, &["push", "-q", REMOTE, BRANCH])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{commit, fetch, init, is_enabled, push, Relation};
+    use std::path::Path;
+    use std::process::Command;
+
+    fn rooster_file(dir: &Path, name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_fetch_relation() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        let status = Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(&remote)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let remote = remote.to_str().unwrap();
+
+        let first = rooster_file(dir.path(), "first.rooster", b"1");
+        assert!(!is_enabled(&first));
+        init(&first, remote).unwrap();
+        assert!(is_enabled(&first));
+        assert!(init(&first, remote).is_err());
+        assert_eq!(fetch(&first).unwrap(), Relation::Ahead);
+        push(&first).unwrap();
+        assert_eq!(fetch(&first).unwrap(), Relation::UpToDate);
+
+        // A machine that has no password file yet gets the remote one
+        let second = dir.path().join("second.rooster");
+        init(&second, remote).unwrap();
+        assert_eq!(fetch(&second).unwrap(), Relation::Behind);
+        super::fast_forward(&second).unwrap();
+        assert_eq!(std::fs::read(&second).unwrap(), b"1");
+
+        // Unchanged files aren't committed again
+        commit(&second, "Nothing").unwrap();
+        assert_eq!(fetch(&second).unwrap(), Relation::UpToDate);
+
+        std::fs::write(&second, b"2").unwrap();
+        commit(&second, "Change").unwrap();
+        push(&second).unwrap();
+        std::fs::write(&first, b"3").unwrap();
+        commit(&first, "Change").unwrap();
+        assert!(push(&first).is_err());
+        assert!(matches!(
+            fetch(&first).unwrap(),
+            Relation::Diverged { base: Some(_) }
+        ));
+        assert_eq!(super::read_remote_file(&first).unwrap(), b"2");
+
+        super::commit_merge(&first).unwrap();
+        assert_eq!(fetch(&first).unwrap(), Relation::Ahead);
+        push(&first).unwrap();
+    }
+}
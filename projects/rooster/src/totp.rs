@@ -0,0 +1,278 @@
+// Time-based one-time passwords, as used for 2FA, see RFC 6238.
+
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use rtoolbox::safe_string::SafeString;
+use rtoolbox::safe_vec::SafeVec;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Totp {
+    /// The shared secret, in base32 like in otpauth:// URIs
+    pub secret: SafeString,
+    pub algorithm: TotpAlgorithm,
+    pub digits: u32,
+    /// How long each code is valid for, in seconds
+    pub period: u64,
+}
+
+impl Totp {
+    /// Reads the secret and settings from an otpauth:// URI, ie:
+    /// `otpauth://totp/Github:me?secret=JBSWY3DPEHPK3PXP&digits=6`, or takes a bare base32 secret
+    /// with the usual settings (SHA1, 6 digits, 30 seconds).
+    pub fn from_uri(uri: &str) -> Result<Totp, String> {
+        let uri = uri.trim();
+        let mut totp = Totp {
+            secret: SafeString::new(),
+            algorithm: TotpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+        };
+
+        if !uri.starts_with("otpauth://") {
+            totp.secret = normalize_secret(uri);
+        } else {
+            let rest = &uri["otpauth://".len()..];
+            if !rest.to_lowercase().starts_with("totp/") {
+                return Err("only time-based (totp) URIs are supported".to_string());
+            }
+            let query = rest.split_once('?').map_or("", |(_, q)| q);
+            for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+                let value = percent_decode(value);
+                let value = value.as_str();
+                match key.to_lowercase().as_str() {
+                    "secret" => totp.secret = normalize_secret(value),
+                    "algorithm" => {
+                        totp.algorithm = match value.to_uppercase().as_str() {
+                            "SHA1" => TotpAlgorithm::Sha1,
+                            "SHA256" => TotpAlgorithm::Sha256,
+                            "SHA512" => TotpAlgorithm::Sha512,
+                            _ => return Err(format!("unknown algorithm {}", value)),
+                        }
+                    }
+                    "digits" => {
+                        totp.digits = value
+                            .parse()
+                            .ok()
+                            .filter(|digits| (6..=8).contains(digits))
+                            .ok_or_else(|| format!("{} digits is not supported", value))?
+                    }
+                    "period" => {
+                        totp.period = value
+                            .parse()
+                            .ok()
+                            .filter(|period| *period > 0)
+                            .ok_or_else(|| format!("invalid period {}", value))?
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        match decode_base32(totp.secret.deref()) {
+            Some(secret) if !secret.is_empty() => Ok(totp),
+            _ => Err("the secret is missing or is not valid base32".to_string()),
+        }
+    }
+
+    /// Writes the secret and settings as an otpauth:// URI, the format `from_uri` reads
+    pub fn to_uri(&self, label: &str) -> SafeString {
+        let algorithm = match self.algorithm {
+            TotpAlgorithm::Sha1 => "SHA1",
+            TotpAlgorithm::Sha256 => "SHA256",
+            TotpAlgorithm::Sha512 => "SHA512",
+        };
+        SafeString::from_string(format!(
+            "otpauth://totp/{}?secret={}&algorithm={}&digits={}&period={}",
+            percent_encode(label),
+            self.secret.deref(),
+            algorithm,
+            self.digits,
+            self.period
+        ))
+    }
+
+    /// Returns the code for the given Unix time
+    pub fn code(&self, time: u64) -> Result<String, ErrorStack> {
+        let secret = SafeVec::new(decode_base32(self.secret.deref()).unwrap_or_default());
+        let digest = match self.algorithm {
+            TotpAlgorithm::Sha1 => MessageDigest::sha1(),
+            TotpAlgorithm::Sha256 => MessageDigest::sha256(),
+            TotpAlgorithm::Sha512 => MessageDigest::sha512(),
+        };
+        let key = PKey::hmac(secret.deref())?;
+        let mut signer = Signer::new(digest, &key)?;
+        signer.update(&(time / self.period).to_be_bytes())?;
+        let hmac = signer.sign_to_vec()?;
+
+        // Dynamic truncation, see RFC 4226 section 5.3
+        let offset = (hmac[hmac.len() - 1] & 0xf) as usize;
+        let binary = u32::from_be_bytes([
+            hmac[offset] & 0x7f,
+            hmac[offset + 1],
+            hmac[offset + 2],
+            hmac[offset + 3],
+        ]);
+        let code = binary as u64 % 10u64.pow(self.digits);
+
+        Ok(format!("{:0width$}", code, width = self.digits as usize))
+    }
+
+    /// Returns how many seconds the code for the given Unix time is still valid for
+    pub fn seconds_left(&self, time: u64) -> u64 {
+        self.period - time % self.period
+    }
+}
+
+/// Secrets are often displayed in groups of 4 lowercase characters, for readability
+fn normalize_secret(secret: &str) -> SafeString {
+    SafeString::from_string(
+        secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '=')
+            .collect::<String>()
+            .to_uppercase(),
+    )
+}
+
+/// Escapes everything but unreserved characters, see RFC 3986 section 2.3
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Decodes %XX escapes in URI parameters, leaving invalid ones as is
+fn percent_decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        let escaped = s
+            .get(i + 1..i + 3)
+            .filter(|_| s.as_bytes()[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(s.as_bytes()[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Decodes base32 without padding, see RFC 4648
+fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in s.chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            '2'..='7' => c as u32 - '2' as u32 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_base32, Totp, TotpAlgorithm};
+    use std::ops::Deref;
+
+    #[test]
+    fn test_decode_base32() {
+        assert_eq!(decode_base32("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(decode_base32("").unwrap(), b"");
+        assert_eq!(decode_base32("MZXW6!"), None);
+    }
+
+    #[test]
+    fn test_from_uri() {
+        let totp = Totp::from_uri(
+            "otpauth://totp/Github:me?secret=jbsw%20y3dp&issuer=Github&algorithm=SHA256&digits=8&period=60",
+        )
+        .unwrap();
+        assert_eq!(totp.secret, "JBSWY3DP".into());
+        assert_eq!(totp.algorithm, TotpAlgorithm::Sha256);
+        assert_eq!((totp.digits, totp.period), (8, 60));
+
+        let totp = Totp::from_uri("jbsw y3dp ehpk 3pxp").unwrap();
+        assert_eq!(totp.secret, "JBSWY3DPEHPK3PXP".into());
+        assert_eq!((totp.digits, totp.period), (6, 30));
+
+        let uri = totp.to_uri("Github: me");
+        assert_eq!(
+            uri.deref(),
+            "otpauth://totp/Github%3A%20me?secret=JBSWY3DPEHPK3PXP&algorithm=SHA1&digits=6&period=30"
+        );
+        assert_eq!(Totp::from_uri(&uri).unwrap(), totp);
+
+        assert!(Totp::from_uri("otpauth://hotp/Github?secret=JBSWY3DP").is_err());
+        assert!(Totp::from_uri("otpauth://totp/Github?digits=6").is_err());
+        assert!(Totp::from_uri("otpauth://totp/Github?secret=JBSWY3DP&digits=12").is_err());
+    }
+
+    #[test]
+    fn test_code() {
+        // Test vectors from RFC 6238, appendix B
+        let secrets = [
+            (TotpAlgorithm::Sha1, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"),
+            (
+                TotpAlgorithm::Sha256,
+                "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA",
+            ),
+            (
+                TotpAlgorithm::Sha512,
+                "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA",
+            ),
+        ];
+        let expected = [
+            (59, ["94287082", "46119246", "90693936"]),
+            (1111111109, ["07081804", "68084774", "25091201"]),
+            (20000000000, ["65353130", "77737706", "47863826"]),
+        ];
+        for (time, codes) in expected.iter() {
+            for ((algorithm, secret), code) in secrets.iter().zip(codes.iter()) {
+                let totp = Totp {
+                    secret: secret.to_string().into(),
+                    algorithm: *algorithm,
+                    digits: 8,
+                    period: 30,
+                };
+                assert_eq!(totp.code(*time).unwrap(), *code);
+            }
+        }
+
+        let totp = Totp::from_uri(secrets[0].1).unwrap();
+        assert_eq!(totp.code(59).unwrap(), "287082");
+        assert_eq!(totp.seconds_left(59), 1);
+    }
+}
@@ -0,0 +1,125 @@
+// The master password of scripts, which can't type it, see `--non-interactive`.
+
+use rtoolbox::safe_string::SafeString;
+use std::io::Result as IoResult;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A file descriptor to read the master password from, ie: `3` with `3< file` in the shell
+pub const MASTER_PASSWORD_FD_ENV_VAR: &str = "ROOSTER_MASTER_PASSWORD_FD";
+
+/// A file to read the master password from
+pub const MASTER_PASSWORD_FILE_ENV_VAR: &str = "ROOSTER_MASTER_PASSWORD_FILE";
+
+/// The master password itself, which other processes of the user may be able to see
+pub const MASTER_PASSWORD_ENV_VAR: &str = "ROOSTER_MASTER_PASSWORD";
+
+/// Reads the master password given in the first of the environment variables above that is set,
+/// along with its name, or returns `None` if none is
+///
+/// The file descriptor is only read once per process, since it's closed after that and its number
+/// may be reused by files we open later. Callers that unlock again, like the shell, keep the
+/// password instead, see `master_password_fd_was_read`.
+pub fn master_password_from_env() -> Option<(&'static str, IoResult<SafeString>)> {
+    if let Some(fd) = std::env::var_os(MASTER_PASSWORD_FD_ENV_VAR) {
+        return Some((
+            MASTER_PASSWORD_FD_ENV_VAR,
+            read_fd(&fd.to_string_lossy()).map(without_newline),
+        ));
+    }
+    if let Some(path) = std::env::var_os(MASTER_PASSWORD_FILE_ENV_VAR) {
+        return Some((
+            MASTER_PASSWORD_FILE_ENV_VAR,
+            std::fs::read_to_string(path).map(without_newline),
+        ));
+    }
+    std::env::var_os(MASTER_PASSWORD_ENV_VAR).map(|password| {
+        (
+            MASTER_PASSWORD_ENV_VAR,
+            Ok(SafeString::from_string(
+                password.to_string_lossy().into_owned(),
+            )),
+        )
+    })
+}
+
+/// Files and commands like `echo` end with a new line, which isn't part of the password
+fn without_newline(mut password: String) -> SafeString {
+    if password.ends_with('\n') {
+        password.pop();
+        if password.ends_with('\r') {
+            password.pop();
+        }
+    }
+    SafeString::from_string(password)
+}
+
+static FD_READ: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether the master password was read from its file descriptor, which can't be read again
+pub fn master_password_fd_was_read() -> bool {
+    FD_READ.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+fn already_read_error() -> std::io::Error {
+    std::io::Error::other("it was already read, and is closed now")
+}
+
+#[cfg(unix)]
+fn read_fd(fd: &str) -> IoResult<String> {
+    use std::io::Read;
+    use std::os::unix::io::FromRawFd;
+
+    if master_password_fd_was_read() {
+        return Err(already_read_error());
+    }
+    let fd = fd
+        .trim()
+        .parse::<i32>()
+        .ok()
+        .filter(|fd| *fd >= 0)
+        .ok_or_else(|| std::io::Error::other(format!("{:?} is not a file descriptor", fd)))?;
+    // Standard input and outputs are ours, and closing them would break the rest of the command
+    if fd <= 2 {
+        return Err(std::io::Error::other(format!(
+            "{} is the standard input or output, not a file of its own",
+            fd
+        )));
+    }
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(std::io::Error::other(format!(
+            "{} is not an open file descriptor",
+            fd
+        )));
+    }
+    if FD_READ.swap(true, Ordering::SeqCst) {
+        return Err(already_read_error());
+    }
+    // The descriptor is given to us, so it's closed once read
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    let mut password = String::new();
+    file.read_to_string(&mut password)?;
+    Ok(password)
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: &str) -> IoResult<String> {
+    Err(std::io::Error::other(format!(
+        "{} is only supported on Linux, BSD and OSX, use {} instead",
+        MASTER_PASSWORD_FD_ENV_VAR, MASTER_PASSWORD_FILE_ENV_VAR
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::without_newline;
+    use std::ops::Deref;
+
+    #[test]
+    fn test_without_newline() {
+        assert_eq!(without_newline("xxxx\n".to_owned()).deref(), "xxxx");
+        assert_eq!(without_newline("xxxx\r\n".to_owned()).deref(), "xxxx");
+        assert_eq!(without_newline("xx xx \n\n".to_owned()).deref(), "xx xx \n");
+        assert_eq!(without_newline("xxxx".to_owned()).deref(), "xxxx");
+    }
+}
@@ -0,0 +1,70 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use rooster::{main_with_args_and_config, Config};
+
+#[test]
+fn test_aliases_and_default_command() {
+    let rooster_file = tempfile();
+    let mut config = Config::default();
+    config
+        .aliases
+        .insert("g".to_string(), "get --show".to_string());
+    // Aliases can't replace Rooster's own commands
+    config
+        .aliases
+        .insert("list".to_string(), "delete".to_string());
+    config.default_command = Some("list".to_string());
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Website", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "--clear-clipboard-after", "0", "g", "website"],
+            &mut io,
+            &rooster_file,
+            &config
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("abcd"));
+
+    for args in [vec!["rooster"], vec!["rooster", "list"]].iter() {
+        let mut io = CursorInputOutput::new("", "xxxx\n");
+        assert_eq!(
+            0,
+            main_with_args_and_config(args, &mut io, &rooster_file, &config)
+        );
+        let output_as_vecu8 = io.stdout_cursor.into_inner();
+        let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+        assert!(output_as_string.contains("Website"));
+        assert!(!output_as_string.contains("abcd"));
+    }
+
+    // The default command can be an alias too
+    config.default_command = Some("g website".to_string());
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args_and_config(&["rooster"], &mut io, &rooster_file, &config)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("abcd"));
+}
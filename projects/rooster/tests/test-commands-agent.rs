@@ -0,0 +1,106 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn agent_socket(rooster_file: &PathBuf) -> PathBuf {
+    let mut path = rooster_file.as_os_str().to_owned();
+    path.push(".agent");
+    PathBuf::from(path)
+}
+
+#[test]
+#[cfg(unix)]
+fn test_command_agent() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Github", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "agent", "stop"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+
+    let agent = {
+        let rooster_file = rooster_file.clone();
+        std::thread::spawn(move || {
+            main_with_args(
+                &["rooster", "agent", "start", "--foreground", "--ttl", "60"],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file,
+            )
+        })
+    };
+    while !agent_socket(&rooster_file).exists() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    // Commands that only read the file don't ask for the master password
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "github"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+
+    // Completion scripts get the app names from it
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list", "--names-only"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert_eq!(
+        String::from_utf8_lossy(output_as_vecu8.as_slice()),
+        "Github\n"
+    );
+
+    // Commands that change it do
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "delete", "github"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "agent", "stop"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+    assert_eq!(0, agent.join().unwrap());
+    assert!(!agent_socket(&rooster_file).exists());
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "github"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+}
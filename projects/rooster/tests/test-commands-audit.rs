@@ -0,0 +1,56 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_audit() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "generate", "-s", "Strong", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "audit"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    for app in ["First", "Second"].iter() {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", app, "me@example.com"],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "audit", "--json"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let report: serde_json::Value = serde_json::from_slice(output_as_vecu8.as_slice()).unwrap();
+    assert_eq!(report["reused"], serde_json::json!([["First", "Second"]]));
+    assert_eq!(report["weak"].as_array().unwrap().len(), 2);
+    assert_eq!(report["stale"], serde_json::json!([]));
+    assert!(!String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("abcd"));
+}
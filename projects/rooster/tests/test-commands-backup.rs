@@ -0,0 +1,99 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use rooster::{main_with_args_and_config, Config};
+
+#[test]
+fn test_command_backup() {
+    let rooster_file = tempfile();
+    let backup_dir = tempfile::tempdir().unwrap();
+    let backup_dir_str = backup_dir.path().to_str().unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // There is nowhere to back up to by default
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "backup", "now"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "backup", "now", "--dir", backup_dir_str],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "backup", "verify", "--dir", backup_dir_str],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let snapshot = std::fs::read_dir(backup_dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let mut corrupted = std::fs::read(&snapshot).unwrap();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 1;
+    std::fs::write(&snapshot, corrupted).unwrap();
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "backup", "verify", snapshot.to_str().unwrap()],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_automatic_backup() {
+    let rooster_file = tempfile();
+    let backup_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        backup_dir: Some(backup_dir.path().join("rooster")),
+        ..Config::default()
+    };
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    for _ in 0..2 {
+        assert_eq!(
+            0,
+            main_with_args_and_config(
+                &["rooster", "list"],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file,
+                &config
+            )
+        );
+    }
+
+    // Only one snapshot is due each week
+    let snapshots = std::fs::read_dir(backup_dir.path().join("rooster")).unwrap();
+    assert_eq!(snapshots.count(), 1);
+}
@@ -0,0 +1,131 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use rooster::{main_with_args_and_config, Config};
+
+#[test]
+fn test_command_backups() {
+    let rooster_file = tempfile();
+    let config = Config {
+        backups_keep: 2,
+        ..Config::default()
+    };
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "backups", "list"],
+            &mut io,
+            &rooster_file,
+            &config
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("There are no backups yet"));
+
+    for app in ["Youtube", "Github", "Gitlab"] {
+        assert_eq!(
+            0,
+            main_with_args_and_config(
+                &["rooster", "add", "-s", app, "me@example.com"],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file,
+                &config
+            )
+        );
+    }
+
+    // Commands that don't change the password file don't back it up
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file,
+            &config
+        )
+    );
+
+    // Only the last 2 backups are kept, from before Github and Gitlab were added
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "backups", "list"],
+            &mut io,
+            &rooster_file,
+            &config
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains(" 2 "));
+    assert!(!output_as_string.contains(" 3 "));
+
+    assert_eq!(
+        1,
+        main_with_args_and_config(
+            &["rooster", "backups", "restore", "3"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file,
+            &config
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args_and_config(
+            &["rooster", "backups", "restore", "2"],
+            &mut CursorInputOutput::new("", "nope\n"),
+            &rooster_file,
+            &config
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "backups", "restore", "2"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file,
+            &config
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube"));
+    assert!(!output_as_string.contains("Github"));
+
+    // The password file that was replaced is the latest backup, so the restore can be undone
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "backups", "restore", "1"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file,
+            &config
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Gitlab"));
+}
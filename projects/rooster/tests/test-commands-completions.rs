@@ -0,0 +1,61 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_completions() {
+    let rooster_file = tempfile();
+
+    for (shell, expected) in [
+        ("bash", "complete -o default -F _rooster rooster"),
+        ("zsh", "#compdef rooster"),
+        ("fish", "complete -c rooster -f"),
+        (
+            "powershell",
+            "Register-ArgumentCompleter -Native -CommandName rooster",
+        ),
+    ] {
+        let mut io = CursorInputOutput::new("", "");
+        assert_eq!(
+            0,
+            main_with_args(&["rooster", "completions", shell], &mut io, &rooster_file)
+        );
+        let output_as_vecu8 = io.stdout_cursor.into_inner();
+        let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+        assert!(output_as_string.contains(expected));
+        assert!(output_as_string.contains("rooster list --names-only"));
+        assert!(output_as_string.contains("backup now"));
+        // Hidden commands and options aren't completed
+        assert!(!output_as_string.contains("force-for-tests"));
+        assert!(!output_as_string.contains("clear-clipboard'"));
+    }
+}
+
+#[test]
+fn test_list_names_only_never_asks() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Github", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Without the agent, nothing is listed, even if the master password could be typed
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "list", "--names-only"], &mut io, &rooster_file)
+    );
+    assert!(io.stdout_cursor.into_inner().is_empty());
+}
@@ -0,0 +1,47 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_copy() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Website", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "copy", "nothing"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Whether there is a clipboard to copy to depends on where tests run, but the password is
+    // never shown either way
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    main_with_args(
+        &["rooster", "copy", "--clear-clipboard-after", "0", "website"],
+        &mut io,
+        &rooster_file,
+    );
+    let stdout = io.stdout_cursor.into_inner();
+    let stderr = io.stderr_cursor.into_inner();
+    assert!(!String::from_utf8_lossy(stdout.as_slice()).contains("abcd"));
+    assert!(!String::from_utf8_lossy(stderr.as_slice()).contains("abcd"));
+}
@@ -0,0 +1,101 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn derive(args: &[&str], ttyin: &str, rooster_file: &std::path::PathBuf) -> (i32, String) {
+    let mut io = CursorInputOutput::new("", ttyin);
+    let code = main_with_args(args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+#[test]
+fn test_command_derive() {
+    let rooster_file = tempfile();
+
+    // No password file is needed without counters
+    let (code, first) = derive(
+        &["rooster", "derive", "--stateless", "-s", "github.com"],
+        "seed\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(first.trim().len(), 32);
+    assert_eq!(
+        derive(
+            &["rooster", "derive", "--stateless", "-s", "GitHub.com"],
+            "seed\n",
+            &rooster_file
+        ),
+        (0, first.clone())
+    );
+    assert_eq!(
+        derive(
+            &[
+                "rooster",
+                "derive",
+                "--stateless",
+                "--next",
+                "-s",
+                "github.com"
+            ],
+            "seed\n",
+            &rooster_file
+        )
+        .0,
+        1
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The counter is saved in the password file once it's incremented
+    assert_eq!(
+        derive(
+            &["rooster", "derive", "-s", "github.com"],
+            "xxxx\nseed\n",
+            &rooster_file
+        ),
+        (0, first.clone())
+    );
+    let (code, second) = derive(
+        &["rooster", "derive", "--next", "-s", "github.com"],
+        "xxxx\nseed\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_ne!(first, second);
+    assert_eq!(
+        derive(
+            &["rooster", "derive", "-s", "github.com"],
+            "xxxx\nseed\n",
+            &rooster_file
+        ),
+        (0, second.clone())
+    );
+    assert_eq!(
+        derive(
+            &[
+                "rooster",
+                "derive",
+                "--stateless",
+                "-c",
+                "2",
+                "-s",
+                "github.com"
+            ],
+            "seed\n",
+            &rooster_file
+        ),
+        (0, second)
+    );
+}
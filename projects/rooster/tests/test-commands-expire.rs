@@ -0,0 +1,99 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::Write;
+
+fn rooster(args: &[&str], ttyin: &str, rooster_file: &std::path::PathBuf) -> (i32, String) {
+    let mut full_args = vec!["rooster"];
+    full_args.extend_from_slice(args);
+    let mut io = CursorInputOutput::new("", ttyin);
+    let code = main_with_args(&full_args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+#[test]
+fn test_command_expire() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        rooster(&["init", "--force-for-tests"], "\nxxxx\n", &rooster_file).0
+    );
+    assert_eq!(
+        0,
+        rooster(&["add", "-s", "Bank", "me"], "xxxx\nabcd\n", &rooster_file).0
+    );
+
+    let (code, output) = rooster(&["expire", "bank"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("The password for Bank doesn't expire"));
+    let (code, output) = rooster(&["expiring"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("No passwords expire within 14 days"));
+
+    assert_eq!(
+        0,
+        rooster(&["expire", "bank", "1w"], "xxxx\n", &rooster_file).0
+    );
+    let (code, output) = rooster(&["expire", "bank"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("lasts 7 days, and expires in 7 days"));
+    let (_, output) = rooster(&["list"], "xxxx\n", &rooster_file);
+    assert!(output.contains("(expires in 7 days)"));
+    let (code, output) = rooster(&["expiring", "--fail-if-expired"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("Bank: expires in 7 days"));
+    let (_, output) = rooster(&["expiring", "--within", "3"], "xxxx\n", &rooster_file);
+    assert!(output.contains("No passwords expire within 3 days"));
+
+    assert_eq!(
+        0,
+        rooster(&["expire", "bank", "--clear"], "xxxx\n", &rooster_file).0
+    );
+    let (_, output) = rooster(&["list"], "xxxx\n", &rooster_file);
+    assert!(!output.contains("expires"));
+}
+
+#[test]
+fn test_command_expiring_fail_if_expired() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        rooster(&["init", "--force-for-tests"], "\nxxxx\n", &rooster_file).0
+    );
+
+    let import_file_json = tempfile();
+    File::create(import_file_json.clone()).unwrap().write_all(
+        "{\"passwords\":[{\"name\":\"Youtube\",\"username\":\"yt@example.com\",\"password\":\"abcd\",\"created_at\":1605554169,\"updated_at\":1605554169,\"expiry\":{\"days\":90,\"expires_at\":1613330169}}]}".as_bytes()
+    ).unwrap();
+    assert_eq!(
+        0,
+        rooster(
+            &["import", "json", import_file_json.to_str().unwrap()],
+            "xxxx\n",
+            &rooster_file
+        )
+        .0
+    );
+
+    let (code, output) = rooster(&["expiring"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("Youtube: expired"));
+    assert_eq!(
+        1,
+        rooster(&["expiring", "--fail-if-expired"], "xxxx\n", &rooster_file).0
+    );
+
+    // Changing the password starts its expiry over
+    assert_eq!(
+        0,
+        rooster(&["change", "youtube", "-s"], "xxxx\nefgh\n", &rooster_file).0
+    );
+    let (code, output) = rooster(&["expiring", "--fail-if-expired"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("No passwords expire within 14 days"));
+}
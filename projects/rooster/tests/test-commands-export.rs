@@ -118,3 +118,211 @@ fn test_command_export_1password() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert_eq!(output_as_string, "Youtube,yt@example.com,abcd\n");
 }
+
+#[test]
+fn test_command_export_mapping_profile() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Export the name as "title", the password masked, and skip the rest
+    let mut io = CursorInputOutput::new("", "xxxx\ny\ntitle\nn\nm\n\nn\nn\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "csv",
+                "--mapping",
+                "--profile",
+                "audit"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert_eq!(output_as_string, "title,password\nYoutube,********\n");
+
+    // The saved profile is reused without asking again
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "export", "json", "--profile", "audit"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let output_as_json = serde_json::from_str::<Value>(output_as_string.as_ref()).unwrap();
+    let saved_password = output_as_json
+        .as_object()
+        .unwrap()
+        .get("passwords")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .get(0)
+        .unwrap()
+        .as_object()
+        .unwrap();
+    assert_eq!(saved_password.len(), 2);
+    assert_eq!(
+        saved_password.get("title").unwrap().as_str().unwrap(),
+        "Youtube"
+    );
+    assert_eq!(
+        saved_password.get("password").unwrap().as_str().unwrap(),
+        "********"
+    );
+
+    // Unknown profiles are an error
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "export", "csv", "--profile", "unknown"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_export_keepass_xml() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\na<b&c\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "export", "keepass-xml"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\""));
+    assert!(output_as_string.contains("<String><Key>Title</Key><Value>Youtube</Value></String>"));
+    assert!(output_as_string
+        .contains("<String><Key>UserName</Key><Value>yt@example.com</Value></String>"));
+    assert!(output_as_string.contains(
+        "<String><Key>Password</Key><Value ProtectInMemory=\"True\">a&lt;b&amp;c</Value></String>"
+    ));
+    assert!(output_as_string.ends_with("</KeePassFile>\n"));
+}
+
+#[test]
+fn test_command_export_to_file() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let export_file = tempfile();
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "csv",
+                "--file",
+                export_file.to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("abcd"));
+    assert_eq!(
+        std::fs::read_to_string(&export_file).unwrap(),
+        "Youtube,yt@example.com,abcd\n"
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&export_file)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Overwriting a file that others can read makes it readable by the owner only too
+        std::fs::set_permissions(&export_file, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(
+            0,
+            main_with_args(
+                &[
+                    "rooster",
+                    "export",
+                    "csv",
+                    "-o",
+                    export_file.to_str().unwrap()
+                ],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+        let mode = std::fs::metadata(&export_file)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    std::fs::remove_file(&export_file).unwrap();
+}
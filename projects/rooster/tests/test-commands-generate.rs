@@ -42,3 +42,58 @@ fn test_command_generate() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("yt@example.com"));
 }
+
+fn generate(args: &[&str], rooster_file: &std::path::PathBuf) -> Option<String> {
+    let mut full_args = vec!["rooster", "generate", "-s"];
+    full_args.extend_from_slice(args);
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    if main_with_args(&full_args, &mut io, rooster_file) != 0 {
+        return None;
+    }
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned();
+    let password = output_as_string.split("Here is your password: ").nth(1)?;
+    // The password is followed by the end of its color
+    let mut password = password.split(|c: char| c.is_whitespace() || c == '\u{1b}');
+    Some(password.next()?.to_string())
+}
+
+#[test]
+fn test_command_generate_styles() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let password = generate(&["--words", "5", "Github", "me"], &rooster_file).unwrap();
+    assert_eq!(password.split('-').count(), 5);
+
+    let password = generate(&["--pattern", "ULLLL-9999", "Gitlab", "me"], &rooster_file).unwrap();
+    assert_eq!(password.len(), 10);
+    assert!(password.chars().next().unwrap().is_ascii_uppercase());
+    assert!(password[6..].chars().all(|c| c.is_ascii_digit()));
+
+    let password = generate(
+        &[
+            "--charset",
+            "a-f0-9",
+            "--no-ambiguous",
+            "-l",
+            "16",
+            "Bitbucket",
+            "me",
+        ],
+        &rooster_file,
+    )
+    .unwrap();
+    assert_eq!(password.len(), 16);
+    assert!(password.chars().all(|c| "abcdef23456789".contains(c)));
+
+    // An invalid set of characters is an error
+    assert!(generate(&["--charset", "z-a", "Youtube", "me"], &rooster_file).is_none());
+}
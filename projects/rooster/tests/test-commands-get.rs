@@ -59,3 +59,38 @@ fn test_command_get() {
     assert!(output_as_string.contains("efgh"));
     assert!(output_as_string.contains("second@example.com"));
 }
+
+#[test]
+fn test_command_get_pass_format() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Website", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "--pass-format", "website"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert_eq!(output_as_string, "abcd\nlogin: me@example.com\n");
+}
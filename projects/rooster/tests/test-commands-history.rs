@@ -0,0 +1,85 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn stdout_of(args: &[&str], ttyin: &str, rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", ttyin);
+    assert_eq!(0, main_with_args(args, &mut io, rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned()
+}
+
+#[test]
+fn test_command_history_and_restore() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Bank", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nfirst\n"),
+            &rooster_file
+        )
+    );
+    assert!(
+        stdout_of(&["rooster", "history", "bank"], "xxxx\n", &rooster_file)
+            .contains("no previous passwords")
+    );
+
+    stdout_of(
+        &["rooster", "change", "-s", "bank"],
+        "xxxx\nsecond\n",
+        &rooster_file,
+    );
+    stdout_of(
+        &["rooster", "change", "-s", "bank"],
+        "xxxx\nthird\n",
+        &rooster_file,
+    );
+
+    let history = stdout_of(&["rooster", "history", "bank"], "xxxx\n", &rooster_file);
+    assert!(history.contains("1: ********"));
+    assert!(!history.contains("second"));
+    let history = stdout_of(
+        &["rooster", "history", "bank", "--show"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert!(history.contains("1: second"));
+    assert!(history.contains("2: first"));
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "restore", "bank", "--version", "3"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    stdout_of(
+        &["rooster", "restore", "bank", "--version", "2"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert!(
+        stdout_of(&["rooster", "get", "-s", "bank"], "xxxx\n", &rooster_file)
+            .contains("Password: first")
+    );
+
+    // The replaced password can be restored in turn
+    let history = stdout_of(
+        &["rooster", "history", "bank", "--show"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert!(history.contains("1: third"));
+    assert!(history.contains("2: second"));
+    assert!(!history.contains("3: "));
+}
@@ -196,3 +196,192 @@ fn test_command_import_1password() {
     assert!(output_as_string.contains("yt@example.com"));
     assert!(output_as_string.contains("Youtube"));
 }
+
+#[test]
+fn test_command_import_keepass_xml() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file_xml = tempfile();
+    File::create(import_file_xml.clone())
+        .unwrap()
+        .write_all(
+            "<KeePassFile><Root><Group><Name>Root</Name>\
+             <Entry>\
+             <Times><LastModificationTime>2021-03-04T05:06:07Z</LastModificationTime></Times>\
+             <String><Key>Title</Key><Value>Youtube</Value></String>\
+             <String><Key>UserName</Key><Value>yt@example.com</Value></String>\
+             <String><Key>Password</Key><Value ProtectInMemory=\"True\">a&amp;b</Value></String>\
+             <History><Entry>\
+             <String><Key>Title</Key><Value>Youtube</Value></String>\
+             <String><Key>Password</Key><Value>old</Value></String>\
+             </Entry></History>\
+             </Entry>\
+             <Entry><String><Key>Title</Key><Value>Empty</Value></String></Entry>\
+             </Group></Root></KeePassFile>"
+                .as_bytes(),
+        )
+        .unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "keepass-xml",
+                import_file_xml.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Empty, invalid format, skipping"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("a&b"));
+    assert!(output_as_string.contains("yt@example.com"));
+}
+
+#[test]
+fn test_command_import_bitwarden_json() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file_json = tempfile();
+    File::create(import_file_json.clone()).unwrap().write_all(
+        "{\"encrypted\":false,\"items\":[{\"type\":1,\"name\":\"Youtube\",\"login\":{\"username\":\"yt@example.com\",\"password\":\"abcd\"},\"revisionDate\":\"2021-03-04T05:06:07.000Z\"},{\"type\":2,\"name\":\"Some note\"}]}".as_bytes()
+    ).unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "bitwarden-json",
+                import_file_json.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Some note, invalid format, skipping"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+    assert!(output_as_string.contains("yt@example.com"));
+}
+
+#[test]
+fn test_command_import_conflicts() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Github", "gh@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file_csv = tempfile();
+    File::create(import_file_csv.clone())
+        .unwrap()
+        .write_all("Youtube,yt@example.com,efgh\nGithub,gh@example.com,ijkl".as_bytes())
+        .unwrap();
+    let import_args = &[
+        "rooster",
+        "import",
+        "csv",
+        import_file_csv.as_path().to_str().unwrap(),
+    ];
+
+    // Invalid answers are asked again, and "s" keeps the password in the store
+    let mut io = CursorInputOutput::new("", "xxxx\nx\ns\no\n");
+    assert_eq!(0, main_with_args(import_args, &mut io, &rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube is already in your password store"));
+    assert!(output_as_string.contains("Github is already in your password store"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("abcd"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "github"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("ijkl"));
+
+    // Uppercase answers apply to all remaining conflicts, and identical passwords are skipped
+    let mut io = CursorInputOutput::new("", "xxxx\nM\n");
+    assert_eq!(0, main_with_args(import_args, &mut io, &rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube is already in your password store"));
+    assert!(!output_as_string.contains("Github is already in your password store"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("efgh"));
+}
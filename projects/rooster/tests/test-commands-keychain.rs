@@ -0,0 +1,39 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_from_keychain_falls_back_to_prompt() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Nothing was enrolled for this file, so the master password is asked for
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--from-keychain", "get", "-s", "youtube"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
@@ -0,0 +1,174 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use rooster::{main_with_args_and_config, Config};
+use std::path::PathBuf;
+
+fn key_file(contents: &[u8]) -> PathBuf {
+    let path = tempfile();
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_init_with_keyfile() {
+    let rooster_file = tempfile();
+    let key_file = key_file(b"some random bytes");
+    let key_file = key_file.to_str().unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--keyfile",
+                key_file,
+                "init",
+                "--force-for-tests"
+            ],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--keyfile",
+                key_file,
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // The master password isn't enough on its own
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("needs a key file"));
+
+    // Neither is another key file
+    let other_key_file = self::key_file(b"other random bytes");
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "--keyfile",
+                other_key_file.to_str().unwrap(),
+                "list"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("not the right password or key file"));
+
+    // The key file can also be set in the config
+    let config = Config {
+        keyfile: Some(PathBuf::from(key_file)),
+        ..Config::default()
+    };
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "get", "-s", "youtube"],
+            &mut io,
+            &rooster_file,
+            &config
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_command_keyfile_add_remove() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let empty_key_file = key_file(b"");
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "keyfile",
+                "add",
+                empty_key_file.to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("the key file is empty"));
+
+    let key_file = key_file(b"some random bytes");
+    let key_file = key_file.to_str().unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "keyfile", "add", key_file],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--keyfile", key_file, "keyfile", "remove"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "keyfile", "remove"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
@@ -0,0 +1,25 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_migrate_up_to_date() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "migrate", "--dry-run"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("up to date (version 8)"));
+}
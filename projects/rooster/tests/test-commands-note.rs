@@ -0,0 +1,88 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn stdout_of(args: &[&str], ttyin: &str, rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", ttyin);
+    assert_eq!(0, main_with_args(args, &mut io, rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned()
+}
+
+#[test]
+fn test_command_note_and_set_field() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Bank", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    assert!(stdout_of(&["rooster", "note", "bank"], "xxxx\n", &rooster_file).contains("no notes"));
+    stdout_of(
+        &["rooster", "note", "bank", "Security question: blue"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(
+        stdout_of(&["rooster", "note", "bank"], "xxxx\n", &rooster_file),
+        "Security question: blue\n"
+    );
+
+    stdout_of(
+        &[
+            "rooster",
+            "set-field",
+            "bank",
+            "URL",
+            "https://bank.example.com",
+        ],
+        "xxxx\n",
+        &rooster_file,
+    );
+    // Values that aren't given are read without showing them
+    stdout_of(
+        &["rooster", "set-field", "bank", "PIN"],
+        "xxxx\n1234\n",
+        &rooster_file,
+    );
+
+    let output = stdout_of(
+        &["rooster", "get", "--show", "--full", "bank"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert!(output.contains("URL: https://bank.example.com"));
+    assert!(output.contains("PIN: 1234"));
+    assert!(output.contains("Security question: blue"));
+
+    stdout_of(
+        &["rooster", "set-field", "--delete", "bank", "PIN"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    stdout_of(
+        &["rooster", "note", "--clear", "bank"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    let output = stdout_of(
+        &["rooster", "get", "--show", "--full", "bank"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert!(!output.contains("PIN"));
+    assert!(!output.contains("Notes"));
+    assert!(output.contains("URL: https://bank.example.com"));
+}
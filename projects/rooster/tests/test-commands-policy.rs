@@ -0,0 +1,85 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn rooster(args: &[&str], ttyin: &str, rooster_file: &std::path::PathBuf) -> (i32, String, String) {
+    let mut full_args = vec!["rooster"];
+    full_args.extend_from_slice(args);
+    let mut io = CursorInputOutput::new("", ttyin);
+    let code = main_with_args(&full_args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let error_as_vecu8 = io.stderr_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+        String::from_utf8_lossy(error_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+#[test]
+fn test_command_policy() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        rooster(&["init", "--force-for-tests"], "\nxxxx\n", &rooster_file).0
+    );
+    assert_eq!(
+        0,
+        rooster(&["add", "-s", "Bank", "me"], "xxxx\nabcd\n", &rooster_file).0
+    );
+
+    let (code, output, _) = rooster(&["policy", "bank"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("There is no policy for Bank"));
+
+    assert_eq!(
+        0,
+        rooster(
+            &["policy", "bank", "--pattern", "9999-9999"],
+            "xxxx\n",
+            &rooster_file
+        )
+        .0
+    );
+    let (code, output, _) = rooster(&["policy", "bank"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("looks like 9999-9999"));
+
+    // Regenerated passwords follow the policy
+    let (code, output, _) = rooster(&["regenerate", "-s", "bank"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("Following the policy of Bank"));
+    let (_, output, _) = rooster(&["get", "-s", "bank"], "xxxx\n", &rooster_file);
+    assert!(output
+        .split(|c: char| !c.is_ascii_digit() && c != '-')
+        .any(|word| word.len() == 9 && word.find('-') == Some(4)));
+
+    // Changed passwords that don't are saved with a warning
+    let (code, _, error) = rooster(&["change", "bank", "-s"], "xxxx\nabcd\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(error.contains("doesn't follow the policy of Bank"));
+    let (code, _, error) = rooster(
+        &["change", "bank", "-s"],
+        "xxxx\n1234-5678\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert!(!error.contains("policy"));
+
+    // Policies that can't generate passwords aren't saved
+    assert_eq!(
+        1,
+        rooster(
+            &["policy", "bank", "--charset", "z-a"],
+            "xxxx\n",
+            &rooster_file
+        )
+        .0
+    );
+    assert_eq!(
+        0,
+        rooster(&["policy", "bank", "--clear"], "xxxx\n", &rooster_file).0
+    );
+    let (_, output, _) = rooster(&["regenerate", "-s", "bank"], "xxxx\n", &rooster_file);
+    assert!(!output.contains("Following the policy"));
+}
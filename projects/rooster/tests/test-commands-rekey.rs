@@ -0,0 +1,133 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_rekey() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "rekey"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_command_rekey_kdf() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    // New files use Argon2id, which comes right after the version in the file
+    assert_eq!(std::fs::read(&rooster_file).unwrap()[4], 2);
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "rekey", "--kdf", "scrypt"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(std::fs::read(&rooster_file).unwrap()[4], 1);
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "rekey",
+                "--kdf",
+                "argon2id",
+                "--memory",
+                "8192",
+                "--iterations",
+                "3",
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Argon2id (8192 KiB of memory, 3 iterations"));
+    assert_eq!(
+        &std::fs::read(&rooster_file).unwrap()[4..13],
+        &[2, 0, 0, 0x20, 0, 0, 0, 0, 3]
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+
+    // Parameters that Argon2id refuses, or that seem too high
+    for args in [
+        &["rooster", "rekey", "--kdf", "argon2id", "--memory", "1"][..],
+        &[
+            "rooster",
+            "rekey",
+            "--kdf",
+            "argon2id",
+            "--iterations",
+            "11",
+        ][..],
+    ] {
+        assert_eq!(
+            1,
+            main_with_args(
+                args,
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+    }
+}
@@ -0,0 +1,60 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn run(args: &[&str], ttyin: &str, rooster_file: &std::path::PathBuf) -> (i32, String) {
+    let mut io = CursorInputOutput::new("", ttyin);
+    let code = main_with_args(args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+#[test]
+fn test_command_search_and_picker() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for (app, password) in [("Facebook", "fb"), ("Book", "bk"), ("eBook store", "eb")] {
+        let ttyin = format!("xxxx\n{}\n", password);
+        assert_eq!(
+            0,
+            run(&["rooster", "add", app, "me"], &ttyin, &rooster_file).0
+        );
+    }
+
+    let (code, output) = run(&["rooster", "search", "book"], "xxxx\n", &rooster_file);
+    assert_eq!(code, 0);
+    let lines = output.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("Book "));
+    assert!(lines[1].starts_with("eBook store "));
+    assert!(lines[2].starts_with("Facebook "));
+    assert_eq!(
+        run(&["rooster", "search", "xyz"], "xxxx\n", &rooster_file).0,
+        1
+    );
+
+    // Several matches are numbered best first
+    let (code, output) = run(&["rooster", "get", "-s", "boo"], "xxxx\n3\n", &rooster_file);
+    assert_eq!(code, 0);
+    assert!(output.contains("Password: fb"));
+
+    // Answering nothing, or not at all, cancels
+    assert_eq!(
+        run(&["rooster", "get", "-s", "boo"], "xxxx\n\n", &rooster_file).0,
+        1
+    );
+    assert_eq!(
+        run(&["rooster", "get", "-s", "boo"], "xxxx\n", &rooster_file).0,
+        1
+    );
+}
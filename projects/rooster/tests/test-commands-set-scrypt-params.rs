@@ -21,7 +21,8 @@ fn test_command_set_scrypt_params() {
         .unwrap()
         .read_to_end(&mut rooster_file_contents)
         .unwrap();
-    assert_eq!(&rooster_file_contents[4..13], &[12, 0, 0, 0, 8, 0, 0, 0, 1]);
+    // New files use Argon2id
+    assert_eq!(rooster_file_contents[4], 2);
 
     let mut io = CursorInputOutput::new("", "xxxx\n");
     assert_eq!(
@@ -63,5 +64,8 @@ fn test_command_set_scrypt_params() {
         .unwrap()
         .read_to_end(&mut rooster_file_contents)
         .unwrap();
-    assert_eq!(&rooster_file_contents[4..13], &[21, 0, 0, 0, 9, 0, 0, 0, 2]);
+    assert_eq!(
+        &rooster_file_contents[4..14],
+        &[1, 21, 0, 0, 0, 9, 0, 0, 0, 2]
+    );
 }
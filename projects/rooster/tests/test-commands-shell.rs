@@ -0,0 +1,129 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use rclio::{Completer, History, PromptProvider, ScriptedPrompts};
+use rooster::{main_with_args_and_config, Config};
+use rtoolbox::safe_string::SafeString;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::time::Duration;
+
+/// Scripted answers, where "(idle)" stands for a shell prompt that nobody answers in time
+struct IdlePrompts(ScriptedPrompts);
+
+impl PromptProvider for IdlePrompts {
+    fn read_line(&mut self) -> IoResult<String> {
+        self.0.read_line()
+    }
+
+    fn prompt_line(&mut self, prompt: &str) -> IoResult<String> {
+        self.0.prompt_line(prompt)
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        self.0.read_password()
+    }
+
+    fn prompt_password(&mut self, prompt: &str) -> IoResult<SafeString> {
+        self.0.prompt_password(prompt)
+    }
+
+    fn prompt_command(
+        &mut self,
+        prompt: &str,
+        _completer: &dyn Completer,
+        history: &mut History,
+        idle: Option<Duration>,
+    ) -> IoResult<String> {
+        match self.0.prompt_line(prompt)?.as_str() {
+            "(idle)" if idle.is_some() => Err(IoError::new(IoErrorKind::TimedOut, "timed out")),
+            command => {
+                history.push(command);
+                Ok(command.to_string())
+            }
+        }
+    }
+}
+
+#[test]
+fn test_command_shell() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The master password is only typed once for all commands
+    let mut io = CursorInputOutput::new(
+        "",
+        "xxxx\n\
+         list\n\
+         generate -s Github me@example.com\n\
+         get -s github\n\
+         delete github\n\
+         get -s nope\n\
+         quit\n\
+         list\n",
+    );
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "shell"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("No passwords on record yet"));
+    assert!(output_as_string.contains("me@example.com"));
+    let error_as_vecu8 = io.stderr_cursor.into_inner();
+    let error_as_string = String::from_utf8_lossy(error_as_vecu8.as_slice());
+    // Only some commands can be run in the shell, and errors don't leave it
+    assert!(error_as_string.contains("delete"));
+    assert!(error_as_string.contains("nope"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "github"], &mut io, &rooster_file)
+    );
+
+    // With --read-only, commands that change the password file are refused
+    let mut io = CursorInputOutput::new("", "xxxx\ngenerate -s Gitlab me\nlist\nquit\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "--read-only", "shell"], &mut io, &rooster_file)
+    );
+    let error_as_vecu8 = io.stderr_cursor.into_inner();
+    let error_as_string = String::from_utf8_lossy(error_as_vecu8.as_slice());
+    assert!(error_as_string.contains("`rooster generate` can change your password file"));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("Gitlab"));
+
+    // Locking asks for the master password again before the next command
+    let config = Config {
+        shell_lock_secs: 60,
+        ..Config::default()
+    };
+    let prompts = ScriptedPrompts::new(&["xxxx", "list", "(idle)", "get -s github", "xxxx"]);
+    let mut io = CursorInputOutput::with_prompts("", Box::new(IdlePrompts(prompts.clone())));
+    assert_eq!(
+        0,
+        main_with_args_and_config(&["rooster", "shell"], &mut io, &rooster_file, &config)
+    );
+    assert_eq!(
+        prompts.prompts(),
+        vec![
+            "Type your master password: ",
+            "rooster> ",
+            "rooster> ",
+            "rooster> ",
+            "Type your master password: ",
+            "rooster> ",
+        ]
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("locked after 60 seconds"));
+}
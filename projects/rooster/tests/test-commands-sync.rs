@@ -0,0 +1,109 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn rooster(args: &[&str], ttyin: &str, rooster_file: &PathBuf) -> i32 {
+    let mut full_args = vec!["rooster"];
+    full_args.extend_from_slice(args);
+    main_with_args(
+        &full_args,
+        &mut CursorInputOutput::new("", ttyin),
+        rooster_file,
+    )
+}
+
+fn get(app: &str, rooster_file: &PathBuf) -> Option<String> {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    if main_with_args(&["rooster", "get", "-s", app], &mut io, rooster_file) != 0 {
+        return None;
+    }
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    Some(String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned())
+}
+
+#[test]
+fn test_command_sync() {
+    let remote_dir = tempfile::tempdir().unwrap();
+    let status = Command::new("git")
+        .args(["init", "-q", "--bare"])
+        .arg(remote_dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let remote = remote_dir.path().to_str().unwrap();
+
+    // Sync must be set up first
+    let laptop = tempfile();
+    assert_eq!(
+        0,
+        rooster(&["init", "--force-for-tests"], "\nxxxx\n", &laptop)
+    );
+    assert_eq!(1, rooster(&["sync", "push"], "", &laptop));
+
+    assert_eq!(
+        0,
+        rooster(
+            &["add", "-s", "Youtube", "yt@example.com"],
+            "xxxx\nyt1\n",
+            &laptop
+        )
+    );
+    assert_eq!(0, rooster(&["sync", "init", remote], "", &laptop));
+    assert_eq!(0, rooster(&["sync", "push"], "", &laptop));
+
+    // Another machine gets the password file from the remote
+    let desktop = tempfile();
+    assert_eq!(0, rooster(&["sync", "init", remote], "", &desktop));
+    assert_eq!(0, rooster(&["sync", "pull"], "", &desktop));
+    assert!(get("youtube", &desktop).unwrap().contains("yt1"));
+
+    // Changes to different passwords are merged without asking
+    assert_eq!(
+        0,
+        rooster(
+            &["add", "-s", "Github", "gh@example.com"],
+            "xxxx\ngh1\n",
+            &laptop
+        )
+    );
+    assert_eq!(0, rooster(&["sync", "push"], "", &laptop));
+    assert_eq!(
+        0,
+        rooster(
+            &["add", "-s", "Gitlab", "gl@example.com"],
+            "xxxx\ngl1\n",
+            &desktop
+        )
+    );
+    assert_eq!(1, rooster(&["sync", "push"], "", &desktop));
+    assert_eq!(0, rooster(&["sync", "pull"], "xxxx\n", &desktop));
+    assert!(get("github", &desktop).unwrap().contains("gh1"));
+    assert!(get("gitlab", &desktop).unwrap().contains("gl1"));
+    assert_eq!(0, rooster(&["sync", "push"], "", &desktop));
+    assert_eq!(0, rooster(&["sync", "pull"], "", &laptop));
+    assert!(get("gitlab", &laptop).unwrap().contains("gl1"));
+
+    // Changes to the same password ask which one to keep
+    assert_eq!(
+        0,
+        rooster(&["change", "-s", "youtube"], "xxxx\nyt2\n", &laptop)
+    );
+    assert_eq!(0, rooster(&["sync", "push"], "", &laptop));
+    assert_eq!(
+        0,
+        rooster(&["change", "-s", "youtube"], "xxxx\nyt3\n", &desktop)
+    );
+    assert_eq!(0, rooster(&["delete", "github"], "xxxx\n", &desktop));
+    let mut io = CursorInputOutput::new("", "xxxx\nmaybe\nr\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "sync", "pull"], &mut io, &desktop)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube was changed"));
+    assert!(get("youtube", &desktop).unwrap().contains("yt2"));
+    assert!(get("github", &desktop).is_none());
+}
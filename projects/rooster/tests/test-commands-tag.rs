@@ -0,0 +1,85 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn stdout_of(args: &[&str], ttyin: &str, rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", ttyin);
+    assert_eq!(0, main_with_args(args, &mut io, rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned()
+}
+
+#[test]
+fn test_command_tag() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    stdout_of(
+        &["rooster", "add", "-s", "Bank", "me@example.com"],
+        "xxxx\nabcd\n",
+        &rooster_file,
+    );
+    stdout_of(
+        &["rooster", "add", "-s", "GitHub", "me"],
+        "xxxx\nefgh\n",
+        &rooster_file,
+    );
+
+    assert!(stdout_of(&["rooster", "tag", "bank"], "xxxx\n", &rooster_file).contains("no tags"));
+    stdout_of(
+        &["rooster", "tag", "bank", "work", "banking"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    stdout_of(
+        &["rooster", "tag", "github", "Work"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    // Tags already there are not added twice
+    stdout_of(&["rooster", "tag", "bank", "WORK"], "xxxx\n", &rooster_file);
+    assert_eq!(
+        stdout_of(&["rooster", "tag", "bank"], "xxxx\n", &rooster_file),
+        "work\nbanking\n"
+    );
+
+    let list = stdout_of(
+        &["rooster", "list", "--tag", "banking"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert!(list.contains("Bank") && list.contains("[work, banking]"));
+    assert!(!list.contains("GitHub"));
+    let list = stdout_of(&["rooster", "list", "-t", "work"], "xxxx\n", &rooster_file);
+    assert!(list.contains("Bank") && list.contains("GitHub"));
+    assert!(stdout_of(
+        &["rooster", "list", "--tag", "work", "--tag", "personal"],
+        "xxxx\n",
+        &rooster_file
+    )
+    .contains("No passwords have these tags"));
+
+    // Searches can match tags too
+    assert!(stdout_of(
+        &["rooster", "get", "-s", "tag:banking b"],
+        "xxxx\n1\n",
+        &rooster_file
+    )
+    .contains("Password: abcd"));
+
+    stdout_of(
+        &["rooster", "tag", "bank", "work", "--remove"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(
+        stdout_of(&["rooster", "tag", "bank"], "xxxx\n", &rooster_file),
+        "banking\n"
+    );
+}
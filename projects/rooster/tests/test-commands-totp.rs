@@ -0,0 +1,85 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_totp() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Github", "gh@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // There is no secret yet
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "totp", "github"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "set-totp", "github"],
+            &mut CursorInputOutput::new("", "xxxx\notpauth://hotp/Github?secret=JBSWY3DP\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-totp", "github"],
+            &mut CursorInputOutput::new(
+                "",
+                "xxxx\notpauth://totp/Github:gh@example.com?secret=JBSWY3DPEHPK3PXP&digits=8\n"
+            ),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "totp", "github"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let code = output_as_string.trim();
+    assert_eq!(code.len(), 8);
+    assert!(code.chars().all(|c| c.is_ascii_digit()));
+
+    // The secret is kept when the password changes
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "-s", "github"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "totp", "github"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
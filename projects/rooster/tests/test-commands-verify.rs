@@ -0,0 +1,111 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn init(rooster_file: &std::path::PathBuf) {
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_verify() {
+    let rooster_file = tempfile();
+    init(&rooster_file);
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "verify"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Format version: 8 (the latest)"));
+    assert!(output_as_string.contains("Key derivation: Argon2id"));
+    assert!(output_as_string.contains("Key file: not needed"));
+    assert!(output_as_string.contains("intact, and has 1 password(s)"));
+
+    // The header alone doesn't need the master password
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "verify", "--header-only"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Format version: 8"));
+    assert!(!output_as_string.contains("signature"));
+}
+
+#[test]
+fn test_command_verify_truncated() {
+    let rooster_file = tempfile();
+    init(&rooster_file);
+    let contents = std::fs::read(&rooster_file).unwrap();
+
+    for len in [2, 100, contents.len() - 5] {
+        std::fs::write(&rooster_file, &contents[..len]).unwrap();
+
+        let mut io = CursorInputOutput::new("", "");
+        assert_eq!(
+            1,
+            main_with_args(
+                &["rooster", "verify", "--header-only"],
+                &mut io,
+                &rooster_file
+            )
+        );
+        let output_as_vecu8 = io.stderr_cursor.into_inner();
+        let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+        assert!(output_as_string.contains("Your Rooster file is corrupted"));
+
+        // Other commands say so too, instead of asking for the master password
+        let mut io = CursorInputOutput::new("", "");
+        assert_eq!(
+            1,
+            main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+        );
+        let output_as_vecu8 = io.stderr_cursor.into_inner();
+        let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+        assert!(output_as_string.contains("Your Rooster file is corrupted"));
+        assert!(!output_as_string.contains("master password"));
+    }
+}
+
+#[test]
+fn test_command_verify_tampered() {
+    let rooster_file = tempfile();
+    init(&rooster_file);
+    let mut contents = std::fs::read(&rooster_file).unwrap();
+    let last = contents.len() - 1;
+    contents[last] ^= 1;
+    std::fs::write(&rooster_file, contents).unwrap();
+
+    // The header is fine, but the signature isn't, which isn't taken for a wrong password
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "verify"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("its signature doesn't match its contents"));
+    assert!(!output_as_string.contains("Let's try again"));
+}
@@ -0,0 +1,38 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_where_used() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    for (app, password) in [("Youtube", "abcd"), ("Google", "abcd"), ("Github", "efgh")] {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", app, "me@example.com"],
+                &mut CursorInputOutput::new("", &format!("xxxx\n{}\n", password)),
+                &rooster_file
+            )
+        );
+    }
+
+    let mut io = CursorInputOutput::new("", "xxxx\nabcd\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "where-used"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube"));
+    assert!(output_as_string.contains("Google"));
+    assert!(!output_as_string.contains("Github"));
+}
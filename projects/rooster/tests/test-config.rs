@@ -0,0 +1,61 @@
+use rooster::Config;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn tempfile() -> PathBuf {
+    tempfile::NamedTempFile::new().unwrap().path().to_path_buf()
+}
+
+#[test]
+fn test_config_defaults() {
+    let config_file = tempfile();
+    assert_eq!(Config::default(), Config::from_file(&config_file).unwrap());
+
+    File::create(&config_file)
+        .unwrap()
+        .write_all(b"{}")
+        .unwrap();
+    assert_eq!(Config::default(), Config::from_file(&config_file).unwrap());
+}
+
+#[test]
+fn test_config_unlock_min_delay() {
+    let config_file = tempfile();
+    File::create(&config_file)
+        .unwrap()
+        .write_all(br#"{"unlock_min_delay_ms": 2000}"#)
+        .unwrap();
+    assert_eq!(
+        2000,
+        Config::from_file(&config_file).unwrap().unlock_min_delay_ms
+    );
+
+    File::create(&config_file)
+        .unwrap()
+        .write_all(b"not json")
+        .unwrap();
+    assert!(Config::from_file(&config_file).is_err());
+}
+
+#[test]
+fn test_config_clipboard_clear_secs() {
+    let config_file = tempfile();
+    assert_eq!(
+        30,
+        Config::from_file(&config_file)
+            .unwrap()
+            .clipboard_clear_secs
+    );
+
+    File::create(&config_file)
+        .unwrap()
+        .write_all(br#"{"clipboard_clear_secs": 0}"#)
+        .unwrap();
+    assert_eq!(
+        0,
+        Config::from_file(&config_file)
+            .unwrap()
+            .clipboard_clear_secs
+    );
+}
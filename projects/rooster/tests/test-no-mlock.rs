@@ -0,0 +1,47 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_no_mlock() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    for args in [
+        &["rooster", "add", "-s", "Youtube", "yt@example.com"][..],
+        &[
+            "rooster",
+            "--no-mlock",
+            "add",
+            "-s",
+            "Github",
+            "gh@example.com",
+        ][..],
+    ] {
+        assert_eq!(
+            0,
+            main_with_args(
+                args,
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "--no-mlock", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Github"));
+    assert!(output_as_string.contains("Youtube"));
+}
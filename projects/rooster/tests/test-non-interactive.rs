@@ -0,0 +1,116 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn rooster(args: &[&str], ttyin: &str, rooster_file: &PathBuf) -> (i32, String, String) {
+    let mut full_args = vec!["rooster"];
+    full_args.extend_from_slice(args);
+    let mut io = CursorInputOutput::new("", ttyin);
+    let code = main_with_args(&full_args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let error_as_vecu8 = io.stderr_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+        String::from_utf8_lossy(error_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+// The environment is shared by the whole process, so this is the only test that sets it
+#[test]
+fn test_non_interactive() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        rooster(&["init", "--force-for-tests"], "\nxxxx\n", &rooster_file).0
+    );
+    assert_eq!(
+        0,
+        rooster(
+            &["add", "-s", "Github", "me"],
+            "xxxx\nabcd\n",
+            &rooster_file
+        )
+        .0
+    );
+
+    // Without a master password, it fails instead of asking, even if it could be typed
+    let (code, _, error) = rooster(
+        &["--non-interactive", "get", "-s", "github"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert!(error.contains("--non-interactive doesn't let me ask questions"));
+
+    std::env::set_var("ROOSTER_MASTER_PASSWORD", "xxxx");
+    let (code, output, _) = rooster(
+        &["--non-interactive", "get", "-s", "github"],
+        "",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert!(output.contains("abcd"));
+
+    // Questions other than the master password fail too
+    let (code, _, error) = rooster(
+        &["--non-interactive", "add", "-s", "Gitlab", "me"],
+        "abcd\n",
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert!(error.contains("--non-interactive doesn't let me ask questions"));
+
+    // A file takes precedence over the password itself, and a wrong password fails right away
+    let password_file = tempfile();
+    File::create(&password_file)
+        .unwrap()
+        .write_all(b"wrong\n")
+        .unwrap();
+    std::env::set_var("ROOSTER_MASTER_PASSWORD_FILE", &password_file);
+    let (code, _, error) = rooster(&["get", "-s", "github"], "xxxx\n", &rooster_file);
+    assert_eq!(1, code);
+    assert!(error.contains("the master password from ROOSTER_MASTER_PASSWORD_FILE does not unlock"));
+
+    // And a file descriptor takes precedence over both
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::IntoRawFd;
+
+        // Standard input and outputs are never taken over, since they'd be closed once read
+        std::env::set_var("ROOSTER_MASTER_PASSWORD_FD", "1");
+        let (code, _, error) = rooster(&["get", "-s", "github"], "xxxx\n", &rooster_file);
+        assert_eq!(1, code);
+        assert!(error.contains("1 is the standard input or output"));
+
+        File::create(&password_file)
+            .unwrap()
+            .write_all(b"xxxx\n")
+            .unwrap();
+        let fd = File::open(&password_file).unwrap().into_raw_fd();
+        std::env::set_var("ROOSTER_MASTER_PASSWORD_FD", fd.to_string());
+        let (code, output, _) = rooster(
+            &["--non-interactive", "get", "-s", "github"],
+            "",
+            &rooster_file,
+        );
+        assert_eq!(0, code);
+        assert!(output.contains("abcd"));
+
+        // The descriptor is closed once read, so it isn't read again, even if its number is reused
+        let (code, _, error) = rooster(
+            &["--non-interactive", "get", "-s", "github"],
+            "",
+            &rooster_file,
+        );
+        assert_eq!(1, code);
+        assert!(error.contains("it was already read"));
+        std::env::remove_var("ROOSTER_MASTER_PASSWORD_FD");
+    }
+
+    std::env::remove_var("ROOSTER_MASTER_PASSWORD_FILE");
+    std::env::remove_var("ROOSTER_MASTER_PASSWORD");
+}
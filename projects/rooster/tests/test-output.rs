@@ -0,0 +1,94 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::path::PathBuf;
+
+fn json(args: &[&str], rooster_file: &PathBuf) -> (i32, serde_json::Value) {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    let code = main_with_args(args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let start = output_as_string.find(['[', '{']).unwrap();
+    (
+        code,
+        serde_json::from_str(&output_as_string[start..]).unwrap(),
+    )
+}
+
+#[test]
+fn test_output_json() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Github", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Secrets are left out unless asked for
+    let (code, list) = json(&["rooster", "list", "--output", "json"], &rooster_file);
+    assert_eq!(0, code);
+    assert_eq!(list[0]["name"], "Github");
+    assert_eq!(list[0]["username"], "me@example.com");
+    assert!(list[0].get("password").is_none());
+
+    // The format can be given before the command too
+    let (code, list) = json(&["rooster", "--output", "json", "list"], &rooster_file);
+    assert_eq!(0, code);
+    assert_eq!(list[0]["name"], "Github");
+
+    let (code, list) = json(
+        &[
+            "rooster",
+            "search",
+            "git",
+            "--output",
+            "json",
+            "--show-secrets",
+        ],
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(list[0]["password"], "abcd");
+    let (code, list) = json(
+        &["rooster", "search", "nope", "--output", "json"],
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert_eq!(list, serde_json::json!([]));
+
+    let (code, password) = json(
+        &["rooster", "get", "github", "--output", "json"],
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(password["name"], "Github");
+    assert!(password.get("password").is_none());
+    let (code, password) = json(
+        &[
+            "rooster",
+            "get",
+            "github",
+            "--output",
+            "json",
+            "--show-secrets",
+        ],
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(password["password"], "abcd");
+
+    let (code, report) = json(&["rooster", "audit", "--output", "json"], &rooster_file);
+    assert_eq!(1, code);
+    assert_eq!(report["weak"][0]["app"], "Github");
+}
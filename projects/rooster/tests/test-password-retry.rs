@@ -1,6 +1,8 @@
 mod helpers;
 
 use crate::helpers::prelude::*;
+use rooster::{main_with_args_and_config, Config};
+use std::time::{Duration, Instant};
 
 #[test]
 fn test_password_retry_ok() {
@@ -45,3 +47,32 @@ fn test_password_retry_nok() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("Decryption of your Rooster file keeps failing"));
 }
+
+#[test]
+fn test_password_retry_min_delay() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let config = Config {
+        unlock_min_delay_ms: 1500,
+        ..Config::default()
+    };
+    let started_at = Instant::now();
+    assert_eq!(
+        0,
+        main_with_args_and_config(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "nok\nxxxx\n"),
+            &rooster_file,
+            &config
+        )
+    );
+    assert!(started_at.elapsed() >= Duration::from_millis(1500));
+}
@@ -0,0 +1,77 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use rclio::ScriptedPrompts;
+
+#[test]
+fn test_scripted_prompts() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let prompts = ScriptedPrompts::new(&["xxxx", "abcd"]);
+    let mut io = CursorInputOutput::with_prompts("", Box::new(prompts.clone()));
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Bank", "me"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        prompts.prompts(),
+        vec![
+            "Type your master password: ",
+            "What password do you want for \"Bank\"? "
+        ]
+    );
+    assert_eq!(prompts.remaining(), 0);
+
+    // Running out of answers fails instead of waiting for more
+    let prompts = ScriptedPrompts::new(&["xxxx"]);
+    let mut io = CursorInputOutput::with_prompts("", Box::new(prompts.clone()));
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "change", "bank"], &mut io, &rooster_file)
+    );
+    assert_eq!(prompts.prompts().len(), 2);
+
+    let prompts = ScriptedPrompts::new(&["xxxx"]);
+    let mut io = CursorInputOutput::with_prompts("", Box::new(prompts.clone()));
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "bank"], &mut io, &rooster_file)
+    );
+    let output = String::from_utf8_lossy(io.stdout_cursor.get_ref()).into_owned();
+    assert!(output.contains("Password: abcd"));
+}
+
+#[test]
+fn test_cursor_prompts() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Without other prompts, answers are read from the TTY cursor, and prompts written to the other
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    assert_eq!(io.ttyin_cursor.position(), 5);
+    let prompts = String::from_utf8_lossy(io.ttyout_cursor.get_ref()).into_owned();
+    assert_eq!(prompts, "Type your master password: ");
+}
@@ -0,0 +1,102 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::path::PathBuf;
+
+fn rooster(args: &[&str], ttyin: &str, rooster_file: &PathBuf) -> (i32, String, String) {
+    let mut full_args = vec!["rooster"];
+    full_args.extend_from_slice(args);
+    let mut io = CursorInputOutput::new("", ttyin);
+    let code = main_with_args(&full_args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let error_as_vecu8 = io.stderr_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+        String::from_utf8_lossy(error_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+#[test]
+fn test_read_only() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        rooster(&["init", "--force-for-tests"], "\nxxxx\n", &rooster_file).0
+    );
+    assert_eq!(
+        0,
+        rooster(
+            &["add", "-s", "Github", "me"],
+            "xxxx\nabcd\n",
+            &rooster_file
+        )
+        .0
+    );
+    let before = std::fs::read(&rooster_file).unwrap();
+
+    // Commands that read work, without saving the file
+    let (code, output, _) = rooster(
+        &["--read-only", "get", "-s", "github"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert!(output.contains("abcd"));
+    assert_eq!(before, std::fs::read(&rooster_file).unwrap());
+
+    // The file can't be written to at all
+    let mut permissions = std::fs::metadata(&rooster_file).unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&rooster_file, permissions).unwrap();
+    assert_eq!(
+        0,
+        rooster(&["list", "--read-only"], "xxxx\n", &rooster_file).0
+    );
+
+    // Commands that only change it in some cases are allowed in the others
+    let (code, output, _) = rooster(&["--read-only", "export", "json"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert!(output.contains("Github"));
+    assert_eq!(
+        0,
+        rooster(
+            &["--read-only", "migrate", "--dry-run"],
+            "xxxx\n",
+            &rooster_file
+        )
+        .0
+    );
+    let (_, _, error) = rooster(&["--read-only", "sync", "push"], "", &rooster_file);
+    assert!(!error.contains("--read-only"));
+
+    // Commands that change it are refused before asking for the master password
+    let (code, _, error) = rooster(&["--read-only", "delete", "github"], "", &rooster_file);
+    assert_eq!(1, code);
+    assert!(error.contains("`rooster delete` can change your password file"));
+    let (code, _, error) = rooster(
+        &[
+            "--read-only",
+            "export",
+            "json",
+            "--mapping",
+            "--profile",
+            "ci",
+        ],
+        "",
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert!(error.contains("`rooster export` can change your password file"));
+    assert_eq!(1, rooster(&["--read-only", "migrate"], "", &rooster_file).0);
+    assert_eq!(
+        1,
+        rooster(
+            &["--read-only", "init", "--force-for-tests"],
+            "",
+            &rooster_file
+        )
+        .0
+    );
+    assert_eq!(before, std::fs::read(&rooster_file).unwrap());
+}
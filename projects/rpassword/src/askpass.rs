@@ -0,0 +1,98 @@
+//! Asks for passwords with a separate program when there is no TTY, like OpenSSH and Git do
+
+use crate::{prompt_password, Error, Result};
+use rtoolbox::safe_string::SafeString;
+use std::ffi::OsStr;
+use std::io;
+use std::process::{Command, Stdio};
+
+/// Prompts for a password on the TTY, or with the program in `SSH_ASKPASS` if there is no TTY
+///
+/// This is what OpenSSH and Git do, so that users of a desktop session can type passwords in a
+/// dialog when the program was started without a terminal, ie: from a file manager or an IDE. If
+/// there is no TTY and `SSH_ASKPASS` isn't set, the `Error::NotATty` is returned.
+///
+/// ```no_run
+/// let password = rpassword::read_password_with_askpass_fallback("Your password: ").unwrap();
+/// ```
+pub fn read_password_with_askpass_fallback(prompt: impl ToString) -> Result<String> {
+    let prompt = prompt.to_string();
+    match prompt_password(prompt.as_str()) {
+        Err(Error::NotATty(err)) => match std::env::var_os("SSH_ASKPASS") {
+            Some(program) if !program.is_empty() => read_password_from_askpass(program, prompt),
+            _ => Err(Error::NotATty(err)),
+        },
+        result => result,
+    }
+}
+
+/// Prompts for a password on the TTY, or with the given askpass program if there is no TTY
+pub fn read_password_with_askpass_program(
+    prompt: impl ToString,
+    program: impl AsRef<OsStr>,
+) -> Result<String> {
+    let prompt = prompt.to_string();
+    match prompt_password(prompt.as_str()) {
+        Err(Error::NotATty(_)) => read_password_from_askpass(program, prompt),
+        result => result,
+    }
+}
+
+/// Runs an askpass program with the prompt as its argument and reads the password it prints
+///
+/// A program that exits with an error, usually because the user closed the dialog, gives
+/// `Error::Interrupted`.
+pub fn read_password_from_askpass(
+    program: impl AsRef<OsStr>,
+    prompt: impl ToString,
+) -> Result<String> {
+    let output = Command::new(program.as_ref())
+        .arg(prompt.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| {
+            Error::Io(io::Error::new(
+                err.kind(),
+                format!(
+                    "could not run askpass program {} ({})",
+                    program.as_ref().to_string_lossy(),
+                    err
+                ),
+            ))
+        })?;
+
+    let password = String::from_utf8(output.stdout).map_err(|_| Error::InvalidUtf8)?;
+    let mut password = SafeString::from_string(password);
+    if !output.status.success() {
+        return Err(Error::Interrupted);
+    }
+
+    if password.ends_with('\n') {
+        password.pop();
+        if password.ends_with('\r') {
+            password.pop();
+        }
+    }
+    Ok(password.into_inner())
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::read_password_from_askpass;
+    use crate::Error;
+
+    #[test]
+    fn can_read_from_askpass() {
+        assert_eq!(
+            read_password_from_askpass("echo", "my-password").unwrap(),
+            "my-password"
+        );
+        assert!(matches!(
+            read_password_from_askpass("false", "Your password: "),
+            Err(Error::Interrupted)
+        ));
+    }
+}
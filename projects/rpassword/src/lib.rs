@@ -1,6 +1,9 @@
 //! This library makes it easy to read passwords in a console application on all platforms, Unix,
 //! Windows, WASM, etc.
 //!
+//! On WASI, hermit and other WASM targets, there is no terminal to hide input on, so passwords are
+//! read from stdin and echoed as they are typed, unless the host hides them.
+//!
 //! Here's how you can read a password:
 //! ```no_run
 //! let password = rpassword::read_password().unwrap();
@@ -27,169 +30,486 @@
 //! let password = rpassword::prompt_password_from_bufread(&mut mock_input, &mut mock_output, "Your password: ").unwrap();
 //! println!("Your password is {}", password);
 //! ```
+//!
+//! If you need more options, like prompting on stderr or refusing passwords that are pasted instead
+//! of typed, you can use `PasswordInput`:
+//! ```no_run
+//! let password = rpassword::PasswordInput::new()
+//!     .prompt("Your password: ")
+//!     .output(rpassword::PromptOutput::Stderr)
+//!     .reject_paste(true)
+//!     .read()
+//!     .unwrap();
+//! println!("Your password is {}", password);
+//! ```
+//!
+//! Command line tools often let users choose where passwords come from, ie: with a
+//! `--password-from` option. `read_password_from_source` reads from the sources it is given, in
+//! order of preference, among environment variables, file descriptors, files and the TTY:
+//! ```no_run
+//! let password = rpassword::read_password_from_source("env:MY_PASSWORD,tty").unwrap();
+//! println!("Your password is {}", password);
+//! ```
+//!
+//! Programs started from a desktop session may have no terminal at all. Like OpenSSH and Git,
+//! `read_password_with_askpass_fallback` then asks with the program named in `SSH_ASKPASS`:
+//! ```no_run
+//! let password = rpassword::read_password_with_askpass_fallback("Your password: ").unwrap();
+//! println!("Your password is {}", password);
+//! ```
+//!
+//! When either a password or something outside of the terminal, like touching a security key,
+//! completes authentication, `prompt_presence` waits for whichever comes first:
+//! ```no_run
+//! let (touched, signal) = std::sync::mpsc::channel::<()>();
+//! // Hand `touched` over to the code that waits for the security key...
+//! let presence = rpassword::prompt_presence("Touch your security key or type your PIN: ", &signal);
+//! ```
+//!
+//! Errors tell apart the situations that call for different reactions. When there is no terminal
+//! to read from, like in a cron job, reading from the TTY fails with `Error::NotATty`, whose
+//! `NoTty` details tell you whether falling back to stdin makes sense:
+//! ```no_run
+//! use rpassword::{Error, NoTty};
+//!
+//! let password = match rpassword::read_password() {
+//!     Ok(password) => password,
+//!     Err(Error::NotATty(err)) => match NoTty::from_io_error(&err) {
+//!         Some(no_tty) if no_tty.stdin_is_pipe => {
+//!             rpassword::read_password_from_bufread(&mut std::io::stdin().lock()).unwrap()
+//!         }
+//!         _ => panic!("{}", err),
+//!     },
+//!     Err(Error::Eof) => std::process::exit(1),
+//!     Err(err) => panic!("{}", err),
+//! };
+//! println!("Your password is {}", password);
+//! ```
+//!
+//! Reading waits for its turn while another component, ie: an `rprompt` prompt on another thread,
+//! is reading from the terminal, see `rtoolbox::tty_lock`.
+
+mod askpass;
+mod masked;
+mod presence;
+mod source;
 
+use masked::StrengthMeter;
+use rtoolbox::atty;
+use rtoolbox::bracketed_paste::{self, PastePolicy};
 use rtoolbox::fix_line_issues::fix_line_issues;
-use rtoolbox::print_tty::{print_tty, print_writer};
+use rtoolbox::print_tty::{print_to, print_tty, print_writer};
 use rtoolbox::safe_string::SafeString;
+use rtoolbox::tty_lock::{self, TtyGuard};
+use rtoolbox::writer_println;
 use std::io::{BufRead, Write};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-#[cfg(target_family = "wasm")]
-mod wasm {
-    use std::io::{self, BufRead};
+pub use askpass::{
+    read_password_from_askpass, read_password_with_askpass_fallback,
+    read_password_with_askpass_program,
+};
+pub use presence::{prompt_presence, Presence};
+#[cfg(feature = "clipboard")]
+pub use rtoolbox::clipboard;
+pub use rtoolbox::error::{Error, Result};
+pub use rtoolbox::print_tty::PromptOutput;
+#[cfg(feature = "locked-memory")]
+pub use rtoolbox::secure_memory;
+#[cfg(feature = "async")]
+pub use rtoolbox::unblock::Unblock;
+pub use source::{read_password_from_source, PasswordSource};
 
-    /// Reads a password from the TTY
-    pub fn read_password() -> std::io::Result<String> {
-        let tty = std::fs::File::open("/dev/tty")?;
-        let mut reader = io::BufReader::new(tty);
+/// Details of an `Error::NotATty`, wrapped in its `std::io::Error`
+///
+/// The fields describe the environment, so that callers can choose a fallback, like reading the
+/// password from stdin when it is a pipe.
+#[derive(Debug)]
+pub struct NoTty {
+    /// Whether stdin is a pipe, ie: `echo $PASSWORD | my-program`
+    pub stdin_is_pipe: bool,
+    /// Whether stdin is a terminal
+    pub stdin_is_tty: bool,
+    /// Whether the process has a controlling terminal (a console on Windows), as far as we can tell
+    pub has_controlling_terminal: bool,
+    source: std::io::Error,
+}
 
-        read_password_from_fd_with_hidden_input(&mut reader)
+impl NoTty {
+    /// Returns the `NoTty` error wrapped in `err`, if any
+    pub fn from_io_error(err: &std::io::Error) -> Option<&NoTty> {
+        err.get_ref()?.downcast_ref::<NoTty>()
     }
+}
 
-    /// Reads a password from a given file descriptor
-    fn read_password_from_fd_with_hidden_input(
-        reader: &mut impl BufRead,
-    ) -> std::io::Result<String> {
-        let mut password = super::SafeString::new();
+impl std::fmt::Display for NoTty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no terminal available ({})", self.source)
+    }
+}
 
-        reader.read_line(&mut password)?;
-        super::fix_line_issues(password.into_inner())
+impl std::error::Error for NoTty {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
     }
 }
 
-#[cfg(target_family = "unix")]
-mod unix {
-    use libc::{c_int, tcsetattr, termios, ECHO, ECHONL, TCSANOW};
-    use std::io::{self, BufRead};
-    use std::mem;
-    use std::os::unix::io::AsRawFd;
+/// Turns the error we get when the TTY can't be opened into an `Error::NotATty` with `NoTty`
+/// details, keeping its kind
+fn tty_error(err: std::io::Error) -> Error {
+    let err = match Error::from_tty_error(err) {
+        Error::NotATty(err) => err,
+        err => return err,
+    };
 
-    struct HiddenInput {
-        fd: i32,
-        term_orig: termios,
+    let kind = err.kind();
+    let no_tty = NoTty {
+        stdin_is_pipe: stdin_is_pipe(),
+        stdin_is_tty: atty::is(atty::Stream::Stdin),
+        has_controlling_terminal: has_controlling_terminal(&err),
+        source: err,
+    };
+    Error::NotATty(std::io::Error::new(kind, no_tty))
+}
+
+/// Waits for other components reading from the terminal, ie: an `rprompt` prompt on another
+/// thread, to be done
+fn lock_tty() -> TtyGuard {
+    tty_lock::acquire("rpassword")
+}
+
+/// WASI, hermit and other WASM targets have no terminal we can configure, so passwords are read
+/// from stdin as is, which means they are echoed unless the host hides them
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+mod stdio {
+    use super::PastePolicy;
+    use std::io;
+    use std::sync::atomic::AtomicBool;
+
+    /// Reads a password from stdin, without hiding it
+    pub fn read_password() -> super::Result<String> {
+        let _tty = super::lock_tty();
+        read_password_with_paste_policy(PastePolicy::Allow)
+    }
+
+    /// Reads a password from stdin, handling pasted content according to `paste_policy`
+    pub fn read_password_with_paste_policy(paste_policy: PastePolicy) -> super::Result<String> {
+        super::read_password_from_bufread_with_paste_policy(&mut io::stdin().lock(), paste_policy)
     }
 
-    impl HiddenInput {
-        fn new(fd: i32) -> io::Result<HiddenInput> {
-            // Make two copies of the terminal settings. The first one will be modified
-            // and the second one will act as a backup for when we want to set the
-            // terminal back to its original state.
-            let mut term = safe_tcgetattr(fd)?;
-            let term_orig = safe_tcgetattr(fd)?;
+    /// Reads a password from stdin, without masking it since the terminal can't be read key by
+    /// key
+    pub fn read_masked_password(
+        prompt: &str,
+        _mask: char,
+        _strength_meter: Option<&super::StrengthMeter>,
+        paste_policy: PastePolicy,
+        _cancel_token: Option<&AtomicBool>,
+        _redraw_handle: &super::RedrawHandle,
+    ) -> super::Result<String> {
+        super::print_tty(prompt).map_err(super::tty_error)?;
+        read_password_with_paste_policy(paste_policy)
+    }
+
+    pub fn stdin_is_pipe() -> bool {
+        false
+    }
 
-            // Hide the password. This is what makes this function useful.
-            term.c_lflag &= !ECHO;
+    pub fn has_controlling_terminal(_err: &io::Error) -> bool {
+        false
+    }
+}
 
-            // But don't hide the NL character when the user hits ENTER.
-            term.c_lflag |= ECHONL;
+#[cfg(target_family = "unix")]
+mod unix {
+    use super::{bracketed_paste, IdleAction, PastePolicy};
+    use rtoolbox::atty::{self, Stream};
+    use rtoolbox::raw_mode::{self, RawMode, RawModeGuard};
+    use std::fs::File;
+    use std::io::{self, BufRead, Read};
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
 
-            // Save the settings for now.
-            io_result(unsafe { tcsetattr(fd, TCSANOW, &term) })?;
+    /// How often the cancel token is checked while waiting for input
+    const CANCEL_TICK: Duration = Duration::from_millis(100);
 
-            Ok(HiddenInput { fd, term_orig })
+    pub fn stdin_is_pipe() -> bool {
+        let mut stat = mem::MaybeUninit::<libc::stat>::uninit();
+        if unsafe { libc::fstat(libc::STDIN_FILENO, stat.as_mut_ptr()) } != 0 {
+            return false;
         }
+        let stat = unsafe { stat.assume_init() };
+        stat.st_mode & libc::S_IFMT == libc::S_IFIFO
     }
 
-    impl Drop for HiddenInput {
-        fn drop(&mut self) {
-            // Set the the mode back to normal
-            unsafe {
-                tcsetattr(self.fd, TCSANOW, &self.term_orig);
-            }
+    pub fn has_controlling_terminal(err: &io::Error) -> bool {
+        // ENXIO means there is no controlling terminal, while ENOENT means /dev/tty itself is
+        // missing, which happens in minimal containers even if the standard streams are terminals.
+        match err.raw_os_error() {
+            Some(libc::ENXIO) => false,
+            _ => [Stream::Stdin, Stream::Stdout, Stream::Stderr]
+                .iter()
+                .any(|stream| atty::is(*stream)),
         }
     }
 
-    /// Turns a C function return into an IO Result
-    fn io_result(ret: c_int) -> std::io::Result<()> {
-        match ret {
-            0 => Ok(()),
-            _ => Err(std::io::Error::last_os_error()),
-        }
+    /// Reads a password from the TTY
+    pub fn read_password() -> super::Result<String> {
+        let _tty = super::lock_tty();
+        read_password_with_paste_policy(PastePolicy::Allow)
     }
 
-    fn safe_tcgetattr(fd: c_int) -> std::io::Result<termios> {
-        let mut term = mem::MaybeUninit::<termios>::uninit();
-        io_result(unsafe { ::libc::tcgetattr(fd, term.as_mut_ptr()) })?;
-        Ok(unsafe { term.assume_init() })
+    /// Reads a password from the TTY, handling pasted content according to `paste_policy`
+    pub fn read_password_with_paste_policy(paste_policy: PastePolicy) -> super::Result<String> {
+        read_password_with_idle_timeout(paste_policy, None, None, &super::RedrawHandle::default())
     }
 
-    /// Reads a password from the TTY
-    pub fn read_password() -> std::io::Result<String> {
-        let tty = std::fs::File::open("/dev/tty")?;
+    /// Reads a password from the TTY, clearing what was typed if it isn't submitted in time, and
+    /// giving up once `cancel_token` is set
+    pub fn read_password_with_idle_timeout(
+        paste_policy: PastePolicy,
+        idle_timeout: Option<(Duration, IdleAction)>,
+        cancel_token: Option<&AtomicBool>,
+        redraw_handle: &super::RedrawHandle,
+    ) -> super::Result<String> {
+        let tty = File::open("/dev/tty").map_err(super::tty_error)?;
         let fd = tty.as_raw_fd();
         let mut reader = io::BufReader::new(tty);
 
-        read_password_from_fd_with_hidden_input(&mut reader, fd)
+        Ok(read_password_from_fd_with_hidden_input(
+            &mut reader,
+            fd,
+            paste_policy,
+            idle_timeout,
+            cancel_token,
+            redraw_handle,
+        )?)
+    }
+
+    /// Reads a password from the TTY key by key, echoing `mask` for each character typed
+    pub fn read_masked_password(
+        prompt: &str,
+        mask: char,
+        strength_meter: Option<&super::StrengthMeter>,
+        paste_policy: PastePolicy,
+        cancel_token: Option<&AtomicBool>,
+        redraw_handle: &super::RedrawHandle,
+    ) -> super::Result<String> {
+        let tty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(super::tty_error)?;
+        let mut writer = tty.try_clone()?;
+        let _raw_mode = RawModeGuard::new(tty.as_raw_fd(), RawMode::Raw)?;
+
+        super::read_masked_password_in_raw_mode(
+            &mut CancellableReader { tty, cancel_token },
+            &mut writer,
+            prompt,
+            mask,
+            strength_meter,
+            paste_policy,
+            redraw_handle,
+        )
     }
 
     /// Reads a password from a given file descriptor
     fn read_password_from_fd_with_hidden_input(
         reader: &mut impl BufRead,
         fd: i32,
+        paste_policy: PastePolicy,
+        idle_timeout: Option<(Duration, IdleAction)>,
+        cancel_token: Option<&AtomicBool>,
+        redraw_handle: &super::RedrawHandle,
     ) -> std::io::Result<String> {
         let mut password = super::SafeString::new();
 
-        let hidden_input = HiddenInput::new(fd)?;
+        let hidden_input = RawModeGuard::new(fd, RawMode::Hidden)?;
 
-        reader.read_line(&mut password)?;
+        // With bracketed paste, we can tell pasted content apart from typed content, which
+        // lets us read multi-line pastes at once.
+        let bracketed_paste = bracketed_paste::is_supported();
+        if bracketed_paste {
+            super::print_tty(bracketed_paste::ENABLE)?;
+        }
+
+        let read_result = match (idle_timeout, cancel_token) {
+            (None, None) => Ok(()),
+            _ => wait_for_line(fd, idle_timeout, cancel_token, redraw_handle),
+        }
+        .and_then(|_| bracketed_paste::read_line(reader, &mut password, paste_policy));
+
+        let disable_result = match bracketed_paste {
+            true => super::print_tty(bracketed_paste::DISABLE),
+            false => Ok(()),
+        };
 
         std::mem::drop(hidden_input);
 
+        read_result?;
+        disable_result?;
+
         super::fix_line_issues(password.into_inner())
     }
+
+    /// Waits until a line is submitted, clearing what was typed each time the idle timeout
+    /// passes without one, and giving up once `cancel_token` is set
+    fn wait_for_line(
+        fd: i32,
+        idle_timeout: Option<(Duration, IdleAction)>,
+        cancel_token: Option<&AtomicBool>,
+        redraw_handle: &super::RedrawHandle,
+    ) -> io::Result<()> {
+        let mut idle_since = Instant::now();
+        loop {
+            if is_cancelled(cancel_token) {
+                return Err(cancelled(fd));
+            }
+
+            let idle_left = idle_timeout.map(|(timeout, _)| {
+                timeout
+                    .checked_sub(idle_since.elapsed())
+                    .unwrap_or_default()
+            });
+            let tick = match (idle_left, cancel_token) {
+                (Some(idle_left), Some(_)) => idle_left.min(CANCEL_TICK),
+                (Some(idle_left), None) => idle_left,
+                (None, _) => CANCEL_TICK,
+            };
+            // With hidden input, the terminal is in canonical mode, so the TTY is only readable
+            // once a full line is there.
+            if raw_mode::wait_for_key(fd, tick)? {
+                return Ok(());
+            }
+
+            let action = match idle_timeout {
+                Some((timeout, action)) if idle_since.elapsed() >= timeout => action,
+                _ => continue,
+            };
+            // What was typed waits in the input queue until ENTER is hit, so this clears it.
+            if unsafe { libc::tcflush(fd, libc::TCIFLUSH) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            match action {
+                IdleAction::Reprompt => {
+                    redraw_handle.reprompt()?;
+                    idle_since = Instant::now();
+                }
+                IdleAction::Abort => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "no password was submitted in time",
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Reads the TTY key by key, failing once `cancel_token` is set
+    struct CancellableReader<'a> {
+        tty: File,
+        cancel_token: Option<&'a AtomicBool>,
+    }
+
+    impl<'a> Read for CancellableReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.cancel_token.is_some() {
+                let fd = self.tty.as_raw_fd();
+                while !raw_mode::wait_for_key(fd, CANCEL_TICK)? {
+                    if is_cancelled(self.cancel_token) {
+                        return Err(cancelled(fd));
+                    }
+                }
+            }
+            self.tty.read(buf)
+        }
+    }
+
+    fn is_cancelled(cancel_token: Option<&AtomicBool>) -> bool {
+        cancel_token.map_or(false, |cancel_token| cancel_token.load(Ordering::SeqCst))
+    }
+
+    /// Clears what was typed, and returns the error for a cancelled read
+    fn cancelled(fd: i32) -> io::Error {
+        unsafe { libc::tcflush(fd, libc::TCIFLUSH) };
+        io::Error::new(
+            io::ErrorKind::Interrupted,
+            "reading the password was cancelled",
+        )
+    }
 }
 
 #[cfg(target_family = "windows")]
 mod windows {
-    use std::io::{self, BufReader};
+    use super::PastePolicy;
+    use rtoolbox::console::ConsoleReader;
+    use rtoolbox::raw_mode::{RawMode, RawModeGuard};
     use std::io::BufRead;
-    use std::os::windows::io::FromRawHandle;
-    use winapi::shared::minwindef::LPDWORD;
-    use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
-    use winapi::um::fileapi::{CreateFileA, OPEN_EXISTING};
+    use std::io::{self, BufReader};
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use std::sync::atomic::AtomicBool;
+    use winapi::um::fileapi::{CreateFileA, GetFileType, OPEN_EXISTING};
     use winapi::um::handleapi::INVALID_HANDLE_VALUE;
-    use winapi::um::wincon::{ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT};
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::winbase::{FILE_TYPE_PIPE, STD_INPUT_HANDLE};
+    use winapi::um::wincon::GetConsoleWindow;
     use winapi::um::winnt::{
         FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE, HANDLE,
     };
 
-    struct HiddenInput {
-        mode: u32,
-        handle: HANDLE,
+    /// Reads a password from the TTY
+    pub fn read_password() -> super::Result<String> {
+        let _tty = super::lock_tty();
+        read_password_with_paste_policy(PastePolicy::Allow)
     }
 
-    impl HiddenInput {
-        fn new(handle: HANDLE) -> io::Result<HiddenInput> {
-            let mut mode = 0;
-
-            // Get the old mode so we can reset back to it when we are done
-            if unsafe { GetConsoleMode(handle, &mut mode as LPDWORD) } == 0 {
-                return Err(std::io::Error::last_os_error());
-            }
-
-            // We want to be able to read line by line, and we still want backspace to work
-            let new_mode_flags = ENABLE_LINE_INPUT | ENABLE_PROCESSED_INPUT;
-            if unsafe { SetConsoleMode(handle, new_mode_flags) } == 0 {
-                return Err(std::io::Error::last_os_error());
-            }
-
-            Ok(HiddenInput { mode, handle })
-        }
+    /// Reads a password from the TTY, handling pasted content according to `paste_policy`
+    pub fn read_password_with_paste_policy(paste_policy: PastePolicy) -> super::Result<String> {
+        let console = open_console(b"CONIN$\x00").map_err(super::tty_error)?;
+        let handle = console.as_raw_handle() as HANDLE;
+        let mut stream = BufReader::new(ConsoleReader::new(console));
+        Ok(read_password_from_handle_with_hidden_input(
+            &mut stream,
+            handle,
+            paste_policy,
+        )?)
     }
 
-    impl Drop for HiddenInput {
-        fn drop(&mut self) {
-            // Set the the mode back to normal
-            unsafe {
-                SetConsoleMode(self.handle, self.mode);
-            }
-        }
+    /// Reads a password from the TTY key by key, echoing `mask` for each character typed
+    pub fn read_masked_password(
+        prompt: &str,
+        mask: char,
+        strength_meter: Option<&super::StrengthMeter>,
+        paste_policy: PastePolicy,
+        _cancel_token: Option<&AtomicBool>,
+        redraw_handle: &super::RedrawHandle,
+    ) -> super::Result<String> {
+        let console = open_console(b"CONIN$\x00").map_err(super::tty_error)?;
+        let mut writer = open_console(b"CONOUT$\x00").map_err(super::tty_error)?;
+        // The console is only closed once the guard is dropped
+        let _raw_mode =
+            unsafe { RawModeGuard::new(console.as_raw_handle() as HANDLE, RawMode::Raw)? };
+
+        super::read_masked_password_in_raw_mode(
+            &mut ConsoleReader::new(console),
+            &mut writer,
+            prompt,
+            mask,
+            strength_meter,
+            paste_policy,
+            redraw_handle,
+        )
     }
 
-    /// Reads a password from the TTY
-    pub fn read_password() -> std::io::Result<String> {
+    pub fn open_console(name: &[u8]) -> io::Result<std::fs::File> {
         let handle = unsafe {
             CreateFileA(
-                b"CONIN$\x00".as_ptr() as *const i8,
+                name.as_ptr() as *const i8,
                 GENERIC_READ | GENERIC_WRITE,
                 FILE_SHARE_READ | FILE_SHARE_WRITE,
                 std::ptr::null_mut(),
@@ -200,23 +520,24 @@ mod windows {
         };
 
         if handle == INVALID_HANDLE_VALUE {
-            return Err(std::io::Error::last_os_error());
+            return Err(io::Error::last_os_error());
         }
 
-        let mut stream = BufReader::new(unsafe { std::fs::File::from_raw_handle(handle) });
-        read_password_from_handle_with_hidden_input(&mut stream, handle)
+        Ok(unsafe { std::fs::File::from_raw_handle(handle) })
     }
 
     /// Reads a password from a given file handle
     fn read_password_from_handle_with_hidden_input(
         reader: &mut impl BufRead,
         handle: HANDLE,
+        paste_policy: PastePolicy,
     ) -> io::Result<String> {
         let mut password = super::SafeString::new();
 
-        let hidden_input = HiddenInput::new(handle)?;
+        // The handle belongs to `reader`, which outlives the guard
+        let hidden_input = unsafe { RawModeGuard::new(handle, RawMode::Hidden)? };
 
-        let reader_return = reader.read_line(&mut password);
+        let reader_return = super::bracketed_paste::read_line(reader, &mut password, paste_policy);
 
         // Newline for windows which otherwise prints on the same line.
         println!();
@@ -229,21 +550,96 @@ mod windows {
 
         super::fix_line_issues(password.into_inner())
     }
+
+    pub fn stdin_is_pipe() -> bool {
+        unsafe { GetFileType(GetStdHandle(STD_INPUT_HANDLE)) == FILE_TYPE_PIPE }
+    }
+
+    pub fn has_controlling_terminal(_err: &io::Error) -> bool {
+        !unsafe { GetConsoleWindow() }.is_null()
+    }
 }
 
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+use stdio::{has_controlling_terminal, stdin_is_pipe};
+#[cfg(target_family = "unix")]
+use unix::{has_controlling_terminal, stdin_is_pipe};
+#[cfg(target_family = "windows")]
+use windows::{has_controlling_terminal, stdin_is_pipe};
+
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+pub use stdio::read_password;
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+use stdio::{read_masked_password, read_password_with_paste_policy};
 #[cfg(target_family = "unix")]
 pub use unix::read_password;
-#[cfg(target_family = "wasm")]
-pub use wasm::read_password;
+#[cfg(target_family = "unix")]
+use unix::{read_masked_password, read_password_with_idle_timeout};
 #[cfg(target_family = "windows")]
 pub use windows::read_password;
+#[cfg(target_family = "windows")]
+use windows::{read_masked_password, read_password_with_paste_policy};
+
+/// Idle timeouts and cancel tokens need to know when a line is submitted, which only Unix
+/// terminals tell us
+#[cfg(not(target_family = "unix"))]
+fn read_password_with_idle_timeout(
+    paste_policy: PastePolicy,
+    _idle_timeout: Option<(Duration, IdleAction)>,
+    _cancel_token: Option<&AtomicBool>,
+    _redraw_handle: &RedrawHandle,
+) -> Result<String> {
+    read_password_with_paste_policy(paste_policy)
+}
+
+/// Reads a masked password from a TTY that was just put in raw mode, handling bracketed paste
+///
+/// Raw mode is expected to stay on until this returns, so that bracketed paste is turned off first.
+#[cfg(any(target_family = "unix", target_family = "windows"))]
+fn read_masked_password_in_raw_mode(
+    reader: &mut impl std::io::Read,
+    writer: &mut impl Write,
+    prompt: &str,
+    mask: char,
+    strength_meter: Option<&StrengthMeter>,
+    paste_policy: PastePolicy,
+    redraw_handle: &RedrawHandle,
+) -> Result<String> {
+    let bracketed_paste = bracketed_paste::is_supported();
+    if bracketed_paste {
+        print_writer(writer, bracketed_paste::ENABLE)?;
+    }
+
+    let password = masked::read_masked(
+        reader,
+        writer,
+        prompt,
+        mask,
+        strength_meter,
+        paste_policy,
+        redraw_handle,
+    );
+
+    if bracketed_paste {
+        print_writer(writer, bracketed_paste::DISABLE)?;
+    }
+
+    Ok(password?)
+}
 
 /// Reads a password from anything that implements BufRead
-pub fn read_password_from_bufread(reader: &mut impl BufRead) -> std::io::Result<String> {
+pub fn read_password_from_bufread(reader: &mut impl BufRead) -> Result<String> {
+    read_password_from_bufread_with_paste_policy(reader, PastePolicy::Allow)
+}
+
+fn read_password_from_bufread_with_paste_policy(
+    reader: &mut impl BufRead,
+    paste_policy: PastePolicy,
+) -> Result<String> {
     let mut password = SafeString::new();
-    reader.read_line(&mut password)?;
+    bracketed_paste::read_line(reader, &mut password, paste_policy)?;
 
-    fix_line_issues(password.into_inner())
+    Ok(fix_line_issues(password.into_inner())?)
 }
 
 /// Prompts on the TTY and then reads a password from anything that implements BufRead
@@ -251,14 +647,420 @@ pub fn prompt_password_from_bufread(
     reader: &mut impl BufRead,
     writer: &mut impl Write,
     prompt: impl ToString,
-) -> std::io::Result<String> {
-    print_writer(writer, prompt.to_string().as_str())
-        .and_then(|_| read_password_from_bufread(reader))
+) -> Result<String> {
+    print_writer(writer, prompt.to_string().as_str())?;
+    read_password_from_bufread(reader)
 }
 
 /// Prompts on the TTY and then reads a password from TTY
-pub fn prompt_password(prompt: impl ToString) -> std::io::Result<String> {
-    print_tty(prompt.to_string().as_str()).and_then(|_| read_password())
+pub fn prompt_password(prompt: impl ToString) -> Result<String> {
+    let _tty = lock_tty();
+    print_tty(prompt.to_string().as_str()).map_err(tty_error)?;
+    read_password()
+}
+
+/// Like `prompt_password`, but reads the password on another thread, so that async code can await
+/// it without blocking other tasks
+///
+/// ```no_run
+/// async fn login() {
+///     let password = rpassword::prompt_password_async("Your password: ").await.unwrap();
+/// }
+/// ```
+#[cfg(feature = "async")]
+pub fn prompt_password_async(prompt: impl ToString) -> Unblock<Result<String>> {
+    let prompt = prompt.to_string();
+    rtoolbox::unblock::unblock(move || prompt_password(prompt))
+}
+
+type CharPredicate = Arc<dyn Fn(char) -> bool + Send + Sync>;
+
+/// Reads a password with options, for when `prompt_password` and `read_password` aren't enough
+#[derive(Clone)]
+pub struct PasswordInput {
+    prompt: Option<String>,
+    output: PromptOutput,
+    paste_policy: PastePolicy,
+    idle_timeout: Option<(Duration, IdleAction)>,
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    allowed_chars: Option<CharPredicate>,
+    invalid_message: Option<String>,
+    mask: Option<char>,
+    strength_meter: Option<StrengthMeter>,
+    cancel_token: Option<Arc<AtomicBool>>,
+    redraw_handle: RedrawHandle,
+}
+
+impl std::fmt::Debug for PasswordInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordInput")
+            .field("prompt", &self.prompt)
+            .field("output", &self.output)
+            .field("paste_policy", &self.paste_policy)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("min_len", &self.min_len)
+            .field("max_len", &self.max_len)
+            .field("allowed_chars", &self.allowed_chars.as_ref().map(|_| "Fn"))
+            .field("invalid_message", &self.invalid_message)
+            .field("mask", &self.mask)
+            .field(
+                "strength_meter",
+                &self.strength_meter.as_ref().map(|_| "Fn"),
+            )
+            .field("cancel_token", &self.cancel_token)
+            .field("redraw_handle", &self.redraw_handle)
+            .finish()
+    }
+}
+
+/// What happens when a password isn't submitted in time, see `PasswordInput::idle_timeout`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleAction {
+    /// Clear what was typed and display the prompt again on a new line
+    Reprompt,
+    /// Clear what was typed and fail with `Error::TimedOut`
+    Abort,
+}
+
+impl Default for PasswordInput {
+    fn default() -> Self {
+        PasswordInput {
+            prompt: None,
+            output: PromptOutput::Tty,
+            paste_policy: PastePolicy::Allow,
+            idle_timeout: None,
+            min_len: None,
+            max_len: None,
+            allowed_chars: None,
+            invalid_message: None,
+            mask: None,
+            strength_meter: None,
+            cancel_token: None,
+            redraw_handle: RedrawHandle::default(),
+        }
+    }
+}
+
+impl PasswordInput {
+    pub fn new() -> PasswordInput {
+        PasswordInput::default()
+    }
+
+    /// Sets the message displayed before reading the password
+    pub fn prompt(mut self, prompt: impl ToString) -> PasswordInput {
+        self.prompt = Some(prompt.to_string());
+        self
+    }
+
+    /// Sets where the prompt is displayed, the TTY by default
+    pub fn output(mut self, output: PromptOutput) -> PasswordInput {
+        self.output = output;
+        self
+    }
+
+    /// Refuses passwords that are pasted instead of typed, with an `Error::Io` of kind
+    /// `InvalidData`
+    ///
+    /// This relies on bracketed paste, so it only works in terminals that support it.
+    pub fn reject_paste(mut self, reject: bool) -> PasswordInput {
+        self.paste_policy = match reject {
+            true => PastePolicy::Reject,
+            false => PastePolicy::Allow,
+        };
+        self
+    }
+
+    /// Clears what was typed if the password isn't submitted within `timeout`, then displays the
+    /// prompt again or gives up, so that a half-typed password isn't left on an unattended screen
+    ///
+    /// The timeout starts over each time the prompt is displayed. This only works on Unix, where
+    /// the terminal tells when a line is submitted, and is ignored elsewhere.
+    ///
+    /// ```no_run
+    /// use rpassword::{IdleAction, PasswordInput};
+    /// use std::time::Duration;
+    ///
+    /// let password = PasswordInput::new()
+    ///     .prompt("Your password: ")
+    ///     .idle_timeout(Duration::from_secs(60), IdleAction::Abort)
+    ///     .read();
+    /// ```
+    pub fn idle_timeout(mut self, timeout: Duration, action: IdleAction) -> PasswordInput {
+        self.idle_timeout = Some((timeout, action));
+        self
+    }
+
+    /// Refuses passwords shorter than `len` characters, asking again
+    pub fn min_len(mut self, len: usize) -> PasswordInput {
+        self.min_len = Some(len);
+        self
+    }
+
+    /// Refuses passwords longer than `len` characters, asking again
+    pub fn max_len(mut self, len: usize) -> PasswordInput {
+        self.max_len = Some(len);
+        self
+    }
+
+    /// Refuses passwords with characters for which `predicate` returns false, asking again
+    ///
+    /// With length constraints, this makes for PIN entry:
+    /// ```no_run
+    /// let pin = rpassword::PasswordInput::new()
+    ///     .prompt("PIN: ")
+    ///     .min_len(6)
+    ///     .max_len(6)
+    ///     .allowed_chars(|c| c.is_ascii_digit())
+    ///     .invalid_message("The PIN is made of 6 digits.")
+    ///     .read()
+    ///     .unwrap();
+    /// ```
+    pub fn allowed_chars<F>(mut self, predicate: F) -> PasswordInput
+    where
+        F: Fn(char) -> bool + Send + Sync + 'static,
+    {
+        self.allowed_chars = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Sets the message displayed when a password is refused, before asking again, which
+    /// describes the constraints by default
+    pub fn invalid_message(mut self, message: impl ToString) -> PasswordInput {
+        self.invalid_message = Some(message.to_string());
+        self
+    }
+
+    /// Echoes `mask` for each character typed, instead of hiding input altogether
+    ///
+    /// The terminal is then read key by key, so the prompt is always displayed on the TTY and idle
+    /// timeouts don't apply. On WASM targets, there is no terminal to read keys from, so input is
+    /// read as usual.
+    pub fn mask(mut self, mask: char) -> PasswordInput {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Displays what `strength_meter` returns for the password typed so far after the mask, on
+    /// the same line, updated on each key
+    ///
+    /// This only applies with `mask`, since hidden input isn't seen before ENTER is hit.
+    /// ```no_run
+    /// let password = rpassword::PasswordInput::new()
+    ///     .prompt("New password: ")
+    ///     .mask('*')
+    ///     .strength_meter(|password| match password.chars().count() {
+    ///         0..=7 => "[#   ] weak".to_string(),
+    ///         8..=11 => "[##  ] fair".to_string(),
+    ///         _ => "[####] strong".to_string(),
+    ///     })
+    ///     .read()
+    ///     .unwrap();
+    /// ```
+    pub fn strength_meter<F>(mut self, strength_meter: F) -> PasswordInput
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.strength_meter = Some(Arc::new(strength_meter));
+        self
+    }
+
+    /// Gives up reading with `Error::Interrupted` once `cancel_token` is set, ie: by a supervising
+    /// thread when a remote session disconnects, clearing what was typed and restoring the
+    /// terminal
+    ///
+    /// The token is checked every 100 milliseconds. This only works on Unix, and is ignored
+    /// elsewhere.
+    ///
+    /// ```no_run
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let cancel_token = Arc::new(AtomicBool::new(false));
+    /// let input = rpassword::PasswordInput::new()
+    ///     .prompt("Your password: ")
+    ///     .cancel_token(cancel_token.clone());
+    /// std::thread::spawn(move || {
+    ///     // Wait for the session to disconnect...
+    ///     cancel_token.store(true, Ordering::SeqCst);
+    /// });
+    /// let password = input.read();
+    /// ```
+    pub fn cancel_token(mut self, cancel_token: Arc<AtomicBool>) -> PasswordInput {
+        self.cancel_token = Some(cancel_token);
+        self
+    }
+
+    /// Returns a handle that other threads can use to display the prompt again while a password is
+    /// being read, ie: after they logged something to the terminal
+    ///
+    /// ```no_run
+    /// let input = rpassword::PasswordInput::new().prompt("Your password: ");
+    /// let redraw_handle = input.redraw_handle();
+    /// std::thread::spawn(move || {
+    ///     eprintln!("\rSome background task is done");
+    ///     redraw_handle.redraw().unwrap();
+    /// });
+    /// let password = input.read().unwrap();
+    /// ```
+    pub fn redraw_handle(&self) -> RedrawHandle {
+        self.redraw_handle.clone()
+    }
+
+    /// Prompts on the chosen output if needed and then reads a password from TTY, until one meets
+    /// the constraints
+    pub fn read(&self) -> Result<String> {
+        let _tty = lock_tty();
+        loop {
+            let password = self.read_once()?;
+            if self.is_valid(&password) {
+                return Ok(password);
+            }
+            std::mem::drop(SafeString::from_string(password));
+            print_to(self.output, format!("{}\n", self.refusal())).map_err(tty_error)?;
+        }
+    }
+
+    fn read_once(&self) -> Result<String> {
+        let password = match self.mask {
+            Some(mask) => read_masked_password(
+                self.prompt.as_deref().unwrap_or(""),
+                mask,
+                self.strength_meter.as_ref(),
+                self.paste_policy,
+                self.cancel_token.as_deref(),
+                &self.redraw_handle,
+            ),
+            None => {
+                if let Some(prompt) = self.prompt.as_ref() {
+                    print_to(self.output, prompt.as_str()).map_err(tty_error)?;
+                    self.redraw_handle
+                        .set_prompt(Some((self.output, prompt.clone())));
+                }
+                read_password_with_idle_timeout(
+                    self.paste_policy,
+                    self.idle_timeout,
+                    self.cancel_token.as_deref(),
+                    &self.redraw_handle,
+                )
+            }
+        };
+        self.redraw_handle.set_prompt(None);
+        password
+    }
+
+    /// Prompts on a writer if needed and then reads a password from anything that implements
+    /// BufRead, until one meets the constraints
+    pub fn read_from_bufread(
+        &self,
+        reader: &mut impl BufRead,
+        writer: &mut impl Write,
+    ) -> Result<String> {
+        loop {
+            let password = match self.mask {
+                Some(mask) => masked::read_masked(
+                    reader,
+                    writer,
+                    self.prompt.as_deref().unwrap_or(""),
+                    mask,
+                    self.strength_meter.as_ref(),
+                    self.paste_policy,
+                    &RedrawHandle::default(),
+                )?,
+                None => {
+                    if let Some(prompt) = self.prompt.as_ref() {
+                        print_writer(writer, prompt.as_str())?;
+                    }
+                    read_password_from_bufread_with_paste_policy(reader, self.paste_policy)?
+                }
+            };
+            if self.is_valid(&password) {
+                return Ok(password);
+            }
+            std::mem::drop(SafeString::from_string(password));
+            writer_println!(writer, "{}", self.refusal())?;
+        }
+    }
+
+    fn is_valid(&self, password: &str) -> bool {
+        let len = password.chars().count();
+        self.min_len.map_or(true, |min_len| len >= min_len)
+            && self.max_len.map_or(true, |max_len| len <= max_len)
+            && self
+                .allowed_chars
+                .as_ref()
+                .map_or(true, |allowed| password.chars().all(|c| allowed(c)))
+    }
+
+    /// Returns the message displayed when a password is refused
+    fn refusal(&self) -> String {
+        if let Some(message) = self.invalid_message.as_ref() {
+            return message.clone();
+        }
+
+        let length = match (self.min_len, self.max_len) {
+            (Some(min_len), Some(max_len)) if min_len == max_len => {
+                format!(" exactly {} characters long", min_len)
+            }
+            (Some(min_len), Some(max_len)) => {
+                format!(" from {} to {} characters long", min_len, max_len)
+            }
+            (Some(min_len), None) => format!(" at least {} characters long", min_len),
+            (None, Some(max_len)) => format!(" at most {} characters long", max_len),
+            (None, None) => String::new(),
+        };
+        let chars = match (self.allowed_chars.is_some(), length.is_empty()) {
+            (false, _) => "",
+            (true, true) => " made of allowed characters only",
+            (true, false) => ", with allowed characters only",
+        };
+        format!("The password must be{}{}.", length, chars)
+    }
+}
+
+/// Displays the prompt of a `PasswordInput` again, see `PasswordInput::redraw_handle`
+///
+/// Hidden input is never echoed, so only the prompt is displayed again, while masked input is
+/// displayed again with its mask and strength meter.
+#[derive(Clone, Debug, Default)]
+pub struct RedrawHandle {
+    /// The line as displayed, ie: the prompt, followed by the mask if any
+    prompt: Arc<Mutex<Option<(PromptOutput, String)>>>,
+}
+
+impl RedrawHandle {
+    /// Displays the prompt again at the start of the current line, if a password is being read
+    ///
+    /// Call this after writing a full line to the terminal, so that the user doesn't end up typing
+    /// their password after your output with no prompt in sight.
+    pub fn redraw(&self) -> std::io::Result<()> {
+        match self.redraw_text() {
+            Some((output, text)) => print_to(output, text),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns what `redraw` displays, and where
+    fn redraw_text(&self) -> Option<(PromptOutput, String)> {
+        let prompt = self.prompt.lock().unwrap_or_else(|err| err.into_inner());
+        prompt
+            .as_ref()
+            .map(|(output, prompt)| (*output, format!("\r{}", prompt)))
+    }
+
+    /// Displays the prompt again on a new line, if a password is being read
+    #[cfg_attr(not(target_family = "unix"), allow(dead_code))]
+    fn reprompt(&self) -> std::io::Result<()> {
+        let prompt = self.prompt.lock().unwrap_or_else(|err| err.into_inner());
+        match prompt.as_ref() {
+            Some((output, prompt)) => print_to(*output, format!("\n{}", prompt)),
+            None => Ok(()),
+        }
+    }
+
+    fn set_prompt(&self, prompt: Option<(PromptOutput, String)>) {
+        *self.prompt.lock().unwrap_or_else(|err| err.into_inner()) = prompt;
+    }
 }
 
 #[cfg(test)]
@@ -288,4 +1090,95 @@ mod tests {
         let response = super::read_password_from_bufread(&mut reader_lf).unwrap();
         assert_eq!(response, "Another mocked response.");
     }
+
+    #[test]
+    fn can_tell_eof_apart() {
+        let err = super::read_password_from_bufread(&mut Cursor::new(&b"no newline"[..]));
+        assert!(matches!(err, Err(super::Error::Eof)));
+    }
+
+    #[test]
+    fn can_reject_pasted_password() {
+        let mut reader = Cursor::new(&b"\x1b[200~pasted\x1b[201~\ntyped\n"[..]);
+        let mut writer = Cursor::new(Vec::new());
+        let input = super::PasswordInput::new()
+            .prompt("Password: ")
+            .reject_paste(true);
+
+        let err = input
+            .read_from_bufread(&mut reader, &mut writer)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let response = input.read_from_bufread(&mut reader, &mut writer).unwrap();
+        assert_eq!(response, "typed");
+        assert_eq!(writer.into_inner(), b"Password: Password: ");
+    }
+
+    #[test]
+    fn can_ask_again_until_constraints_are_met() {
+        let mut reader = Cursor::new(&b"12345\n12345a\n123456\n"[..]);
+        let mut writer = Cursor::new(Vec::new());
+        let input = super::PasswordInput::new()
+            .prompt("PIN: ")
+            .min_len(6)
+            .max_len(6)
+            .allowed_chars(|c| c.is_ascii_digit());
+
+        let response = input.read_from_bufread(&mut reader, &mut writer).unwrap();
+        assert_eq!(response, "123456");
+
+        let refusal =
+            "The password must be exactly 6 characters long, with allowed characters only.";
+        let expected = format!("PIN: {0}\nPIN: {0}\nPIN: ", refusal);
+        assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), expected);
+    }
+
+    #[test]
+    fn can_mask_password_read_from_bufread() {
+        let mut reader = Cursor::new(&b"pin\r1234\r"[..]);
+        let mut writer = Cursor::new(Vec::new());
+        let input = super::PasswordInput::new()
+            .prompt("PIN: ")
+            .mask('*')
+            .allowed_chars(|c| c.is_ascii_digit());
+
+        let response = input.read_from_bufread(&mut reader, &mut writer).unwrap();
+        assert_eq!(response, "1234");
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(output.ends_with("\rPIN: ****\n"));
+        assert!(!output.contains("1234"));
+    }
+
+    #[test]
+    fn can_redraw_only_while_reading() {
+        let input = super::PasswordInput::new().prompt("Password: ");
+        let redraw_handle = input.redraw_handle();
+        assert!(redraw_handle.redraw().is_ok());
+
+        redraw_handle.set_prompt(Some((
+            super::PromptOutput::Stdout,
+            "Password: ".to_string(),
+        )));
+        assert!(redraw_handle.redraw().is_ok());
+        assert!(input.redraw_handle.prompt.lock().unwrap().is_some());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn can_tell_when_there_is_no_tty() {
+        let source = std::io::Error::from_raw_os_error(libc::ENXIO);
+        let kind = source.kind();
+        let err = match super::tty_error(source) {
+            super::Error::NotATty(err) => err,
+            err => panic!("unexpected error: {:?}", err),
+        };
+        assert_eq!(err.kind(), kind);
+
+        let no_tty = super::NoTty::from_io_error(&err).unwrap();
+        assert!(!no_tty.has_controlling_terminal);
+
+        let err = super::tty_error(std::io::Error::from_raw_os_error(libc::EACCES));
+        assert!(matches!(err, super::Error::Io(_)));
+    }
 }
@@ -0,0 +1,206 @@
+//! Masked entry, which echoes a mask character for each character typed instead of hiding input
+
+use crate::RedrawHandle;
+use rtoolbox::bracketed_paste::PastePolicy;
+use rtoolbox::key::{read_key, Key};
+use rtoolbox::print_tty::{last_line, PromptOutput};
+use rtoolbox::safe_string::SafeString;
+use rtoolbox::writer_print;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// Returns what to display after the mask, ie: a strength bar, given what's typed so far
+pub(crate) type StrengthMeter = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Reads a password key by key, echoing `mask` for each character, followed by what
+/// `strength_meter` returns for the password so far
+///
+/// The terminal is expected to be in raw mode already, so that we get every key as it is typed.
+/// The line is given to `redraw_handle` each time it changes, so that it can be displayed again.
+pub(crate) fn read_masked(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    prompt: &str,
+    mask: char,
+    strength_meter: Option<&StrengthMeter>,
+    paste_policy: PastePolicy,
+    redraw_handle: &RedrawHandle,
+) -> io::Result<String> {
+    let mut line = MaskedLine {
+        writer,
+        prompt: last_line(prompt),
+        mask,
+        strength_meter,
+        redraw_handle,
+        password: SafeString::new(),
+        rendered_len: 0,
+    };
+    line.write(prompt)?;
+    line.render()?;
+
+    loop {
+        let key = match read_key(reader)? {
+            Some(key) => key,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+
+        match key {
+            Key::Enter => {
+                line.write("\n")?;
+                return Ok(line.password.into_inner());
+            }
+            Key::Backspace if line.password.pop().is_some() => line.render()?,
+            Key::Ctrl('u') => {
+                line.password.clear();
+                line.render()?;
+            }
+            Key::Ctrl('c') => {
+                line.write("\n")?;
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            Key::Ctrl('d') if line.password.is_empty() => {
+                line.write("\n")?;
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            Key::Paste(pasted) => {
+                if paste_policy == PastePolicy::Reject {
+                    line.write("\n")?;
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "pasting is not allowed",
+                    ));
+                }
+                line.password.push_str(&pasted);
+                line.render()?;
+            }
+            Key::Char(c) => {
+                line.password.push(c);
+                line.render()?;
+            }
+            // Other keys, like arrow keys, are ignored
+            _ => {}
+        }
+    }
+}
+
+/// The prompt, the mask and the strength meter, as displayed on the current line
+struct MaskedLine<'a, W: Write> {
+    writer: &'a mut W,
+    prompt: &'a str,
+    mask: char,
+    strength_meter: Option<&'a StrengthMeter>,
+    redraw_handle: &'a RedrawHandle,
+    password: SafeString,
+    rendered_len: usize,
+}
+
+impl<'a, W: Write> MaskedLine<'a, W> {
+    /// Rewrites the mask and the strength meter, erasing leftovers from a longer line, and leaves
+    /// the cursor right after the mask
+    fn render(&mut self) -> io::Result<()> {
+        let masked = self.mask.to_string().repeat(self.password.chars().count());
+        let meter = match self.strength_meter {
+            Some(strength_meter) => format!(" {}", strength_meter(&self.password)),
+            None => String::new(),
+        };
+        let len = masked.chars().count() + meter.chars().count();
+        let padding = " ".repeat(self.rendered_len.saturating_sub(len));
+        self.rendered_len = len;
+        self.redraw_handle.set_prompt(Some((
+            PromptOutput::Tty,
+            format!("{0}{1}{2}\r{0}{1}", self.prompt, masked, meter),
+        )));
+
+        writer_print!(
+            self.writer,
+            "\r{0}{1}{2}{3}\r{0}{1}",
+            self.prompt,
+            masked,
+            meter,
+            padding
+        )
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        self.writer
+            .write_all(s.as_bytes())
+            .and_then(|_| self.writer.flush())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_masked, StrengthMeter};
+    use crate::RedrawHandle;
+    use rtoolbox::bracketed_paste::PastePolicy;
+    use rtoolbox::testing::{keys, MockTty};
+    use std::sync::Arc;
+
+    fn run(tty: &mut MockTty, strength_meter: Option<&StrengthMeter>) -> std::io::Result<String> {
+        let (input, output) = tty.split();
+        read_masked(
+            input,
+            output,
+            "Password: ",
+            '*',
+            strength_meter,
+            PastePolicy::Allow,
+            &RedrawHandle::default(),
+        )
+    }
+
+    #[test]
+    fn can_mask_typed_characters() {
+        let mut tty = MockTty::new()
+            .typed("hunter")
+            .key(keys::BACKSPACE)
+            .typed("r2é")
+            .key(keys::LEFT)
+            .pasted("!\n")
+            .key(keys::ENTER);
+        assert_eq!(run(&mut tty, None).unwrap(), "hunter2é!");
+        assert_eq!(tty.visible_line(), "");
+        assert!(tty.output_string().ends_with("Password: *********\n"));
+        assert!(!tty.output_string().contains("hunter"));
+    }
+
+    #[test]
+    fn can_update_strength_meter_on_each_key() {
+        let strength_meter: StrengthMeter = Arc::new(|password| match password.len() {
+            0..=3 => "[weak]".to_string(),
+            _ => "[strong]".to_string(),
+        });
+        let mut tty = MockTty::new().typed("abcd");
+        let err = run(&mut tty, Some(&strength_meter)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(tty.visible_line(), "Password: **** [strong]");
+
+        let mut tty = MockTty::new().typed("abcd").key(keys::BACKSPACE);
+        let _ = run(&mut tty, Some(&strength_meter));
+        assert_eq!(tty.visible_line(), "Password: *** [weak]");
+    }
+
+    #[test]
+    fn can_redraw_mask_and_strength_meter() {
+        let strength_meter: StrengthMeter = Arc::new(|_| "[weak]".to_string());
+        let redraw_handle = RedrawHandle::default();
+        let mut tty = MockTty::new().typed("abc");
+        let (input, output) = tty.split();
+        let _ = read_masked(
+            input,
+            output,
+            "Password: ",
+            '*',
+            Some(&strength_meter),
+            PastePolicy::Allow,
+            &redraw_handle,
+        );
+
+        // Another thread logs a line, then the prompt is displayed again with its mask
+        output.extend_from_slice(b"\rSome background task is done\n");
+        let (_, redraw) = redraw_handle.redraw_text().unwrap();
+        output.extend_from_slice(redraw.as_bytes());
+        assert_eq!(tty.visible_line(), "Password: *** [weak]");
+        assert!(tty.output_string().ends_with("\rPassword: ***"));
+    }
+}
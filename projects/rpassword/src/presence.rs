@@ -0,0 +1,231 @@
+//! Waits for the user to either type a password or do something outside of the terminal, like
+//! touching a security key
+
+use rtoolbox::bracketed_paste::{self, PastePolicy};
+use rtoolbox::print_tty;
+use rtoolbox::safe_string::SafeString;
+use rtoolbox::writer_print;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+/// How often the spinner turns, and how often the signal is checked
+const TICK: Duration = Duration::from_millis(200);
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// How the user completed `prompt_presence`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Presence {
+    /// The user typed a password, which may be empty, and hit ENTER
+    Typed(String),
+    /// The caller signaled that the user did something else, ie: touched their security key
+    Signaled,
+}
+
+/// Displays a message on the TTY, then waits until either a password is typed and ENTER is hit,
+/// or something is sent on `signal`
+///
+/// This is meant for authentication that either a password or an external event completes, like
+/// the touch of a FIDO2 security key, which the caller waits for on another thread:
+/// ```no_run
+/// use rpassword::Presence;
+///
+/// let (touched, signal) = std::sync::mpsc::channel();
+/// std::thread::spawn(move || {
+///     // Wait for the security key to be touched...
+///     touched.send(()).unwrap();
+/// });
+/// match rpassword::prompt_presence("Touch your security key or type your PIN: ", &signal) {
+///     Ok(Presence::Typed(pin)) => println!("Your PIN is {}", pin),
+///     Ok(Presence::Signaled) => println!("Your security key was touched"),
+///     Err(err) => panic!("{}", err),
+/// }
+/// ```
+///
+/// A spinner turns after the prompt in the meantime. Typed input is hidden, and discarded if the
+/// signal comes first. If `signal` is disconnected, only ENTER completes the prompt. On Windows,
+/// the signal is no longer checked once the user starts typing, and on WASM targets, where there
+/// is no terminal to wait on, a password is read from stdin unless the signal already came.
+pub fn prompt_presence(prompt: impl ToString, signal: &Receiver<()>) -> crate::Result<Presence> {
+    let _tty = crate::lock_tty();
+    prompt_presence_on_tty(prompt.to_string().as_str(), signal)
+}
+
+#[cfg(target_family = "unix")]
+fn prompt_presence_on_tty(prompt: &str, signal: &Receiver<()>) -> crate::Result<Presence> {
+    use rtoolbox::raw_mode::{self, RawMode, RawModeGuard};
+    use std::os::unix::io::AsRawFd;
+
+    let tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(crate::tty_error)?;
+    let fd = tty.as_raw_fd();
+    let mut writer = tty.try_clone()?;
+    let mut reader = io::BufReader::new(tty);
+    let hidden_input = RawModeGuard::new(fd, RawMode::Hidden)?;
+
+    let presence = wait_for_presence(
+        &mut reader,
+        &mut writer,
+        prompt,
+        signal,
+        // With hidden input, the terminal is in canonical mode, so the TTY is only readable once
+        // a full line is there.
+        |timeout| raw_mode::wait_for_key(fd, timeout),
+    );
+
+    // What was typed waits in the input queue until ENTER is hit, so this clears it.
+    if let Ok(Presence::Signaled) = presence {
+        if unsafe { libc::tcflush(fd, libc::TCIFLUSH) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+    }
+
+    std::mem::drop(hidden_input);
+
+    Ok(presence?)
+}
+
+#[cfg(target_family = "windows")]
+fn prompt_presence_on_tty(prompt: &str, signal: &Receiver<()>) -> crate::Result<Presence> {
+    use crate::windows::open_console;
+    use rtoolbox::console::ConsoleReader;
+    use rtoolbox::raw_mode::{self, RawMode, RawModeGuard};
+    use std::os::windows::io::AsRawHandle;
+
+    let console = open_console(b"CONIN$\x00").map_err(crate::tty_error)?;
+    let mut writer = open_console(b"CONOUT$\x00").map_err(crate::tty_error)?;
+    let handle = console.as_raw_handle() as winapi::um::winnt::HANDLE;
+    let mut reader = io::BufReader::new(ConsoleReader::new(console));
+    // The handle belongs to `reader`, which outlives the guard
+    let hidden_input = unsafe { RawModeGuard::new(handle, RawMode::Hidden)? };
+
+    let presence = wait_for_presence(&mut reader, &mut writer, prompt, signal, |timeout| {
+        // The handle belongs to `reader`, which is only dropped after
+        unsafe { raw_mode::wait_for_key(handle, timeout) }
+    });
+
+    // Newline for windows which otherwise prints on the same line.
+    if let Ok(Presence::Typed(_)) = presence {
+        crate::print_writer(&mut writer, "\n")?;
+    }
+
+    std::mem::drop(hidden_input);
+
+    Ok(presence?)
+}
+
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+fn prompt_presence_on_tty(prompt: &str, signal: &Receiver<()>) -> crate::Result<Presence> {
+    // There is no terminal to wait on, so stdin is read right away
+    Ok(wait_for_presence(
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+        prompt,
+        signal,
+        |_| Ok(true),
+    )?)
+}
+
+/// Displays the prompt and a spinner, until `wait_for_line` says a line can be read or something
+/// is sent on `signal`
+fn wait_for_presence(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    prompt: &str,
+    signal: &Receiver<()>,
+    mut wait_for_line: impl FnMut(Duration) -> io::Result<bool>,
+) -> io::Result<Presence> {
+    let last_line = print_tty::last_line(prompt);
+    writer_print!(writer, "{}", prompt)?;
+
+    let mut signal_connected = true;
+    let mut frame = 0;
+    loop {
+        if signal_connected {
+            match signal.try_recv() {
+                Ok(()) => {
+                    writer_print!(writer, " \r{}\n", last_line)?;
+                    return Ok(Presence::Signaled);
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => signal_connected = false,
+            }
+        }
+
+        writer_print!(writer, "{}\r{}", SPINNER[frame % SPINNER.len()], last_line)?;
+        frame += 1;
+
+        if wait_for_line(TICK)? {
+            writer_print!(writer, " \r{}", last_line)?;
+            break;
+        }
+    }
+
+    let mut password = SafeString::new();
+    if bracketed_paste::read_line(reader, &mut password, PastePolicy::Allow)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Presence::Typed(crate::fix_line_issues(
+        password.into_inner(),
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wait_for_presence, Presence};
+    use std::io::Cursor;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn can_complete_with_signal() {
+        let (sender, signal) = channel();
+        let mut writer = Vec::new();
+        let mut ticks = 0;
+        let presence = wait_for_presence(
+            &mut Cursor::new("typed but not submitted"),
+            &mut writer,
+            "Touch your key: ",
+            &signal,
+            |_| {
+                ticks += 1;
+                if ticks == 2 {
+                    sender.send(()).unwrap();
+                }
+                Ok(false)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(presence, Presence::Signaled);
+        assert_eq!(
+            String::from_utf8(writer).unwrap(),
+            "Touch your key: |\rTouch your key: /\rTouch your key:  \rTouch your key: \n"
+        );
+    }
+
+    #[test]
+    fn can_complete_with_typed_password() {
+        let (sender, signal) = channel::<()>();
+        std::mem::drop(sender);
+        let mut writer = Vec::new();
+        let mut ticks = 0;
+        let presence = wait_for_presence(
+            &mut Cursor::new("1234\n"),
+            &mut writer,
+            "PIN: ",
+            &signal,
+            |_| {
+                ticks += 1;
+                Ok(ticks == 3)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(presence, Presence::Typed("1234".to_string()));
+        assert!(!String::from_utf8(writer).unwrap().contains("1234"));
+    }
+}
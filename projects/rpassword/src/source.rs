@@ -0,0 +1,226 @@
+//! Reads passwords from where the user says, ie: with a `--password-from` option
+
+use crate::{read_password, Error, Result};
+use rtoolbox::safe_string::SafeString;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Where to read a password from
+///
+/// Sources are written like `env:VAR`, `fd:3`, `file:/path` and `tty`, and parsed with `parse`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// The value of an environment variable, as is
+    Env(String),
+    /// The first line read from an open file descriptor, ie: `my-program 3< secret` (Unix only)
+    Fd(i32),
+    /// The first line of a file, ie: a Docker secret in `/run/secrets`
+    File(PathBuf),
+    /// The TTY, with hidden input, like `read_password`
+    Tty,
+}
+
+impl PasswordSource {
+    /// Reads the password from this source
+    pub fn read(&self) -> Result<String> {
+        match self {
+            PasswordSource::Env(name) => match std::env::var(name) {
+                Ok(password) => Ok(password),
+                Err(std::env::VarError::NotPresent) => Err(Error::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("environment variable {} is not set", name),
+                ))),
+                Err(std::env::VarError::NotUnicode(_)) => Err(Error::InvalidUtf8),
+            },
+            PasswordSource::Fd(fd) => read_fd(*fd),
+            PasswordSource::File(path) => {
+                read_first_line(&mut BufReader::new(std::fs::File::open(path)?))
+            }
+            PasswordSource::Tty => read_password(),
+        }
+    }
+
+    /// Returns true if the error means that the source isn't there, rather than that reading it
+    /// failed, in which case the next source in a list can be tried
+    fn is_missing(&self, err: &Error) -> bool {
+        match (self, err) {
+            (PasswordSource::Tty, Error::NotATty(_)) => true,
+            (PasswordSource::Tty, _) => false,
+            (_, Error::Io(err)) => matches!(err.kind(), io::ErrorKind::NotFound),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for PasswordSource {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<PasswordSource> {
+        let source = match spec.split_once(':') {
+            _ if spec == "tty" => Some(PasswordSource::Tty),
+            Some(("env", name)) if !name.is_empty() => Some(PasswordSource::Env(name.to_string())),
+            Some(("fd", fd)) => fd.parse().ok().map(PasswordSource::Fd),
+            Some(("file", path)) if !path.is_empty() => Some(PasswordSource::File(path.into())),
+            _ => None,
+        };
+
+        source.ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "invalid password source \"{}\", expected env:VAR, fd:N, file:PATH or tty",
+                    spec
+                ),
+            ))
+        })
+    }
+}
+
+/// Reads a password from the sources in `spec`, ie: the value of a `--password-from` option
+///
+/// `spec` is a comma separated list of sources, tried in order until one of them is there: with
+/// `env:MY_PASSWORD,tty`, the password is read from the TTY only if `MY_PASSWORD` isn't set. Only
+/// missing sources are skipped: an unset environment variable, a file or file descriptor that
+/// doesn't exist, or no TTY. Other errors, like a password that isn't valid UTF-8, are returned.
+///
+/// ```no_run
+/// let password = rpassword::read_password_from_source("env:MY_PASSWORD,tty").unwrap();
+/// ```
+pub fn read_password_from_source(spec: &str) -> Result<String> {
+    let sources = parse_sources(spec)?;
+
+    let mut error = None;
+    for source in sources.iter() {
+        match source.read() {
+            Err(err) if source.is_missing(&err) => error = Some(err),
+            result => return result,
+        }
+    }
+
+    Err(error.unwrap_or_else(|| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no password source",
+        ))
+    }))
+}
+
+/// Splits a list of sources, keeping commas that are part of a file path
+fn parse_sources(spec: &str) -> Result<Vec<PasswordSource>> {
+    let mut specs: Vec<String> = Vec::new();
+    for part in spec.split(',') {
+        let is_source = part == "tty"
+            || ["env:", "fd:", "file:"]
+                .iter()
+                .any(|prefix| part.starts_with(prefix));
+        match specs.last_mut() {
+            Some(last) if !is_source && last.starts_with("file:") => {
+                last.push(',');
+                last.push_str(part);
+            }
+            _ => specs.push(part.to_string()),
+        }
+    }
+
+    specs.iter().map(|spec| spec.parse()).collect()
+}
+
+/// Reads the first line, accepting a missing final newline, which secret files often don't have
+fn read_first_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = SafeString::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(Error::Eof);
+    }
+
+    let mut line = line.into_inner();
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(line)
+}
+
+#[cfg(unix)]
+fn read_fd(fd: i32) -> Result<String> {
+    use std::os::unix::io::FromRawFd;
+
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("file descriptor {} is not open", fd),
+        )));
+    }
+
+    // The file descriptor belongs to the caller, so it must not be closed when we are done. We
+    // read byte by byte, so that nothing after the first line is consumed.
+    let file = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    read_first_line(&mut BufReader::with_capacity(1, &*file))
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> Result<String> {
+    Err(Error::Io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reading from a file descriptor is only supported on Unix",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_sources, read_password_from_source, PasswordSource};
+
+    #[test]
+    fn can_parse_sources() {
+        assert_eq!(
+            parse_sources("env:PASSWORD,fd:3,file:/run/secrets/a,b,tty").unwrap(),
+            vec![
+                PasswordSource::Env("PASSWORD".to_string()),
+                PasswordSource::Fd(3),
+                PasswordSource::File("/run/secrets/a,b".into()),
+                PasswordSource::Tty,
+            ]
+        );
+        assert!("env:".parse::<PasswordSource>().is_err());
+        assert!("fd:three".parse::<PasswordSource>().is_err());
+        assert!("password".parse::<PasswordSource>().is_err());
+    }
+
+    #[test]
+    fn can_fall_back_to_next_source() {
+        let path = std::env::temp_dir().join(format!("rpassword-source-{}", std::process::id()));
+        std::fs::write(&path, "from file\nignored\n").unwrap();
+
+        std::env::remove_var("RPASSWORD_TEST_UNSET");
+        let spec = format!("env:RPASSWORD_TEST_UNSET,file:{}", path.display());
+        assert_eq!(read_password_from_source(&spec).unwrap(), "from file");
+
+        std::env::set_var("RPASSWORD_TEST_SET", "from env");
+        let spec = format!("env:RPASSWORD_TEST_SET,file:{}", path.display());
+        assert_eq!(read_password_from_source(&spec).unwrap(), "from env");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn can_read_from_fd() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let written = b"from fd";
+        assert_eq!(
+            unsafe { libc::write(fds[1], written.as_ptr() as *const _, written.len()) },
+            written.len() as isize
+        );
+        unsafe { libc::close(fds[1]) };
+
+        let spec = format!("fd:{}", fds[0]);
+        assert_eq!(read_password_from_source(&spec).unwrap(), "from fd");
+
+        // The file descriptor is still open
+        assert_ne!(unsafe { libc::fcntl(fds[0], libc::F_GETFD) }, -1);
+        unsafe { libc::close(fds[0]) };
+    }
+}
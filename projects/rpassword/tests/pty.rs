@@ -0,0 +1,105 @@
+//! This test checks that passwords typed in a real terminal are not echoed.
+#![cfg(unix)]
+
+use rpassword::IdleAction;
+use rtoolbox::testing::pty::fork_test;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn echo_enabled(fd: i32) -> bool {
+    let mut term = std::mem::MaybeUninit::<libc::termios>::uninit();
+    assert_eq!(unsafe { libc::tcgetattr(fd, term.as_mut_ptr()) }, 0);
+    unsafe { term.assume_init() }.c_lflag & libc::ECHO != 0
+}
+
+#[test]
+fn can_hide_typed_password() {
+    fork_test(
+        "can_hide_typed_password",
+        || {
+            let password = rpassword::prompt_password("Password: ").unwrap();
+            assert_eq!(password, "hunter2");
+
+            // Echo is back once the password has been read
+            assert!(echo_enabled(libc::STDIN_FILENO));
+        },
+        |pty| {
+            pty.expect("Password: ");
+            pty.wait_for_echo(false);
+            pty.send("hunter2\n");
+
+            let output = pty.read_to_end();
+            assert!(!output.contains("hunter2"), "{:?} was echoed", output);
+        },
+    );
+}
+
+#[test]
+fn can_clear_idle_input_and_prompt_again() {
+    fork_test(
+        "can_clear_idle_input_and_prompt_again",
+        || {
+            let password = rpassword::PasswordInput::new()
+                .prompt("Password: ")
+                .idle_timeout(Duration::from_millis(500), IdleAction::Reprompt)
+                .read()
+                .unwrap();
+            assert_eq!(password, "hunter2");
+        },
+        |pty| {
+            pty.expect("Password: ");
+            pty.wait_for_echo(false);
+            pty.send("forgot");
+
+            // The half-typed password is cleared when the prompt is displayed again
+            pty.expect("\nPassword: ");
+            pty.send("hunter2\n");
+            pty.read_to_end();
+        },
+    );
+}
+
+#[test]
+fn can_give_up_when_idle() {
+    fork_test(
+        "can_give_up_when_idle",
+        || {
+            let err = rpassword::PasswordInput::new()
+                .prompt("Password: ")
+                .idle_timeout(Duration::from_millis(100), IdleAction::Abort)
+                .read()
+                .unwrap_err();
+            assert!(matches!(err, rpassword::Error::TimedOut));
+        },
+        |pty| {
+            pty.expect("Password: ");
+            pty.read_to_end();
+        },
+    );
+}
+
+#[test]
+fn can_cancel_pending_read() {
+    fork_test(
+        "can_cancel_pending_read",
+        || {
+            let cancel_token = Arc::new(AtomicBool::new(false));
+            let input = rpassword::PasswordInput::new()
+                .prompt("Password: ")
+                .cancel_token(cancel_token.clone());
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(200));
+                cancel_token.store(true, Ordering::SeqCst);
+            });
+            let err = input.read().unwrap_err();
+            assert!(matches!(err, rpassword::Error::Interrupted));
+            assert!(echo_enabled(libc::STDIN_FILENO));
+        },
+        |pty| {
+            pty.expect("Password: ");
+            pty.send("not submitted");
+            pty.read_to_end();
+        },
+    );
+}
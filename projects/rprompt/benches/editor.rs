@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::io::Cursor;
+
+fn complete_fruits(line: &str) -> Vec<String> {
+    ["apple", "apricot", "banana"]
+        .iter()
+        .filter(|fruit| fruit.starts_with(line))
+        .map(|fruit| fruit.to_string())
+        .collect()
+}
+
+fn read(input: &[u8]) -> String {
+    rprompt::prompt_reply_with_completer_from_bufread(
+        &mut Cursor::new(input),
+        &mut std::io::sink(),
+        "Fruit? ",
+        &complete_fruits,
+    )
+    .unwrap()
+}
+
+fn key_decoding(c: &mut Criterion) {
+    // Arrow keys, function keys and multi-byte characters, which are decoded and ignored
+    let input = "\x1b[A\x1b[B\x1bOPpêche\x1b[C\x1b[D\n".as_bytes();
+    c.bench_function("editor/keys", |b| b.iter(|| read(black_box(input))));
+}
+
+fn redraw(c: &mut Criterion) {
+    // Each backspace and each Tab redraws the line
+    let input = b"banana\x7f\x7f\x7f\x7f\x7f\x7fap\t\t\t\t\n";
+    c.bench_function("editor/redraw", |b| b.iter(|| read(black_box(input))));
+}
+
+criterion_group!(benches, key_decoding, redraw);
+criterion_main!(benches);
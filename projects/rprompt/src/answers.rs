@@ -0,0 +1,206 @@
+//! Answers prompts without anybody at the terminal, ie: to run an interactive installer unattended
+//!
+//! Once answers are set, prompts that read from the terminal look for their answer first, by the
+//! text of the prompt without its surrounding whitespace:
+//! ```
+//! use rprompt::answers::{self, Answers, Unanswered};
+//!
+//! answers::set(Answers::new().answer("Port?", "8080").unanswered(Unanswered::Error));
+//! assert_eq!(rprompt::prompt_reply("Port? ").unwrap(), "8080");
+//! assert!(rprompt::prompt_reply("Host? ").is_err());
+//! answers::clear();
+//! ```
+//!
+//! With `assume_defaults`, prompts that have a default reply, ie: `prompt_reply_with_timeout` or a
+//! `Prompt` with a `default_reply`, reply with it instead of waiting for the user.
+//!
+//! Prompts that read from a `BufRead` don't look for answers, since their input is already under
+//! the caller's control.
+
+use crate::{Error, Result};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Mutex, MutexGuard};
+
+static ANSWERS: Mutex<Option<Answers>> = Mutex::new(None);
+
+/// What prompts that have no answer do
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unanswered {
+    /// Fail with an `Error::Io` of kind `NotFound`, so unattended runs don't wait for a user who
+    /// isn't there
+    #[default]
+    Error,
+    /// Read the reply from the terminal, as if there were no answers
+    Interactive,
+}
+
+/// Replies to give to prompts, by prompt text
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Answers {
+    replies: HashMap<String, String>,
+    defaults: bool,
+    unanswered: Unanswered,
+}
+
+impl Answers {
+    pub fn new() -> Answers {
+        Answers::default()
+    }
+
+    /// Reads answers from a JSON object, ie: `{"Port?": 8080, "Deploy? [Y/n]": "y"}`
+    ///
+    /// Numbers and booleans are turned into the text that would be typed for them.
+    #[cfg(feature = "json")]
+    pub fn from_json(reader: impl io::Read) -> Result<Answers> {
+        let invalid =
+            |message: String| Error::Io(io::Error::new(io::ErrorKind::InvalidData, message));
+        let object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_reader(reader).map_err(|err| invalid(err.to_string()))?;
+
+        let mut answers = Answers::new();
+        for (prompt, value) in object {
+            let reply = match value {
+                serde_json::Value::String(reply) => reply,
+                serde_json::Value::Number(number) => number.to_string(),
+                serde_json::Value::Bool(value) => value.to_string(),
+                _ => {
+                    return Err(invalid(format!(
+                        "the answer to \"{}\" should be a string, a number or a boolean",
+                        prompt
+                    )))
+                }
+            };
+            answers = answers.answer(prompt, reply);
+        }
+        Ok(answers)
+    }
+
+    /// Replies `reply` to the prompt whose text is `prompt`, ignoring surrounding whitespace
+    pub fn answer(mut self, prompt: impl ToString, reply: impl ToString) -> Answers {
+        self.replies
+            .insert(prompt.to_string().trim().to_string(), reply.to_string());
+        self
+    }
+
+    /// Replies with their default reply to prompts that have one and no answer
+    pub fn assume_defaults(mut self) -> Answers {
+        self.defaults = true;
+        self
+    }
+
+    /// Sets what prompts that have no answer do, `Unanswered::Error` by default
+    pub fn unanswered(mut self, unanswered: Unanswered) -> Answers {
+        self.unanswered = unanswered;
+        self
+    }
+}
+
+fn lock_answers() -> MutexGuard<'static, Option<Answers>> {
+    ANSWERS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Makes prompts reply with `answers` for the rest of the process, or until `clear` is called
+pub fn set(answers: Answers) {
+    *lock_answers() = Some(answers);
+}
+
+/// Makes prompts read from the terminal again
+pub fn clear() {
+    *lock_answers() = None;
+}
+
+/// Makes prompts that have a default reply reply with it, keeping the answers that are set, if any
+///
+/// When no answers were set, prompts that have no default reply are `Unanswered::Error`.
+pub fn assume_defaults() {
+    let mut answers = lock_answers();
+    let assumed = answers.take().unwrap_or_default().assume_defaults();
+    *answers = Some(assumed);
+}
+
+/// Returns the reply to give to `prompt`, or `None` if it should be read from the terminal
+pub(crate) fn lookup(prompt: &str, default: Option<&str>) -> Result<Option<String>> {
+    let answers = lock_answers();
+    let answers = match answers.as_ref() {
+        Some(answers) => answers,
+        None => return Ok(None),
+    };
+
+    let prompt = prompt.trim();
+    if let Some(reply) = answers.replies.get(prompt) {
+        return Ok(Some(reply.clone()));
+    }
+    match (default, answers.unanswered) {
+        (Some(default), _) if answers.defaults => Ok(Some(default.to_string())),
+        (_, Unanswered::Interactive) => Ok(None),
+        (_, Unanswered::Error) => Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no answer to the prompt \"{}\"", prompt),
+        ))),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{assume_defaults, clear, lookup, set, Answers, Unanswered};
+    use std::sync::{Mutex, MutexGuard};
+
+    // Answers are process-wide, so tests using them can't run at the same time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    pub(crate) fn serial() -> MutexGuard<'static, ()> {
+        SERIAL.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    #[test]
+    fn can_answer_by_prompt_text() {
+        let _serial = serial();
+        assert_eq!(lookup("Port? ", None).unwrap(), None);
+
+        set(Answers::new().answer(" Port? ", "8080"));
+        assert_eq!(lookup("Port?\n", None).unwrap(), Some("8080".to_string()));
+        let err = lookup("Host? ", Some("localhost")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("\"Host?\""));
+
+        set(Answers::new().unanswered(Unanswered::Interactive));
+        assert_eq!(lookup("Host? ", Some("localhost")).unwrap(), None);
+        clear();
+    }
+
+    #[test]
+    fn can_assume_defaults() {
+        let _serial = serial();
+        set(Answers::new().answer("Port?", "8080"));
+        assume_defaults();
+        assert_eq!(
+            lookup("Port?", Some("80")).unwrap(),
+            Some("8080".to_string())
+        );
+        assert_eq!(
+            lookup("Host?", Some("localhost")).unwrap(),
+            Some("localhost".to_string())
+        );
+        assert!(lookup("Name?", None).is_err());
+        clear();
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn can_read_answers_from_json() {
+        let answers =
+            Answers::from_json(&br#"{"Port?": 8080, "Deploy? [Y/n]": "y", "Verbose?": true}"#[..])
+                .unwrap();
+        assert_eq!(
+            answers,
+            Answers::new()
+                .answer("Port?", "8080")
+                .answer("Deploy? [Y/n]", "y")
+                .answer("Verbose?", "true")
+        );
+
+        assert!(Answers::from_json(&br#"{"Port?": [8080]}"#[..]).is_err());
+        assert!(Answers::from_json(&b"[]"[..]).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Prompts for command line arguments that are missing, with clap's value parsers
+//!
+//! Arguments that can be prompted for shouldn't be marked as required in clap, otherwise clap
+//! errors out before we get a chance to prompt:
+//! ```no_run
+//! use clap::{value_parser, Arg, Command};
+//!
+//! let matches = Command::new("server")
+//!     .arg(Arg::new("port").long("port").value_parser(value_parser!(u16)))
+//!     .get_matches();
+//! let port = rprompt::args::get_or_prompt(&matches, "port", "Port? ", value_parser!(u16)).unwrap();
+//! ```
+
+use crate::{Error, Prompt, Result};
+use clap::builder::TypedValueParser;
+use clap::{ArgMatches, Command};
+use std::ffi::OsStr;
+use std::io::{BufRead, Write};
+
+/// Returns the value of the argument `id` if it was given on the command line, prompts for it on
+/// the TTY otherwise, until the reply is accepted by `parser`
+pub fn get_or_prompt<P>(
+    matches: &ArgMatches,
+    id: &str,
+    prompt: impl ToString,
+    parser: P,
+) -> Result<P::Value>
+where
+    P: TypedValueParser,
+    P::Value: Clone + Send + Sync + 'static,
+{
+    if let Some(value) = get_one(matches, id)? {
+        return Ok(value);
+    }
+    let reply = prompt_for(prompt, &parser).read()?;
+    parse_reply(&parser, &reply)
+}
+
+/// Returns the value of the argument `id` if it was given on the command line, displays a message
+/// on a writer and reads the reply from anything that implements BufRead otherwise, until the
+/// reply is accepted by `parser`
+pub fn get_or_prompt_from_bufread<P>(
+    matches: &ArgMatches,
+    id: &str,
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    prompt: impl ToString,
+    parser: P,
+) -> Result<P::Value>
+where
+    P: TypedValueParser,
+    P::Value: Clone + Send + Sync + 'static,
+{
+    if let Some(value) = get_one(matches, id)? {
+        return Ok(value);
+    }
+    let reply = prompt_for(prompt, &parser).read_from_bufread(reader, writer)?;
+    parse_reply(&parser, &reply)
+}
+
+fn get_one<T>(matches: &ArgMatches, id: &str) -> Result<Option<T>>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    match matches.try_get_one::<T>(id) {
+        Ok(value) => Ok(value.cloned()),
+        Err(err) => Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            err.to_string(),
+        ))),
+    }
+}
+
+/// Builds a prompt that only accepts replies that `parser` can parse
+fn prompt_for<P: TypedValueParser>(prompt: impl ToString, parser: &P) -> Prompt {
+    let parser = parser.clone();
+    Prompt::new().text(prompt).validate(move |reply| {
+        parse(&parser, reply)
+            .map(|_| ())
+            .map_err(|err| err.to_string().trim_end().to_string())
+    })
+}
+
+fn parse_reply<P: TypedValueParser>(parser: &P, reply: &str) -> Result<P::Value> {
+    parse(parser, reply).map_err(|err| {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            err.to_string(),
+        ))
+    })
+}
+
+fn parse<P: TypedValueParser>(
+    parser: &P,
+    reply: &str,
+) -> std::result::Result<P::Value, clap::Error> {
+    parser.parse_ref(&Command::new(""), None, OsStr::new(reply))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_or_prompt_from_bufread;
+    use clap::{value_parser, Arg, Command};
+    use std::io::Cursor;
+
+    fn command() -> Command {
+        Command::new("server").arg(
+            Arg::new("port")
+                .long("port")
+                .value_parser(value_parser!(u16)),
+        )
+    }
+
+    #[test]
+    fn can_use_command_line_value() {
+        let matches = command().get_matches_from(["server", "--port", "8080"]);
+        let mut writer = Cursor::new(Vec::new());
+        let port = get_or_prompt_from_bufread(
+            &matches,
+            "port",
+            &mut Cursor::new(""),
+            &mut writer,
+            "Port? ",
+            value_parser!(u16),
+        )
+        .unwrap();
+        assert_eq!(port, 8080);
+        assert!(writer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn can_prompt_until_value_is_valid() {
+        let matches = command().get_matches_from(["server"]);
+        let mut writer = Cursor::new(Vec::new());
+        let port = get_or_prompt_from_bufread(
+            &matches,
+            "port",
+            &mut Cursor::new("http\n99999\n8080\n"),
+            &mut writer,
+            "Port? ",
+            value_parser!(u16),
+        )
+        .unwrap();
+        assert_eq!(port, 8080);
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(output.matches("Port? ").count(), 3);
+    }
+}
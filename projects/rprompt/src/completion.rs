@@ -0,0 +1,50 @@
+/// Provides candidates when the user hits Tab while typing a reply
+///
+/// Any `Fn(&str) -> Vec<String>` closure is a completer, which makes it easy to offer completion
+/// from a static list:
+/// ```
+/// let commands = ["get", "generate", "list"];
+/// let completer = |line: &str| {
+///     commands
+///         .iter()
+///         .filter(|c| c.starts_with(line))
+///         .map(|c| c.to_string())
+///         .collect::<Vec<String>>()
+/// };
+/// ```
+pub trait Completer {
+    /// Returns the replies that could complete what the user typed so far
+    ///
+    /// Each candidate replaces the whole line when it is picked, so it should include the part
+    /// that was already typed.
+    fn complete(&self, line: &str) -> Vec<String>;
+}
+
+impl<F> Completer for F
+where
+    F: Fn(&str) -> Vec<String>,
+{
+    fn complete(&self, line: &str) -> Vec<String> {
+        self(line)
+    }
+}
+
+/// Returns the longest prefix shared by all candidates
+pub(crate) fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix = match candidates.first() {
+        Some(first) => first.clone(),
+        None => return String::new(),
+    };
+
+    for candidate in &candidates[1..] {
+        let len = prefix
+            .chars()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        prefix.truncate(len);
+    }
+
+    prefix
+}
@@ -0,0 +1,404 @@
+use crate::completion::{common_prefix, Completer};
+use crate::number::NumberRange;
+use rtoolbox::key::{read_key, Key};
+use rtoolbox::print_tty::last_line;
+use std::io::{self, Read, Write};
+
+const BELL: &str = "\x07";
+
+/// Candidates listed after an ambiguous Tab, which the next Tabs cycle through
+struct Cycle {
+    candidates: Vec<String>,
+    next: usize,
+}
+
+/// Reads a line byte by byte, echoing it and handling editing keys along the way
+///
+/// The terminal is expected to be in raw mode already, so that we get every key as it is typed.
+pub(crate) struct LineEditor<'a, W: Write, C: Completer + ?Sized> {
+    writer: &'a mut W,
+    prompt: String,
+    completer: &'a C,
+    line: String,
+    rendered_len: usize,
+    cycle: Option<Cycle>,
+    width: Option<usize>,
+    range: Option<NumberRange>,
+    history: &'a [String],
+    /// The reply from `history` being shown, if Up was hit
+    recalled: Option<usize>,
+    /// What was typed before Up was hit, which Down brings back past the last reply
+    draft: String,
+}
+
+impl<'a, W: Write, C: Completer + ?Sized> LineEditor<'a, W, C> {
+    pub(crate) fn new(writer: &'a mut W, prompt: &str, completer: &'a C) -> Self {
+        LineEditor {
+            writer,
+            prompt: last_line(prompt).to_string(),
+            completer,
+            line: String::new(),
+            rendered_len: 0,
+            cycle: None,
+            width: None,
+            range: None,
+            history: &[],
+            recalled: None,
+            draft: String::new(),
+        }
+    }
+
+    /// Only accepts numbers in `range`, which Up and Down increment and decrement
+    pub(crate) fn number_range(mut self, range: NumberRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Brings back previous replies with Up and Down, latest first
+    pub(crate) fn history(mut self, history: &'a [String]) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Sets the width of the terminal, so that long lists of candidates are wrapped
+    #[cfg(any(unix, windows))]
+    pub(crate) fn width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub(crate) fn read_line(mut self, reader: &mut impl Read) -> io::Result<String> {
+        loop {
+            let key = match read_key(reader)? {
+                Some(key) => key,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "unexpected end of file",
+                    ))
+                }
+            };
+
+            if key != Key::Tab {
+                self.cycle = None;
+            }
+
+            match key {
+                Key::Enter => {
+                    if let Some(range) = self.range {
+                        if range.parse(&self.line).is_none() {
+                            self.write(BELL)?;
+                            continue;
+                        }
+                    }
+                    self.write("\n")?;
+                    return Ok(self.line);
+                }
+                Key::Tab => self.complete()?,
+                Key::Backspace if self.line.pop().is_some() => self.render()?,
+                Key::Ctrl('u') => {
+                    self.line.clear();
+                    self.render()?;
+                }
+                Key::Ctrl('c') => {
+                    self.write("\n")?;
+                    return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+                }
+                Key::Ctrl('d') if self.line.is_empty() => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "unexpected end of file",
+                    ));
+                }
+                // Pasted content is inserted at once, so that pasted tabs or newlines don't
+                // trigger completion or submit the line.
+                Key::Paste(pasted) => self.insert(&pasted)?,
+                Key::Up => self.spin(true)?,
+                Key::Down => self.spin(false)?,
+                Key::Char(c) => self.insert(c.encode_utf8(&mut [0u8; 4]))?,
+                _ => {}
+            }
+        }
+    }
+
+    fn insert(&mut self, s: &str) -> io::Result<()> {
+        self.line.push_str(s);
+        self.rendered_len += s.chars().count();
+        self.write(s)
+    }
+
+    fn spin(&mut self, up: bool) -> io::Result<()> {
+        match self.range {
+            Some(range) => {
+                self.line = range.spin(&self.line, up).to_string();
+                self.render()
+            }
+            None => self.recall(up),
+        }
+    }
+
+    fn recall(&mut self, up: bool) -> io::Result<()> {
+        let position = match (self.recalled, up) {
+            (None, true) if !self.history.is_empty() => {
+                self.draft = self.line.clone();
+                self.history.len() - 1
+            }
+            (Some(position), true) if position > 0 => position - 1,
+            (Some(position), false) if position + 1 < self.history.len() => position + 1,
+            (Some(_), false) => {
+                self.recalled = None;
+                self.line = std::mem::take(&mut self.draft);
+                return self.render();
+            }
+            _ => return Ok(()),
+        };
+        self.recalled = Some(position);
+        self.line = self.history[position].clone();
+        self.render()
+    }
+
+    fn complete(&mut self) -> io::Result<()> {
+        if let Some(cycle) = self.cycle.as_mut() {
+            self.line = cycle.candidates[cycle.next].clone();
+            cycle.next = (cycle.next + 1) % cycle.candidates.len();
+            return self.render();
+        }
+
+        let candidates = self.completer.complete(&self.line);
+        match candidates.len() {
+            0 => self.write(BELL),
+            1 => {
+                self.line = candidates[0].clone();
+                self.render()
+            }
+            _ => {
+                let prefix = common_prefix(&candidates);
+                if prefix.chars().count() > self.line.chars().count() {
+                    self.line = prefix;
+                    return self.render();
+                }
+
+                // Nothing more can be completed, so we show the candidates and let the next
+                // Tabs cycle through them.
+                self.write("\n")?;
+                self.write(&layout_candidates(&candidates, self.width))?;
+                self.write("\n")?;
+                self.rendered_len = 0;
+                self.cycle = Some(Cycle {
+                    candidates,
+                    next: 0,
+                });
+                self.render()
+            }
+        }
+    }
+
+    /// Rewrites the prompt and the current line, erasing leftovers from a longer line
+    fn render(&mut self) -> io::Result<()> {
+        let len = self.line.chars().count();
+        let mut output = format!("\r{}{}", self.prompt, self.line);
+        if len < self.rendered_len {
+            output.push_str(&" ".repeat(self.rendered_len - len));
+            output.push_str(&format!("\r{}{}", self.prompt, self.line));
+        }
+        self.rendered_len = len;
+        self.write(&output)
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        self.writer
+            .write_all(s.as_bytes())
+            .and_then(|_| self.writer.flush())
+    }
+}
+
+/// Lists candidates on as many lines as needed to fit in `width` columns
+fn layout_candidates(candidates: &[String], width: Option<usize>) -> String {
+    let width = match width {
+        Some(width) => width,
+        None => return candidates.join("  "),
+    };
+
+    let mut lines = vec![];
+    let mut line = String::new();
+    for candidate in candidates {
+        if !line.is_empty() && line.chars().count() + 2 + candidate.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push_str("  ");
+        }
+        line.push_str(candidate);
+    }
+    lines.push(line);
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout_candidates, LineEditor};
+    use crate::number::NumberRange;
+    use rtoolbox::testing::{keys, MockTty};
+
+    fn complete_fruits(line: &str) -> Vec<String> {
+        ["apple", "apricot", "banana"]
+            .iter()
+            .filter(|f| f.starts_with(line))
+            .map(|f| f.to_string())
+            .collect()
+    }
+
+    fn read(input: &[u8]) -> (std::io::Result<String>, String) {
+        let mut tty = MockTty::new().bytes(input);
+        let result = run(&mut tty);
+        (result, tty.output_string())
+    }
+
+    fn run(tty: &mut MockTty) -> std::io::Result<String> {
+        let (input, output) = tty.split();
+        LineEditor::new(output, "Fruit? ", &complete_fruits).read_line(input)
+    }
+
+    #[test]
+    fn can_complete_unique_candidate() {
+        assert_eq!(read(b"b\t\n").0.unwrap(), "banana");
+    }
+
+    #[test]
+    fn can_complete_common_prefix_then_cycle() {
+        assert_eq!(read(b"a\t\n").0.unwrap(), "ap");
+
+        let (reply, output) = read(b"ap\t\t\n");
+        assert_eq!(reply.unwrap(), "apple");
+        assert!(output.contains("apple  apricot"));
+
+        assert_eq!(read(b"ap\t\t\t\n").0.unwrap(), "apricot");
+    }
+
+    #[test]
+    fn can_wrap_candidates() {
+        let candidates = vec![
+            "apple".to_string(),
+            "apricot".to_string(),
+            "avocado".to_string(),
+        ];
+        assert_eq!(
+            layout_candidates(&candidates, None),
+            "apple  apricot  avocado"
+        );
+        assert_eq!(
+            layout_candidates(&candidates, Some(16)),
+            "apple  apricot\navocado"
+        );
+        assert_eq!(
+            layout_candidates(&candidates, Some(3)),
+            "apple\napricot\navocado"
+        );
+    }
+
+    #[test]
+    fn can_edit_line() {
+        assert_eq!(read(b"pear\x7f\x7fach\n").0.unwrap(), "peach");
+        assert_eq!(read(b"pear\x15kiwi\n").0.unwrap(), "kiwi");
+        assert_eq!(read(b"ki\x1b[Dwi\n").0.unwrap(), "kiwi");
+        assert_eq!(read("pêche\n".as_bytes()).0.unwrap(), "pêche");
+    }
+
+    #[test]
+    fn can_redraw_shorter_line() {
+        let mut tty = MockTty::new()
+            .typed("ap")
+            .key(keys::TAB)
+            .key(keys::TAB)
+            .key(keys::CTRL_U)
+            .typed("b")
+            .key(keys::ENTER);
+        assert_eq!(run(&mut tty).unwrap(), "b");
+        assert!(tty.output_string().contains("apple  apricot"));
+
+        let mut tty = MockTty::new()
+            .typed("apricot")
+            .key(keys::BACKSPACE)
+            .key(keys::CTRL_U)
+            .typed("ap");
+        assert!(run(&mut tty).is_err());
+        assert_eq!(tty.visible_line(), "Fruit? ap");
+    }
+
+    #[test]
+    fn can_spin_number() {
+        let mut tty = MockTty::new()
+            .key(keys::UP)
+            .key(keys::UP)
+            .key(keys::BACKSPACE)
+            .typed("9")
+            .key(keys::ENTER)
+            .key(keys::DOWN)
+            .key(keys::ENTER);
+        let (input, output) = tty.split();
+        let reply = LineEditor::new(output, "Threads? ", &complete_fruits)
+            .number_range(NumberRange::new(1, 8).step(2))
+            .read_line(input)
+            .unwrap();
+        // 9 is out of range, so the first ENTER is refused with a bell
+        assert_eq!(reply, "7");
+        assert!(tty.output_string().contains("\x07"));
+    }
+
+    #[test]
+    fn can_recall_history() {
+        let history = ["apple".to_string(), "banana".to_string()];
+        let mut tty = MockTty::new()
+            .typed("ki")
+            .key(keys::UP)
+            .key(keys::UP)
+            .key(keys::UP)
+            .key(keys::ENTER);
+        let (input, output) = tty.split();
+        let reply = LineEditor::new(output, "Fruit? ", &complete_fruits)
+            .history(&history)
+            .read_line(input)
+            .unwrap();
+        assert_eq!(reply, "apple");
+
+        // Going down past the latest reply brings back what was being typed
+        let mut tty = MockTty::new()
+            .typed("ki")
+            .key(keys::UP)
+            .key(keys::DOWN)
+            .key(keys::DOWN)
+            .typed("wi")
+            .key(keys::ENTER);
+        let (input, output) = tty.split();
+        let reply = LineEditor::new(output, "Fruit? ", &complete_fruits)
+            .history(&history)
+            .read_line(input)
+            .unwrap();
+        assert_eq!(reply, "kiwi");
+    }
+
+    #[test]
+    fn can_paste_at_once() {
+        assert_eq!(
+            read(b"ki\x1b[200~w\ti\nkiwi\x1b[201~\n").0.unwrap(),
+            "kiwikiwi"
+        );
+    }
+
+    #[test]
+    fn can_stop_reading() {
+        assert_eq!(
+            read(b"kiwi").0.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            read(b"\x04").0.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            read(b"kiwi\x03").0.unwrap_err().kind(),
+            std::io::ErrorKind::Interrupted
+        );
+    }
+}
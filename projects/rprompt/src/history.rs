@@ -0,0 +1,68 @@
+/// Replies typed so far, oldest first, which Up and Down bring back while typing a new one
+///
+/// Empty replies and replies that repeat the previous one aren't kept, and only the last `limit`
+/// replies are, 100 by default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct History {
+    entries: Vec<String>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::new()
+    }
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            entries: Vec::new(),
+            limit: 100,
+        }
+    }
+
+    /// Only keeps the last `limit` replies
+    pub fn limit(mut self, limit: usize) -> History {
+        self.limit = limit;
+        self.truncate();
+        self
+    }
+
+    pub fn push(&mut self, reply: impl ToString) {
+        let reply = reply.to_string();
+        if reply.trim().is_empty() || self.entries.last() == Some(&reply) {
+            return;
+        }
+        self.entries.push(reply);
+        self.truncate();
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    fn truncate(&mut self) {
+        if self.entries.len() > self.limit {
+            self.entries.drain(..self.entries.len() - self.limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn can_skip_empty_and_repeated_replies() {
+        let mut history = History::new().limit(2);
+        history.push("get github");
+        history.push("  ");
+        history.push("get github");
+        history.push("list");
+        assert_eq!(history.entries(), ["get github", "list"]);
+
+        history.push("get gitlab");
+        assert_eq!(history.entries(), ["list", "get gitlab"]);
+    }
+}
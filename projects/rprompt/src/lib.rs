@@ -1,6 +1,13 @@
 //! This library makes it easy to prompt for input in a console application on all platforms, Unix
 //! and Windows alike.
 //!
+//! Functions return an `Error` that tells apart the user hitting Ctrl-D (`Error::Eof`) or Ctrl-C
+//! (`Error::Interrupted`) from having no terminal to read from (`Error::NotATty`), and which
+//! converts into an `std::io::Error` for callers that don't care.
+//!
+//! On WASI, hermit and other WASM targets, there is no terminal to configure, so replies are read
+//! from stdin and completion isn't available.
+//!
 //! Here's how you can prompt for a reply:
 //! ```no_run
 //! let name = rprompt::prompt_reply("What's your name? ").unwrap();
@@ -22,28 +29,186 @@
 //! let name = rprompt::prompt_reply_from_bufread(&mut stdin.lock(), &mut stdout.lock(), "What's your name? ").unwrap();
 //! println!("Your name is {}", name);
 //! ```
+//!
+//! You can also offer completion when the user hits Tab, with any type that implements `Completer`:
+//! ```no_run
+//! let complete_fruit = |line: &str| {
+//!     ["apple", "apricot", "banana"]
+//!         .iter()
+//!         .filter(|fruit| fruit.starts_with(line))
+//!         .map(|fruit| fruit.to_string())
+//!         .collect::<Vec<String>>()
+//! };
+//! let fruit = rprompt::prompt_reply_with_completer("Favorite fruit? ", &complete_fruit).unwrap();
+//! println!("Your favorite fruit is {}", fruit);
+//! ```
+//!
+//! In a loop, previous replies can be brought back with Up and Down by keeping them in a `History`:
+//! ```no_run
+//! use rprompt::History;
+//!
+//! let mut history = History::new();
+//! loop {
+//!     let command = rprompt::prompt_reply_with_history("> ", &|_: &str| vec![], &mut history, None)
+//!         .unwrap();
+//!     if command == "quit" {
+//!         break;
+//!     }
+//! }
+//! ```
+//!
+//! Numbers can be picked with Up and Down, as well as typed:
+//! ```no_run
+//! use rprompt::NumberRange;
+//!
+//! let threads = rprompt::prompt_number("Threads? ", NumberRange::new(1, 64)).unwrap();
+//! println!("Using {} threads", threads);
+//! ```
+//!
+//! A default reply can apply when nobody is there to answer, optionally with a countdown:
+//! ```no_run
+//! use rprompt::Timeout;
+//! use std::time::Duration;
+//!
+//! let timeout = Timeout::new(Duration::from_secs(10), "y").countdown();
+//! let reply = rprompt::prompt_reply_with_timeout("Deploy? [Y/n] ", timeout).unwrap();
+//! println!("You replied {}", reply);
+//! ```
+//!
+//! Prompts can be styled, styles are only applied when the prompt is displayed on a terminal and
+//! when the `styling` feature, which is enabled by default, is on:
+//! ```no_run
+//! use rprompt::{Color, Style};
+//!
+//! let name = rprompt::prompt_reply_styled(&[
+//!     Style::new().fg(Color::Green).bold().apply("? "),
+//!     "What's your name? ".into(),
+//! ])
+//! .unwrap();
+//! println!("Your name is {}", name);
+//! ```
+//!
+//! Unattended runs can answer prompts in advance, or reply with their default, see `answers`:
+//! ```no_run
+//! use rprompt::answers::{self, Answers};
+//!
+//! answers::set(Answers::new().answer("What's your name?", "Ferris"));
+//! answers::assume_defaults();
+//! let name = rprompt::prompt_reply("What's your name? ").unwrap();
+//! assert_eq!(name, "Ferris");
+//! ```
+//!
+//! Prompts wait for their turn while another component, ie: an `rpassword` prompt on another
+//! thread, is reading from the terminal, see `rtoolbox::tty_lock`.
+
+pub mod answers;
+#[cfg(feature = "clap")]
+pub mod args;
+mod completion;
+mod editor;
+mod history;
+mod number;
+mod prompt;
+mod timeout;
 
+pub use answers::{Answers, Unanswered};
+pub use completion::Completer;
+use editor::LineEditor;
+pub use history::History;
+pub use number::NumberRange;
+pub use prompt::{Prompt, Reply};
+#[cfg(any(unix, windows))]
+use rtoolbox::bracketed_paste;
+#[cfg(windows)]
+use rtoolbox::console::ConsoleReader;
 use rtoolbox::fix_line_issues::fix_line_issues;
-use rtoolbox::print_tty::{print_tty, print_writer};
+use rtoolbox::print_tty::{print_styled_to, print_to, print_tty, print_writer};
+#[cfg(any(unix, windows))]
+use rtoolbox::raw_mode::{self, RawMode, RawModeGuard};
+#[cfg(any(unix, windows))]
+use rtoolbox::term;
+use rtoolbox::tty_lock;
+#[cfg(any(unix, windows))]
+use rtoolbox::writer_println;
 use std::io::{BufRead, BufReader, Write};
+use std::time::Duration;
+pub use timeout::Timeout;
+
+#[cfg(feature = "clipboard")]
+pub use rtoolbox::clipboard;
+pub use rtoolbox::error::{Error, Result};
+pub use rtoolbox::print_tty::PromptOutput;
+#[cfg(feature = "locked-memory")]
+pub use rtoolbox::secure_memory;
+use rtoolbox::style;
+pub use rtoolbox::style::{Color, Style, Styled};
+#[cfg(feature = "async")]
+pub use rtoolbox::unblock::Unblock;
 
 /// Reads user input from stdin
-pub fn read_reply() -> std::io::Result<String> {
+pub fn read_reply() -> Result<String> {
+    let _tty = tty_lock::acquire("rprompt");
     read_reply_from_bufread(&mut get_tty_reader()?)
 }
 
 /// Reads user input from anything that implements BufRead
-pub fn read_reply_from_bufread(reader: &mut impl BufRead) -> std::io::Result<String> {
+pub fn read_reply_from_bufread(reader: &mut impl BufRead) -> Result<String> {
     let mut reply = String::new();
 
     reader.read_line(&mut reply)?;
 
-    fix_line_issues(reply)
+    Ok(fix_line_issues(reply)?)
 }
 
 /// Displays a message on the TTY, then reads user input from stdin
-pub fn prompt_reply(prompt: impl ToString) -> std::io::Result<String> {
-    print_tty(prompt).and_then(|_| read_reply_from_bufread(&mut get_tty_reader()?))
+pub fn prompt_reply(prompt: impl ToString) -> Result<String> {
+    let prompt = prompt.to_string();
+    if let Some(reply) = answers::lookup(&prompt, None)? {
+        return Ok(reply);
+    }
+    let _tty = tty_lock::acquire("rprompt");
+    print_tty(prompt).map_err(Error::from_tty_error)?;
+    read_reply_from_bufread(&mut get_tty_reader()?)
+}
+
+/// Like `prompt_reply`, but reads the reply on another thread, so that async code can await it
+/// without blocking other tasks
+///
+/// ```no_run
+/// async fn ask_name() -> String {
+///     rprompt::prompt_reply_async("What's your name? ").await.unwrap()
+/// }
+/// ```
+#[cfg(feature = "async")]
+pub fn prompt_reply_async(prompt: impl ToString) -> Unblock<Result<String>> {
+    let prompt = prompt.to_string();
+    rtoolbox::unblock::unblock(move || prompt_reply(prompt))
+}
+
+/// Displays a message on the given output, then reads user input from the TTY
+///
+/// Displaying the message on stderr, for instance, keeps it out of stdout when it is piped:
+/// ```no_run
+/// let name = rprompt::prompt_reply_to(rprompt::PromptOutput::Stderr, "What's your name? ").unwrap();
+/// ```
+pub fn prompt_reply_to(output: PromptOutput, prompt: impl ToString) -> Result<String> {
+    let prompt = prompt.to_string();
+    if let Some(reply) = answers::lookup(&prompt, None)? {
+        return Ok(reply);
+    }
+    let _tty = tty_lock::acquire("rprompt");
+    print_to(output, prompt).map_err(Error::from_tty_error)?;
+    read_reply_from_bufread(&mut get_tty_reader()?)
+}
+
+/// Displays styled text on the TTY, then reads user input from the TTY
+pub fn prompt_reply_styled(prompt: &[Styled]) -> Result<String> {
+    if let Some(reply) = answers::lookup(&style::render(prompt, false), None)? {
+        return Ok(reply);
+    }
+    let _tty = tty_lock::acquire("rprompt");
+    print_styled_to(PromptOutput::Tty, prompt).map_err(Error::from_tty_error)?;
+    read_reply_from_bufread(&mut get_tty_reader()?)
 }
 
 /// Displays a message on the TTY, then reads user input from anything that implements BufRead
@@ -51,19 +216,304 @@ pub fn prompt_reply_from_bufread(
     reader: &mut impl BufRead,
     writer: &mut impl Write,
     prompt: impl ToString,
-) -> std::io::Result<String> {
-    print_writer(writer, prompt.to_string().as_str()).and_then(|_| read_reply_from_bufread(reader))
+) -> Result<String> {
+    print_writer(writer, prompt.to_string().as_str())?;
+    read_reply_from_bufread(reader)
+}
+
+/// Displays a message on the TTY, then reads user input from the TTY, completing it with
+/// `completer` when the user hits Tab
+#[cfg(any(unix, windows))]
+pub fn prompt_reply_with_completer(
+    prompt: impl ToString,
+    completer: &impl Completer,
+) -> Result<String> {
+    let prompt = prompt.to_string();
+    if let Some(reply) = answers::lookup(&prompt, None)? {
+        return Ok(reply);
+    }
+    let _tty = tty_lock::acquire("rprompt");
+    let (mut reader, mut writer, raw_mode) = get_raw_tty()?;
+    let bracketed_paste = bracketed_paste::is_supported();
+
+    if bracketed_paste {
+        print_writer(&mut writer, bracketed_paste::ENABLE)?;
+    }
+
+    let reply = print_writer(&mut writer, prompt.as_str()).and_then(|_| {
+        LineEditor::new(&mut writer, prompt.as_str(), completer)
+            .width(term::size().map(|(columns, _)| columns as usize))
+            .read_line(&mut reader)
+    });
+
+    if bracketed_paste {
+        print_writer(&mut writer, bracketed_paste::DISABLE)?;
+    }
+
+    std::mem::drop(raw_mode);
+
+    Ok(reply?)
+}
+
+/// Displays a message on stdout, then reads user input from stdin, without completion since there
+/// is no terminal to read keys from
+#[cfg(not(any(unix, windows)))]
+pub fn prompt_reply_with_completer(
+    prompt: impl ToString,
+    _completer: &impl Completer,
+) -> Result<String> {
+    prompt_reply(prompt)
+}
+
+/// Displays a message on a writer, then reads user input from anything that implements BufRead,
+/// completing it with `completer` when a Tab is read
+pub fn prompt_reply_with_completer_from_bufread(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    prompt: impl ToString,
+    completer: &impl Completer,
+) -> Result<String> {
+    let prompt = prompt.to_string();
+    print_writer(writer, prompt.as_str())?;
+    Ok(LineEditor::new(writer, prompt.as_str(), completer).read_line(reader)?)
+}
+
+/// Displays a message on the TTY, then reads user input from the TTY like
+/// `prompt_reply_with_completer`, bringing back the replies in `history` with Up and Down, and
+/// adds the reply to `history`
+///
+/// With `idle`, this is an `Error::TimedOut` if no key is typed before it runs out, ie: to lock an
+/// interactive shell. Once a key is typed, the reply is read without a time limit.
+#[cfg(any(unix, windows))]
+pub fn prompt_reply_with_history(
+    prompt: impl ToString,
+    completer: &(impl Completer + ?Sized),
+    history: &mut History,
+    idle: Option<Duration>,
+) -> Result<String> {
+    let prompt = prompt.to_string();
+    if let Some(reply) = answers::lookup(&prompt, None)? {
+        history.push(&reply);
+        return Ok(reply);
+    }
+    let _tty = tty_lock::acquire("rprompt");
+    let (mut reader, mut writer, raw_mode) = get_raw_tty()?;
+    let bracketed_paste = bracketed_paste::is_supported();
+
+    if bracketed_paste {
+        print_writer(&mut writer, bracketed_paste::ENABLE)?;
+    }
+
+    let reply = print_writer(&mut writer, prompt.as_str())
+        .and_then(|_| match idle {
+            Some(idle) => wait_for_key(&reader, idle),
+            None => Ok(true),
+        })
+        .and_then(|typed| match typed {
+            true => LineEditor::new(&mut writer, prompt.as_str(), completer)
+                .width(term::size().map(|(columns, _)| columns as usize))
+                .history(history.entries())
+                .read_line(&mut reader),
+            false => writer_println!(&mut writer).and_then(|_| {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "timed out",
+                ))
+            }),
+        });
+
+    if bracketed_paste {
+        print_writer(&mut writer, bracketed_paste::DISABLE)?;
+    }
+
+    std::mem::drop(raw_mode);
+
+    let reply = reply?;
+    history.push(&reply);
+    Ok(reply)
+}
+
+/// Displays a message on stdout, then reads user input from stdin and adds it to `history`,
+/// without recalling it nor a time limit since there is no terminal to read keys from
+#[cfg(not(any(unix, windows)))]
+pub fn prompt_reply_with_history(
+    prompt: impl ToString,
+    _completer: &(impl Completer + ?Sized),
+    history: &mut History,
+    _idle: Option<Duration>,
+) -> Result<String> {
+    let reply = prompt_reply(prompt)?;
+    history.push(&reply);
+    Ok(reply)
+}
+
+/// Displays a message on a writer, then reads user input from anything that implements BufRead,
+/// handling Tab, Up and Down like `prompt_reply_with_history`
+pub fn prompt_reply_with_history_from_bufread(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    prompt: impl ToString,
+    completer: &(impl Completer + ?Sized),
+    history: &mut History,
+) -> Result<String> {
+    let prompt = prompt.to_string();
+    print_writer(writer, prompt.as_str())?;
+    let reply = LineEditor::new(writer, prompt.as_str(), completer)
+        .history(history.entries())
+        .read_line(reader)?;
+    history.push(&reply);
+    Ok(reply)
+}
+
+/// Displays a message on the TTY, then reads a number in `range` from the TTY, which Up and Down
+/// increment and decrement by the range's step
+///
+/// Replies that aren't a number in range are refused when ENTER is hit. If the terminal can't be
+/// put in raw mode, the reply is read as a line instead, and is an `Error::Io` of kind
+/// `InvalidData` if it isn't a number in range.
+#[cfg(any(unix, windows))]
+pub fn prompt_number(prompt: impl ToString, range: NumberRange) -> Result<i64> {
+    let prompt = prompt.to_string();
+    if let Some(reply) = answers::lookup(&prompt, None)? {
+        return Ok(range.parse_or_err(&reply)?);
+    }
+    let _tty = tty_lock::acquire("rprompt");
+    let (mut reader, mut writer, raw_mode) = match get_raw_tty() {
+        Ok(tty) => tty,
+        Err(Error::NotATty(err)) => return Err(Error::NotATty(err)),
+        Err(_) => return Ok(range.parse_or_err(&prompt_reply(prompt)?)?),
+    };
+
+    let reply = print_writer(&mut writer, prompt.as_str()).and_then(|_| {
+        LineEditor::new(&mut writer, prompt.as_str(), &no_completion)
+            .number_range(range)
+            .read_line(&mut reader)
+    });
+
+    std::mem::drop(raw_mode);
+
+    Ok(range.parse_or_err(&reply?)?)
+}
+
+/// Displays a message on stdout, then reads a number in `range` from stdin, since there is no
+/// terminal to read keys from
+#[cfg(not(any(unix, windows)))]
+pub fn prompt_number(prompt: impl ToString, range: NumberRange) -> Result<i64> {
+    Ok(range.parse_or_err(&prompt_reply(prompt)?)?)
+}
+
+/// Displays a message on a writer, then reads a number in `range` from anything that implements
+/// BufRead, handling Up and Down like `prompt_number`
+pub fn prompt_number_from_bufread(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    prompt: impl ToString,
+    range: NumberRange,
+) -> Result<i64> {
+    let prompt = prompt.to_string();
+    print_writer(writer, prompt.as_str())?;
+    let reply = LineEditor::new(writer, prompt.as_str(), &no_completion)
+        .number_range(range)
+        .read_line(reader)?;
+    Ok(range.parse_or_err(&reply)?)
+}
+
+/// Displays a message on the TTY, then reads user input from the TTY, unless no key is typed before
+/// the timeout, in which case the timeout's default reply is returned
+///
+/// Once a key is typed, the reply is read without a time limit. If the terminal can't be put in raw
+/// mode, the reply is read as a line instead, without a time limit either.
+#[cfg(any(unix, windows))]
+pub fn prompt_reply_with_timeout(prompt: impl ToString, timeout: Timeout) -> Result<String> {
+    let prompt = prompt.to_string();
+    if let Some(reply) = answers::lookup(&prompt, Some(timeout.default_reply()))? {
+        return Ok(reply);
+    }
+    let _tty = tty_lock::acquire("rprompt");
+    let (mut reader, mut writer, raw_mode) = match get_raw_tty() {
+        Ok(tty) => tty,
+        Err(Error::NotATty(err)) => return Err(Error::NotATty(err)),
+        Err(_) => return prompt_reply(prompt),
+    };
+
+    let reply = print_writer(&mut writer, prompt.as_str())
+        .and_then(|_| {
+            timeout.wait(&mut writer, prompt.as_str(), |duration| {
+                wait_for_key(&reader, duration)
+            })
+        })
+        .and_then(|typed| match typed {
+            true => {
+                LineEditor::new(&mut writer, prompt.as_str(), &no_completion).read_line(&mut reader)
+            }
+            false => writer_println!(&mut writer, "{}", timeout.default_reply())
+                .map(|_| timeout.default_reply().to_string()),
+        });
+
+    std::mem::drop(raw_mode);
+
+    Ok(reply?)
+}
+
+/// Displays a message on stdout, then reads user input from stdin, without a time limit since there
+/// is no terminal to wait for keys on
+#[cfg(not(any(unix, windows)))]
+pub fn prompt_reply_with_timeout(prompt: impl ToString, timeout: Timeout) -> Result<String> {
+    let prompt = prompt.to_string();
+    match answers::lookup(&prompt, Some(timeout.default_reply()))? {
+        Some(reply) => Ok(reply),
+        None => prompt_reply(prompt),
+    }
+}
+
+fn no_completion(_line: &str) -> Vec<String> {
+    Vec::new()
 }
 
 #[cfg(unix)]
-fn get_tty_reader() -> std::io::Result<impl BufRead> {
-    Ok(BufReader::new(
-        std::fs::OpenOptions::new().read(true).open("/dev/tty")?,
-    ))
+fn get_raw_tty() -> Result<(std::fs::File, std::fs::File, RawModeGuard)> {
+    use std::os::unix::io::AsRawFd;
+
+    let tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(Error::from_tty_error)?;
+    let raw_mode = RawModeGuard::new(tty.as_raw_fd(), RawMode::Raw)?;
+    let writer = tty.try_clone()?;
+
+    Ok((tty, writer, raw_mode))
 }
 
 #[cfg(windows)]
-fn get_tty_reader() -> std::io::Result<impl BufRead> {
+fn get_raw_tty() -> Result<(ConsoleReader, std::fs::File, RawModeGuard)> {
+    use std::os::windows::io::AsRawHandle;
+
+    let reader = open_console(b"CONIN$\x00").map_err(Error::from_tty_error)?;
+    let writer = open_console(b"CONOUT$\x00").map_err(Error::from_tty_error)?;
+    // The guard is returned along with the console, which is only closed after it
+    let raw_mode = unsafe { RawModeGuard::new(reader.as_raw_handle() as _, RawMode::Raw)? };
+
+    Ok((ConsoleReader::new(reader), writer, raw_mode))
+}
+
+/// Waits up to `timeout` for a key to be typed on the TTY that `reader` reads, see
+/// `raw_mode::wait_for_key`
+#[cfg(unix)]
+fn wait_for_key(reader: &std::fs::File, timeout: Duration) -> std::io::Result<bool> {
+    raw_mode::wait_for_key(std::os::unix::io::AsRawFd::as_raw_fd(reader), timeout)
+}
+
+#[cfg(windows)]
+fn wait_for_key(reader: &ConsoleReader, timeout: Duration) -> std::io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+
+    // The console is borrowed, so it stays open while we wait
+    unsafe { raw_mode::wait_for_key(reader.as_raw_handle() as _, timeout) }
+}
+
+#[cfg(windows)]
+fn open_console(name: &[u8]) -> std::io::Result<std::fs::File> {
     use std::os::windows::io::FromRawHandle;
     use winapi::um::fileapi::{CreateFileA, OPEN_EXISTING};
     use winapi::um::handleapi::INVALID_HANDLE_VALUE;
@@ -71,7 +521,7 @@ fn get_tty_reader() -> std::io::Result<impl BufRead> {
 
     let handle = unsafe {
         CreateFileA(
-            b"CONIN$\x00".as_ptr() as *const i8,
+            name.as_ptr() as *const i8,
             GENERIC_READ | GENERIC_WRITE,
             FILE_SHARE_READ | FILE_SHARE_WRITE,
             std::ptr::null_mut(),
@@ -85,8 +535,27 @@ fn get_tty_reader() -> std::io::Result<impl BufRead> {
         return Err(std::io::Error::last_os_error());
     }
 
-    Ok(BufReader::new(unsafe {
-        std::fs::File::from_raw_handle(handle)
-    }))
+    Ok(unsafe { std::fs::File::from_raw_handle(handle) })
+}
+
+#[cfg(unix)]
+fn get_tty_reader() -> Result<impl BufRead> {
+    Ok(BufReader::new(
+        std::fs::OpenOptions::new()
+            .read(true)
+            .open("/dev/tty")
+            .map_err(Error::from_tty_error)?,
+    ))
+}
+
+#[cfg(windows)]
+fn get_tty_reader() -> Result<impl BufRead> {
+    Ok(BufReader::new(ConsoleReader::new(
+        open_console(b"CONIN$\x00").map_err(Error::from_tty_error)?,
+    )))
 }
 
+#[cfg(not(any(unix, windows)))]
+fn get_tty_reader() -> Result<impl BufRead> {
+    Ok(BufReader::new(std::io::stdin()))
+}
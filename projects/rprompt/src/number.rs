@@ -0,0 +1,82 @@
+use std::io;
+
+/// The numbers that `prompt_number` accepts, and how much Up and Down change the reply by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberRange {
+    min: i64,
+    max: i64,
+    step: i64,
+}
+
+impl NumberRange {
+    /// Accepts numbers from `min` to `max`, both included, which Up and Down change by 1
+    pub fn new(min: i64, max: i64) -> NumberRange {
+        NumberRange {
+            min: min.min(max),
+            max: min.max(max),
+            step: 1,
+        }
+    }
+
+    /// Sets how much Up and Down change the reply by
+    pub fn step(mut self, step: i64) -> NumberRange {
+        self.step = step.max(1);
+        self
+    }
+
+    /// Returns the number in `reply`, if it is one and it is in range
+    pub fn parse(&self, reply: &str) -> Option<i64> {
+        reply
+            .trim()
+            .parse()
+            .ok()
+            .filter(|n| (self.min..=self.max).contains(n))
+    }
+
+    /// Like `parse`, with an error that tells the user what's expected
+    pub(crate) fn parse_or_err(&self, reply: &str) -> io::Result<i64> {
+        self.parse(reply).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected a whole number from {} to {}", self.min, self.max),
+            )
+        })
+    }
+
+    /// Returns the reply after hitting Up (`up` is true) or Down, which starts from `min` or `max`
+    /// if the reply isn't a number yet
+    pub(crate) fn spin(&self, reply: &str, up: bool) -> i64 {
+        match reply.trim().parse::<i64>() {
+            Ok(n) if up => n.saturating_add(self.step).clamp(self.min, self.max),
+            Ok(n) => n.saturating_sub(self.step).clamp(self.min, self.max),
+            Err(_) if up => self.min,
+            Err(_) => self.max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NumberRange;
+
+    #[test]
+    fn can_parse_within_bounds() {
+        let range = NumberRange::new(1, 65535);
+        assert_eq!(range.parse("8080"), Some(8080));
+        assert_eq!(range.parse(" 1 "), Some(1));
+        assert_eq!(range.parse("0"), None);
+        assert_eq!(range.parse("65536"), None);
+        assert_eq!(range.parse("http"), None);
+    }
+
+    #[test]
+    fn can_spin_within_bounds() {
+        let range = NumberRange::new(0, 10).step(4);
+        assert_eq!(range.spin("", true), 0);
+        assert_eq!(range.spin("", false), 10);
+        assert_eq!(range.spin("4", true), 8);
+        assert_eq!(range.spin("8", true), 10);
+        assert_eq!(range.spin("2", false), 0);
+        assert_eq!(range.spin("-50", true), 0);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{answers, get_tty_reader, Error};
+use rtoolbox::fix_line_issues::fix_line_issues;
+use rtoolbox::print_tty::{print_styled_to, print_writer_styled, PromptOutput};
+use rtoolbox::style::{self, Color, Style, Styled};
+use rtoolbox::tty_lock;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type Validator = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+/// A reply, along with how it was obtained
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reply {
+    /// The last line read, as typed, including its line ending
+    pub raw: String,
+    /// The reply, without its line ending
+    pub value: String,
+    /// How many times the prompt was displayed, ie: 3 if the first 2 replies were invalid
+    pub attempts: u32,
+    /// How long it took from the first prompt to the valid reply
+    pub duration: Duration,
+}
+
+/// Builds a prompt out of styled parts, then reads the reply
+///
+/// ```no_run
+/// use rprompt::{Color, Prompt, Style};
+///
+/// let name = Prompt::new()
+///     .styled("? ", Style::new().fg(Color::Green).bold())
+///     .text("What's your name? ")
+///     .read()
+///     .unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct Prompt {
+    parts: Vec<Styled>,
+    output: PromptOutput,
+    default: Option<String>,
+    validator: Option<Validator>,
+}
+
+impl std::fmt::Debug for Prompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Prompt")
+            .field("parts", &self.parts)
+            .field("output", &self.output)
+            .field("default", &self.default)
+            .field("validator", &self.validator.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+impl Prompt {
+    pub fn new() -> Prompt {
+        Prompt::default()
+    }
+
+    /// Adds text without any style
+    pub fn text(self, text: impl ToString) -> Prompt {
+        self.styled(text, Style::new())
+    }
+
+    /// Adds text with a style, which is only applied when the prompt is displayed on a terminal
+    pub fn styled(mut self, text: impl ToString, style: Style) -> Prompt {
+        self.parts.push(style.apply(text));
+        self
+    }
+
+    /// Sets where the prompt is displayed, the TTY by default
+    pub fn output(mut self, output: PromptOutput) -> Prompt {
+        self.output = output;
+        self
+    }
+
+    /// Replies `default` when the reply is empty, and when defaults are assumed, see
+    /// `answers::assume_defaults`
+    pub fn default_reply(mut self, default: impl ToString) -> Prompt {
+        self.default = Some(default.to_string());
+        self
+    }
+
+    /// Checks replies with `validator`, displaying its error and prompting again until it accepts
+    /// one
+    pub fn validate<F>(mut self, validator: F) -> Prompt
+    where
+        F: Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Displays the prompt, then reads user input from the TTY
+    pub fn read(&self) -> crate::Result<String> {
+        self.read_reply().map(|reply| reply.value)
+    }
+
+    /// Like `read`, but returns the reply along with how it was obtained
+    ///
+    /// Replies that come from `answers` are checked once, and are an `Error::Io` of kind
+    /// `InvalidData` if they aren't valid, since nobody is there to reply again.
+    pub fn read_reply(&self) -> crate::Result<Reply> {
+        let start = Instant::now();
+        let text = style::render(&self.parts, false);
+        if let Some(value) = answers::lookup(&text, self.default.as_deref())? {
+            if let Some(error) = self.check(&value) {
+                return Err(Error::Io(io::Error::new(io::ErrorKind::InvalidData, error)));
+            }
+            return Ok(Reply {
+                raw: value.clone(),
+                value,
+                attempts: 1,
+                duration: start.elapsed(),
+            });
+        }
+
+        let _tty = tty_lock::acquire("rprompt");
+        let mut reader = get_tty_reader()?;
+        self.read_reply_with(&mut reader, |parts| {
+            print_styled_to(self.output, parts).map_err(Error::from_tty_error)
+        })
+    }
+
+    /// Displays the prompt on a writer, without styles, then reads user input from anything that
+    /// implements BufRead
+    pub fn read_from_bufread(
+        &self,
+        reader: &mut impl BufRead,
+        writer: &mut impl Write,
+    ) -> crate::Result<String> {
+        self.read_reply_from_bufread(reader, writer)
+            .map(|reply| reply.value)
+    }
+
+    /// Like `read_from_bufread`, but returns the reply along with how it was obtained
+    pub fn read_reply_from_bufread(
+        &self,
+        reader: &mut impl BufRead,
+        writer: &mut impl Write,
+    ) -> crate::Result<Reply> {
+        self.read_reply_with(reader, |parts| {
+            Ok(print_writer_styled(writer, parts, false)?)
+        })
+    }
+
+    fn read_reply_with(
+        &self,
+        reader: &mut impl BufRead,
+        mut print: impl FnMut(&[Styled]) -> crate::Result<()>,
+    ) -> crate::Result<Reply> {
+        let start = Instant::now();
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            print(&self.parts)?;
+
+            let mut raw = String::new();
+            reader.read_line(&mut raw)?;
+            let mut value = fix_line_issues(raw.clone())?;
+            if let (true, Some(default)) = (value.is_empty(), self.default.as_ref()) {
+                value = default.clone();
+            }
+
+            match self.check(&value) {
+                Some(error) => print(&[Style::new().fg(Color::Red).apply(error), "\n".into()])?,
+                None => {
+                    return Ok(Reply {
+                        raw,
+                        value,
+                        attempts,
+                        duration: start.elapsed(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Returns the validator's error if it refuses `value`
+    fn check(&self, value: &str) -> Option<String> {
+        match self.validator.as_ref() {
+            Some(validator) => validator(value).err(),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Prompt;
+    use crate::answers::{self, tests::serial, Answers};
+    use rtoolbox::style::{Color, Style};
+    use std::io::Cursor;
+
+    #[test]
+    fn can_prompt_without_styles_on_writers() {
+        let mut writer = Cursor::new(Vec::new());
+        let reply = Prompt::new()
+            .styled("? ", Style::new().fg(Color::Green))
+            .text("Name: ")
+            .read_from_bufread(&mut Cursor::new("Ferris\n"), &mut writer)
+            .unwrap();
+        assert_eq!(reply, "Ferris");
+        assert_eq!(writer.into_inner(), b"? Name: ");
+    }
+
+    #[test]
+    fn can_count_attempts() {
+        let mut writer = Cursor::new(Vec::new());
+        let reply = Prompt::new()
+            .text("Age: ")
+            .validate(|reply| match reply.parse::<u8>() {
+                Ok(_) => Ok(()),
+                Err(_) => Err("Not a number".to_string()),
+            })
+            .read_reply_from_bufread(&mut Cursor::new("old\r\n42\r\n"), &mut writer)
+            .unwrap();
+        assert_eq!(reply.raw, "42\r\n");
+        assert_eq!(reply.value, "42");
+        assert_eq!(reply.attempts, 2);
+        assert_eq!(writer.into_inner(), b"Age: Not a number\nAge: ");
+    }
+
+    #[test]
+    fn can_reply_default_when_empty() {
+        let mut writer = Cursor::new(Vec::new());
+        let prompt = Prompt::new().text("Deploy? [Y/n] ").default_reply("y");
+        let reply = prompt
+            .read_from_bufread(&mut Cursor::new("\nn\n"), &mut writer)
+            .unwrap();
+        assert_eq!(reply, "y");
+    }
+
+    #[test]
+    fn can_reply_from_answers() {
+        let _serial = serial();
+        let prompt = Prompt::new()
+            .styled("? ", Style::new().fg(Color::Green))
+            .text("Age: ")
+            .validate(|reply| match reply.parse::<u8>() {
+                Ok(_) => Ok(()),
+                Err(_) => Err("Not a number".to_string()),
+            });
+
+        answers::set(Answers::new().answer("? Age:", "42"));
+        let reply = prompt.read_reply().unwrap();
+        assert_eq!(reply.value, "42");
+        assert_eq!(reply.attempts, 1);
+
+        answers::set(Answers::new().answer("? Age:", "old"));
+        let err = prompt.read().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "Not a number");
+
+        answers::set(Answers::new());
+        answers::assume_defaults();
+        assert_eq!(prompt.clone().default_reply("7").read().unwrap(), "7");
+        answers::clear();
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(any(unix, windows))]
+use rtoolbox::print_tty::last_line;
+#[cfg(any(unix, windows))]
+use rtoolbox::writer_print;
+#[cfg(any(unix, windows))]
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// How long `prompt_reply_with_timeout` waits for the user to start typing, and the reply it gives
+/// if they don't
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timeout {
+    duration: Duration,
+    default: String,
+    countdown: bool,
+}
+
+impl Timeout {
+    /// Replies `default` if no key is typed within `duration`
+    pub fn new(duration: Duration, default: impl ToString) -> Timeout {
+        Timeout {
+            duration,
+            default: default.to_string(),
+            countdown: false,
+        }
+    }
+
+    /// Displays the seconds left after the prompt, ie: `(8s)`, until a key is typed
+    pub fn countdown(mut self) -> Timeout {
+        self.countdown = true;
+        self
+    }
+
+    /// Returns the reply given when no key is typed in time
+    pub fn default_reply(&self) -> &str {
+        self.default.as_str()
+    }
+
+    /// Waits for a key with `wait_for_key`, updating the countdown in place every second if there
+    /// is one, and returns false if time ran out
+    ///
+    /// The cursor is expected right after `prompt`, which is written again to move it back there
+    /// once the countdown is updated or erased.
+    #[cfg(any(unix, windows))]
+    pub(crate) fn wait(
+        &self,
+        writer: &mut impl Write,
+        prompt: &str,
+        mut wait_for_key: impl FnMut(Duration) -> io::Result<bool>,
+    ) -> io::Result<bool> {
+        let prompt = last_line(prompt);
+        let mut remaining = self.duration;
+        let mut rendered_len = 0;
+
+        let typed = loop {
+            if remaining == Duration::ZERO {
+                break false;
+            }
+
+            // We wake up whenever the number of seconds displayed changes
+            let seconds = remaining.as_millis().div_ceil(1000) as u64;
+            let tick = remaining - Duration::from_secs(seconds - 1);
+            if self.countdown {
+                let label = format!("({}s)", seconds);
+                writer_print!(writer, "{:<2$}\r{}", label, prompt, rendered_len)?;
+                rendered_len = label.len();
+            }
+
+            if wait_for_key(tick)? {
+                break true;
+            }
+            remaining -= tick;
+        };
+
+        if rendered_len > 0 {
+            writer_print!(writer, "{:1$}\r{2}", "", rendered_len, prompt)?;
+        }
+        Ok(typed)
+    }
+}
+
+#[cfg(all(test, any(unix, windows)))]
+mod tests {
+    use super::Timeout;
+    use std::time::Duration;
+
+    #[test]
+    fn can_count_down_until_time_runs_out() {
+        let mut writer = Vec::new();
+        let mut ticks = Vec::new();
+        let timeout = Timeout::new(Duration::from_millis(10500), "y").countdown();
+        let typed = timeout
+            .wait(&mut writer, "Deploy?\n[Y/n] ", |tick| {
+                ticks.push(tick);
+                Ok(false)
+            })
+            .unwrap();
+
+        assert!(!typed);
+        assert_eq!(ticks.len(), 11);
+        assert_eq!(ticks[0], Duration::from_millis(500));
+        assert_eq!(ticks.iter().sum::<Duration>(), Duration::from_millis(10500));
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.starts_with("(11s)\r[Y/n] (10s)\r[Y/n] (9s) \r[Y/n] (8s)\r[Y/n] "));
+        assert!(output.ends_with("(1s)\r[Y/n]     \r[Y/n] "));
+    }
+
+    #[test]
+    fn can_stop_counting_down_on_key() {
+        let mut writer = Vec::new();
+        let timeout = Timeout::new(Duration::from_secs(8), "y").countdown();
+        assert!(timeout.wait(&mut writer, "> ", |_| Ok(true)).unwrap());
+        assert_eq!(writer, b"(8s)\r>     \r> ");
+
+        let mut writer = Vec::new();
+        let timeout = Timeout::new(Duration::from_secs(8), "y");
+        assert!(timeout.wait(&mut writer, "> ", |_| Ok(true)).unwrap());
+        assert!(writer.is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rtoolbox::bracketed_paste::{self, PastePolicy};
+use rtoolbox::fix_line_issues::fix_line_issues;
+use rtoolbox::safe_string::SafeString;
+use std::io::Cursor;
+
+fn line_reading(c: &mut Criterion) {
+    let typed = "correct horse battery staple\r\n";
+    let pasted = "\x1b[200~correct horse\nbattery staple\x1b[201~\n";
+
+    c.bench_function("read_line/typed", |b| {
+        b.iter(|| {
+            let mut line = String::new();
+            bracketed_paste::read_line(
+                &mut Cursor::new(black_box(typed)),
+                &mut line,
+                PastePolicy::Allow,
+            )
+            .unwrap();
+            fix_line_issues(line).unwrap()
+        })
+    });
+
+    c.bench_function("read_line/pasted", |b| {
+        b.iter(|| {
+            let mut line = String::new();
+            bracketed_paste::read_line(
+                &mut Cursor::new(black_box(pasted)),
+                &mut line,
+                PastePolicy::Allow,
+            )
+            .unwrap();
+            fix_line_issues(line).unwrap()
+        })
+    });
+
+    c.bench_function("read_pasted", |b| {
+        b.iter(|| bracketed_paste::read_pasted(&mut Cursor::new(black_box(&pasted[6..]))).unwrap())
+    });
+}
+
+fn safe_string_growth(c: &mut Criterion) {
+    for len in [64, 1024] {
+        c.bench_function(&format!("safe_string/push/{}", len), |b| {
+            b.iter(|| {
+                let mut s = SafeString::new();
+                for _ in 0..len {
+                    s.push(black_box('x'));
+                }
+                s
+            })
+        });
+    }
+}
+
+criterion_group!(benches, line_reading, safe_string_growth);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+//! Fails if benchmarks got slower than a threshold, compared to a criterion baseline
+//!
+//! ```sh
+//! git checkout main && cargo bench -- --save-baseline main
+//! git checkout my-branch && cargo bench -- --baseline main
+//! cargo run --example check_bench_regressions -- target/criterion 10
+//! ```
+
+use std::path::Path;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let dir = args
+        .get(1)
+        .map(String::as_str)
+        .unwrap_or("target/criterion");
+    let threshold: f64 = match args.get(2).map(|t| t.parse()) {
+        Some(Ok(threshold)) => threshold,
+        Some(Err(_)) => {
+            eprintln!("usage: check_bench_regressions [criterion-dir] [threshold-percent]");
+            std::process::exit(2);
+        }
+        None => 10.0,
+    };
+
+    let mut changes = vec![];
+    collect_changes(Path::new(dir), Path::new(dir), &mut changes);
+    if changes.is_empty() {
+        eprintln!(
+            "No comparison found in {}, did you run `cargo bench -- --baseline <name>`?",
+            dir
+        );
+        std::process::exit(2);
+    }
+
+    let mut regressions = 0;
+    for (name, change) in changes {
+        let percent = change * 100.0;
+        let regressed = percent > threshold;
+        if regressed {
+            regressions += 1;
+        }
+        println!(
+            "{} {:+.1}% {}",
+            name,
+            percent,
+            if regressed { "REGRESSED" } else { "ok" }
+        );
+    }
+
+    if regressions > 0 {
+        eprintln!(
+            "{} benchmark(s) got more than {}% slower",
+            regressions, threshold
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Finds the mean relative change of each benchmark, in `<name>/change/estimates.json` files
+fn collect_changes(root: &Path, dir: &Path, changes: &mut Vec<(String, f64)>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let estimates = path.join("change").join("estimates.json");
+        let change = std::fs::read_to_string(&estimates)
+            .ok()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+            .and_then(|json| json["mean"]["point_estimate"].as_f64());
+        match change {
+            Some(change) => {
+                let name = path.strip_prefix(root).unwrap_or(&path);
+                changes.push((name.display().to_string(), change))
+            }
+            None => collect_changes(root, &path, changes),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::safe_string::SafeString;
+use crate::safe_vec::SafeVec;
+use std::io::{BufRead, Read};
+
+/// Asks the terminal to wrap pasted content between `PASTE_START` and `PASTE_END`
+pub const ENABLE: &str = "\x1b[?2004h";
+
+/// Asks the terminal to stop wrapping pasted content
+pub const DISABLE: &str = "\x1b[?2004l";
+
+/// Sent by the terminal before pasted content
+pub const PASTE_START: &str = "\x1b[200~";
+
+/// Sent by the terminal after pasted content
+pub const PASTE_END: &str = "\x1b[201~";
+
+/// What to do when the user pastes content instead of typing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PastePolicy {
+    /// Pasted content is accepted, without its control characters
+    Allow,
+    /// Pasted content is refused with an `InvalidData` error
+    Reject,
+}
+
+/// Returns true if the terminal is likely to understand bracketed paste
+///
+/// Terminals that don't support it ignore the sequences, so this is only used to avoid printing
+/// garbage on very basic terminals.
+#[cfg(not(windows))]
+pub fn is_supported() -> bool {
+    match std::env::var("TERM") {
+        Ok(term) => !term.is_empty() && term != "dumb",
+        Err(_) => false,
+    }
+}
+
+/// Returns true if the terminal is likely to understand bracketed paste
+///
+/// The legacy Windows console doesn't, but Windows Terminal does.
+#[cfg(windows)]
+pub fn is_supported() -> bool {
+    std::env::var_os("WT_SESSION").is_some()
+}
+
+/// Removes control characters, including newlines, from pasted content
+pub fn sanitize(pasted: &str) -> String {
+    pasted.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Reads a line like `BufRead::read_line`, handling content pasted with bracketed paste
+///
+/// A paste is handled as a whole: newlines inside it don't end the line and its control
+/// characters are removed, so that pasting a multi-line secret doesn't submit part of it.
+pub fn read_line(
+    reader: &mut impl BufRead,
+    buf: &mut String,
+    policy: PastePolicy,
+) -> std::io::Result<usize> {
+    let start = buf.len();
+    reader.read_line(buf)?;
+
+    while has_unterminated_paste(&buf[start..]) {
+        if reader.read_line(buf)? == 0 {
+            break;
+        }
+    }
+
+    if !buf[start..].contains(PASTE_START) {
+        return Ok(buf.len() - start);
+    }
+
+    if policy == PastePolicy::Reject {
+        clear(buf, start);
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "pasting is not allowed",
+        ));
+    }
+
+    let stripped = SafeString::from_string(strip_pastes(&buf[start..]));
+    clear(buf, start);
+    buf.push_str(&stripped);
+
+    Ok(buf.len() - start)
+}
+
+/// Reads pasted content right after `PASTE_START` was read, up to and including `PASTE_END`
+///
+/// The content is returned without its control characters.
+pub fn read_pasted(reader: &mut impl Read) -> std::io::Result<SafeString> {
+    let mut pasted = SafeVec::new(Vec::new());
+    let mut byte = [0u8; 1];
+
+    while !pasted.ends_with(PASTE_END.as_bytes()) {
+        if reader.read(&mut byte)? == 0 {
+            break;
+        }
+        pasted.inner_mut().push(byte[0]);
+    }
+
+    if pasted.ends_with(PASTE_END.as_bytes()) {
+        let len = pasted.len() - PASTE_END.len();
+        pasted.inner_mut().truncate(len);
+    }
+
+    Ok(SafeString::from_string(sanitize(&String::from_utf8_lossy(
+        &pasted,
+    ))))
+}
+
+fn has_unterminated_paste(line: &str) -> bool {
+    match (line.rfind(PASTE_START), line.rfind(PASTE_END)) {
+        (Some(start), Some(end)) => start > end,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Removes paste markers from a line and sanitizes what was pasted between them
+fn strip_pastes(line: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find(PASTE_START) {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start + PASTE_START.len()..];
+
+        let end = rest.find(PASTE_END).unwrap_or(rest.len());
+        stripped.push_str(&sanitize(&rest[..end]));
+        rest = &rest[(end + PASTE_END.len()).min(rest.len())..];
+    }
+    stripped.push_str(rest);
+
+    stripped
+}
+
+/// Zeroes then truncates what was read into `buf` after `start`
+fn clear(buf: &mut String, start: usize) {
+    for b in unsafe { buf[start..].as_bytes_mut() } {
+        *b = 0;
+    }
+    buf.truncate(start);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_line, read_pasted, PastePolicy};
+    use std::io::Cursor;
+
+    #[test]
+    fn can_read_typed_line() {
+        let mut line = String::new();
+        read_line(&mut Cursor::new("typed\n"), &mut line, PastePolicy::Reject).unwrap();
+        assert_eq!(line, "typed\n");
+    }
+
+    #[test]
+    fn can_read_multi_line_paste_at_once() {
+        let mut reader = Cursor::new("a\x1b[200~multi\nline\t\x1b[201~b\nnext\n");
+        let mut line = String::new();
+        read_line(&mut reader, &mut line, PastePolicy::Allow).unwrap();
+        assert_eq!(line, "amultilineb\n");
+
+        let mut line = String::new();
+        read_line(&mut reader, &mut line, PastePolicy::Allow).unwrap();
+        assert_eq!(line, "next\n");
+    }
+
+    #[test]
+    fn can_reject_paste() {
+        let mut line = String::new();
+        let err = read_line(
+            &mut Cursor::new("\x1b[200~pasted\x1b[201~\n"),
+            &mut line,
+            PastePolicy::Reject,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(line, "");
+    }
+
+    #[test]
+    fn can_read_pasted_bytes() {
+        let mut reader = Cursor::new("pas\x07ted\x1b[201~after");
+        assert_eq!(read_pasted(&mut reader).unwrap().as_str(), "pasted");
+    }
+}
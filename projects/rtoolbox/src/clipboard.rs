@@ -0,0 +1,36 @@
+//! Copies text to and from the system clipboard
+//!
+//! ```no_run
+//! use rtoolbox::clipboard;
+//! use rtoolbox::safe_string::SafeString;
+//!
+//! clipboard::copy(&SafeString::from_string("hunter2".to_string())).unwrap();
+//! assert_eq!(&*clipboard::paste().unwrap(), "hunter2");
+//! ```
+//!
+//! On Linux and BSD, this talks to the X11 server, so Wayland sessions need XWayland.
+
+use crate::safe_string::SafeString;
+use clipboard::{ClipboardContext, ClipboardProvider};
+use std::io;
+use std::ops::Deref;
+
+/// Replaces the contents of the clipboard with `text`
+pub fn copy(text: &SafeString) -> io::Result<()> {
+    let mut context: ClipboardContext = ClipboardProvider::new().map_err(clipboard_error)?;
+    context
+        .set_contents(text.deref().to_owned())
+        .map_err(clipboard_error)
+}
+
+/// Returns the text in the clipboard
+pub fn paste() -> io::Result<SafeString> {
+    let mut context: ClipboardContext = ClipboardProvider::new().map_err(clipboard_error)?;
+    Ok(SafeString::from_string(
+        context.get_contents().map_err(clipboard_error)?,
+    ))
+}
+
+fn clipboard_error(err: Box<dyn std::error::Error>) -> io::Error {
+    io::Error::other(err.to_string())
+}
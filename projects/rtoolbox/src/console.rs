@@ -0,0 +1,186 @@
+use crate::key::Key;
+use crate::safe_vec::SafeVec;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::windows::io::{AsRawHandle, RawHandle};
+use winapi::shared::minwindef::DWORD;
+use winapi::um::consoleapi::ReadConsoleW;
+
+/// How many UTF-16 units we ask the console for at once
+const BUFFER_LEN: usize = 1024;
+
+/// Reads text from a Windows console as UTF-8, whatever the console code page is
+///
+/// Reading a console like a file goes through the console code page, which mangles characters
+/// outside of it, like Cyrillic, CJK or emoji with most western code pages. Instead, we read
+/// UTF-16 with `ReadConsoleW` and convert it. Decoded text waiting to be read is zeroed when
+/// dropped, since it may be a password.
+pub struct ConsoleReader {
+    console: File,
+    pending: SafeVec,
+    high_surrogate: Option<u16>,
+}
+
+impl ConsoleReader {
+    /// Wraps a handle to `CONIN$`
+    pub fn new(console: File) -> ConsoleReader {
+        ConsoleReader {
+            console,
+            pending: SafeVec::new(Vec::new()),
+            high_surrogate: None,
+        }
+    }
+
+    /// Reads UTF-16 from the console and appends it to the pending UTF-8 bytes
+    fn fill(&mut self) -> io::Result<()> {
+        let mut utf16 = [0u16; BUFFER_LEN];
+        let mut read: DWORD = 0;
+
+        let ret = unsafe {
+            ReadConsoleW(
+                self.console.as_raw_handle() as _,
+                utf16.as_mut_ptr() as _,
+                BUFFER_LEN as DWORD,
+                &mut read,
+                std::ptr::null_mut(),
+            )
+        };
+        if ret == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut units = self.high_surrogate.take().into_iter().collect::<Vec<u16>>();
+        units.extend_from_slice(&utf16[..read as usize]);
+
+        // A character outside of the BMP may be split across two reads
+        if let Some(&last) = units.last() {
+            if (0xD800..=0xDBFF).contains(&last) {
+                self.high_surrogate = units.pop();
+            }
+        }
+
+        let mut utf8 = [0u8; 4];
+        for c in char::decode_utf16(units.iter().cloned()) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.pending
+                .inner_mut()
+                .extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+        }
+
+        zero(&mut utf16);
+        zero(&mut units);
+        zero(&mut utf8);
+
+        Ok(())
+    }
+}
+
+impl ConsoleReader {
+    /// Reads the next key from the console events, which tell keys that aren't text apart
+    ///
+    /// The console should be in `Raw` mode, so that Ctrl-C is read like any other key. Key
+    /// releases and other events, like window resizes, are skipped.
+    pub fn read_key(&mut self) -> io::Result<Option<Key>> {
+        use winapi::um::consoleapi::ReadConsoleInputW;
+        use winapi::um::wincon::{LEFT_CTRL_PRESSED, RIGHT_CTRL_PRESSED, SHIFT_PRESSED};
+        use winapi::um::wincontypes::{INPUT_RECORD, KEY_EVENT};
+        use winapi::um::winuser::*;
+
+        loop {
+            let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+            let mut read: DWORD = 0;
+            let ret = unsafe {
+                ReadConsoleInputW(self.console.as_raw_handle() as _, &mut record, 1, &mut read)
+            };
+            if ret == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if read == 0 {
+                return Ok(None);
+            }
+            if record.EventType != KEY_EVENT {
+                continue;
+            }
+            let event = unsafe { record.Event.KeyEvent() };
+            if event.bKeyDown == 0 {
+                continue;
+            }
+
+            let ctrl = event.dwControlKeyState & (LEFT_CTRL_PRESSED | RIGHT_CTRL_PRESSED) != 0;
+            let shift = event.dwControlKeyState & SHIFT_PRESSED != 0;
+            let unit = unsafe { *event.uChar.UnicodeChar() };
+            let key = match event.wVirtualKeyCode as i32 {
+                VK_RETURN => Key::Enter,
+                VK_TAB if shift => Key::BackTab,
+                VK_TAB => Key::Tab,
+                VK_BACK => Key::Backspace,
+                VK_DELETE => Key::Delete,
+                VK_INSERT => Key::Insert,
+                VK_ESCAPE => Key::Escape,
+                VK_UP => Key::Up,
+                VK_DOWN => Key::Down,
+                VK_LEFT => Key::Left,
+                VK_RIGHT => Key::Right,
+                VK_HOME => Key::Home,
+                VK_END => Key::End,
+                VK_PRIOR => Key::PageUp,
+                VK_NEXT => Key::PageDown,
+                code @ VK_F1..=VK_F24 => Key::F((code - VK_F1 + 1) as u8),
+                // Keys like Shift alone don't type anything
+                _ if unit == 0 => continue,
+                _ if ctrl && (0x01..=0x1a).contains(&unit) => {
+                    Key::Ctrl((b'a' + unit as u8 - 1) as char)
+                }
+                // Characters outside of the BMP come in two events
+                _ if (0xD800..=0xDBFF).contains(&unit) => {
+                    self.high_surrogate = Some(unit);
+                    continue;
+                }
+                _ => {
+                    let units = self.high_surrogate.take().into_iter().chain(Some(unit));
+                    match char::decode_utf16(units).next() {
+                        Some(Ok(c)) => Key::Char(c),
+                        _ => Key::Unknown(vec![]),
+                    }
+                }
+            };
+            return Ok(Some(key));
+        }
+    }
+}
+
+impl AsRawHandle for ConsoleReader {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.console.as_raw_handle()
+    }
+}
+
+impl Read for ConsoleReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // A read of zero units means the end of the input, so we keep reading while we only got
+        // half of a surrogate pair.
+        while self.pending.is_empty() {
+            let had_surrogate = self.high_surrogate.is_some();
+            self.fill()?;
+            if self.pending.is_empty() && self.high_surrogate.is_some() == had_surrogate {
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.pending.len());
+        buf[..len].copy_from_slice(&self.pending[..len]);
+
+        let pending = self.pending.inner_mut();
+        zero(&mut pending[..len]);
+        pending.drain(..len);
+
+        Ok(len)
+    }
+}
+
+fn zero<T: Default>(buf: &mut [T]) {
+    for unit in buf.iter_mut() {
+        unsafe { std::ptr::write_volatile(unit, T::default()) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
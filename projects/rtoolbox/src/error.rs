@@ -0,0 +1,140 @@
+use std::io;
+
+/// Why reading input failed
+///
+/// Callers can tell apart situations that call for different reactions, like the user hitting
+/// Ctrl-D, which usually means "cancel", and not having a terminal at all, which may call for a
+/// fallback, like reading an environment variable.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// There is no terminal to read from, ie: in a cron job or a container started without one
+    NotATty(io::Error),
+    /// Reading was interrupted, ie: the user hit Ctrl-C
+    Interrupted,
+    /// Nothing was read in time
+    TimedOut,
+    /// The input ended before a full line was read, ie: the user hit Ctrl-D or stdin was closed
+    Eof,
+    /// The input isn't valid UTF-8
+    InvalidUtf8,
+    /// Another component is reading from the terminal, see `tty_lock`
+    Busy(String),
+    /// Any other I/O error
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Turns an error we got while opening the terminal into `NotATty` if there is no terminal
+    pub fn from_tty_error(err: io::Error) -> Error {
+        match is_missing_tty_error(&err) {
+            true => Error::NotATty(err),
+            false => Error::from(err),
+        }
+    }
+
+    /// Returns the `std::io::ErrorKind` that the error has once converted to an `std::io::Error`
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::NotATty(err) | Error::Io(err) => err.kind(),
+            Error::Interrupted => io::ErrorKind::Interrupted,
+            Error::TimedOut => io::ErrorKind::TimedOut,
+            Error::Eof => io::ErrorKind::UnexpectedEof,
+            Error::InvalidUtf8 => io::ErrorKind::InvalidData,
+            Error::Busy(_) => io::ErrorKind::WouldBlock,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotATty(err) => write!(f, "no terminal available ({})", err),
+            Error::Interrupted => write!(f, "interrupted"),
+            Error::TimedOut => write!(f, "timed out"),
+            Error::Eof => write!(f, "unexpected end of file"),
+            Error::InvalidUtf8 => write!(f, "input is not valid UTF-8"),
+            Error::Busy(owner) => write!(f, "the terminal is in use by {}", owner),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NotATty(err) | Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        match err.kind() {
+            io::ErrorKind::Interrupted => Error::Interrupted,
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Error::TimedOut,
+            io::ErrorKind::UnexpectedEof => Error::Eof,
+            // `BufRead::read_line` reports invalid UTF-8 with a bare `InvalidData` error, while
+            // our own `InvalidData` errors, like rejected pastes, carry a message
+            io::ErrorKind::InvalidData if err.get_ref().is_none() => Error::InvalidUtf8,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err {
+            Error::NotATty(err) | Error::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_missing_tty_error(err: &io::Error) -> bool {
+    // ENXIO means there is no controlling terminal, ENOENT that /dev/tty itself is missing
+    matches!(err.raw_os_error(), Some(libc::ENXIO) | Some(libc::ENOENT))
+}
+
+#[cfg(windows)]
+fn is_missing_tty_error(err: &io::Error) -> bool {
+    use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_INVALID_HANDLE};
+
+    matches!(
+        err.raw_os_error().map(|code| code as u32),
+        Some(ERROR_FILE_NOT_FOUND) | Some(ERROR_INVALID_HANDLE)
+    )
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_missing_tty_error(_err: &io::Error) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use std::io::{self, BufRead, Cursor};
+
+    #[test]
+    fn can_classify_io_errors() {
+        let err = Error::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(matches!(err, Error::Eof));
+
+        let err = Error::from(io::Error::new(io::ErrorKind::Interrupted, "Ctrl-C"));
+        assert!(matches!(err, Error::Interrupted));
+
+        let err = Cursor::new(&b"\xff\n"[..])
+            .read_line(&mut String::new())
+            .unwrap_err();
+        assert!(matches!(Error::from(err), Error::InvalidUtf8));
+
+        let err = Error::from(io::Error::new(io::ErrorKind::InvalidData, "pasting"));
+        assert!(matches!(err, Error::Io(_)));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,289 @@
+//! Decodes the keys typed in a terminal in raw mode, ie: arrow keys and Ctrl combinations
+//!
+//! ```
+//! use rtoolbox::key::{read_key, Key};
+//! use rtoolbox::testing::{keys, MockTty};
+//!
+//! let mut tty = MockTty::new().typed("é").key(keys::UP).key(keys::CTRL_C);
+//! assert_eq!(read_key(&mut tty).unwrap(), Some(Key::Char('é')));
+//! assert_eq!(read_key(&mut tty).unwrap(), Some(Key::Up));
+//! assert_eq!(read_key(&mut tty).unwrap(), Some(Key::Ctrl('c')));
+//! assert_eq!(read_key(&mut tty).unwrap(), None);
+//! ```
+//!
+//! On Unix, keys are read from the bytes the terminal sends, where keys that aren't text are
+//! escape sequences. Windows consoles send key events instead, which `ConsoleReader::read_key`
+//! decodes into the same keys.
+
+use crate::bracketed_paste;
+use crate::safe_string::SafeString;
+use crate::safe_vec::SafeVec;
+use std::io::{self, Read};
+
+const ESCAPE: u8 = 0x1b;
+
+/// A key typed in the terminal
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Key {
+    /// Text, ie: a letter, with Shift applied
+    Char(char),
+    /// A letter typed while holding Ctrl, in lowercase, ie: `Ctrl('c')` for Ctrl-C
+    Ctrl(char),
+    Enter,
+    Tab,
+    /// Shift-Tab
+    BackTab,
+    Backspace,
+    Delete,
+    Insert,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// A function key, ie: `F(1)` for F1
+    F(u8),
+    /// Text pasted at once, without its control characters, see `bracketed_paste`
+    Paste(SafeString),
+    /// Bytes we can't decode, ie: Alt combinations or invalid UTF-8, which are usually ignored
+    Unknown(Vec<u8>),
+}
+
+/// Reads the next key, or returns `None` at the end of the input
+///
+/// Modifiers other than Ctrl on letters aren't reported, ie: Ctrl-Up is read as `Up`. Reads
+/// interrupted by signals are retried, but not reads cancelled with `ErrorKind::Interrupted`
+/// errors that carry a reason.
+pub fn read_key(reader: &mut impl Read) -> io::Result<Option<Key>> {
+    let byte = match read_byte(reader)? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x08 | 0x7f => Key::Backspace,
+        ESCAPE => read_escape_sequence(reader)?,
+        0x01..=0x1a => Key::Ctrl((b'a' + byte - 1) as char),
+        byte if byte < 0x20 => Key::Unknown(vec![byte]),
+        byte => read_char(reader, byte)?,
+    };
+    Ok(Some(key))
+}
+
+/// Reads the rest of a UTF-8 character, given its first byte
+fn read_char(reader: &mut impl Read, first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 1,
+    };
+    // Characters may be part of a password
+    let mut bytes = SafeVec::new(vec![first]);
+    while bytes.len() < len {
+        match read_byte(reader)? {
+            Some(byte) => bytes.inner_mut().push(byte),
+            None => break,
+        }
+        // A byte that doesn't continue the character ends it early, as invalid
+        if bytes[bytes.len() - 1] & 0xc0 != 0x80 {
+            break;
+        }
+    }
+
+    let key = match std::str::from_utf8(&bytes) {
+        Ok(s) => s.chars().next().map(Key::Char),
+        Err(_) => None,
+    };
+    Ok(key.unwrap_or_else(|| Key::Unknown(bytes.to_vec())))
+}
+
+/// Reads the rest of an escape sequence, once ESC was read
+fn read_escape_sequence(reader: &mut impl Read) -> io::Result<Key> {
+    match read_byte(reader)? {
+        // CSI sequences end with a byte in the 0x40-0x7e range, ie: ESC [ A
+        Some(b'[') => {
+            let mut sequence = vec![];
+            while let Some(byte) = read_byte(reader)? {
+                sequence.push(byte);
+                if (0x40..=0x7e).contains(&byte) {
+                    break;
+                }
+            }
+            if sequence == bracketed_paste::PASTE_START.as_bytes()[2..] {
+                return Ok(Key::Paste(bracketed_paste::read_pasted(reader)?));
+            }
+            Ok(csi_key(&sequence).unwrap_or_else(|| {
+                let mut bytes = vec![ESCAPE, b'['];
+                bytes.extend(sequence);
+                Key::Unknown(bytes)
+            }))
+        }
+        // SS3 sequences have a single byte after the O, ie: ESC O P
+        Some(b'O') => {
+            let byte = read_byte(reader)?;
+            Ok(byte.and_then(final_byte_key).unwrap_or_else(|| {
+                let mut bytes = vec![ESCAPE, b'O'];
+                bytes.extend(byte);
+                Key::Unknown(bytes)
+            }))
+        }
+        Some(byte) => Ok(Key::Unknown(vec![ESCAPE, byte])),
+        None => Ok(Key::Escape),
+    }
+}
+
+/// Decodes the parameters and final byte of a CSI sequence, ie: `A` or `1;5A` for Up, `3~` for
+/// Delete
+fn csi_key(sequence: &[u8]) -> Option<Key> {
+    let (&last, params) = sequence.split_last()?;
+    if last != b'~' {
+        return final_byte_key(last);
+    }
+
+    // The first parameter says which key, the others which modifiers were held
+    let number = std::str::from_utf8(params).ok()?.split(';').next()?;
+    let key = match number.parse::<u8>().ok()? {
+        1 | 7 => Key::Home,
+        2 => Key::Insert,
+        3 => Key::Delete,
+        4 | 8 => Key::End,
+        5 => Key::PageUp,
+        6 => Key::PageDown,
+        n @ 11..=15 => Key::F(n - 10),
+        n @ 17..=21 => Key::F(n - 11),
+        n @ 23..=26 => Key::F(n - 12),
+        n @ 28..=29 => Key::F(n - 13),
+        n @ 31..=34 => Key::F(n - 14),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Decodes the keys whose sequences are told apart by their last byte, ie: ESC [ A or ESC O A
+fn final_byte_key(byte: u8) -> Option<Key> {
+    let key = match byte {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        b'Z' => Key::BackTab,
+        b'P'..=b'S' => Key::F(byte - b'P' + 1),
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn read_byte(reader: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut buf = [0u8; 1];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(buf[0])),
+            // Only signals are retried, not cancelled reads
+            Err(err) if err.kind() == io::ErrorKind::Interrupted && err.get_ref().is_none() => {
+                continue
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_key, Key};
+    use crate::testing::{keys, MockTty};
+
+    fn read_all(input: &[u8]) -> Vec<Key> {
+        let mut tty = MockTty::new().bytes(input);
+        let mut keys = vec![];
+        while let Some(key) = read_key(&mut tty).unwrap() {
+            keys.push(key);
+        }
+        keys
+    }
+
+    #[test]
+    fn can_read_text_and_control_keys() {
+        assert_eq!(
+            read_all("aé😀\r\n\t\x7f\x08\x03\x15".as_bytes()),
+            vec![
+                Key::Char('a'),
+                Key::Char('é'),
+                Key::Char('😀'),
+                Key::Enter,
+                Key::Enter,
+                Key::Tab,
+                Key::Backspace,
+                Key::Backspace,
+                Key::Ctrl('c'),
+                Key::Ctrl('u'),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_read_escape_sequences() {
+        let input = [
+            keys::UP,
+            keys::DOWN,
+            keys::RIGHT,
+            keys::LEFT,
+            "\x1bOA\x1b[1;5A",
+            "\x1b[H\x1b[1~\x1bOF\x1b[4~",
+            "\x1b[2~\x1b[3~\x1b[5~\x1b[6~\x1b[Z",
+            "\x1bOP\x1b[15~\x1b[24~",
+            "\x1bx",
+            "\x1b[99~",
+            keys::ESCAPE,
+        ]
+        .concat();
+        assert_eq!(
+            read_all(input.as_bytes()),
+            vec![
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left,
+                Key::Up,
+                Key::Up,
+                Key::Home,
+                Key::Home,
+                Key::End,
+                Key::End,
+                Key::Insert,
+                Key::Delete,
+                Key::PageUp,
+                Key::PageDown,
+                Key::BackTab,
+                Key::F(1),
+                Key::F(5),
+                Key::F(12),
+                Key::Unknown(b"\x1bx".to_vec()),
+                Key::Unknown(b"\x1b[99~".to_vec()),
+                Key::Escape,
+            ]
+        );
+    }
+
+    #[test]
+    fn can_read_pastes_and_invalid_bytes() {
+        assert_eq!(
+            read_all(b"\x1b[200~a\tb\x1b[201~\xc3a\xff"),
+            vec![
+                Key::Paste("ab".into()),
+                Key::Unknown(b"\xc3a".to_vec()),
+                Key::Unknown(b"\xff".to_vec()),
+            ]
+        );
+    }
+}
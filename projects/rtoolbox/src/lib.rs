@@ -1,7 +1,31 @@
+#[cfg(all(feature = "async", target_family = "wasm"))]
+compile_error!("the `async` feature reads input on another thread, which WASM targets don't have");
+#[cfg(all(feature = "locked-memory", not(any(unix, windows))))]
+compile_error!("the `locked-memory` feature is only supported on Unix and Windows");
+#[cfg(all(feature = "clipboard", not(any(unix, windows))))]
+compile_error!("the `clipboard` feature is only supported on Unix and Windows");
+
 pub mod atty;
+pub mod bracketed_paste;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+#[cfg(windows)]
+pub mod console;
+pub mod error;
 pub mod fix_line_issues;
+pub mod key;
 pub mod print_tty;
+#[cfg(any(unix, windows))]
+pub mod raw_mode;
 pub mod safe_string;
 #[cfg(feature = "serde")]
 pub mod safe_string_serde;
 pub mod safe_vec;
+#[cfg(feature = "locked-memory")]
+pub mod secure_memory;
+pub mod style;
+pub mod term;
+pub mod testing;
+pub mod tty_lock;
+#[cfg(feature = "async")]
+pub mod unblock;
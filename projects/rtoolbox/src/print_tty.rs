@@ -1,5 +1,5 @@
-#[cfg(target_family = "wasm")]
-mod wasm {
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+mod stdio {
     use std::io::Write;
 
     /// Displays a message on the STDOUT
@@ -9,31 +9,88 @@ mod wasm {
         stdout.flush()?;
         Ok(())
     }
+
+    pub fn supports_ansi(_output: super::PromptOutput) -> bool {
+        false
+    }
+
+    pub fn open_tty_output() -> std::io::Result<std::io::Stdout> {
+        Ok(std::io::stdout())
+    }
 }
 
 #[cfg(target_family = "unix")]
 mod unix {
+    use super::PromptOutput;
+    use crate::atty::{self, Stream};
     use std::io::Write;
 
     /// Displays a message on the TTY
     pub fn print_tty(prompt: impl ToString) -> std::io::Result<()> {
-        let mut stream = std::fs::OpenOptions::new().write(true).open("/dev/tty")?;
+        let mut stream = open_tty_output()?;
         stream
             .write_all(prompt.to_string().as_str().as_bytes())
             .and_then(|_| stream.flush())
     }
+
+    /// Returns true if the output is a terminal, which we expect to understand ANSI sequences
+    pub fn supports_ansi(output: PromptOutput) -> bool {
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term.is_empty() || term == "dumb" {
+            return false;
+        }
+        match output {
+            PromptOutput::Tty => true,
+            PromptOutput::Stdout => atty::is(Stream::Stdout),
+            PromptOutput::Stderr => atty::is(Stream::Stderr),
+        }
+    }
+
+    pub fn open_tty_output() -> std::io::Result<std::fs::File> {
+        std::fs::OpenOptions::new().write(true).open("/dev/tty")
+    }
 }
 
 #[cfg(target_family = "windows")]
 mod windows {
+    use super::PromptOutput;
+    use crate::atty::{self, Stream};
+    use crate::style::enable_ansi;
     use std::io::Write;
-    use std::os::windows::io::FromRawHandle;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
     use winapi::um::fileapi::{CreateFileA, OPEN_EXISTING};
     use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::winbase::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
     use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE};
 
     /// Displays a message on the TTY
     pub fn print_tty(prompt: impl ToString) -> std::io::Result<()> {
+        let mut stream = open_tty_output()?;
+
+        stream
+            .write_all(prompt.to_string().as_str().as_bytes())
+            .and_then(|_| stream.flush())
+    }
+
+    /// Returns true if the output is a console that interprets ANSI sequences, after asking it to
+    pub fn supports_ansi(output: PromptOutput) -> bool {
+        match output {
+            // The console is only closed once it's dropped, and standard handles stay open
+            PromptOutput::Tty => match open_tty_output() {
+                Ok(console) => unsafe { enable_ansi(console.as_raw_handle() as _) },
+                Err(_) => false,
+            },
+            PromptOutput::Stdout => {
+                atty::is(Stream::Stdout) && unsafe { enable_ansi(GetStdHandle(STD_OUTPUT_HANDLE)) }
+            }
+            PromptOutput::Stderr => {
+                atty::is(Stream::Stderr) && unsafe { enable_ansi(GetStdHandle(STD_ERROR_HANDLE)) }
+            }
+        }
+    }
+
+    pub fn open_tty_output() -> std::io::Result<std::fs::File> {
         let handle = unsafe {
             CreateFileA(
                 b"CONOUT$\x00".as_ptr() as *const i8,
@@ -49,14 +106,106 @@ mod windows {
             return Err(std::io::Error::last_os_error());
         }
 
-        let mut stream = unsafe { std::fs::File::from_raw_handle(handle) };
+        Ok(unsafe { std::fs::File::from_raw_handle(handle) })
+    }
+}
 
-        stream
-            .write_all(prompt.to_string().as_str().as_bytes())
-            .and_then(|_| stream.flush())
+/// Where prompts are displayed
+///
+/// Prompting on the TTY, which is the default, keeps prompts out of pipes, ie: `my-program | jq`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PromptOutput {
+    #[default]
+    Tty,
+    Stdout,
+    Stderr,
+}
+
+/// Displays a message on the given output
+pub fn print_to(output: PromptOutput, prompt: impl ToString) -> std::io::Result<()> {
+    match output {
+        PromptOutput::Tty => print_tty(prompt),
+        PromptOutput::Stdout => print_writer(&mut std::io::stdout().lock(), prompt),
+        PromptOutput::Stderr => print_writer(&mut std::io::stderr().lock(), prompt),
     }
 }
 
+/// Displays styled text on the given output, without styles if the output isn't a terminal or if
+/// the user asked for no colors
+pub fn print_styled_to(output: PromptOutput, prompt: &[Styled]) -> std::io::Result<()> {
+    let ansi = style::is_allowed() && supports_ansi(output);
+    print_to(output, style::render(prompt, ansi))
+}
+
+/// Returns the last line of a prompt, which is all that redraws display again since they happen
+/// on the current line only
+///
+/// ```
+/// assert_eq!(rtoolbox::print_tty::last_line("Passwords differ.\nPassword: "), "Password: ");
+/// ```
+pub fn last_line(prompt: &str) -> &str {
+    prompt.rsplit('\n').next().unwrap_or("")
+}
+
+/// Formats a message straight onto the TTY, without building a `String` first, see `tty_print!`
+pub fn print_tty_fmt(args: std::fmt::Arguments) -> std::io::Result<()> {
+    print_writer_fmt(&mut open_tty_output()?, args)
+}
+
+/// Formats a message straight onto a writer and flushes it, see `writer_print!`
+pub fn print_writer_fmt(stream: &mut impl Write, args: std::fmt::Arguments) -> std::io::Result<()> {
+    stream.write_fmt(args).and_then(|_| stream.flush())
+}
+
+/// Displays a formatted message on the TTY, like `print!`, returning an `std::io::Result`
+///
+/// ```no_run
+/// let app = "Github";
+/// rtoolbox::tty_print!("Password for {}: ", app).unwrap();
+/// ```
+#[macro_export]
+macro_rules! tty_print {
+    ($($arg:tt)*) => {
+        $crate::print_tty::print_tty_fmt(format_args!($($arg)*))
+    };
+}
+
+/// Like `tty_print!`, followed by a newline
+#[macro_export]
+macro_rules! tty_println {
+    () => {
+        $crate::tty_print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::print_tty::print_tty_fmt(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// Prints a formatted message to a writer and flushes it, returning an `std::io::Result`
+///
+/// ```
+/// let mut writer = Vec::new();
+/// rtoolbox::writer_print!(&mut writer, "{} attempts left", 2).unwrap();
+/// assert_eq!(writer, b"2 attempts left");
+/// ```
+#[macro_export]
+macro_rules! writer_print {
+    ($writer:expr, $($arg:tt)*) => {
+        $crate::print_tty::print_writer_fmt($writer, format_args!($($arg)*))
+    };
+}
+
+/// Like `writer_print!`, followed by a newline
+#[macro_export]
+macro_rules! writer_println {
+    ($writer:expr) => {
+        $crate::writer_print!($writer, "\n")
+    };
+    ($writer:expr, $($arg:tt)*) => {
+        $crate::print_tty::print_writer_fmt($writer, format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
 /// Prints a message to a writer
 pub fn print_writer(stream: &mut impl Write, prompt: impl ToString) -> std::io::Result<()> {
     stream
@@ -64,10 +213,26 @@ pub fn print_writer(stream: &mut impl Write, prompt: impl ToString) -> std::io::
         .and_then(|_| stream.flush())
 }
 
+/// Prints styled text to a writer, with ANSI sequences only if `ansi` is true
+pub fn print_writer_styled(
+    stream: &mut impl Write,
+    prompt: &[Styled],
+    ansi: bool,
+) -> std::io::Result<()> {
+    print_writer(stream, style::render(prompt, ansi))
+}
+
+use crate::style::{self, Styled};
 use std::io::Write;
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+pub use stdio::print_tty;
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+use stdio::{open_tty_output, supports_ansi};
 #[cfg(target_family = "unix")]
 pub use unix::print_tty;
-#[cfg(target_family = "wasm")]
-pub use wasm::print_tty;
+#[cfg(target_family = "unix")]
+use unix::{open_tty_output, supports_ansi};
 #[cfg(target_family = "windows")]
 pub use windows::print_tty;
+#[cfg(target_family = "windows")]
+use windows::{open_tty_output, supports_ansi};
@@ -0,0 +1,278 @@
+//! Changes how the terminal handles input, restoring it when done
+//!
+//! ```no_run
+//! use rtoolbox::raw_mode::{RawMode, RawModeGuard};
+//! use std::os::unix::io::AsRawFd;
+//!
+//! let tty = std::fs::File::open("/dev/tty").unwrap();
+//! let guard = RawModeGuard::new(tty.as_raw_fd(), RawMode::Raw).unwrap();
+//! // Read keys as they are typed...
+//! drop(guard);
+//! ```
+//!
+//! Guards can be nested: each one restores the mode that was in place when it was acquired, so
+//! they must be dropped in the reverse order of their acquisition, which scopes make natural.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+pub type Handle = std::os::unix::io::RawFd;
+
+#[cfg(windows)]
+pub type Handle = winapi::um::winnt::HANDLE;
+
+/// How the terminal should handle input while a `RawModeGuard` is alive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawMode {
+    /// Input is read line by line with the usual editing keys, but it isn't echoed, except for the
+    /// final newline, which is what you want for passwords
+    Hidden,
+    /// Every key is read as it is typed, nothing is echoed and Ctrl-C is read like any other key
+    Raw,
+    /// Like `Raw`, but reads return nothing if no key is typed within the timeout, which is
+    /// rounded to tenths of a second and capped at 25.5 seconds (Unix only)
+    RawWithTimeout(Duration),
+}
+
+/// Puts the terminal in a `RawMode` until it is dropped
+pub struct RawModeGuard {
+    handle: Handle,
+    #[cfg(unix)]
+    original: libc::termios,
+    #[cfg(windows)]
+    original: u32,
+}
+
+#[cfg(unix)]
+impl RawModeGuard {
+    pub fn new(handle: Handle, mode: RawMode) -> io::Result<RawModeGuard> {
+        use libc::{ECHO, ECHONL, ICANON, ISIG, VMIN, VTIME};
+
+        let original = tcgetattr(handle)?;
+        let mut term = original;
+
+        match mode {
+            RawMode::Hidden => {
+                // Hide what's typed, but don't hide the NL character when the user hits ENTER
+                term.c_lflag &= !ECHO;
+                term.c_lflag |= ECHONL;
+            }
+            RawMode::Raw => {
+                term.c_lflag &= !(ICANON | ECHO | ISIG);
+                term.c_cc[VMIN] = 1;
+                term.c_cc[VTIME] = 0;
+            }
+            RawMode::RawWithTimeout(timeout) => {
+                term.c_lflag &= !(ICANON | ECHO | ISIG);
+                term.c_cc[VMIN] = 0;
+                term.c_cc[VTIME] = (timeout.as_millis() / 100).clamp(1, 255) as libc::cc_t;
+            }
+        }
+
+        tcsetattr(handle, &term)?;
+
+        Ok(RawModeGuard { handle, original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        // Set the the mode back to what it was
+        let _ = tcsetattr(self.handle, &self.original);
+    }
+}
+
+#[cfg(unix)]
+fn tcgetattr(fd: Handle) -> io::Result<libc::termios> {
+    let mut term = std::mem::MaybeUninit::<libc::termios>::uninit();
+    if unsafe { libc::tcgetattr(fd, term.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { term.assume_init() })
+}
+
+#[cfg(unix)]
+fn tcsetattr(fd: Handle, term: &libc::termios) -> io::Result<()> {
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, term) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+impl RawModeGuard {
+    /// # Safety
+    ///
+    /// `handle` must be a console input handle that stays open until the guard is dropped.
+    pub unsafe fn new(handle: Handle, mode: RawMode) -> io::Result<RawModeGuard> {
+        use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
+        use winapi::um::wincon::{ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT};
+
+        let mut original = 0;
+
+        // Get the old mode so we can reset back to it when we are done
+        if unsafe { GetConsoleMode(handle, &mut original) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let new_mode = match mode {
+            // We want to be able to read line by line, and we still want backspace to work
+            RawMode::Hidden => ENABLE_LINE_INPUT | ENABLE_PROCESSED_INPUT,
+            // No line input, no echo and no processed input: keys are handled by the reader
+            RawMode::Raw | RawMode::RawWithTimeout(_) => 0,
+        };
+        if unsafe { SetConsoleMode(handle, new_mode) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(RawModeGuard { handle, original })
+    }
+}
+
+#[cfg(windows)]
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        // Set the the mode back to what it was
+        unsafe {
+            winapi::um::consoleapi::SetConsoleMode(self.handle, self.original);
+        }
+    }
+}
+
+/// Waits up to `timeout` for a key to be typed, returning false if none was
+///
+/// The key isn't read, so the next read returns it. The terminal should be in `Raw` mode, otherwise
+/// keys may only be available once ENTER is hit.
+#[cfg(unix)]
+pub fn wait_for_key(handle: Handle, timeout: Duration) -> io::Result<bool> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let timeout_ms = remaining.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        let mut pollfd = libc::pollfd {
+            fd: handle,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            0 => return Ok(false),
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            _ => return Ok(true),
+        }
+    }
+}
+
+/// Waits up to `timeout` for a key to be typed, returning false if none was
+///
+/// The key isn't read, so the next read returns it. Other console events, like key releases or
+/// keys without a character like Shift, are dropped along the way, since reads wouldn't return
+/// them.
+///
+/// # Safety
+///
+/// `handle` must be a console input handle that stays open until this returns.
+#[cfg(windows)]
+pub unsafe fn wait_for_key(handle: Handle, timeout: Duration) -> io::Result<bool> {
+    use winapi::shared::winerror::WAIT_TIMEOUT;
+    use winapi::um::consoleapi::ReadConsoleInputW;
+    use winapi::um::synchapi::WaitForSingleObject;
+    use winapi::um::winbase::WAIT_OBJECT_0;
+    use winapi::um::wincon::PeekConsoleInputW;
+    use winapi::um::wincontypes::{INPUT_RECORD, KEY_EVENT};
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let timeout_ms = remaining.as_millis().min(u32::MAX as u128 - 1) as u32;
+        match unsafe { WaitForSingleObject(handle, timeout_ms) } {
+            WAIT_OBJECT_0 => {}
+            WAIT_TIMEOUT => return Ok(false),
+            _ => return Err(io::Error::last_os_error()),
+        }
+
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        let mut read = 0;
+        if unsafe { PeekConsoleInputW(handle, &mut record, 1, &mut read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if read == 1 && record.EventType == KEY_EVENT {
+            let key_event = unsafe { record.Event.KeyEvent() };
+            if key_event.bKeyDown != 0 && unsafe { *key_event.uChar.UnicodeChar() } != 0 {
+                return Ok(true);
+            }
+        }
+        if read == 1 && unsafe { ReadConsoleInputW(handle, &mut record, 1, &mut read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::{tcgetattr, wait_for_key, RawMode, RawModeGuard};
+    use std::time::Duration;
+
+    fn open_pty() -> (i32, i32) {
+        let (mut master, mut slave) = (0, 0);
+        let ret = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(ret, 0);
+        (master, slave)
+    }
+
+    #[test]
+    fn can_nest_guards() {
+        let (master, slave) = open_pty();
+        let lflag = |fd| tcgetattr(fd).unwrap().c_lflag;
+        let original = lflag(slave);
+
+        {
+            let _hidden = RawModeGuard::new(slave, RawMode::Hidden).unwrap();
+            let hidden = lflag(slave);
+            assert_eq!(hidden & libc::ECHO, 0);
+            assert_ne!(hidden & libc::ICANON, 0);
+
+            {
+                let _raw = RawModeGuard::new(slave, RawMode::Raw).unwrap();
+                assert_eq!(lflag(slave) & libc::ICANON, 0);
+            }
+
+            assert_eq!(lflag(slave), hidden);
+        }
+
+        assert_eq!(lflag(slave), original);
+
+        unsafe {
+            libc::close(slave);
+            libc::close(master);
+        }
+    }
+    #[test]
+    fn can_wait_for_key() {
+        let (master, slave) = open_pty();
+        let _raw = RawModeGuard::new(slave, RawMode::Raw).unwrap();
+
+        assert!(!wait_for_key(slave, Duration::from_millis(10)).unwrap());
+        assert_eq!(unsafe { libc::write(master, b"y".as_ptr() as _, 1) }, 1);
+        assert!(wait_for_key(slave, Duration::from_secs(10)).unwrap());
+
+        unsafe {
+            libc::close(slave);
+            libc::close(master);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::convert::Into;
 use std::ops::{Deref, DerefMut, Drop};
+#[cfg(not(feature = "zeroize"))]
 use std::{ptr, sync::atomic};
 
 /// String that is zeroed when dropped
@@ -24,6 +25,7 @@ impl SafeString {
     }
 }
 
+#[cfg(not(feature = "zeroize"))]
 impl Drop for SafeString {
     fn drop(&mut self) {
         let default = u8::default();
@@ -37,6 +39,23 @@ impl Drop for SafeString {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl Drop for SafeString {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.inner);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SafeString {
+    fn zeroize(&mut self) {
+        self.inner.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for SafeString {}
+
 impl Deref for SafeString {
     type Target = String;
 
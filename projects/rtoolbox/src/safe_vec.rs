@@ -1,6 +1,8 @@
+use std::io;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::ops::Drop;
+#[cfg(not(feature = "zeroize"))]
 use std::{ptr, sync::atomic};
 
 /// Vec that is zeroed when dropped
@@ -19,6 +21,7 @@ impl SafeVec {
     }
 }
 
+#[cfg(not(feature = "zeroize"))]
 impl Drop for SafeVec {
     fn drop(&mut self) {
         let default = u8::default();
@@ -32,6 +35,42 @@ impl Drop for SafeVec {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl Drop for SafeVec {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.inner);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SafeVec {
+    fn zeroize(&mut self) {
+        self.inner.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for SafeVec {}
+
+/// Appends bytes, ie: to serialize secrets, without leaving copies behind when the vec grows
+impl io::Write for SafeVec {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let needed = self.inner.len() + buf.len();
+        if needed > self.inner.capacity() {
+            let mut grown = Vec::with_capacity(needed.max(self.inner.capacity() * 2));
+            grown.extend_from_slice(&self.inner);
+            // The old buffer is zeroed when dropped
+            drop(SafeVec::new(std::mem::replace(&mut self.inner, grown)));
+        }
+        self.inner.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl Deref for SafeVec {
     type Target = [u8];
 
@@ -45,3 +84,18 @@ impl DerefMut for SafeVec {
         self.inner.deref_mut()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SafeVec;
+    use std::io::Write;
+
+    #[test]
+    fn can_write() {
+        let mut vec = SafeVec::new(Vec::with_capacity(2));
+        vec.write_all(b"hun").unwrap();
+        vec.write_all(b"ter2").unwrap();
+        assert_eq!(&vec[..], b"hunter2");
+        assert!(vec.inner.capacity() >= 7);
+    }
+}
@@ -0,0 +1,286 @@
+//! Keeps secrets out of swap and core dumps
+//!
+//! ```
+//! use rtoolbox::safe_vec::SafeVec;
+//! use rtoolbox::secure_memory::{disable_core_dumps, LockedMemory};
+//!
+//! disable_core_dumps().unwrap();
+//! let secret = SafeVec::new(b"hunter2".to_vec());
+//! let locked = LockedMemory::new(&secret).unwrap();
+//! // Use the secret, which isn't swapped out to disk until `locked` is dropped
+//! drop(locked);
+//! ```
+//!
+//! Memory is locked by page, so a lock covers the memory around the bytes too. A page stays locked
+//! until every lock that covers it is dropped.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Mutex, OnceLock};
+
+/// Keeps memory from being swapped out to disk until it's dropped
+///
+/// The OS limits how much memory can be locked, ie: with `ulimit -l` on Linux, so locking can fail
+/// in constrained environments.
+#[derive(Debug)]
+pub struct LockedMemory<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> LockedMemory<'a> {
+    pub fn new(bytes: &'a [u8]) -> io::Result<LockedMemory<'a>> {
+        lock(bytes)?;
+        Ok(LockedMemory { bytes })
+    }
+}
+
+impl Drop for LockedMemory<'_> {
+    fn drop(&mut self) {
+        unlock(self.bytes);
+    }
+}
+
+/// Keeps the memory of many secrets from being swapped out to disk until it's dropped
+///
+/// Unlike `LockedMemory`, it doesn't borrow the secrets, so that it can be kept next to them, ie: in
+/// the struct that owns them, and lock new secrets as they're added. Secrets must be unlocked
+/// before they're dropped, so that their pages aren't kept locked for whatever reuses them.
+#[derive(Debug, Default)]
+pub struct LockedRegions {
+    /// The address and length of each locked secret
+    regions: Vec<(usize, usize)>,
+}
+
+impl LockedRegions {
+    pub fn new() -> LockedRegions {
+        LockedRegions::default()
+    }
+
+    /// Locks the memory of `bytes`, until they're unlocked or this is dropped
+    pub fn lock(&mut self, bytes: &[u8]) -> io::Result<()> {
+        lock(bytes)?;
+        if !bytes.is_empty() {
+            self.regions.push((bytes.as_ptr() as usize, bytes.len()));
+        }
+        Ok(())
+    }
+
+    /// Unlocks the memory of `bytes`, if they were locked with `lock`
+    pub fn unlock(&mut self, bytes: &[u8]) {
+        let region = (bytes.as_ptr() as usize, bytes.len());
+        if let Some(i) = self.regions.iter().position(|r| *r == region) {
+            self.regions.swap_remove(i);
+            unlock(bytes);
+        }
+    }
+}
+
+impl Drop for LockedRegions {
+    fn drop(&mut self) {
+        for &(address, len) in &self.regions {
+            unlock_pages(address, len);
+        }
+    }
+}
+
+/// How many locks cover each locked page, by address
+///
+/// The OS unlocks whole pages, so a page is only unlocked once the last lock covering it is gone.
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+/// Returns the address of each page that holds some of the `len` bytes at `address`
+fn pages(address: usize, len: usize) -> impl Iterator<Item = usize> {
+    static PAGE_SIZE: OnceLock<usize> = OnceLock::new();
+    let page_size = *PAGE_SIZE.get_or_init(page_size);
+    (address - address % page_size..address + len).step_by(page_size)
+}
+
+fn lock(bytes: &[u8]) -> io::Result<()> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+
+    let mut locked_pages = LOCKED_PAGES.lock().unwrap_or_else(|err| err.into_inner());
+    let pages = pages(bytes.as_ptr() as usize, bytes.len()).collect::<Vec<usize>>();
+    let new_pages = pages
+        .iter()
+        .copied()
+        .filter(|page| !locked_pages.contains_key(page))
+        .collect::<Vec<usize>>();
+    for (i, &page) in new_pages.iter().enumerate() {
+        if let Err(err) = lock_page(page) {
+            for &page in &new_pages[..i] {
+                let _ = unlock_page(page);
+            }
+            return Err(err);
+        }
+    }
+    for page in pages {
+        *locked_pages.entry(page).or_insert(0) += 1;
+    }
+    Ok(())
+}
+
+fn unlock(bytes: &[u8]) {
+    unlock_pages(bytes.as_ptr() as usize, bytes.len())
+}
+
+fn unlock_pages(address: usize, len: usize) {
+    if len == 0 {
+        return;
+    }
+
+    let mut locked_pages = LOCKED_PAGES.lock().unwrap_or_else(|err| err.into_inner());
+    for page in pages(address, len) {
+        if let Some(count) = locked_pages.get_mut(&page) {
+            *count -= 1;
+            if *count == 0 {
+                locked_pages.remove(&page);
+                let _ = unlock_page(page);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[cfg(unix)]
+fn lock_page(page: usize) -> io::Result<()> {
+    if unsafe { libc::mlock(page as *const libc::c_void, page_size()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn unlock_page(page: usize) -> io::Result<()> {
+    if unsafe { libc::munlock(page as *const libc::c_void, page_size()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn page_size() -> usize {
+    let mut info: winapi::um::sysinfoapi::SYSTEM_INFO = unsafe { std::mem::zeroed() };
+    unsafe { winapi::um::sysinfoapi::GetSystemInfo(&mut info) };
+    info.dwPageSize as usize
+}
+
+#[cfg(windows)]
+fn lock_page(page: usize) -> io::Result<()> {
+    let address = page as *mut winapi::ctypes::c_void;
+    if unsafe { winapi::um::memoryapi::VirtualLock(address, page_size()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn unlock_page(page: usize) -> io::Result<()> {
+    let address = page as *mut winapi::ctypes::c_void;
+    if unsafe { winapi::um::memoryapi::VirtualUnlock(address, page_size()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Keeps the memory of the process from being written to disk if it crashes, for the rest of its
+/// life
+///
+/// On Linux, this also keeps other processes of the user from reading its memory with `ptrace`.
+#[cfg(unix)]
+pub fn disable_core_dumps() -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Keeps the memory of the process from being written to disk if it crashes, for the rest of its
+/// life
+///
+/// This turns off the crash dialog, which offers to send a dump of the process to Microsoft.
+#[cfg(windows)]
+pub fn disable_core_dumps() -> io::Result<()> {
+    use winapi::um::errhandlingapi::{GetErrorMode, SetErrorMode};
+    use winapi::um::winbase::{SEM_FAILCRITICALERRORS, SEM_NOGPFAULTERRORBOX};
+
+    unsafe { SetErrorMode(GetErrorMode() | SEM_FAILCRITICALERRORS | SEM_NOGPFAULTERRORBOX) };
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::{LockedMemory, LockedRegions};
+    use std::sync::Mutex;
+
+    // Locked memory is counted for the whole process, so tests using it can't run at the same time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn locked_kib() -> u64 {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmLck:"))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+            .unwrap()
+    }
+
+    #[test]
+    fn can_lock_memory() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let secret = vec![7u8; 64 * 1024];
+        let before = locked_kib();
+        let locked = LockedMemory::new(&secret).unwrap();
+        assert!(locked_kib() >= before + 64);
+        drop(locked);
+        assert!(locked_kib() < before + 64);
+
+        assert!(LockedMemory::new(&[]).is_ok());
+    }
+
+    #[test]
+    fn can_keep_memory_locked_until_last_lock_is_dropped() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let secret = vec![7u8; 64 * 1024];
+        let before = locked_kib();
+        let locked = LockedMemory::new(&secret).unwrap();
+        let parse_buffer = LockedMemory::new(&secret[1000..2000]).unwrap();
+        drop(parse_buffer);
+        assert!(locked_kib() >= before + 64);
+        drop(locked);
+        assert!(locked_kib() < before + 64);
+    }
+
+    #[test]
+    fn can_lock_regions_until_dropped() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let secrets = vec![vec![7u8; 64 * 1024], vec![8u8; 64 * 1024]];
+        let before = locked_kib();
+        let mut locked = LockedRegions::new();
+        for secret in &secrets {
+            locked.lock(secret).unwrap();
+        }
+        locked.lock(&[]).unwrap();
+        assert!(locked_kib() >= before + 128);
+        locked.unlock(&secrets[0]);
+        assert!(locked_kib() < before + 128);
+        assert!(locked_kib() >= before + 64);
+        drop(locked);
+        assert!(locked_kib() < before + 64);
+    }
+}
@@ -0,0 +1,157 @@
+/// Foreground colors supported by most terminals
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    fn ansi_code(self) -> u8 {
+        match self {
+            Color::Black => 30,
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+        }
+    }
+}
+
+/// How text looks in a terminal
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    bold: bool,
+    dim: bool,
+    fg: Option<Color>,
+}
+
+impl Style {
+    pub fn new() -> Style {
+        Style::default()
+    }
+
+    pub fn bold(mut self) -> Style {
+        self.bold = true;
+        self
+    }
+
+    pub fn dim(mut self) -> Style {
+        self.dim = true;
+        self
+    }
+
+    pub fn fg(mut self, color: Color) -> Style {
+        self.fg = Some(color);
+        self
+    }
+
+    /// Applies the style to some text
+    pub fn apply(self, text: impl ToString) -> Styled {
+        Styled {
+            text: text.to_string(),
+            style: self,
+        }
+    }
+
+    fn ansi_codes(&self) -> Vec<String> {
+        let mut codes = vec![];
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.dim {
+            codes.push("2".to_string());
+        }
+        if let Some(color) = self.fg {
+            codes.push(color.ansi_code().to_string());
+        }
+        codes
+    }
+}
+
+/// Text with a style, ie: `Style::new().fg(Color::Green).apply("?")`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Styled {
+    pub text: String,
+    pub style: Style,
+}
+
+impl Styled {
+    /// Returns the text, wrapped in ANSI escape sequences if `ansi` is true
+    pub fn render(&self, ansi: bool) -> String {
+        let codes = self.style.ansi_codes();
+        if !ansi || codes.is_empty() {
+            return self.text.clone();
+        }
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), self.text)
+    }
+}
+
+impl<T: ToString> From<T> for Styled {
+    fn from(text: T) -> Styled {
+        Style::new().apply(text)
+    }
+}
+
+/// Renders styled parts one after the other
+pub fn render(parts: &[Styled], ansi: bool) -> String {
+    parts.iter().map(|part| part.render(ansi)).collect()
+}
+
+/// Returns false if styles were disabled with the `styling` feature or if the user asked for no
+/// colors, see https://no-color.org
+pub fn is_allowed() -> bool {
+    if !cfg!(feature = "styling") {
+        return false;
+    }
+    match std::env::var_os("NO_COLOR") {
+        Some(no_color) => no_color.is_empty(),
+        None => true,
+    }
+}
+
+/// Makes sure the console interprets ANSI escape sequences, returns true if it does
+///
+/// Windows 10 consoles need to be asked to interpret them, other terminals always do.
+///
+/// # Safety
+///
+/// `handle` must be a console output handle, or a standard handle, that stays open until this
+/// returns.
+#[cfg(windows)]
+pub unsafe fn enable_ansi(handle: winapi::um::winnt::HANDLE) -> bool {
+    use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
+    use winapi::um::wincon::ENABLE_VIRTUAL_TERMINAL_PROCESSING;
+
+    let mut mode = 0;
+    if unsafe { GetConsoleMode(handle, &mut mode) } == 0 {
+        return false;
+    }
+    if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 {
+        return true;
+    }
+    unsafe { SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, Color, Style, Styled};
+
+    #[test]
+    fn can_render_styles() {
+        let parts = vec![
+            Style::new().fg(Color::Green).bold().apply("?"),
+            Styled::from(" Name: "),
+        ];
+        assert_eq!(render(&parts, true), "\x1b[1;32m?\x1b[0m Name: ");
+        assert_eq!(render(&parts, false), "? Name: ");
+    }
+}
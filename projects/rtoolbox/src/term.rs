@@ -0,0 +1,158 @@
+/// Returns the size of the terminal as `(columns, rows)`, if there is one
+#[cfg(target_family = "unix")]
+pub fn size() -> Option<(u16, u16)> {
+    use std::os::unix::io::AsRawFd;
+
+    let tty = std::fs::File::open("/dev/tty").ok();
+    let fd = match tty.as_ref() {
+        Some(tty) => tty.as_raw_fd(),
+        None => libc::STDOUT_FILENO,
+    };
+
+    let mut winsize = std::mem::MaybeUninit::<libc::winsize>::uninit();
+    if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, winsize.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let winsize = unsafe { winsize.assume_init() };
+
+    match (winsize.ws_col, winsize.ws_row) {
+        (0, _) | (_, 0) => None,
+        size => Some(size),
+    }
+}
+
+/// Returns the size of the terminal as `(columns, rows)`, if there is one
+#[cfg(target_family = "windows")]
+pub fn size() -> Option<(u16, u16)> {
+    let info = windows::screen_buffer_info()?;
+    let columns = info.srWindow.Right - info.srWindow.Left + 1;
+    let rows = info.srWindow.Bottom - info.srWindow.Top + 1;
+
+    Some((columns as u16, rows as u16))
+}
+
+/// Returns the size of the terminal as `(columns, rows)`, if there is one
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+pub fn size() -> Option<(u16, u16)> {
+    None
+}
+
+/// Returns the position of the cursor as `(column, row)`, starting at `(0, 0)` in the top left
+/// corner, if there is a terminal
+///
+/// On Unix, the terminal is asked for the position, which it must answer within half a second.
+#[cfg(target_family = "unix")]
+pub fn cursor_position() -> Option<(u16, u16)> {
+    use crate::raw_mode::{RawMode, RawModeGuard};
+    use std::io::{Read, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+
+    // The answer must not be echoed, and we don't want to wait for a newline that never comes
+    let raw_mode = RawModeGuard::new(
+        tty.as_raw_fd(),
+        RawMode::RawWithTimeout(Duration::from_millis(500)),
+    )
+    .ok()?;
+
+    let mut response = Vec::new();
+    if tty.write_all(b"\x1b[6n").and_then(|_| tty.flush()).is_ok() {
+        let mut byte = [0u8; 1];
+        while let Ok(1) = tty.read(&mut byte) {
+            response.push(byte[0]);
+            if byte[0] == b'R' {
+                break;
+            }
+        }
+    }
+
+    std::mem::drop(raw_mode);
+
+    parse_cursor_position(&response)
+}
+
+/// Returns the position of the cursor as `(column, row)`, starting at `(0, 0)` in the top left
+/// corner, if there is a terminal
+#[cfg(target_family = "windows")]
+pub fn cursor_position() -> Option<(u16, u16)> {
+    let info = windows::screen_buffer_info()?;
+    let column = info.dwCursorPosition.X - info.srWindow.Left;
+    let row = info.dwCursorPosition.Y - info.srWindow.Top;
+
+    Some((column as u16, row as u16))
+}
+
+/// Returns the position of the cursor as `(column, row)`, starting at `(0, 0)` in the top left
+/// corner, if there is a terminal
+#[cfg(any(target_family = "wasm", target_os = "hermit"))]
+pub fn cursor_position() -> Option<(u16, u16)> {
+    None
+}
+
+/// Parses a cursor position report, ie: `ESC [ 12 ; 40 R` for row 12 and column 40
+#[cfg_attr(not(target_family = "unix"), allow(dead_code))]
+fn parse_cursor_position(response: &[u8]) -> Option<(u16, u16)> {
+    let response = std::str::from_utf8(response).ok()?;
+    let start = response.rfind("\x1b[")?;
+    let (row, column) = response[start + 2..].strip_suffix('R')?.split_once(';')?;
+
+    let row = row.parse::<u16>().ok()?.checked_sub(1)?;
+    let column = column.parse::<u16>().ok()?.checked_sub(1)?;
+
+    Some((column, row))
+}
+
+#[cfg(target_family = "windows")]
+mod windows {
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use winapi::um::fileapi::{CreateFileA, OPEN_EXISTING};
+    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::wincon::{GetConsoleScreenBufferInfo, CONSOLE_SCREEN_BUFFER_INFO};
+    use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE};
+
+    pub fn screen_buffer_info() -> Option<CONSOLE_SCREEN_BUFFER_INFO> {
+        let handle = unsafe {
+            CreateFileA(
+                b"CONOUT$\x00".as_ptr() as *const i8,
+                GENERIC_READ | GENERIC_WRITE,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                std::ptr::null_mut(),
+                OPEN_EXISTING,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return None;
+        }
+        let console = unsafe { std::fs::File::from_raw_handle(handle) };
+
+        let mut info = std::mem::MaybeUninit::<CONSOLE_SCREEN_BUFFER_INFO>::uninit();
+        if unsafe { GetConsoleScreenBufferInfo(console.as_raw_handle() as _, info.as_mut_ptr()) }
+            == 0
+        {
+            return None;
+        }
+
+        Some(unsafe { info.assume_init() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_cursor_position;
+
+    #[test]
+    fn can_parse_cursor_position() {
+        assert_eq!(parse_cursor_position(b"\x1b[12;40R"), Some((39, 11)));
+        assert_eq!(parse_cursor_position(b"typed\x1b[1;1R"), Some((0, 0)));
+        assert_eq!(parse_cursor_position(b"\x1b[12;40"), None);
+        assert_eq!(parse_cursor_position(b""), None);
+    }
+}
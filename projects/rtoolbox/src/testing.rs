@@ -0,0 +1,208 @@
+//! Helpers to test interactive input without a real terminal
+
+#[cfg(all(unix, feature = "pty"))]
+pub mod pty;
+
+use crate::bracketed_paste;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read, Write};
+
+/// Bytes that terminals send for keys that aren't text, to script them with `MockTty::key`
+pub mod keys {
+    pub const ENTER: &str = "\r";
+    pub const TAB: &str = "\t";
+    pub const BACKSPACE: &str = "\x7f";
+    pub const CTRL_C: &str = "\x03";
+    pub const CTRL_D: &str = "\x04";
+    pub const CTRL_U: &str = "\x15";
+    pub const ESCAPE: &str = "\x1b";
+    pub const UP: &str = "\x1b[A";
+    pub const DOWN: &str = "\x1b[B";
+    pub const RIGHT: &str = "\x1b[C";
+    pub const LEFT: &str = "\x1b[D";
+}
+
+/// A fake terminal, which reads scripted keystrokes and records everything written to it
+///
+/// It implements `BufRead` and `Write`, so it can stand in for the TTY wherever a reader and a
+/// writer are expected, ie: with `prompt_password_from_bufread`. Since a single value can't be
+/// borrowed as both at once, `split` gives the input and output halves separately.
+///
+/// ```
+/// use rtoolbox::testing::{keys, MockTty};
+/// use std::io::{BufRead, Write};
+///
+/// let mut tty = MockTty::new().typed("hunter").key(keys::BACKSPACE).typed("r2\n");
+/// write!(tty, "Password: ").unwrap();
+/// let mut line = String::new();
+/// tty.read_line(&mut line).unwrap();
+/// assert_eq!(line, "hunter\x7fr2\n");
+/// assert_eq!(tty.output_string(), "Password: ");
+/// ```
+#[derive(Debug, Default)]
+pub struct MockTty {
+    input: MockInput,
+    output: Vec<u8>,
+}
+
+/// The keystrokes half of a `MockTty`
+#[derive(Debug, Default)]
+pub struct MockInput {
+    bytes: VecDeque<u8>,
+}
+
+impl MockTty {
+    pub fn new() -> MockTty {
+        MockTty::default()
+    }
+
+    /// Adds text to the keystrokes, as if it was typed
+    pub fn typed(mut self, text: &str) -> MockTty {
+        self.input.bytes.extend(text.as_bytes());
+        self
+    }
+
+    /// Adds a key to the keystrokes, ie: one of the `keys` constants
+    pub fn key(self, key: &str) -> MockTty {
+        self.typed(key)
+    }
+
+    /// Adds raw bytes to the keystrokes, ie: invalid UTF-8
+    pub fn bytes(mut self, bytes: &[u8]) -> MockTty {
+        self.input.bytes.extend(bytes);
+        self
+    }
+
+    /// Adds text to the keystrokes as a bracketed paste
+    pub fn pasted(self, text: &str) -> MockTty {
+        self.typed(bracketed_paste::PASTE_START)
+            .typed(text)
+            .typed(bracketed_paste::PASTE_END)
+    }
+
+    /// Returns the input and output halves, to pass them as the reader and the writer of a call
+    pub fn split(&mut self) -> (&mut MockInput, &mut Vec<u8>) {
+        (&mut self.input, &mut self.output)
+    }
+
+    /// Returns the keystrokes that haven't been read yet
+    pub fn remaining_input(&self) -> Vec<u8> {
+        self.input.bytes.iter().copied().collect()
+    }
+
+    /// Returns everything that was written, including control sequences
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Returns everything that was written, as text
+    pub fn output_string(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+
+    /// Returns the last line as a terminal would show it, once carriage returns have moved the
+    /// cursor back and redraws have overwritten what was there, without escape sequences and
+    /// trailing spaces
+    pub fn visible_line(&self) -> String {
+        let output = self.output_string();
+        let last_line = output.rsplit('\n').next().unwrap_or("");
+
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        let mut chars = last_line.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => cursor = 0,
+                '\x1b' => skip_escape_sequence(&mut chars),
+                '\x07' => {}
+                c => {
+                    if cursor < line.len() {
+                        line[cursor] = c;
+                    } else {
+                        line.push(c);
+                    }
+                    cursor += 1;
+                }
+            }
+        }
+
+        line.into_iter().collect::<String>().trim_end().to_string()
+    }
+}
+
+/// Skips a CSI sequence, ie: `[?2004h`, or the single character after a lone escape
+fn skip_escape_sequence(chars: &mut std::str::Chars) {
+    if chars.next() == Some('[') {
+        for c in chars {
+            if ('\x40'..='\x7e').contains(&c) {
+                break;
+            }
+        }
+    }
+}
+
+impl Read for MockInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for MockInput {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.bytes.make_contiguous();
+        Ok(self.bytes.as_slices().0)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.bytes.drain(..amt);
+    }
+}
+
+impl Read for MockTty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl BufRead for MockTty {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.input.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.input.consume(amt)
+    }
+}
+
+impl Write for MockTty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{keys, MockTty};
+    use std::io::{Read, Write};
+
+    #[test]
+    fn can_script_keys_and_replay_redraws() {
+        let mut tty = MockTty::new().typed("ab").key(keys::UP).pasted("c\td");
+        let mut input = Vec::new();
+        tty.split().0.read_to_end(&mut input).unwrap();
+        assert_eq!(input, b"ab\x1b[A\x1b[200~c\td\x1b[201~");
+        assert!(tty.remaining_input().is_empty());
+
+        write!(tty, "\x1b[?2004hFruit? apricot\rFruit? ap     \rFruit? ap").unwrap();
+        assert_eq!(tty.visible_line(), "Fruit? ap");
+        assert!(tty.output_string().starts_with("\x1b[?2004h"));
+    }
+}
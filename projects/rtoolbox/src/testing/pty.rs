@@ -0,0 +1,224 @@
+//! Runs tests under a real pseudo-terminal, to check what only a TTY does, like hiding input
+//!
+//! `fork_test` runs the current test again in a child process whose controlling terminal is a new
+//! pty, while the parent drives the other end of it. Unlike `MockTty`, this goes through the
+//! terminal settings for real.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Tells the child process which test it runs the child side of
+const CHILD_ENV: &str = "RTOOLBOX_PTY_TEST";
+
+/// How long to wait for the child before failing the test
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The parent's end of the pty, which reads what the child writes and types for the user
+pub struct Pty {
+    master: File,
+    output: Vec<u8>,
+}
+
+impl Pty {
+    /// Types `input`, as if the user did
+    pub fn send(&mut self, input: &str) {
+        self.master
+            .write_all(input.as_bytes())
+            .and_then(|_| self.master.flush())
+            .expect("could not write to the pty");
+    }
+
+    /// Reads until `pattern` is written, and returns everything written up to and including it
+    pub fn expect(&mut self, pattern: &str) -> String {
+        let started_at = Instant::now();
+        loop {
+            if let Some(position) = find(&self.output, pattern.as_bytes()) {
+                let rest = self.output.split_off(position + pattern.len());
+                let seen = std::mem::replace(&mut self.output, rest);
+                return String::from_utf8_lossy(&seen).into_owned();
+            }
+
+            let read = match TIMEOUT.checked_sub(started_at.elapsed()) {
+                Some(timeout) => self.read_some(timeout),
+                None => false,
+            };
+            if !read {
+                panic!(
+                    "the pty never got {:?}, only {:?}",
+                    pattern,
+                    String::from_utf8_lossy(&self.output)
+                );
+            }
+        }
+    }
+
+    /// Reads until the child closes the pty, usually by exiting, and returns everything written
+    pub fn read_to_end(&mut self) -> String {
+        let started_at = Instant::now();
+        while let Some(timeout) = TIMEOUT.checked_sub(started_at.elapsed()) {
+            if !self.read_some(timeout) {
+                let output = std::mem::take(&mut self.output);
+                return String::from_utf8_lossy(&output).into_owned();
+            }
+        }
+        panic!("the pty was never closed");
+    }
+
+    /// Returns true if what's typed is echoed
+    pub fn echo_enabled(&self) -> bool {
+        let mut term = std::mem::MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(self.master.as_raw_fd(), term.as_mut_ptr()) } != 0 {
+            panic!(
+                "could not get the pty settings: {}",
+                io::Error::last_os_error()
+            );
+        }
+        unsafe { term.assume_init() }.c_lflag & libc::ECHO != 0
+    }
+
+    /// Waits until echo is turned on or off, ie: before typing a password
+    pub fn wait_for_echo(&self, enabled: bool) {
+        let started_at = Instant::now();
+        while self.echo_enabled() != enabled {
+            if started_at.elapsed() > TIMEOUT {
+                panic!(
+                    "echo was never turned {}",
+                    if enabled { "on" } else { "off" }
+                );
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Reads what's available, waiting up to `timeout`, and returns false if the pty was closed
+    fn read_some(&mut self, timeout: Duration) -> bool {
+        let mut fds = libc::pollfd {
+            fd: self.master.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        if unsafe { libc::poll(&mut fds, 1, timeout_ms) } <= 0 {
+            return false;
+        }
+
+        let mut buffer = [0u8; 1024];
+        match self.master.read(&mut buffer) {
+            Ok(0) => false,
+            Ok(len) => {
+                self.output.extend_from_slice(&buffer[..len]);
+                true
+            }
+            // Linux fails with EIO once the other end is closed
+            Err(err) if err.raw_os_error() == Some(libc::EIO) => false,
+            Err(err) => panic!("could not read from the pty: {}", err),
+        }
+    }
+}
+
+/// Runs `child` under a pty, in a copy of the current test process, while `parent` drives the pty
+///
+/// `test_name` is the name of the calling test, as the test harness prints it, ie:
+/// `tests::can_hide_input`, so that only this test runs in the child process. The test fails if
+/// `child` panics, with the child's output in the message.
+///
+/// ```no_run
+/// use rtoolbox::testing::pty::fork_test;
+///
+/// fork_test(
+///     "can_hide_input",
+///     || {
+///         // Runs with the pty as its controlling terminal, ie: reads from /dev/tty
+///     },
+///     |pty| {
+///         pty.wait_for_echo(false);
+///         pty.send("secret\n");
+///         assert!(!pty.read_to_end().contains("secret"));
+///     },
+/// );
+/// ```
+pub fn fork_test(test_name: &str, child: impl FnOnce(), parent: impl FnOnce(&mut Pty)) {
+    if std::env::var_os(CHILD_ENV).as_deref() == Some(OsStr::new(test_name)) {
+        child();
+        return;
+    }
+
+    let (master, slave) = open_pty().expect("could not open a pty");
+
+    let mut command = Command::new(std::env::current_exe().expect("could not find the test"));
+    command
+        .args([test_name, "--exact", "--test-threads=1"])
+        .env(CHILD_ENV, test_name)
+        .stdin(Stdio::from(slave))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // The pty is the child's stdin by now, which makes it its controlling terminal in a new session.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let process = command.spawn().expect("could not run the test under a pty");
+    // The parent's copy of the slave end must be closed, so that reads fail once the child exits.
+    std::mem::drop(command);
+
+    let mut pty = Pty {
+        master,
+        output: Vec::new(),
+    };
+    parent(&mut pty);
+
+    let output = process
+        .wait_with_output()
+        .expect("could not wait for the test");
+    assert!(
+        output.status.success(),
+        "{} failed under the pty:\n{}{}",
+        test_name,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn open_pty() -> io::Result<(File, File)> {
+    let mut master = 0;
+    let mut slave = 0;
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    for fd in [master.as_raw_fd(), slave.as_raw_fd()] {
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok((master, slave))
+}
+
+/// Returns where `needle` first appears in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,206 @@
+//! Makes sure only one component reads from the terminal at a time
+//!
+//! When an application combines several crates that read from the terminal, ie: a password prompt
+//! shown while a line editor is running on another thread, both would read keys in turn and the
+//! user would see their input split between them. Components that read from the terminal hold a
+//! `TtyGuard` while they do, and others wait for their turn, in the order they asked for it.
+//!
+//! ```
+//! use rtoolbox::tty_lock;
+//!
+//! let guard = tty_lock::acquire("my app");
+//! // Reading from the terminal here, others wait until `guard` is dropped
+//! assert_eq!(tty_lock::holder(), Some("my app".to_string()));
+//! ```
+//!
+//! The lock is held per thread, so a thread that holds it can acquire it again, ie: when a prompt
+//! is shown while reading from the terminal in the same thread.
+
+use crate::error::{Error, Result};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+static STATE: Mutex<State> = Mutex::new(State {
+    holder: None,
+    depth: 0,
+    queue: Vec::new(),
+    next_ticket: 0,
+});
+static RELEASED: Condvar = Condvar::new();
+
+struct State {
+    holder: Option<(ThreadId, String)>,
+    depth: usize,
+    /// Tickets of the threads waiting for the lock, first come first served
+    queue: Vec<u64>,
+    next_ticket: u64,
+}
+
+/// Gives the terminal back to the next component in line once dropped
+#[derive(Debug)]
+pub struct TtyGuard {
+    // Guards can't be sent to another thread, since the lock is held per thread
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for TtyGuard {
+    fn drop(&mut self) {
+        let mut state = lock_state();
+        state.depth -= 1;
+        if state.depth == 0 {
+            state.holder = None;
+            RELEASED.notify_all();
+        }
+    }
+}
+
+/// Waits until the terminal is free, then holds it until the guard is dropped
+///
+/// `owner` names the component holding the terminal, for the `Error::Busy` others get.
+pub fn acquire(owner: &str) -> TtyGuard {
+    acquire_until(owner, None).expect("waiting without a deadline can't time out")
+}
+
+/// Holds the terminal until the guard is dropped, or fails with `Error::Busy` if another
+/// component holds it or is waiting for it
+pub fn try_acquire(owner: &str) -> Result<TtyGuard> {
+    acquire_timeout(owner, Duration::ZERO)
+}
+
+/// Like `acquire`, but fails with `Error::Busy` if the terminal isn't free within `timeout`
+pub fn acquire_timeout(owner: &str, timeout: Duration) -> Result<TtyGuard> {
+    acquire_until(owner, Instant::now().checked_add(timeout))
+}
+
+/// Returns the name of the component holding the terminal, if any
+pub fn holder() -> Option<String> {
+    lock_state().holder.as_ref().map(|(_, owner)| owner.clone())
+}
+
+fn acquire_until(owner: &str, deadline: Option<Instant>) -> Result<TtyGuard> {
+    let current = thread::current().id();
+    let mut state = lock_state();
+    if let Some((thread, _)) = &state.holder {
+        if *thread == current {
+            state.depth += 1;
+            return Ok(new_guard());
+        }
+    }
+
+    let ticket = state.next_ticket;
+    state.next_ticket += 1;
+    state.queue.push(ticket);
+    while state.holder.is_some() || state.queue.first() != Some(&ticket) {
+        let timeout = match deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+            None => Duration::MAX,
+        };
+        if timeout == Duration::ZERO {
+            let busy_owner = match &state.holder {
+                Some((_, owner)) => owner.clone(),
+                None => "a component waiting for the terminal".to_string(),
+            };
+            state.queue.retain(|t| *t != ticket);
+            // The next in line may have been waiting for us to leave the front of the queue
+            RELEASED.notify_all();
+            return Err(Error::Busy(busy_owner));
+        }
+        state = match deadline {
+            Some(_) => {
+                RELEASED
+                    .wait_timeout(state, timeout)
+                    .unwrap_or_else(|err| err.into_inner())
+                    .0
+            }
+            None => RELEASED.wait(state).unwrap_or_else(|err| err.into_inner()),
+        };
+    }
+
+    state.queue.remove(0);
+    state.holder = Some((current, owner.to_string()));
+    state.depth = 1;
+    Ok(new_guard())
+}
+
+fn new_guard() -> TtyGuard {
+    TtyGuard {
+        _not_send: std::marker::PhantomData,
+    }
+}
+
+/// A component that panicked while holding the lock can't be reading anymore, so the state is
+/// still usable
+fn lock_state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acquire, acquire_timeout, holder, try_acquire};
+    use crate::error::Error;
+    use std::sync::mpsc::channel;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    // The lock is process-wide, so tests using it can't run at the same time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn can_acquire_again_in_same_thread() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let guard = acquire("editor");
+        let nested = try_acquire("password prompt").unwrap();
+        std::mem::drop(nested);
+        assert_eq!(holder(), Some("editor".to_string()));
+        std::mem::drop(guard);
+        assert_eq!(holder(), None);
+    }
+
+    #[test]
+    fn can_tell_terminal_is_busy() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let guard = acquire("editor");
+        let err = thread::spawn(|| try_acquire("password prompt").map(|_| ()))
+            .join()
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(err, Error::Busy(owner) if owner == "editor"));
+
+        let err = thread::spawn(|| {
+            acquire_timeout("password prompt", Duration::from_millis(50)).map(|_| ())
+        })
+        .join()
+        .unwrap()
+        .unwrap_err();
+        assert!(matches!(err, Error::Busy(_)));
+        std::mem::drop(guard);
+    }
+
+    #[test]
+    fn can_take_turns_in_order() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let guard = acquire("first");
+        let (sender, turns) = channel();
+        let waiters: Vec<_> = ["second", "third"]
+            .iter()
+            .map(|owner| {
+                let sender = sender.clone();
+                let waiter = thread::spawn(move || {
+                    let _guard = acquire(owner);
+                    sender.send(owner.to_string()).unwrap();
+                });
+                // Lets the waiter get in line before the next one
+                thread::sleep(Duration::from_millis(50));
+                waiter
+            })
+            .collect();
+
+        std::mem::drop(guard);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(turns.try_iter().collect::<Vec<_>>(), ["second", "third"]);
+    }
+}
@@ -0,0 +1,126 @@
+//! Runs blocking reads on another thread, so that async code can await them
+//!
+//! ```
+//! use rtoolbox::unblock::unblock;
+//!
+//! async fn answer() -> u32 {
+//!     // Reading from the terminal here wouldn't block the other tasks of the executor
+//!     unblock(|| 42).await
+//! }
+//! ```
+//!
+//! This doesn't depend on an async runtime, so it works with any executor.
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// The result of a function running on another thread, see `unblock`
+pub struct Unblock<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+struct State<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T> std::fmt::Debug for Unblock<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Unblock")
+            .field("done", &lock_state(&self.state).result.is_some())
+            .finish()
+    }
+}
+
+/// Runs `f` on a new thread, and returns a future that resolves to what it returns
+///
+/// If `f` panics, the panic is resumed where the future is polled.
+pub fn unblock<T, F>(f: F) -> Unblock<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let state = Arc::new(Mutex::new(State {
+        result: None,
+        waker: None,
+    }));
+    let shared = state.clone();
+    thread::spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        let mut state = lock_state(&shared);
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+    Unblock { state }
+}
+
+impl<T> Future for Unblock<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = lock_state(&self.state);
+        match state.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(panic)) => panic::resume_unwind(panic),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn lock_state<T>(state: &Mutex<State<T>>) -> MutexGuard<'_, State<T>> {
+    state.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unblock;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn can_await_blocking_function() {
+        let value = block_on(unblock(|| {
+            thread::sleep(Duration::from_millis(50));
+            42
+        }));
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    fn can_resume_panic_when_polled() {
+        let result = std::panic::catch_unwind(|| block_on(unblock(|| panic!("no terminal"))));
+        assert!(result.is_err());
+    }
+}